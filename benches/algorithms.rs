@@ -0,0 +1,135 @@
+// Benchmarks of the core algorithms on a synthetic network, so performance work (indexing, parallelism, hashing)
+// is measured rather than guessed: `cargo bench`, or `cargo bench -- closeness` for one group
+// The network is generated from a fixed seed, so runs on different machines and commits see the same data
+
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use nj_delays::prelude::*;
+use nj_delays::metrics::{delta_stepping_distances, shortest_distances, PathAlgorithm};
+use nj_delays::landmarks::DEFAULT_LANDMARKS;
+use nj_delays::report::line_on_time;
+use nj_delays::table::RecordTable;
+
+const LINES: usize = 8;  // Lines radiating from one hub
+const STOPS: usize = 25; // Stops per line, hub included
+const DAYS: usize = 10;  // Service days
+const TRIPS: usize = 6;  // Trips per line per day, alternating outbound and inbound
+
+// Stop ID and name of a line's stop: stop 0 is the shared hub, and each line ends at the middle stop of the next,
+// so the lines form a connected network with transfers rather than a star
+fn stop(line: usize, stop: usize) -> (String, String) {
+    if stop == 0 {
+        ("1.0".to_string(), "Hub".to_string())
+    } else if stop == STOPS - 1 {
+        self::stop((line + 1) % LINES, STOPS / 2)
+    } else {
+        let id = 100 * (line + 1) + stop;
+        (format!("{}.0", id), format!("Station {}", id))
+    }
+}
+
+// One record per segment of every trip, with delays that drift along each trip
+fn synthetic_records() -> Vec<TrainRecord> {
+    let mut rng = StdRng::seed_from_u64(DEFAULT_SEED);
+    let mut records = Vec::new();
+    for day in 1..=DAYS {
+        let date = format!("2019-01-{:02}", day);
+        for line in 0..LINES {
+            for trip in 0..TRIPS {
+                let mut stops: Vec<usize> = (0..STOPS).collect();
+                if trip % 2 == 1 {
+                    stops.reverse();
+                }
+                let hour = 6 + 2 * trip;
+                let mut delay: f32 = rng.random_range(0.0..3.0);
+                for (sequence, pair) in stops.windows(2).enumerate() {
+                    delay = (delay + rng.random_range(-1.0..1.5)).max(0.0);
+                    let ((from_id, from), (to_id, to)) = (stop(line, pair[0]), stop(line, pair[1]));
+                    let minute = 2 * sequence;
+                    records.push(TrainRecord {
+                        date: date.clone(),
+                        train_id: format!("{}{:02}", line + 1, trip),
+                        stop_sequence: format!("{}.0", sequence + 1),
+                        from, from_id, to, to_id,
+                        scheduled_time: format!("{} {:02}:{:02}:00", date, hour + minute / 60, minute % 60),
+                        actual_time: String::new(),
+                        delay_minutes: Some(delay),
+                        status: "departed".to_string(),
+                        line: format!("Line {}", line + 1),
+                        r#type: "NJ Transit".to_string(),
+                        month: "1".to_string(),
+                        year: "2019".to_string(),
+                    });
+                }
+            }
+        }
+    }
+    records
+}
+
+// The records as the CSV the loader reads
+fn synthetic_csv(records: &[TrainRecord]) -> Vec<u8> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for r in records {
+        writer.serialize(r).expect("serialize record");
+    }
+    writer.into_inner().expect("flush CSV")
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let records = synthetic_records();
+    let csv = synthetic_csv(&records);
+    let graph = TransitGraph::from_records(&records);
+    let hub = graph.station("1").expect("hub station").clone();
+    // The far end of the first line: a long path crossing several transfers
+    let far = graph.station(&format!("{}", 100 + STOPS - 2)).expect("far station").clone();
+
+    c.bench_function("load", |b| b.iter(|| read_data(black_box(csv.as_slice()), "synthetic").expect("load")));
+    c.bench_function("graph_build", |b| b.iter(|| TransitGraph::from_records(black_box(&records))));
+
+    // Per-route and per-line delay aggregation: HashMap updates per record against passes over the columnar table
+    let table = RecordTable::from_records(&records);
+    let mut aggregation = c.benchmark_group("aggregation");
+    aggregation.bench_function("routes_hashmap", |b| b.iter(|| graph.get_route_average_delays()));
+    aggregation.bench_function("routes_columnar", |b| b.iter(|| table.route_average_delays()));
+    aggregation.bench_function("lines_hashmap", |b| b.iter(|| line_on_time(black_box(&records), 6.0)));
+    aggregation.bench_function("lines_columnar", |b| b.iter(|| table.line_on_time(black_box(6.0))));
+    aggregation.bench_function("table_build", |b| b.iter(|| RecordTable::from_records(black_box(&records))));
+    aggregation.finish();
+
+    let mut dijkstra = c.benchmark_group("dijkstra");
+    // The free functions skip the graph's path cache, so every iteration runs the search
+    dijkstra.bench_function("point_to_point", |b| b.iter(|| shortest_path(&graph, black_box(&hub), black_box(&far))));
+    dijkstra.bench_function("single_source", |b| b.iter(|| shortest_distances(&graph, black_box(&hub))));
+    dijkstra.bench_function("single_source_delta_stepping", |b| b.iter(|| delta_stepping_distances(&graph, black_box(&hub), None)));
+    // The cached method, on a miss (cache cleared first) and on a hit
+    dijkstra.bench_function("cached_miss", |b| b.iter(|| {
+        graph.path_cache().clear();
+        graph.shortest_path(black_box(&hub), black_box(&far))
+    }));
+    // A* with landmark bounds: the one-time preprocessing, then a query
+    dijkstra.bench_function("landmarks_build", |b| b.iter(|| graph.landmark_index(black_box(DEFAULT_LANDMARKS))));
+    let landmarks = graph.landmark_index(DEFAULT_LANDMARKS);
+    dijkstra.bench_function("landmarks_query", |b| b.iter(|| landmarks.shortest_path(black_box(&hub), black_box(&far))));
+    dijkstra.bench_function("cached_hit", |b| b.iter(|| graph.shortest_path(black_box(&hub), black_box(&far))));
+    dijkstra.finish();
+
+    // One Dijkstra or Brandes pass per station: the slowest benchmarks, so fewer samples
+    let opts = RankOptions { top_n: usize::MAX, ..Default::default() };
+    let mut centrality = c.benchmark_group("centrality");
+    centrality.sample_size(10);
+    centrality.bench_function("closeness", |b| b.iter(|| graph.rank_stations_by_closeness(black_box(&opts))));
+    let delta_opts = RankOptions { path_algorithm: PathAlgorithm::DeltaStepping, ..opts };
+    centrality.bench_function("closeness_delta_stepping", |b| b.iter(|| graph.rank_stations_by_closeness(black_box(&delta_opts))));
+    centrality.bench_function("betweenness", |b| b.iter(|| graph.betweenness_centrality()));
+    // The top 10 only, with searches cut by upper bounds
+    let top_opts = RankOptions { top_n: 10, prune: true, ..Default::default() };
+    centrality.bench_function("closeness_top10_pruned", |b| b.iter(|| graph.rank_stations_by_closeness(black_box(&top_opts))));
+    centrality.bench_function("betweenness_top10_pruned", |b| b.iter(|| graph.rank_stations_by_betweenness(black_box(&top_opts))));
+    centrality.finish();
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+// Build fingerprint for the result cache: a hash of the Rust sources, the manifest, the lockfile and the enabled
+// features, exported as NJ_DELAYS_BUILD so entries cached by a differently built binary are never served
+// The package version alone stays 0.1.0 across code changes, so it cannot tell two builds apart
+
+use std::env;
+use std::fs;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    sources(&root.join("src"), &mut files);
+    files.sort();
+    files.extend(["Cargo.toml", "Cargo.lock"].map(|name| root.join(name)).into_iter().filter(|path| path.exists()));
+    let mut hasher = DefaultHasher::new();
+    for file in &files {
+        // Paths and lengths separate the files, so moving code between them changes the hash
+        let contents = fs::read(file).unwrap_or_default();
+        hasher.write(file.strip_prefix(&root).unwrap_or(file).to_string_lossy().as_bytes());
+        hasher.write_u64(contents.len() as u64);
+        hasher.write(&contents);
+    }
+    let mut features: Vec<String> = env::vars().map(|(name, _)| name).filter(|name| name.starts_with("CARGO_FEATURE_")).collect();
+    features.sort();
+    for feature in &features {
+        hasher.write(feature.as_bytes());
+    }
+    println!("cargo:rustc-env=NJ_DELAYS_BUILD={:016x}", hasher.finish());
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+// Every .rs file under `dir`
+fn sources(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            sources(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
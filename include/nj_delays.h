@@ -0,0 +1,37 @@
+/* C API of the nj_delays library (src/ffi.rs). Link against the cdylib built by `cargo build --release`
+ * (libnj_delays.so, libnj_delays.dylib or nj_delays.dll).
+ *
+ * Strings are NUL-terminated UTF-8. Functions returning char * hand over ownership: release with nj_string_free.
+ * On failure they return NULL and nj_last_error() describes why. Stations are given by name or stop ID. */
+#ifndef NJ_DELAYS_H
+#define NJ_DELAYS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque delay-weighted transit graph */
+typedef struct NjGraph NjGraph;
+
+/* Builds the graph from a CSV file of train records; NULL on failure. Release with nj_graph_free. */
+NjGraph *nj_graph_from_csv(const char *path);
+void nj_graph_free(NjGraph *graph);
+
+/* Least-delay path as {"delay": minutes, "stations": [names]}, or "null" if the stations are not connected */
+char *nj_shortest_path_json(const NjGraph *graph, const char *from, const char *to);
+
+/* Top-N ranking as a JSON array; kind is "closeness", "betweenness", "worst-routes" or "best-routes".
+ * Routes with fewer than min_trips observations are left out. */
+char *nj_rankings_json(const NjGraph *graph, const char *kind, size_t top_n, size_t min_trips);
+
+/* Message of the last failed call on this thread, or NULL; do not free */
+const char *nj_last_error(void);
+void nj_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NJ_DELAYS_H */
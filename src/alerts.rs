@@ -0,0 +1,114 @@
+// Delay alerts posted to a chat webhook: when a watched directory's new trips trip the anomaly thresholds, one message
+// lists the offending lines and routes with how much later they ran than before
+// Slack, Discord and Teams incoming webhooks each take a JSON body with the message text under their own key; the
+// generic format posts the anomalies themselves for other receivers
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::live::{AnomalyScope, DelayAnomaly};
+
+// Anomalies listed in one message; the rest are summarized as a count
+pub const MAX_LISTED: usize = 10;
+
+// Body format of the webhook
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum WebhookFormat {
+    /// Slack incoming webhook: {"text": ...} with Slack mrkdwn
+    #[default]
+    Slack,
+    /// Discord webhook: {"content": ...} with Markdown
+    Discord,
+    /// Microsoft Teams incoming webhook: {"text": ...} with Markdown
+    Teams,
+    /// {"title": ..., "anomalies": [...]} with the anomaly rows as JSON
+    Json,
+}
+
+// The message text for the anomalies, highest z-score first, in the format's markup
+pub fn alert_text(format: WebhookFormat, anomalies: &[DelayAnomaly]) -> String {
+    let bold = |text: &str| match format {
+        WebhookFormat::Slack => format!("*{}*", text),
+        _ => format!("**{}**", text),
+    };
+    let mut lines = vec![format!("{}: {} {} running late", bold("NJ Transit delay alert"), anomalies.len(), plural(anomalies.len()))];
+    for a in anomalies.iter().take(MAX_LISTED) {
+        let kind = match a.scope {
+            AnomalyScope::Line => "line",
+            AnomalyScope::Route => "route",
+        };
+        lines.push(format!(
+            "• {} ({}): {:.1} min average over {} new trips, {:+.1} min vs {:.1} min before (z = {:.1})",
+            bold(&a.name), kind, a.mean_delay, a.trips, a.mean_delay - a.baseline_delay, a.baseline_delay, a.z_score,
+        ));
+    }
+    if anomalies.len() > MAX_LISTED {
+        lines.push(format!("…and {} more", anomalies.len() - MAX_LISTED));
+    }
+    lines.join("\n")
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "line or route" } else { "lines or routes" }
+}
+
+// The JSON body to post for the anomalies
+pub fn alert_payload(format: WebhookFormat, anomalies: &[DelayAnomaly]) -> serde_json::Value {
+    match format {
+        WebhookFormat::Slack | WebhookFormat::Teams => serde_json::json!({ "text": alert_text(format, anomalies) }),
+        WebhookFormat::Discord => serde_json::json!({ "content": alert_text(format, anomalies) }),
+        WebhookFormat::Json => serde_json::json!({ "title": "NJ Transit delay alert", "anomalies": anomalies }),
+    }
+}
+
+// Posts alerts to one webhook (sending needs the `async` feature)
+#[derive(Debug, Clone)]
+pub struct WebhookAlerter {
+    url: String,
+    format: WebhookFormat,
+}
+
+impl WebhookAlerter {
+    pub fn new(url: impl Into<String>, format: WebhookFormat) -> Self {
+        Self { url: url.into(), format }
+    }
+
+    pub fn format(&self) -> WebhookFormat {
+        self.format
+    }
+
+    // The webhook's scheme and host, safe to print: Slack and Discord webhook paths are credentials
+    pub fn host(&self) -> &str {
+        let start = self.url.find("://").map_or(0, |i| i + 3);
+        let end = self.url[start..].find('/').map_or(self.url.len(), |i| start + i);
+        &self.url[..end]
+    }
+}
+
+#[cfg(feature = "async")]
+impl WebhookAlerter {
+    // Posts one message listing the anomalies; nothing is sent when there are none
+    // Output: a notify error naming the webhook if it cannot be reached or answers with an error status
+    pub async fn send(&self, anomalies: &[DelayAnomaly]) -> crate::Result<()> {
+        if anomalies.is_empty() {
+            return Ok(());
+        }
+        let failed = |e: reqwest::Error| crate::Error::Notify { url: self.host().to_string(), reason: e.without_url().to_string() };
+        let body = alert_payload(self.format, anomalies).to_string();
+        reqwest::Client::new()
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(failed)?;
+        Ok(())
+    }
+
+    // `send` for callers without a runtime, on a single-threaded one of its own
+    pub fn send_blocking(&self, anomalies: &[DelayAnomaly]) -> crate::Result<()> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+            .map_err(|e| crate::Error::Notify { url: self.host().to_string(), reason: e.to_string() })?;
+        runtime.block_on(self.send(anomalies))
+    }
+}
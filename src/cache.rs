@@ -0,0 +1,131 @@
+// Memoized shortest paths: an LRU cache of (from, to) queries kept inside each graph, so repeated path queries
+// (the REPL, the server, via-station routing) skip Dijkstra; the graph never changes once built, so entries stay valid
+
+use std::mem::size_of;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use crate::graph::Station;
+use crate::memory::{station_bytes, table_overhead, MemoryUsage};
+
+// Entries a new graph's cache holds; a path is a few stations, so this stays well under a megabyte
+pub const DEFAULT_PATH_CACHE_CAPACITY: usize = 4096;
+
+// A least-weight path, or None if the stations are not connected (remembered too, since that is as costly to find)
+type CachedPath<W> = Option<(W, Vec<Station>)>;
+
+// Hit and miss counts of a path cache, for tuning its capacity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+pub struct PathCache<W> {
+    entries: Mutex<LruCache<(Station, Station), CachedPath<W>>>,
+    capacity: AtomicUsize, // Requested capacity; the LruCache keeps one slot even when this is 0
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<W: Clone> PathCache<W> {
+    // A cache of at most `capacity` paths; 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(Self::slots(capacity))),
+            capacity: AtomicUsize::new(capacity),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    // LruCache needs at least one slot; a zero capacity is honored by never inserting
+    fn slots(capacity: usize) -> NonZeroUsize {
+        NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<(Station, Station), CachedPath<W>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Returns the cached path for (from, to), or computes and remembers it
+    // Logic: the lock is not held while computing, so parallel callers only serialize on the lookup and the insert
+    pub fn get_or_compute(&self, from: &Station, to: &Station, compute: impl FnOnce() -> CachedPath<W>) -> CachedPath<W> {
+        let key = (from.clone(), to.clone());
+        if let Some(path) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return path.clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let path = compute();
+        if self.capacity() > 0 {
+            self.lock().put(key, path.clone());
+        }
+        path
+    }
+
+    // Maximum number of paths kept; 0 when caching is off
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    // Changes the capacity, evicting the least recently used paths if it shrinks; 0 turns caching off
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut entries = self.lock();
+        entries.resize(Self::slots(capacity));
+        if capacity == 0 {
+            entries.clear();
+        }
+        self.capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    // Footprint of the cached paths; each LRU entry also holds two list pointers and a boxed node
+    pub(crate) fn memory_usage(&self) -> MemoryUsage {
+        let entries = self.lock();
+        let slot = size_of::<((Station, Station), CachedPath<W>)>() + 2 * size_of::<usize>();
+        let (mut stations, mut strings) = (0, 0);
+        for ((from, to), path) in entries.iter() {
+            strings += station_bytes(from) + station_bytes(to);
+            if let Some((_, path)) = path {
+                stations += path.capacity();
+                strings += path.iter().map(station_bytes).sum::<usize>();
+            }
+        }
+        let key_slot = 2 * size_of::<usize>();
+        MemoryUsage::new(
+            "graph path cache",
+            entries.len(),
+            entries.len() * slot + stations * size_of::<Station>(),
+            strings,
+            table_overhead(entries.cap().get(), entries.len(), key_slot),
+        )
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+            capacity: self.capacity(),
+        }
+    }
+}
+
+// Summarized rather than listing every cached path
+impl<W> std::fmt::Debug for PathCache<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathCache")
+            .field("capacity", &self.capacity.load(Ordering::Relaxed))
+            .field("hits", &self.hits.load(Ordering::Relaxed))
+            .field("misses", &self.misses.load(Ordering::Relaxed))
+            .finish()
+    }
+}
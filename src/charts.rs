@@ -0,0 +1,327 @@
+// Chart images (SVG or PNG, chosen by file extension) of worst routes, the monthly delay trend, the delay histogram, and the hour × weekday heatmap,
+// plus animated GIF delay playback (requires the `charts` feature)
+
+use std::io;
+use std::path::{Path, PathBuf};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use crate::graph::{Station, TransitGraph};
+use crate::heatmap::{DelayHeatmap, WEEKDAYS};
+use crate::load::{Coordinates, TrainRecord};
+use crate::metrics::RankOptions;
+use crate::playback::Frame;
+use crate::report::{monthly_trend, GroupOnTime};
+use crate::robustness::{RobustnessPoint, RobustnessReport};
+use crate::stats::{histogram, HistogramBin};
+
+const WIDTH: u32 = 900;
+const HEIGHT: u32 = 540;
+const HISTOGRAM_BIN_MINUTES: f32 = 2.0;
+const HISTOGRAM_MAX_MINUTES: f32 = 60.0;
+const BAR_COLOR: RGBColor = RGBColor(59, 110, 165);
+const TREND_COLOR: RGBColor = RGBColor(192, 57, 43);
+
+// Image file type for a chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChartFormat {
+    /// Scalable vector graphics
+    Svg,
+    /// Raster image
+    Png,
+}
+
+impl ChartFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChartFormat::Svg => "svg",
+            ChartFormat::Png => "png",
+        }
+    }
+}
+
+// A chart that can draw itself onto any plotters backend
+pub trait Chart {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String>;
+}
+
+// Top N routes by average delay, as horizontal bars
+pub struct WorstRoutes(pub Vec<(String, f32)>);
+
+// Average delay per month, as a line with point markers
+pub struct MonthlyTrend(pub Vec<GroupOnTime>);
+
+// Trips per delay bin, as vertical bars
+pub struct DelayHistogram(pub Vec<HistogramBin>);
+
+impl WorstRoutes {
+    pub fn new(graph: &TransitGraph, opts: &RankOptions) -> Self {
+        WorstRoutes(graph.rank_routes_by_average_delay(opts).into_iter()
+            .map(|r| (format!("{} → {}", r.from, r.to), r.average_delay))
+            .collect())
+    }
+}
+
+impl DelayHistogram {
+    // 2-minute bins up to an hour, with everything later in one final bin
+    pub fn new(records: &[TrainRecord]) -> Self {
+        let delays: Vec<f32> = records.iter().filter_map(|r| r.delay()).collect();
+        DelayHistogram(histogram(&delays, HISTOGRAM_BIN_MINUTES, HISTOGRAM_MAX_MINUTES).unwrap_or_default()) // Constant, valid bins
+    }
+}
+
+impl Chart for WorstRoutes {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String> {
+        let bars = &self.0;
+        let max = bars.iter().map(|(_, d)| *d).fold(1.0f32, f32::max) * 1.1;
+        let mut chart = ChartBuilder::on(area)
+            .caption(format!("Top {} routes by average delay", bars.len()), ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(320)
+            .build_cartesian_2d(0f32..max, (0..bars.len().max(1) - 1).into_segmented())
+            .map_err(|e| e.to_string())?;
+        chart.configure_mesh()
+            .disable_y_mesh()
+            .x_desc("Average delay (minutes)")
+            .y_labels(bars.len())
+            .y_label_formatter(&|v| match v {
+                SegmentValue::CenterOf(i) => bars.len().checked_sub(i + 1).map(|j| bars[j].0.clone()).unwrap_or_default(),
+                _ => String::new(),
+            })
+            .draw()
+            .map_err(|e| e.to_string())?;
+        // Worst route at the top
+        chart.draw_series(bars.iter().rev().enumerate().map(|(i, (_, delay))| {
+            let mut bar = Rectangle::new([(0.0, SegmentValue::Exact(i)), (*delay, SegmentValue::Exact(i + 1))], BAR_COLOR.filled());
+            bar.set_margin(4, 4, 0, 0);
+            bar
+        }))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+impl Chart for MonthlyTrend {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String> {
+        let months = &self.0;
+        let max = months.iter().map(|m| m.average_delay).fold(1.0f32, f32::max) * 1.1;
+        let mut chart = ChartBuilder::on(area)
+            .caption("Average delay by month", ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(56)
+            .build_cartesian_2d(0..months.len().max(2) - 1, 0f32..max)
+            .map_err(|e| e.to_string())?;
+        chart.configure_mesh()
+            .x_labels(12)
+            .x_label_formatter(&|i| months.get(*i).map(|m| m.group.clone()).unwrap_or_default())
+            .y_desc("Average delay (minutes)")
+            .draw()
+            .map_err(|e| e.to_string())?;
+        let points: Vec<(usize, f32)> = months.iter().enumerate().map(|(i, m)| (i, m.average_delay)).collect();
+        chart.draw_series(LineSeries::new(points.iter().copied(), TREND_COLOR.stroke_width(2)))
+            .map_err(|e| e.to_string())?;
+        chart.draw_series(points.iter().map(|&p| Circle::new(p, 3, TREND_COLOR.filled())))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+impl Chart for DelayHistogram {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String> {
+        let bins = &self.0;
+        let max = bins.iter().map(|b| b.count).max().unwrap_or(0).max(1) as f64 * 1.1;
+        let mut chart = ChartBuilder::on(area)
+            .caption("Delay distribution", ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(56)
+            .build_cartesian_2d((0..bins.len().max(1) - 1).into_segmented(), 0f64..max)
+            .map_err(|e| e.to_string())?;
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_desc("Delay (minutes)")
+            .y_desc("Trips")
+            .x_label_formatter(&|v| match v {
+                SegmentValue::CenterOf(i) => bins.get(*i)
+                    .map(|b| if b.upper.is_finite() { format!("{}", b.lower) } else { format!("{}+", b.lower) })
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .draw()
+            .map_err(|e| e.to_string())?;
+        chart.draw_series(bins.iter().enumerate().map(|(i, b)| {
+            let mut bar = Rectangle::new([(SegmentValue::Exact(i), 0.0), (SegmentValue::Exact(i + 1), b.count as f64)], TREND_COLOR.filled());
+            bar.set_margin(0, 0, 1, 1);
+            bar
+        }))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+impl Chart for DelayHeatmap {
+    // Weekdays top to bottom from Monday, hours left to right; cells shade from white to red with average delay
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String> {
+        let max = self.max_average().unwrap_or(0.0).max(1.0);
+        let mut chart = ChartBuilder::on(area)
+            .caption(format!("Average delay by hour and weekday (darkest = {:.1} min)", max), ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(56)
+            .build_cartesian_2d(0u32..24, 0u32..7)
+            .map_err(|e| e.to_string())?;
+        chart.configure_mesh()
+            .disable_mesh()
+            .x_desc("Scheduled hour")
+            .x_labels(24)
+            .y_labels(7)
+            .x_label_formatter(&|h| format!("{:02}", h))
+            .y_label_formatter(&|y| 6usize.checked_sub(*y as usize).map(|d| WEEKDAYS[d].to_string()).unwrap_or_default())
+            .y_label_offset(-(HEIGHT as i32 / 20))
+            .draw()
+            .map_err(|e| e.to_string())?;
+        let cells = self.cells.iter().enumerate().flat_map(|(day, hours)| {
+            hours.iter().enumerate().map(move |(hour, cell)| {
+                let color = match cell.average_delay() {
+                    Some(delay) => {
+                        let shade = 1.0 - (delay / max).clamp(0.0, 1.0);
+                        RGBColor(255, (255.0 * shade) as u8, (255.0 * shade) as u8).filled()
+                    }
+                    None => RGBColor(230, 230, 230).filled(), // No trips in this window
+                };
+                let y = 6 - day as u32;
+                let mut rect = Rectangle::new([(hour as u32, y), (hour as u32 + 1, y + 1)], color);
+                rect.set_margin(1, 1, 1, 1);
+                rect
+            })
+        });
+        chart.draw_series(cells).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+impl Chart for RobustnessReport {
+    // Giant-component share above and mean least delay below, against the share of stations removed; random removal
+    // in blue, the targeted attack in red
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>) -> Result<(), String> {
+        type Value = fn(&RobustnessPoint) -> Option<f32>;
+        let panels = area.split_evenly((2, 1));
+        let last = self.points.last().map_or(1.0, |p| p.removed_fraction).max(f32::EPSILON);
+        let max_delay = self.points.iter().flat_map(|p| [p.random_delay, p.targeted_delay]).flatten().fold(1.0f32, f32::max) * 1.1;
+        let plots: [(&str, &str, f32, Value, Value); 2] = [
+            ("Giant component under station removal", "Share of stations", 1.05, |p| Some(p.random_giant), |p| Some(p.targeted_giant)),
+            ("Mean least delay under station removal", "Delay (minutes)", max_delay, |p| p.random_delay, |p| p.targeted_delay),
+        ];
+        for (panel, (caption, y_desc, max, random, targeted)) in panels.iter().zip(plots) {
+            let mut chart = ChartBuilder::on(panel)
+                .caption(caption, ("sans-serif", 20))
+                .margin(10)
+                .x_label_area_size(36)
+                .y_label_area_size(56)
+                .build_cartesian_2d(0f32..last, 0f32..max)
+                .map_err(|e| e.to_string())?;
+            chart.configure_mesh()
+                .x_desc("Share of stations removed")
+                .y_desc(y_desc)
+                .draw()
+                .map_err(|e| e.to_string())?;
+            for (label, value, color) in [("Random", random, BAR_COLOR), ("Targeted (betweenness)", targeted, TREND_COLOR)] {
+                let points = self.points.iter().filter_map(|p| Some((p.removed_fraction, value(p)?)));
+                chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+                    .map_err(|e| e.to_string())?
+                    .label(label)
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 16, y)], color.stroke_width(2)));
+            }
+            chart.configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+// Draws a chart to `path` as PNG if the extension is .png, otherwise as SVG
+pub fn render(path: &Path, chart: &impl Chart) -> io::Result<()> {
+    let result = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) {
+        let root = BitMapBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| e.to_string())
+            .and_then(|_| chart.draw(&root))
+            .and_then(|_| root.present().map_err(|e| e.to_string()))
+    } else {
+        let root = SVGBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| e.to_string())
+            .and_then(|_| chart.draw(&root))
+            .and_then(|_| root.present().map_err(|e| e.to_string()))
+    };
+    result.map_err(|e| io::Error::other(format!("cannot draw {}: {}", path.display(), e)))
+}
+
+// Writes the worst-routes, monthly-trend, and delay-histogram charts into `dir`
+// Output: the paths written, in order
+pub fn write_charts(dir: &Path, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: ChartFormat) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let path = |name: &str| dir.join(format!("{}.{}", name, format.extension()));
+    let written = vec![path("worst-routes"), path("monthly-trend"), path("delay-histogram")];
+    render(&written[0], &WorstRoutes::new(graph, opts))?;
+    render(&written[1], &MonthlyTrend(monthly_trend(records, opts.on_time_threshold)))?;
+    render(&written[2], &DelayHistogram::new(records))?;
+    Ok(written)
+}
+
+// Milliseconds each playback frame stays on screen
+const PLAYBACK_FRAME_MS: u32 = 400;
+
+// Draws delay playback as an animated GIF map: every located route in light gray, then each frame's routes on top,
+// green when on time, amber up to twice the threshold, and red beyond
+// Logic: stations are placed by longitude/latitude within the bounding box of all coordinates in use
+pub fn render_playback_gif(path: &Path, frames: &[Frame], coords: &Coordinates, threshold: f32) -> io::Result<()> {
+    let located = |station: &Station| coords.get(&station.name).map(|&(lat, lon)| (lon, lat));
+    let segments: Vec<((f64, f64), (f64, f64))> = {
+        let mut all: Vec<_> = frames.iter().flat_map(|f| &f.routes).filter_map(|r| Some((located(&r.from)?, located(&r.to)?))).collect();
+        all.sort_by(|(a, b), (c, d)| {
+            a.0.total_cmp(&c.0).then(a.1.total_cmp(&c.1)).then(b.0.total_cmp(&d.0)).then(b.1.total_cmp(&d.1))
+        });
+        all.dedup();
+        all
+    };
+    let points = segments.iter().flat_map(|(a, b)| [*a, *b]);
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for (x, y) in points {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    if segments.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no routes have coordinates for both stations"));
+    }
+    let pad = ((max.0 - min.0).max(max.1 - min.1) * 0.05).max(0.01);
+    let result = (|| -> Result<(), String> {
+        let root = BitMapBackend::gif(path, (WIDTH, HEIGHT), PLAYBACK_FRAME_MS).map_err(|e| e.to_string())?.into_drawing_area();
+        for frame in frames {
+            root.fill(&WHITE).map_err(|e| e.to_string())?;
+            let mut chart = ChartBuilder::on(&root)
+                .caption(format!("Route delays, {}", frame.time), ("sans-serif", 22))
+                .margin(12)
+                .build_cartesian_2d(min.0 - pad..max.0 + pad, min.1 - pad..max.1 + pad)
+                .map_err(|e| e.to_string())?;
+            chart.draw_series(segments.iter().map(|&(a, b)| PathElement::new(vec![a, b], RGBColor(220, 220, 220).stroke_width(1))))
+                .map_err(|e| e.to_string())?;
+            let active = frame.routes.iter().filter_map(|r| {
+                let color = if r.mean_delay <= threshold {
+                    RGBColor(39, 174, 96)
+                } else if r.mean_delay <= 2.0 * threshold {
+                    RGBColor(243, 156, 18)
+                } else {
+                    TREND_COLOR
+                };
+                Some(PathElement::new(vec![located(&r.from)?, located(&r.to)?], color.stroke_width(3)))
+            });
+            chart.draw_series(active).map_err(|e| e.to_string())?;
+            root.present().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })();
+    result.map_err(|e| io::Error::other(format!("cannot draw {}: {}", path.display(), e)))
+}
@@ -0,0 +1,90 @@
+// Checkpoints of long per-source computations (betweenness, closeness over every station): the progress so far is
+// saved to disk every so often, so an interrupted multi-hour run on the full network continues instead of starting over
+// A checkpoint belongs to one graph and one set of options; the caller names the file so that other inputs never find it
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+// Time between saves unless set; a save writes one number per station, so this is about bounding lost work
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+// Progress of a per-source metric: sources are processed in index order, so the finished ones are a prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub metric: String,
+    pub sources_done: usize,      // Sources 0..sources_done are finished
+    pub values: Vec<Option<f32>>, // Per station: the metric's partial state (running sums, or scores of finished sources)
+}
+
+// Where a metric saves its checkpoints, how often, and whether to continue from one left by an earlier run
+#[derive(Debug)]
+pub struct Checkpointer {
+    path: PathBuf,
+    interval: Duration,
+    resume: bool,
+    last_saved: Instant,
+}
+
+impl Checkpointer {
+    // Checkpoints in `path` every `interval`; with `resume`, a matching checkpoint already there is continued
+    pub fn new(path: impl Into<PathBuf>, interval: Duration, resume: bool) -> Self {
+        Self { path: path.into(), interval, resume, last_saved: Instant::now() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The checkpoint to continue from: only when resuming, and only one of the same metric over `stations` stations
+    // (anything else, including an unreadable file, means starting from the first source)
+    pub fn resume_from(&self, metric: &str, stations: usize) -> Option<Checkpoint> {
+        if !self.resume {
+            return None;
+        }
+        let file = File::open(&self.path).ok()?;
+        let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(file)).ok()?;
+        (checkpoint.metric == metric && checkpoint.values.len() == stations && checkpoint.sources_done <= stations)
+            .then_some(checkpoint)
+    }
+
+    // Whether the interval has passed since the last save (or since the computation started)
+    pub fn due(&self) -> bool {
+        self.last_saved.elapsed() >= self.interval
+    }
+
+    // Writes the checkpoint, replacing the previous one
+    // Logic: written to a temporary file, flushed and synced, and only then renamed into place, so an interruption or a
+    // failed write mid-save keeps the previous one
+    pub fn save(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut partial = self.path.clone().into_os_string();
+        partial.push(".tmp");
+        let written = File::create(&partial).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, checkpoint)?;
+            // Dropping the writer would discard a failed write of its last buffer, and the rename would then replace a
+            // good checkpoint with a truncated one
+            writer.flush()?;
+            writer.get_ref().sync_all()
+        });
+        let result = written.and_then(|()| fs::rename(&partial, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        self.last_saved = Instant::now();
+        result
+    }
+
+    // Removes the checkpoint once the computation has finished; a missing file is not an error
+    pub fn finish(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,1950 @@
+// Command-line interface: argument definitions and dispatch to the analysis modules
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::NaiveDate;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::error::Error;
+use crate::graph::{join_names, Station, TransitGraph};
+use crate::heatmap::DelayHeatmap;
+use crate::playback::{delay_frames, write_geojson_frames, FrameStep};
+use crate::export::{write_export, write_ranking_csv, ExportKind, ReportMetadata};
+use crate::load::{load_coordinates, Coordinates, RecordFilter, TrainRecord};
+use crate::metrics::{describe_threshold, parse_delay_threshold, PathAlgorithm, RankOptions, DEFAULT_SEED};
+use crate::output::{diagnostic, emit, note, paginate, set_table_style, set_verbosity, verbosity, write_section, ColorChoice, OutputFormat, TableStyle, Verbosity};
+use crate::routes::{route_summaries, route_trends, sort_routes, RouteColumn};
+use crate::stations::{busiest_hours, sort_summaries, station_detail, station_summaries, worst_days, StationColumn};
+use crate::pipeline::{self, Observer};
+use crate::plugin::MetricRegistry;
+use crate::stats::{HistogramBin, Sample};
+use crate::memory;
+use crate::checkpoint::{Checkpointer, DEFAULT_CHECKPOINT_INTERVAL};
+use crate::result_cache::{CacheKey, ResultCache};
+use crate::stream::{self, StreamAggregator};
+use crate::alerts::{WebhookAlerter, WebhookFormat};
+use crate::mqtt::MqttPublisher;
+use crate::live::{self, AnomalyThresholds, LiveFeed};
+use crate::{comparison, congestion, events, flows, forecast, kml, inject, operators, padding, profiles, report, robustness, sim, transfers, stats, textplot, validate, watch, whatif};
+#[cfg(feature = "tui")]
+use crate::tui;
+#[cfg(feature = "charts")]
+use crate::charts;
+#[cfg(feature = "async")]
+use crate::{daemon, departures, nonblocking};
+#[cfg(feature = "serve")]
+use crate::server;
+#[cfg(feature = "email")]
+use crate::{digest::Digest, mail};
+
+// Default dataset location, relative to the repository root
+pub const DEFAULT_DATA_PATH: &str = "src/data/filtered/stations_filtered.csv";
+
+/// NJ Transit delay network analysis
+#[derive(Debug, Parser)]
+#[command(
+    name = "nj-delays",
+    version,
+    about = "Delay-weighted network analysis of NJ Transit rail data",
+    after_help = "Exit codes: 0 success, 1 internal error, 2 bad input, 3 unknown station, 4 no path found"
+)]
+pub struct Cli {
+    /// Path to the filtered train records CSV
+    #[arg(short, long, global = true, default_value = DEFAULT_DATA_PATH)]
+    pub data: String,
+
+    /// Number of entries printed by each ranking
+    #[arg(short = 'n', long, global = true, default_value_t = 10)]
+    pub top: usize,
+
+    /// Minimum trips for a route or station to be ranked
+    #[arg(long, global = true, default_value_t = 5)]
+    pub min_trips: usize,
+
+    /// Delay at or below which a trip counts as on time, in minutes or as a profile: fra-6min, strict-2min
+    #[arg(long = "delay-threshold", value_name = "MINUTES|PROFILE", visible_alias = "on-time-threshold", global = true, default_value = "fra-6min", value_parser = parse_delay_threshold)]
+    pub on_time_threshold: f32,
+
+    /// Seed for sampled analyses such as the small-world random baselines; reruns with the same seed match exactly
+    #[arg(long, global = true, default_value_t = DEFAULT_SEED)]
+    pub seed: u64,
+
+    /// Shortest-path search for closeness rankings; delta-stepping relaxes large distance buckets in parallel
+    #[arg(long, global = true, value_enum, default_value_t = PathAlgorithm::Dijkstra)]
+    pub path_algorithm: PathAlgorithm,
+
+    /// Rank closeness and betweenness for the top N only: closeness searches stop once a station cannot reach the
+    /// top N (same results), betweenness stops once the top N order is certain (scores then estimated)
+    #[arg(long, global = true)]
+    pub prune: bool,
+
+    /// CSV of station coordinates (station,lat,lon) for exports that place stations on a map
+    #[arg(long, global = true)]
+    pub coords: Option<String>,
+
+    /// Only use records on this line (repeatable)
+    #[arg(long, global = true)]
+    pub line: Vec<String>,
+
+    /// Only use records of this service type
+    #[arg(long = "type", global = true)]
+    pub service_type: Option<String>,
+
+    /// Only use records on or after this date (YYYY-MM-DD)
+    #[arg(long, global = true)]
+    pub from_date: Option<NaiveDate>,
+
+    /// Only use records on or before this date (YYYY-MM-DD)
+    #[arg(long, global = true)]
+    pub to_date: Option<NaiveDate>,
+
+    /// Read the data in one pass without keeping the records in memory, for files larger than RAM; supported by rank
+    /// (closeness, betweenness, worst-routes, best-routes, links), metric, path, isochrone and the network histogram
+    #[arg(long, global = true)]
+    pub stream: bool,
+
+    /// With --stream from a nats://HOST/SUBJECT or kafka://BROKERS/TOPIC[?group=GROUP] --data source: stop after this
+    /// many records
+    #[arg(long, global = true)]
+    pub max_events: Option<usize>,
+
+    /// With --stream from an event source: stop once no message arrives for this many seconds
+    #[arg(long, global = true, default_value_t = 10)]
+    pub idle_timeout: u64,
+
+    /// After the command, report the estimated memory of the records, the graph and the metric intermediates
+    #[arg(long, global = true)]
+    pub mem_stats: bool,
+
+    /// Also upsert this run's per-route and per-line metrics into timestamped tables of this PostgreSQL database
+    /// (needs the `postgres` feature)
+    #[arg(long, global = true, value_name = "postgres://...")]
+    pub sink: Option<String>,
+
+    /// Compute rankings afresh, neither reading nor writing the result cache
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Directory of cached rankings, reused while the data file and options are unchanged [default: ~/.cache/nj-delays]
+    #[arg(long, global = true)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Continue an interrupted closeness or betweenness ranking from its last checkpoint (saved every minute in the
+    /// cache directory) instead of starting over; the data file and options must be the same
+    #[arg(long, global = true)]
+    pub resume: bool,
+
+    /// Output format for every ranking and metric
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Color delay cells in tables by severity (green on time, yellow up to twice the threshold, red beyond)
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Cut table cells longer than this many characters; 0 for no limit
+    #[arg(long, global = true, default_value_t = TableStyle::DEFAULT.max_width)]
+    pub max_width: usize,
+
+    /// Format of error reports on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// Print only results: no titles or notes
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print timings and filter counts to stderr; repeat (-vv) for per-section and skipped-row detail
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the full analysis: rankings, forecasts, comparisons and topology metrics
+    Analyze,
+    /// Find the least-delay paths between two stations, with per-segment delays
+    Path {
+        /// Origin station name or stop ID
+        from: String,
+        /// Destination station name or stop ID
+        to: String,
+        /// Number of alternative loopless paths to list
+        #[arg(long, default_value_t = 1)]
+        k: usize,
+        /// Station to route around (repeatable)
+        #[arg(long)]
+        avoid: Vec<String>,
+        /// Require the path to pass through this station
+        #[arg(long, conflicts_with_all = ["k", "avoid"])]
+        via: Option<String>,
+        /// Print the Pareto front over delay, hops and transfers instead of a single path
+        #[arg(long)]
+        pareto: bool,
+        /// Maximum segments per journey for --pareto
+        #[arg(long, default_value_t = 30)]
+        max_hops: usize,
+        /// Also write the paths to this KML file for Google Earth or Maps (requires --coords)
+        #[arg(long)]
+        kml: Option<String>,
+    },
+    /// List every station reachable from one station within a budget of accumulated delay
+    Isochrone {
+        from: String,
+        /// Delay budget in minutes along the least-delay path
+        #[arg(long, default_value_t = 10.0)]
+        within: f32,
+        /// Also write the stations and their least-delay paths to this KML file (requires --coords)
+        #[arg(long)]
+        kml: Option<String>,
+    },
+    /// Simulate an itinerary's total delay from each segment's delay histogram, reporting its percentiles and the
+    /// chance of exceeding --threshold
+    ///
+    /// Consecutive stations without a direct segment are joined by their least-delay path
+    Simulate {
+        /// Stations in travel order, by name or stop ID
+        #[arg(required = true, num_args = 2..)]
+        stations: Vec<String>,
+        /// Number of simulated journeys
+        #[arg(long, default_value_t = sim::DEFAULT_RUNS)]
+        runs: usize,
+        /// Total delay in minutes whose exceedance probability is reported
+        #[arg(long, default_value_t = 15.0)]
+        threshold: f32,
+        /// Width in minutes of the bins of each segment's delay histogram
+        #[arg(long, default_value_t = sim::DEFAULT_BIN_WIDTH)]
+        bin_width: f32,
+    },
+    /// Take a station or route out of the network, or add a hypothetical connection, and report what changes:
+    /// reachability, least delays between the stations in both networks, the worst least-delay journey, and centrality
+    #[command(name = "what-if", group(clap::ArgGroup::new("change").required(true).multiple(true).args(["remove_station", "remove_route", "connect"])))]
+    WhatIf {
+        /// Station to remove, with every segment into and out of it
+        #[arg(long, value_name = "STATION")]
+        remove_station: Option<String>,
+        /// Directed segment to remove
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        remove_route: Option<Vec<String>>,
+        /// Hypothetical direct connection to add, e.g. a new transfer or a restored line
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"], requires = "minutes")]
+        connect: Option<Vec<String>>,
+        /// Assumed delay in minutes on the --connect segment
+        #[arg(long, requires = "connect")]
+        minutes: Option<f32>,
+        /// Also add the --connect segment in the opposite direction
+        #[arg(long, requires = "connect")]
+        both_ways: bool,
+    },
+    /// Scale the delays of chosen lines or segments, optionally only in a time window of the day, and report how least
+    /// delays between stations, the worst least-delay journey, centrality, and segment average delays change
+    ///
+    /// Each injection is TARGET:CHANGE[@WINDOW]: TARGET is a line name, FROM>TO for a segment or * for every trip;
+    /// CHANGE is +N%, -N% or xF; WINDOW is am-peak, pm-peak or HH:MM-HH:MM of the scheduled time.
+    /// E.g. "Northeast Corrdr:+30%@pm-peak". Only late trains are scaled
+    Inject {
+        /// Delay injections, applied together; a trip matched by several gets every multiplier
+        #[arg(required = true, value_name = "INJECTION")]
+        injections: Vec<inject::Injection>,
+    },
+    /// Estimate passenger flows with a gravity model (station trip volumes as masses) assigned onto least-delay paths,
+    /// and rank segments by the passenger-minutes of delay they cause
+    Flows {
+        /// Total passengers the origin-destination demand is scaled to
+        #[arg(long, default_value_t = flows::DEFAULT_PASSENGERS)]
+        passengers: f32,
+        /// Distance-decay exponent on the segments between two stations (0 ignores distance)
+        #[arg(long, default_value_t = flows::DEFAULT_BETA)]
+        beta: f32,
+    },
+    /// Recommend where to change trains: score every interchange between two stations on expected delay and
+    /// missed-connection risk from the historical delays, for one pair or the busiest gravity-model pairs
+    Transfers {
+        /// Origin and destination; without them, the --pairs busiest pairs with a station between them
+        #[arg(num_args = 2, value_names = ["FROM", "TO"])]
+        pair: Option<Vec<String>>,
+        /// Number of busiest origin-destination pairs to evaluate
+        #[arg(long, default_value_t = transfers::DEFAULT_PAIRS, conflicts_with = "pair")]
+        pairs: usize,
+        /// Minutes of lateness an arrival can absorb before the connection is missed
+        #[arg(long, default_value_t = transfers::DEFAULT_SLACK)]
+        slack: f32,
+        /// Minutes waited for the next train after a missed connection
+        #[arg(long, default_value_t = transfers::DEFAULT_MISS_PENALTY)]
+        miss_penalty: f32,
+    },
+    /// Recommend schedule padding: the minutes each line's segments would need added for a target share of trips to
+    /// arrive within the on-time threshold (--threshold), and the runtime that adds per line
+    Padding {
+        /// On-time rate to reach, in (0, 1]
+        #[arg(long, default_value_t = padding::DEFAULT_TARGET)]
+        target: f32,
+    },
+    /// Remove stations one at a time, randomly and by descending betweenness, and report the giant component and
+    /// average least delay after each removal, with a robustness index per strategy
+    Robustness {
+        /// Share of the stations to remove, in (0, 1]
+        #[arg(long, default_value_t = robustness::DEFAULT_FRACTION)]
+        fraction: f32,
+        /// Random removal orders to average (seeded by --seed)
+        #[arg(long, default_value_t = robustness::DEFAULT_TRIALS)]
+        trials: usize,
+        /// Also draw the curves to this image file (.svg or .png)
+        #[cfg(feature = "charts")]
+        #[arg(long)]
+        image: Option<String>,
+    },
+    /// Print a single ranking
+    Rank {
+        #[arg(value_enum)]
+        ranking: Ranking,
+        /// Write typed rows (rank, station or route, score, trips) to this CSV file instead of printing
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Print the ranking of a custom metric registered by the binary (none in the stock build)
+    Metric {
+        /// Registered metric name
+        name: String,
+        /// Write typed rows (rank, station or route, score) to this CSV file instead of printing
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Compare two datasets or two periods: headline numbers, route delay shifts, and centrality shifts
+    Compare {
+        /// First dataset; defaults to --data
+        #[arg(long)]
+        a: Option<String>,
+        /// Second dataset; defaults to --data
+        #[arg(long)]
+        b: Option<String>,
+        /// Date range for the first side, as START..END (YYYY-MM-DD)
+        #[arg(long, value_parser = parse_period)]
+        period_a: Option<(NaiveDate, NaiveDate)>,
+        /// Date range for the second side, as START..END (YYYY-MM-DD)
+        #[arg(long, value_parser = parse_period)]
+        period_b: Option<(NaiveDate, NaiveDate)>,
+    },
+    /// Compare two operators (e.g. NJ Transit and Amtrak) on the stations and corridors both serve; ignores --type
+    Operators {
+        /// Second operator's dataset; without it, --data is split by its `type` column into its two largest operators
+        #[arg(long)]
+        other: Option<String>,
+        /// Tag every --data record with this operator, replacing its `type`
+        #[arg(long, requires = "other")]
+        operator: Option<String>,
+        /// Tag every --other record with this operator, replacing its `type`
+        #[arg(long, requires = "other")]
+        other_operator: Option<String>,
+    },
+    /// Watch a directory of CSV files and re-emit headline metrics whenever files are added or change
+    Watch {
+        dir: String,
+        /// Seconds between directory scans
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        #[command(flatten)]
+        alerts: AlertArgs,
+        #[command(flatten)]
+        mqtt: MqttArgs,
+    },
+    /// Fetch a data source periodically, accumulating new records and a daily snapshot in a state directory
+    #[cfg(feature = "async")]
+    Daemon {
+        /// Source to fetch: a local CSV path, an http(s):// URL, or s3://bucket/key of a public object
+        source: String,
+        /// Directory for the accumulated records.csv and the snapshots/ of each day
+        #[arg(long, default_value = "nj-delays-state")]
+        state_dir: String,
+        /// Seconds between fetches
+        #[arg(long, default_value_t = 3600)]
+        every: u64,
+        /// Days of snapshots to keep
+        #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+        keep_days: u64,
+        #[command(flatten)]
+        mqtt: MqttArgs,
+    },
+    /// Fetch a station's current departures from NJ Transit's rail data API and compare their delays with --data
+    ///
+    /// Credentials come from NJT_RAIL_TOKEN, or NJT_RAIL_USERNAME and NJT_RAIL_PASSWORD to sign in for a token
+    #[cfg(feature = "async")]
+    Departures {
+        /// Two-letter station code, e.g. NY for New York Penn Station
+        station: String,
+        /// Base URL of the API's train data endpoints
+        #[arg(long, default_value = departures::DEFAULT_API_URL)]
+        api_url: String,
+        /// Poll every this many seconds instead of fetching once
+        #[arg(long)]
+        every: Option<u64>,
+        /// Also append the departures as records to this CSV file (the dataset's format), e.g. in a `watch` directory
+        #[arg(long)]
+        save: Option<String>,
+    },
+    /// Check a data file for unreadable rows and data-quality issues without running any analysis
+    Validate {
+        /// File to check; defaults to --data
+        file: Option<String>,
+    },
+    /// Drill down into one station: centrality, inbound/outbound routes, busiest hours, and worst days
+    Station {
+        /// Station name or stop ID
+        name: String,
+    },
+    /// List or look up stations
+    Stations {
+        #[command(subcommand)]
+        action: StationsCommand,
+    },
+    /// List routes with delay statistics
+    Routes {
+        #[command(subcommand)]
+        action: RoutesCommand,
+    },
+    /// Average delay by scheduled hour × weekday for the network, one line (--line), or one route
+    Heatmap {
+        /// Restrict to one route instead of the whole network (use the global --line for one line)
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        route: Option<Vec<String>>,
+        /// Write the matrix as CSV to this file instead of printing it
+        #[arg(long)]
+        out: Option<String>,
+        /// Also draw the heatmap to this image file (.svg or .png)
+        #[cfg(feature = "charts")]
+        #[arg(long)]
+        image: Option<String>,
+    },
+    /// Distribution of delays as a terminal bar chart for the network, one line (--line), or one route
+    Histogram {
+        /// Restrict to one route instead of the whole network (use the global --line for one line)
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        route: Option<Vec<String>>,
+        /// Width of each bin in minutes
+        #[arg(long, default_value_t = 5.0)]
+        bin_width: f32,
+        /// Delays beyond this many minutes share one overflow bin
+        #[arg(long, default_value_t = 60.0)]
+        max: f32,
+    },
+    /// Replay per-route delays frame by frame as GeoJSON Lines or an animated GIF (requires --coords)
+    Playback {
+        /// Length of each frame
+        #[arg(long, value_enum, default_value_t = FrameStep::Day)]
+        step: FrameStep,
+        /// Output file: .gif draws an animation (needs the `charts` feature), anything else gets GeoJSON Lines; stdout if omitted
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Run a bundle of analyses and write each to its own file (format set by --output)
+    Report {
+        /// Which analyses to include
+        #[arg(long, value_enum, default_value_t = report::Preset::Quick)]
+        preset: report::Preset,
+        /// Directory to write the report files into; created if missing
+        #[arg(long, default_value = "report")]
+        out_dir: String,
+        /// Also write the whole report as one self-contained report.html with charts
+        #[arg(long)]
+        html: bool,
+        /// Also write the whole report as one report.md, ready to paste into an issue or wiki
+        #[arg(long)]
+        markdown: bool,
+        /// Also write the whole report as a paginated report.pdf with charts and tables
+        #[arg(long)]
+        pdf: bool,
+        /// Also draw the worst-route, monthly-trend and delay-histogram charts as image files
+        #[cfg(feature = "charts")]
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        charts: Option<charts::ChartFormat>,
+        /// Email a digest (worst routes, on-time trend, anomalies on the latest day) with report.html attached to this
+        /// address; repeatable (needs the `email` feature)
+        #[arg(long, value_name = "ADDRESS")]
+        email_to: Vec<String>,
+        /// SMTP server to send the digest through: smtp://[USER:PASSWORD@]HOST[:PORT] (STARTTLS) or smtps://... (TLS)
+        #[arg(long, value_name = "URL", requires = "email_to")]
+        smtp: Option<String>,
+        /// Sender address of the digest
+        #[arg(long, value_name = "ADDRESS", default_value = "nj-delays@localhost")]
+        email_from: String,
+    },
+    /// Write a browsable static site: one profile page per station (centrality, monthly trend, worst routes, busiest hours) plus an index
+    Profiles {
+        /// Directory to write the pages into; created if missing
+        #[arg(long, default_value = "profiles")]
+        out_dir: String,
+        /// Write Markdown pages instead of HTML
+        #[arg(long)]
+        markdown: bool,
+    },
+    /// Browse rankings and station details in an interactive terminal dashboard
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Serve rankings, paths and station data as a JSON HTTP API, loading the data once, with Prometheus metrics at /metrics
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// Directory of CSV files to watch, pushing per-line delay summaries and anomaly alerts to /live clients
+        #[arg(long)]
+        watch: Option<String>,
+        /// Seconds between scans of --watch
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Require `Authorization: Bearer TOKEN` on /admin routes (POST /admin/reload re-reads --data)
+        #[arg(long)]
+        admin_token: Option<String>,
+        #[command(flatten)]
+        alerts: AlertArgs,
+    },
+    /// Write metrics to CSV, the graph to GraphML or GeoJSON, or everything to one JSON report
+    Export {
+        #[arg(value_enum)]
+        kind: ExportKind,
+        /// Output file; stdout if omitted (no short flag: -o is the global --output)
+        #[arg(long)]
+        out: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StationsCommand {
+    /// Case-insensitive substring and fuzzy search over station names
+    Find {
+        query: String,
+    },
+    /// List every station with degree, trip volume, average inbound delay, and lines served
+    List {
+        /// Column to sort by
+        #[arg(long, value_enum, default_value_t = StationColumn::Station)]
+        sort: StationColumn,
+        /// Sort in descending order
+        #[arg(long)]
+        desc: bool,
+        /// Page to show, starting at 1
+        #[arg(long, default_value_t = 1)]
+        page: usize,
+        /// Stations per page
+        #[arg(long, default_value_t = 25)]
+        per_page: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RoutesCommand {
+    /// List every route meeting --min-trips with trips, mean, median and p90 delay, and on-time rate
+    List {
+        /// Column to sort by
+        #[arg(long, value_enum, default_value_t = RouteColumn::MeanDelay)]
+        sort_by: RouteColumn,
+        /// Sort in ascending order (default is descending, worst first)
+        #[arg(long)]
+        asc: bool,
+        /// Page to show, starting at 1
+        #[arg(long, default_value_t = 1)]
+        page: usize,
+        /// Routes per page
+        #[arg(long, default_value_t = 25)]
+        per_page: usize,
+        /// Add a sparkline of each route's monthly average delay
+        #[arg(long)]
+        trend: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Ranking {
+    /// Stations by delay-weighted closeness centrality
+    Closeness,
+    /// Stations by unweighted betweenness centrality
+    Betweenness,
+    /// Routes with the highest average delay
+    WorstRoutes,
+    /// Routes with the lowest average delay
+    BestRoutes,
+    /// Stations by trips per day times average delay
+    Congestion,
+    /// Routes by estimated passenger-minutes of delay (gravity-model riders times average delay)
+    PassengerDelay,
+    /// Routes by forecast next-week delay
+    Forecast,
+    /// Predicted new connections by Adamic-Adar score
+    Links,
+}
+
+// Format of error reports selected by --error-format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// "error: ..." lines
+    #[default]
+    Text,
+    /// One JSON object: {"error": {"kind", "code", "message", ...}}
+    Json,
+}
+
+// Process exit codes, one per failure class
+pub const EXIT_INTERNAL: i32 = 1;
+pub const EXIT_BAD_INPUT: i32 = 2; // Matches clap's exit code for usage errors
+pub const EXIT_UNKNOWN_STATION: i32 = 3;
+pub const EXIT_NO_PATH: i32 = 4;
+
+// A command failure, mapped onto a distinct exit code
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    BadInput(String), // Unreadable or invalid data files, arguments, or output paths
+    UnknownStation { station: String, suggestions: Vec<String> },
+    NoPath { from: String, to: String },
+    Internal(String), // Anything else, including panics
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::BadInput(_) => EXIT_BAD_INPUT,
+            CliError::UnknownStation { .. } => EXIT_UNKNOWN_STATION,
+            CliError::NoPath { .. } => EXIT_NO_PATH,
+            CliError::Internal(_) => EXIT_INTERNAL,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::BadInput(_) => "bad_input",
+            CliError::UnknownStation { .. } => "unknown_station",
+            CliError::NoPath { .. } => "no_path",
+            CliError::Internal(_) => "internal",
+        }
+    }
+
+    // The error as a JSON object, with the variant's fields alongside kind, code, and message
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut error = serde_json::json!({ "kind": self.kind(), "code": self.exit_code(), "message": self.to_string() });
+        match self {
+            CliError::UnknownStation { station, suggestions } => {
+                error["station"] = station.as_str().into();
+                error["suggestions"] = suggestions.clone().into();
+            }
+            CliError::NoPath { from, to } => {
+                error["from"] = from.as_str().into();
+                error["to"] = to.as_str().into();
+            }
+            CliError::BadInput(_) | CliError::Internal(_) => {}
+        }
+        serde_json::json!({ "error": error })
+    }
+
+    // Writes the error to stderr in the given format
+    pub fn report(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Text => eprintln!("error: {}", self),
+            ErrorFormat::Json => eprintln!("{}", self.to_json()),
+        }
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::UnknownStation { station, suggestions } => {
+                CliError::UnknownStation { station, suggestions: suggestions.into_iter().map(|s| s.name).collect() }
+            }
+            Error::Load { .. } | Error::Fetch { .. } | Error::EmptyGraph { .. } | Error::InvalidParameter { .. } => CliError::BadInput(error.to_string()),
+            Error::Cancelled | Error::Notify { .. } => CliError::Internal(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::BadInput(message) | CliError::Internal(message) => write!(f, "{}", message),
+            CliError::UnknownStation { station, suggestions } if suggestions.is_empty() => write!(f, "unknown station {:?}", station),
+            CliError::UnknownStation { station, suggestions } => {
+                write!(f, "unknown station {:?}; did you mean {}?", station, suggestions.join(", "))
+            }
+            CliError::NoPath { from, to } => write!(f, "no path found from {} to {}", from, to),
+        }
+    }
+}
+
+// Parses arguments, runs the command, and reports any failure
+// Output: the process exit code
+pub fn execute() -> i32 {
+    execute_with(&MetricRegistry::default())
+}
+
+// Like `execute`, with custom metrics that `metric`, `analyze` and `report` include alongside the built-in ones
+pub fn execute_with(registry: &MetricRegistry) -> i32 {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() && requested_error_format() == ErrorFormat::Json => {
+            CliError::BadInput(e.to_string().trim_end().to_string()).report(ErrorFormat::Json);
+            return EXIT_BAD_INPUT;
+        }
+        Err(e) => e.exit(), // Help, version, and text usage errors print as clap formats them
+    };
+    let error_format = cli.error_format;
+    // Panics are internal errors: report them in the requested format with the internal exit code
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match error_format {
+            ErrorFormat::Text => default_hook(info),
+            ErrorFormat::Json => CliError::Internal(info.to_string()).report(ErrorFormat::Json),
+        }
+        std::process::exit(EXIT_INTERNAL);
+    }));
+    match run(cli, registry) {
+        Ok(()) => 0,
+        Err(e) => {
+            e.report(error_format);
+            e.exit_code()
+        }
+    }
+}
+
+// Looks for --error-format in the raw arguments, for reporting errors from argument parsing itself
+fn requested_error_format() -> ErrorFormat {
+    let args: Vec<String> = std::env::args().collect();
+    let json = args.iter().enumerate().any(|(i, a)| {
+        a == "--error-format=json" || (a == "--error-format" && args.get(i + 1).is_some_and(|v| v == "json"))
+    });
+    if json { ErrorFormat::Json } else { ErrorFormat::Text }
+}
+
+// Parses a START..END date range
+fn parse_period(s: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let (start, end) = s.split_once("..").ok_or("expected START..END, e.g. 2018-01-01..2018-12-31")?;
+    let parse = |d: &str| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|e| format!("{:?}: {}", d, e));
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("{} is after {}", start, end));
+    }
+    Ok((start, end))
+}
+
+impl Cli {
+    // Collects the ranking parameters shared by every command
+    pub fn rank_options(&self) -> RankOptions {
+        RankOptions {
+            top_n: self.top,
+            min_trips: self.min_trips,
+            on_time_threshold: self.on_time_threshold,
+            seed: self.seed,
+            path_algorithm: self.path_algorithm,
+            prune: self.prune,
+        }
+    }
+
+    // Maps -q / -v / -vv onto an output verbosity
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+
+    // Collects the dataset filter flags, applied before the graph is built
+    pub fn record_filter(&self) -> RecordFilter {
+        RecordFilter {
+            lines: self.line.clone(),
+            r#type: self.service_type.clone(),
+            from_date: self.from_date,
+            to_date: self.to_date,
+        }
+    }
+}
+
+// When `watch` and `serve --watch` flag new trips as anomalies, and where they post alerts
+#[derive(Debug, Clone, clap::Args)]
+pub struct AlertArgs {
+    /// Post an alert to this webhook when new trips on a line or route run later than before beyond the thresholds
+    /// (needs the `async` feature)
+    #[arg(long, value_name = "URL")]
+    pub alert_webhook: Option<String>,
+    /// Message format of --alert-webhook
+    #[arg(long, value_enum, default_value_t)]
+    pub alert_format: WebhookFormat,
+    /// Standard errors above the earlier mean delay for new trips to count as an anomaly
+    #[arg(long, default_value_t = live::ANOMALY_Z_SCORE)]
+    pub alert_z: f32,
+    /// Minutes above the earlier mean delay for new trips to count as an anomaly
+    #[arg(long, value_name = "MINUTES", default_value_t = 0.0)]
+    pub alert_min_excess: f32,
+}
+
+impl AlertArgs {
+    pub fn thresholds(&self) -> AnomalyThresholds {
+        AnomalyThresholds { z_score: self.alert_z, min_excess: self.alert_min_excess }
+    }
+
+    // The webhook to alert, if one is set; bad input without the `async` feature, which sending needs
+    pub fn alerter(&self) -> Result<Option<WebhookAlerter>, CliError> {
+        match &self.alert_webhook {
+            Some(_) if !cfg!(feature = "async") => Err(CliError::BadInput("--alert-webhook needs a build with --features async".into())),
+            Some(url) => Ok(Some(WebhookAlerter::new(url, self.alert_format))),
+            None => Ok(None),
+        }
+    }
+}
+
+// Where `watch` and `daemon` publish station and line delay state after each ingest
+#[derive(Debug, Clone, clap::Args)]
+pub struct MqttArgs {
+    /// Publish each station's and line's expected delay as retained JSON to this broker, under PREFIX/station/NAME and
+    /// PREFIX/line/NAME (default prefix njtransit); credentials come from NJT_MQTT_USERNAME and NJT_MQTT_PASSWORD
+    /// (needs the `mqtt` feature)
+    #[arg(long, value_name = "mqtt://HOST[:PORT][/PREFIX]")]
+    pub mqtt: Option<String>,
+}
+
+impl MqttArgs {
+    // The broker to publish to, if one is set; bad input without the `mqtt` feature, which publishing needs
+    pub fn publisher(&self) -> Result<Option<MqttPublisher>, CliError> {
+        let Some(url) = &self.mqtt else { return Ok(None) };
+        if !cfg!(feature = "mqtt") {
+            return Err(CliError::BadInput("--mqtt needs a build with --features mqtt".into()));
+        }
+        let publisher = MqttPublisher::parse(url).map_err(|e| CliError::BadInput(e.to_string()))?;
+        Ok(Some(match (std::env::var("NJT_MQTT_USERNAME"), std::env::var("NJT_MQTT_PASSWORD")) {
+            (Ok(user), Ok(password)) => publisher.with_credentials(user, password),
+            (Ok(user), Err(_)) => publisher.with_credentials(user, ""),
+            _ => publisher,
+        }))
+    }
+}
+
+// Loads the dataset and runs the selected command, with the custom metrics in `registry`
+pub(crate) fn run(cli: Cli, registry: &MetricRegistry) -> Result<(), CliError> {
+    let opts = cli.rank_options();
+    let format = cli.output;
+    set_verbosity(cli.verbosity());
+    set_table_style(TableStyle { max_width: cli.max_width, color: cli.color, delay_threshold: opts.on_time_threshold });
+    let started = Instant::now();
+    if let Command::Validate { file } = &cli.command {
+        let path = file.as_deref().unwrap_or(&cli.data);
+        let (summary, issues) = validate::validate_file(path)?;
+        let errors = summary.errors;
+        diagnostic(Verbosity::Verbose, &format!("Validated {} rows in {:.1?}", summary.rows, started.elapsed()));
+        emit(format, "Validation summary:", &[summary]);
+        emit(format, "Row-level issues:", &issues);
+        if errors > 0 {
+            return Err(CliError::BadInput(format!("{} failed validation with {} errors", path, errors)));
+        }
+        return Ok(());
+    }
+    if let Some(url) = &cli.sink {
+        if !url.starts_with("postgres://") && !url.starts_with("postgresql://") {
+            return Err(CliError::BadInput(format!("--sink expects a postgres:// URL, not {:?}", url)));
+        }
+        if !cfg!(feature = "postgres") {
+            return Err(CliError::BadInput("--sink needs a build with --features postgres".into()));
+        }
+        if cli.stream {
+            return Err(CliError::BadInput("--sink needs the loaded records; run without --stream".into()));
+        }
+    }
+    let job = rank_job(&cli);
+    // A run with --sink loads the data even when the ranking is cached, since the sink writes its metrics
+    if let Some(Section { title, rows }) = job.as_ref().filter(|_| cli.sink.is_none()).and_then(RankJob::cached) {
+        emit(format, &title, &rows);
+        return Ok(());
+    }
+    if !cli.stream && events::EventSource::parse(&cli.data)?.is_some() {
+        return Err(CliError::BadInput(format!("{} is an event stream; read it with --stream", cli.data)));
+    }
+    if cli.stream {
+        run_streaming(cli, registry, format, job.as_ref())?;
+        diagnostic(Verbosity::Verbose, &format!("Finished in {:.1?}", started.elapsed()));
+        return Ok(());
+    }
+    if let Command::Compare { a, b, period_a, period_b } = &cli.command {
+        let filter = cli.record_filter();
+        let [side_a, side_b] = [("A", a, period_a), ("B", b, period_b)].map(|(side, file, period)| {
+            let path = file.as_deref().unwrap_or(&cli.data);
+            let filter = match period {
+                Some((start, end)) => RecordFilter { from_date: Some(*start), to_date: Some(*end), ..filter.clone() },
+                None => filter.clone(),
+            };
+            let source = match period {
+                Some((start, end)) => format!("{} {}..{}", path, start, end),
+                None => path.to_string(),
+            };
+            let (records, graph) = load_graph(path, &filter)?;
+            Ok::<_, CliError>((side, source, records, graph))
+        });
+        compare(&[side_a?, side_b?], &opts, format);
+        return Ok(());
+    }
+    if let Command::Operators { other, operator, other_operator } = &cli.command {
+        let filter = RecordFilter { r#type: None, ..cli.record_filter() };
+        let load = |path: &str, operator: &Option<String>| {
+            let (mut records, _) = load_graph(path, &filter)?;
+            if let Some(operator) = operator {
+                operators::tag_operator(&mut records, operator);
+            }
+            Ok::<_, CliError>(records)
+        };
+        let sides = match other {
+            Some(other) => vec![(cli.data.clone(), load(&cli.data, operator)?), (other.clone(), load(other, other_operator)?)]
+                .into_iter()
+                .flat_map(|(source, records)| operators::split_operators(records).into_iter().map(move |(op, r)| (op, source.clone(), r)))
+                .collect::<Vec<_>>(),
+            None => operators::split_operators(load(&cli.data, &None)?).into_iter().map(|(op, r)| (op, cli.data.clone(), r)).collect(),
+        };
+        let [a, b] = match other {
+            Some(_) => two_operators(sides, "--data and --other each need records of a single operator; tag them with --operator and --other-operator")?,
+            None => two_operators(sides.into_iter().take(2).collect(), "--data has records of only one operator; pass another operator's dataset with --other")?,
+        };
+        compare_operators(&[a, b], &opts, format);
+        return Ok(());
+    }
+    if let Command::Watch { dir, interval, alerts, mqtt } = &cli.command {
+        let feed = live::LiveFeed::new(dir, cli.record_filter(), opts).with_thresholds(alerts.thresholds());
+        let (alerter, publisher) = (alerts.alerter()?, mqtt.publisher()?);
+        return watch_directory(feed, Duration::from_secs(*interval), alerter.as_ref(), publisher.as_ref(), &opts, format);
+    }
+    #[cfg(feature = "async")]
+    if let Command::Daemon { source, state_dir, every, keep_days, mqtt } = &cli.command {
+        let publisher = mqtt.publisher()?;
+        let ingestor = daemon::Ingestor::open(state_dir, *keep_days as usize, cli.record_filter(), opts)?;
+        return run_daemon(source, ingestor, Duration::from_secs(*every), publisher.as_ref(), &opts, format);
+    }
+    let (records, graph) = load_graph(&cli.data, &cli.record_filter())?;
+    #[cfg(feature = "async")]
+    if let Command::Departures { station, api_url, every, save } = &cli.command {
+        return run_departures(station, api_url, &graph, every.map(Duration::from_secs), save.as_deref(), format);
+    }
+    #[cfg(feature = "serve")]
+    if let Command::Serve { addr, watch, interval, admin_token, alerts } = &cli.command {
+        let alerter = alerts.alerter()?;
+        let feed = watch.as_ref().map(|dir| {
+            let feed = live::LiveFeed::new(dir, cli.record_filter(), opts).with_thresholds(alerts.thresholds());
+            (feed, Duration::from_secs(*interval), alerter)
+        });
+        let (data, filter) = (cli.data.clone(), cli.record_filter());
+        let mut state = server::ServerState::new(records, graph, opts).with_loader(Box::new(move || load_graph(&data, &filter)));
+        if let Some(token) = admin_token {
+            state = state.with_admin_token(token);
+        }
+        return serve(addr, state, feed);
+    }
+    let coords = match &cli.coords {
+        Some(path) => Some(load_coordinates(path)?),
+        None => None,
+    };
+    let metadata = ReportMetadata::new(&cli.data, &cli.record_filter(), &opts, &records);
+    let mem_stats = cli.mem_stats;
+    match (&job, cli.command) {
+        (Some(job), Command::Rank { ranking, .. }) => job.run(ranking, &records, &graph, &opts, format)?,
+        (_, command) => run_command(command, &records, &graph, coords.as_ref(), &metadata, registry, format)?,
+    }
+    if mem_stats {
+        emit(format, "Estimated memory use:", &memory::estimate(&records, &graph));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(url) = &cli.sink {
+        let report = crate::sink::write_metrics(url, &records, &graph, &metadata)
+            .map_err(|e| CliError::BadInput(format!("cannot write metrics to the --sink database: {}", e)))?;
+        note(&format!("Wrote {} route and {} line metrics for the run at {} to the --sink database", report.routes, report.lines, report.run_at.format("%Y-%m-%d %H:%M:%S %:z")));
+    }
+    diagnostic(Verbosity::Verbose, &format!("Finished in {:.1?}", started.elapsed()));
+    Ok(())
+}
+
+// Runs a command on a one-pass stream of the dataset (--stream), for the commands that need only the graph or the
+// running totals; any other command is a bad-input error rather than a silently partial answer
+fn run_streaming(cli: Cli, registry: &MetricRegistry, format: OutputFormat, job: Option<&RankJob>) -> Result<(), CliError> {
+    let opts = cli.rank_options();
+    let (bin_width, max) = match &cli.command {
+        Command::Histogram { route: None, bin_width, max } => (*bin_width, *max),
+        Command::Rank { ranking: Ranking::Closeness | Ranking::Betweenness | Ranking::WorstRoutes | Ranking::BestRoutes | Ranking::Links, .. }
+        | Command::Metric { .. }
+        | Command::Path { .. }
+        | Command::Isochrone { .. } => (stream::DEFAULT_BIN_WIDTH, stream::DEFAULT_HISTOGRAM_MAX),
+        _ => return Err(CliError::BadInput(
+            "--stream supports rank closeness|betweenness|worst-routes|best-routes|links, metric, path, isochrone, and histogram without --route".into(),
+        )),
+    };
+    let filter = cli.record_filter();
+    let aggregator = StreamAggregator::new(opts.on_time_threshold, bin_width, max)?;
+    let (summary, graph) = match events::EventSource::parse(&cli.data)? {
+        Some(source) => {
+            let limits = events::EventLimits { max_records: cli.max_events, idle: Duration::from_secs(cli.idle_timeout) };
+            pipeline::stream_events(&source, &limits, &filter, aggregator, &Diagnostics)?
+        }
+        None => pipeline::stream_graph(&cli.data, &filter, aggregator, &Diagnostics)?,
+    };
+    if let Command::Histogram { .. } = cli.command {
+        emit_histogram(&format!("Delay distribution (minutes), network ({} trips):", summary.delayed), &summary.histogram, format);
+        return Ok(());
+    }
+    let coords = cli.coords.as_deref().map(load_coordinates).transpose()?;
+    // The supported commands read only the graph, so no records are passed on
+    let metadata = ReportMetadata { records: summary.records, ..ReportMetadata::new(&cli.data, &filter, &opts, &[]) };
+    let mem_stats = cli.mem_stats;
+    match (job, cli.command) {
+        (Some(job), Command::Rank { ranking, .. }) => job.run(ranking, &[], &graph, &opts, format)?,
+        (_, command) => run_command(command, &[], &graph, coords.as_ref(), &metadata, registry, format)?,
+    }
+    if mem_stats {
+        emit(format, "Estimated memory use (records not held):", &memory::estimate(&[], &graph));
+    }
+    Ok(())
+}
+
+// A titled section of rows, as emitted and as stored in the result cache
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Section {
+    title: String,
+    rows: Vec<serde_json::Value>,
+}
+
+impl Section {
+    // Rows go through their JSON text rather than `to_value`, which would widen f32 fields and print 0.1 as
+    // 0.10000000149011612; parsed back, each number keeps the shortest form it was written in
+    fn new<T: Serialize>(title: String, rows: &[T]) -> Self {
+        let value = |row: &T| serde_json::to_string(row).and_then(|text| serde_json::from_str(&text)).unwrap_or_default();
+        Self { title, rows: rows.iter().map(value).collect() }
+    }
+}
+
+// What determines a printed ranking, besides the data file's contents
+#[derive(Serialize)]
+struct RankingParameters {
+    ranking: String,
+    filter: RecordFilter,
+    options: RankOptions,
+}
+
+// A `rank` command with its place in the cache directory: the result cache entry, used when the ranking is printed
+// rather than written with --out, and the checkpoint file of closeness and betweenness
+struct RankJob {
+    cache: ResultCache,
+    key: CacheKey, // Hash of the data file and the parameters; names both files
+    parameters: RankingParameters,
+    out: Option<String>,
+    use_cache: bool, // False with --no-cache or --out
+    resume: bool,
+}
+
+impl RankJob {
+    // The stored section, if this run may use the cache and one exists
+    fn cached(&self) -> Option<Section> {
+        let section = self.cache.get(self.key).filter(|_| self.use_cache)?;
+        diagnostic(Verbosity::Verbose, &format!("Read ranking from {}", self.cache.path(self.key).display()));
+        Some(section)
+    }
+
+    // Checkpoints for the per-source rankings; the others finish in seconds
+    fn checkpointer(&self, ranking: Ranking) -> Option<Checkpointer> {
+        let path = self.cache.dir().join("checkpoints").join(format!("{}.json", self.key));
+        matches!(ranking, Ranking::Closeness | Ranking::Betweenness).then(|| Checkpointer::new(path, DEFAULT_CHECKPOINT_INTERVAL, self.resume))
+    }
+
+    // Computes the ranking, checkpointing long ones, and prints and caches it or writes it to the --out file
+    // A cache that cannot be written only costs the next run time, so that is a diagnostic rather than an error
+    fn run(&self, ranking: Ranking, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) -> Result<(), CliError> {
+        let mut checkpoints = self.checkpointer(ranking);
+        if let Some(path) = &self.out {
+            let file = File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+            let rows = rank_csv(ranking, records, graph, opts, checkpoints.as_mut(), file)
+                .map_err(|e| CliError::Internal(format!("failed to write {}: {}", path, e)))?;
+            note(&format!("Wrote {} rows to {}", rows, path));
+            return Ok(());
+        }
+        let section = ranking_section(ranking, records, graph, opts, checkpoints.as_mut());
+        emit(format, &section.title, &section.rows);
+        if self.use_cache {
+            match self.cache.put(self.key, &self.parameters, &section) {
+                Ok(path) => diagnostic(Verbosity::Debug, &format!("Cached ranking in {}", path.display())),
+                Err(e) => diagnostic(Verbosity::Verbose, &format!("Could not write the result cache in {}: {}", self.cache.dir().display(), e)),
+            }
+        }
+        Ok(())
+    }
+}
+
+// The job of a `rank` command, or None without a cache directory or with unreadable data (loading then reports it)
+fn rank_job(cli: &Cli) -> Option<RankJob> {
+    let Command::Rank { ranking, out } = &cli.command else { return None };
+    let cache = cli.cache_dir.clone().map(ResultCache::new).or_else(ResultCache::user)?;
+    let parameters = RankingParameters {
+        ranking: ranking.to_possible_value().map_or(String::new(), |v| v.get_name().to_string()),
+        filter: cli.record_filter(),
+        options: cli.rank_options(),
+    };
+    let key = ResultCache::key(&[Path::new(&cli.data)], &parameters).ok()?;
+    Some(RankJob { cache, key, parameters, out: out.clone(), use_cache: !cli.no_cache && out.is_none(), resume: cli.resume })
+}
+
+// Reports pipeline events as -v / -vv diagnostics on stderr
+struct Diagnostics;
+
+impl Observer for Diagnostics {
+    fn on_records_loaded(&self, source: &str, records: &[TrainRecord], elapsed: Duration) {
+        diagnostic(Verbosity::Verbose, &format!("Loaded {} records from {} in {:.1?}", records.len(), source, elapsed));
+    }
+
+    fn on_records_filtered(&self, filter: &RecordFilter, kept: usize, total: usize) {
+        diagnostic(Verbosity::Verbose, &format!("Filter {:?} kept {} of {} records ({} dropped)", filter, kept, total, total - kept));
+    }
+
+    fn on_graph_built(&self, graph: &TransitGraph, records: &[TrainRecord], elapsed: Duration) {
+        for r in records.iter().filter(|r| r.delay().is_none()) {
+            diagnostic(Verbosity::Debug, &format!("Skipped row without delay: {} train {} {} → {}", r.date, r.train_id, r.from, r.to));
+        }
+        let edges = graph.edges().count();
+        diagnostic(Verbosity::Verbose, &format!(
+            "Built graph with {} stations and {} edges ({} records without delay skipped) in {:.1?}",
+            graph.all_stations().len(), edges, records.len() - edges, elapsed,
+        ));
+    }
+
+    fn on_events_skipped(&self, source: &str, skipped: usize, first_error: &str) {
+        diagnostic(Verbosity::Normal, &format!("Skipped {} messages from {} that are not train records; the first: {}", skipped, source, first_error));
+    }
+
+    fn on_records_streamed(&self, source: &str, read: usize, kept: usize, graph: &TransitGraph, elapsed: Duration) {
+        diagnostic(Verbosity::Verbose, &format!(
+            "Streamed {} records from {} ({} kept by the filter) into a graph with {} stations and {} edges in {:.1?}",
+            read, source, kept, graph.stations().count(), graph.edges().count(), elapsed,
+        ));
+    }
+
+    fn on_metric_resumed(&self, metric: &str, done: usize, total: usize) {
+        diagnostic(Verbosity::Verbose, &format!("Resuming {} from its checkpoint: {} of {} sources done", metric, done, total));
+    }
+
+    fn on_checkpoint_saved(&self, metric: &str, done: usize, total: usize, result: &io::Result<()>) {
+        match result {
+            Ok(()) => diagnostic(Verbosity::Debug, &format!("{}: checkpoint saved after {} of {} sources", metric, done, total)),
+            Err(e) => diagnostic(Verbosity::Verbose, &format!("{}: could not save a checkpoint ({}); continuing without", metric, e)),
+        }
+    }
+
+    fn on_metric_pruned(&self, metric: &str, cut: usize, total: usize) {
+        diagnostic(Verbosity::Verbose, &format!("{}: pruned {} of {} searches that could not reach the top", metric, cut, total));
+    }
+
+    // Quarter milestones only, so -vv stays readable on the full network
+    fn on_metric_progress(&self, metric: &str, done: usize, total: usize) {
+        if done == total || (total >= 4 && done.is_multiple_of(total / 4)) {
+            diagnostic(Verbosity::Debug, &format!("{}: {} of {} done", metric, done, total));
+        }
+    }
+}
+
+// Loads and filters one dataset and builds its graph, reporting timings and counts as diagnostics
+fn load_graph(path: &str, filter: &RecordFilter) -> Result<(Vec<TrainRecord>, TransitGraph), CliError> {
+    Ok(pipeline::load_graph(path, filter, &Diagnostics)?)
+}
+
+// Runs a command against the loaded records and graph
+fn run_command(
+    command: Command,
+    records: &[TrainRecord],
+    graph: &TransitGraph,
+    coords: Option<&Coordinates>,
+    metadata: &ReportMetadata,
+    registry: &MetricRegistry,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let opts = &metadata.parameters;
+    match command {
+        Command::Analyze => {
+            analyze(records, graph, opts, format);
+            for metric in registry.iter() {
+                emit_metric(registry, metric.name(), graph, opts, format)?;
+            }
+        }
+        Command::Path { from, to, k, avoid, via, pareto, max_hops, kml } => {
+            let (from, to) = (graph.resolve_station(&from)?, graph.resolve_station(&to)?);
+            let via = via.map(|name| graph.resolve_station(&name)).transpose()?;
+            let avoid = avoid.iter().map(|name| graph.resolve_station(name)).collect::<Result<HashSet<Station>, _>>()?;
+            let kml_coords = kml_coordinates(kml.as_deref(), coords)?;
+            let no_path = || CliError::NoPath { from: from.name.clone(), to: to.name.clone() };
+            let paths: Vec<(f32, Vec<Station>)> = if pareto {
+                let front = graph.pareto_paths(&from, &to, max_hops);
+                if front.is_empty() {
+                    return Err(no_path());
+                }
+                emit(format, &format!("Pareto-optimal journeys {} → {} (delay / hops / transfers):", from, to), &front);
+                front.into_iter().map(|p| (p.delay, p.stations)).collect()
+            } else {
+                let paths = match &via {
+                    Some(via) => graph.shortest_path_via(&from, via, &to).into_iter().collect(),
+                    None => graph.k_shortest_paths(&from, &to, k.max(1), &avoid),
+                };
+                if paths.is_empty() {
+                    return Err(no_path());
+                }
+                for (i, (delay, stations)) in paths.iter().enumerate() {
+                    let title = format!("Path {}: {:.2} minutes, {} stops ({})", i + 1, delay, stations.len(), join_names(stations, " → "));
+                    emit(format, &title, &graph.path_segments(stations));
+                }
+                paths
+            };
+            if let (Some(path), Some(coords)) = (&kml, kml_coords) {
+                let file = File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+                kml::write_paths(&paths, graph, coords, io::BufWriter::new(file))
+                    .map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+                note(&format!("Wrote {} paths to {}", paths.len(), path));
+            }
+        }
+        Command::Simulate { stations, runs, threshold, bin_width } => {
+            let stops = stations.iter().map(|name| graph.resolve_station(name)).collect::<Result<Vec<_>, _>>()?;
+            let mut itinerary = vec![stops[0].clone()];
+            for pair in stops.windows(2) {
+                let leg = match graph.observations(&pair[0], &pair[1]).next() {
+                    Some(_) => vec![pair[0].clone(), pair[1].clone()],
+                    None => graph.shortest_path(&pair[0], &pair[1])
+                        .ok_or_else(|| CliError::NoPath { from: pair[0].name.clone(), to: pair[1].name.clone() })?.1,
+                };
+                itinerary.extend(leg.into_iter().skip(1));
+            }
+            let options = sim::SimulationOptions { runs, bin_width, threshold, seed: opts.seed };
+            let report = sim::simulate(graph, &itinerary, &options)?;
+            emit(format, &format!("Simulated total delay of {} journeys:", runs), &[&report]);
+            emit_histogram("Distribution of total delay (minutes, up to the 99th percentile):", &report.distribution, format);
+        }
+        Command::WhatIf { remove_station, remove_route, connect, minutes, both_ways } => {
+            let mut scenario = whatif::Scenario::new(graph);
+            if let Some(station) = &remove_station {
+                scenario = scenario.remove(whatif::Element::Station(station))?;
+            }
+            if let Some(route) = &remove_route {
+                scenario = scenario.remove(whatif::Element::Route(&route[0], &route[1]))?;
+            }
+            if let Some(ends) = &connect {
+                let minutes = minutes.unwrap_or_default();
+                scenario = scenario.connect(&ends[0], &ends[1], minutes)?;
+                if both_ways {
+                    scenario = scenario.connect(&ends[1], &ends[0], minutes)?;
+                }
+            }
+            emit_impact(&scenario.evaluate()?, opts, format);
+        }
+        Command::Inject { injections } => {
+            let report = inject::run(records, graph, &injections, opts)?;
+            note(&format!("Scaled the delays of {} trips", report.records_changed));
+            emit_impact(&report.impact, opts, format);
+            emit(format, &format!("Top {} segments by change in average delay (baseline → injected):", opts.top_n), &report.route_shifts[..report.route_shifts.len().min(opts.top_n)]);
+        }
+        Command::Flows { passengers, beta } => {
+            let assignment = flows::assign_flows(records, graph, &flows::FlowOptions { passengers, beta })?;
+            emit(format, "Gravity-model passenger flows:", &[&assignment.summary]);
+            let segments: Vec<_> = assignment.segments.iter().filter(|s| s.trips >= opts.min_trips).take(opts.top_n).collect();
+            emit(format, &format!("Top {} segments by passenger-minutes of delay:", opts.top_n), &segments);
+        }
+        Command::Transfers { pair, pairs, slack, miss_penalty } => {
+            let options = transfers::TransferOptions { slack, miss_penalty, min_arrivals: opts.min_trips };
+            let plans = match &pair {
+                Some(ends) => {
+                    let (from, to) = (graph.resolve_station(&ends[0])?, graph.resolve_station(&ends[1])?);
+                    let plan = transfers::TransferPlanner::new(records, graph)?.plan(&from, &to, &options)?;
+                    if plan.recommendation.direct_delay.is_none() {
+                        return Err(CliError::NoPath { from: from.name, to: to.name });
+                    }
+                    vec![plan]
+                }
+                None => transfers::recommend_major(records, graph, pairs, &options)?,
+            };
+            let recommendations: Vec<_> = plans.iter().map(|p| &p.recommendation).collect();
+            emit(format, &format!("Recommended transfer points (expected cost = delay + miss risk × {} min):", miss_penalty), &recommendations);
+            for plan in &plans {
+                let title = format!("Top {} transfer points {} → {}:", opts.top_n, plan.recommendation.origin, plan.recommendation.destination);
+                emit(format, &title, &plan.options[..plan.options.len().min(opts.top_n)]);
+            }
+        }
+        Command::Padding { target } => {
+            let report = padding::recommend_padding(records, graph, target, opts)?;
+            let goal = format!("{:.0}% within {}", target * 100.0, describe_threshold(report.threshold));
+            emit(format, &format!("Schedule padding per line to reach {} (minutes added over every segment):", goal), &report.lines);
+            emit(format, &format!("Top {} segments by padding needed to reach {}:", opts.top_n, goal), &report.segments[..report.segments.len().min(opts.top_n)]);
+        }
+        Command::Robustness { fraction, trials, #[cfg(feature = "charts")] image } => {
+            let report = robustness::robustness(graph, &robustness::RobustnessOptions { fraction, trials, seed: opts.seed })?;
+            emit(format, "Robustness to station removal (index = mean giant-component share):", &[&report.summary]);
+            emit(format, "Giant-component share and mean least delay after each removal:", &report.points);
+            if format == OutputFormat::Table && verbosity() != Verbosity::Quiet {
+                let curve = |value: fn(&robustness::RobustnessPoint) -> Option<f32>| textplot::sparkline(&report.points.iter().map(value).collect::<Vec<_>>());
+                println!("Giant component, random:   {}", curve(|p| Some(p.random_giant)));
+                println!("Giant component, targeted: {}", curve(|p| Some(p.targeted_giant)));
+                println!("Mean delay, random:        {}", curve(|p| p.random_delay));
+                println!("Mean delay, targeted:      {}", curve(|p| p.targeted_delay));
+            }
+            #[cfg(feature = "charts")]
+            if let Some(path) = &image {
+                charts::render(Path::new(path), &report).map_err(|e| CliError::BadInput(e.to_string()))?;
+                note(&format!("Drew robustness curves to {}", path));
+            }
+        }
+        Command::Isochrone { from, within, kml } => {
+            let origin = graph.resolve_station(&from)?;
+            if !(within >= 0.0 && within.is_finite()) {
+                return Err(CliError::BadInput("--within must be a non-negative number of minutes".into()));
+            }
+            let kml_coords = kml_coordinates(kml.as_deref(), coords)?;
+            let reachable = graph.delay_isochrone(&origin, within);
+            emit(format, &format!("{} stations within {} minutes of delay from {}:", reachable.len(), within, origin), &reachable);
+            if let (Some(path), Some(coords)) = (&kml, kml_coords) {
+                let file = File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+                kml::write_isochrone(&reachable, within, coords, io::BufWriter::new(file))
+                    .map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+                note(&format!("Wrote isochrone to {}", path));
+            }
+        }
+        Command::Stations { action: StationsCommand::Find { query } } => {
+            emit(format, &format!("Stations matching {:?}:", query), &graph.find_stations(&query, opts.top_n));
+        }
+        Command::Stations { action: StationsCommand::List { sort, desc, page, per_page } } => {
+            let mut summaries = station_summaries(records, graph);
+            sort_summaries(&mut summaries, sort, desc);
+            let (rows, pages) = paginate(&summaries, page, per_page)
+                .ok_or_else(|| CliError::BadInput(format!("page {} is out of range", page)))?;
+            emit(format, &format!("Stations (page {} of {}, {} total):", page, pages, summaries.len()), rows);
+        }
+        Command::Station { name } => {
+            let station = graph.resolve_station(&name)?;
+            let detail = station_detail(records, graph, &station).ok_or_else(|| CliError::UnknownStation { station: name, suggestions: Vec::new() })?;
+            emit(format, &format!("{}:", station), &[detail]);
+            let every_route = RankOptions { min_trips: 1, ..*opts };
+            let routes = route_summaries(graph, &every_route);
+            let mut outbound: Vec<_> = routes.iter().filter(|r| r.from == station).cloned().collect();
+            let mut inbound: Vec<_> = routes.iter().filter(|r| r.to == station).cloned().collect();
+            sort_routes(&mut outbound, RouteColumn::MeanDelay, true);
+            sort_routes(&mut inbound, RouteColumn::MeanDelay, true);
+            emit(format, &format!("Outbound routes from {}:", station), &outbound);
+            emit(format, &format!("Inbound routes to {}:", station), &inbound);
+            let hours: Vec<_> = busiest_hours(records, &station).into_iter().take(opts.top_n).collect();
+            emit(format, &format!("Busiest {} hours for arrivals at {}:", hours.len(), station), &hours);
+            let days: Vec<_> = worst_days(records, &station).into_iter().take(opts.top_n).collect();
+            emit(format, &format!("Worst {} days for arrivals at {}:", days.len(), station), &days);
+        }
+        Command::Routes { action: RoutesCommand::List { sort_by, asc, page, per_page, trend } } => {
+            let mut summaries = route_summaries(graph, opts);
+            sort_routes(&mut summaries, sort_by, !asc);
+            let (rows, pages) = paginate(&summaries, page, per_page)
+                .ok_or_else(|| CliError::BadInput(format!("page {} is out of range", page)))?;
+            let title = format!(
+                "Routes with at least {} trips ({}; page {} of {}, {} total):",
+                opts.min_trips, describe_threshold(opts.on_time_threshold), page, pages, summaries.len(),
+            );
+            if trend {
+                let (rows, months) = route_trends(records, rows);
+                let span = match (months.first(), months.last()) {
+                    (Some(first), Some(last)) => format!("{}\nTrend: monthly average delay, {} to {}", title, first, last),
+                    _ => title,
+                };
+                emit(format, &span, &rows);
+            } else {
+                emit(format, &title, rows);
+            }
+        }
+        Command::Histogram { route, bin_width, max } => {
+            let delays: Vec<f32> = match &route {
+                Some(ends) => {
+                    let (from, to) = (graph.resolve_station(&ends[0])?, graph.resolve_station(&ends[1])?);
+                    Sample::Route(from.name, to.name).delays(records)
+                }
+                None => records.iter().filter_map(|r| r.delay()).collect(),
+            };
+            let bins = stats::histogram(&delays, bin_width, max)?;
+            let name = match &route {
+                Some(ends) => format!("{} → {}", ends[0], ends[1]),
+                None => "network".to_string(),
+            };
+            emit_histogram(&format!("Delay distribution (minutes), {} ({} trips):", name, delays.len()), &bins, format);
+        }
+        Command::Heatmap { route, out, #[cfg(feature = "charts")] image } => {
+            let selected: Vec<&TrainRecord> = match &route {
+                Some(ends) => {
+                    for name in ends {
+                        graph.resolve_station(name)?;
+                    }
+                    let sample = Sample::Route(ends[0].clone(), ends[1].clone());
+                    records.iter().filter(|r| sample.matches(r)).collect()
+                }
+                None => records.iter().collect(),
+            };
+            let heatmap = DelayHeatmap::from_records(selected);
+            let scope = match &route {
+                Some(ends) => format!("{} → {}", ends[0], ends[1]),
+                None => "network".to_string(),
+            };
+            let title = format!("Average delay (minutes) by weekday and scheduled hour, {} ({} trips):", scope, heatmap.trips());
+            match &out {
+                Some(path) => {
+                    let mut file = File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+                    write_section(&mut file, OutputFormat::Csv, &title, &heatmap.rows())
+                        .map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+                    note(&format!("Wrote {} heatmap to {}", scope, path));
+                }
+                None => emit(format, &title, &heatmap.rows()),
+            }
+            #[cfg(feature = "charts")]
+            if let Some(path) = &image {
+                charts::render(Path::new(path), &heatmap).map_err(|e| CliError::BadInput(e.to_string()))?;
+                note(&format!("Drew {} heatmap to {}", scope, path));
+            }
+        }
+        Command::Playback { step, out } => {
+            let Some(coords) = coords else {
+                return Err(CliError::BadInput("playback needs station coordinates: pass --coords <station,lat,lon CSV>".into()));
+            };
+            let frames = delay_frames(records, step);
+            match out.as_deref() {
+                Some(path) if path.to_lowercase().ends_with(".gif") => {
+                    #[cfg(feature = "charts")]
+                    charts::render_playback_gif(Path::new(path), &frames, coords, opts.on_time_threshold)
+                        .map_err(|e| CliError::BadInput(e.to_string()))?;
+                    #[cfg(not(feature = "charts"))]
+                    return Err(CliError::BadInput(format!("cannot write {}: GIF playback needs a build with --features charts", path)));
+                }
+                Some(path) => {
+                    let file = File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+                    write_geojson_frames(&frames, coords, io::BufWriter::new(file))
+                        .map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+                }
+                None => match write_geojson_frames(&frames, coords, io::stdout()) {
+                    // A closed pipe (e.g. `| head`) is not an error worth reporting
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                    result => result.map_err(|e| CliError::Internal(format!("failed to write playback: {}", e)))?,
+                },
+            }
+            if let Some(path) = &out {
+                note(&format!("Wrote {} playback frames to {}", frames.len(), path));
+            }
+        }
+        Command::Report { preset, out_dir, html, markdown, pdf, #[cfg(feature = "charts")] charts, email_to, smtp, email_from } => {
+            if !email_to.is_empty() {
+                if !cfg!(feature = "email") {
+                    return Err(CliError::BadInput("--email-to needs a build with --features email".into()));
+                }
+                if smtp.is_none() {
+                    return Err(CliError::BadInput("--email-to needs --smtp naming the server to send through".into()));
+                }
+            }
+            let write_error = |e: io::Error| CliError::BadInput(format!("cannot write report to {}: {}", out_dir, e));
+            let mut files = report::write_report(preset, Path::new(&out_dir), records, graph, opts, format).map_err(write_error)?;
+            let first = files.len() + 1;
+            files.extend(report::write_metric_sections(registry, first, Path::new(&out_dir), graph, opts, format).map_err(write_error)?);
+            if html {
+                let path = Path::new(&out_dir).join("report.html");
+                std::fs::write(&path, report::render_html(preset, records, graph, metadata)).map_err(write_error)?;
+                let rows = preset.sections().len();
+                files.push(report::ReportFile { section: "html".to_string(), path: path.display().to_string(), rows });
+            }
+            if markdown {
+                let path = Path::new(&out_dir).join("report.md");
+                std::fs::write(&path, report::render_markdown(preset, records, graph, metadata)).map_err(write_error)?;
+                let rows = preset.sections().len();
+                files.push(report::ReportFile { section: "markdown".to_string(), path: path.display().to_string(), rows });
+            }
+            if pdf {
+                let path = Path::new(&out_dir).join("report.pdf");
+                std::fs::write(&path, report::render_pdf(preset, records, graph, metadata)).map_err(write_error)?;
+                let rows = preset.sections().len();
+                files.push(report::ReportFile { section: "pdf".to_string(), path: path.display().to_string(), rows });
+            }
+            #[cfg(feature = "charts")]
+            if let Some(chart_format) = charts {
+                for path in charts::write_charts(Path::new(&out_dir), records, graph, opts, chart_format).map_err(write_error)? {
+                    let section = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                    files.push(report::ReportFile { section, path: path.display().to_string(), rows: 1 });
+                }
+            }
+            // The file list goes to the terminal as a table; the report contents are already in `format`
+            emit(OutputFormat::Table, &format!("Wrote {:?} report to {}:", preset, out_dir), &files);
+            #[cfg(feature = "email")]
+            if let Some(url) = smtp.filter(|_| !email_to.is_empty()) {
+                email_digest(&url, &email_from, email_to, preset, records, graph, metadata)?;
+            }
+            #[cfg(not(feature = "email"))]
+            let _ = (smtp, email_from);
+        }
+        Command::Profiles { out_dir, markdown } => {
+            let written = profiles::write_profiles(Path::new(&out_dir), records, graph, metadata, markdown)
+                .map_err(|e| CliError::BadInput(format!("cannot write profiles to {}: {}", out_dir, e)))?;
+            let index = written.first().map(|p| p.display().to_string()).unwrap_or_default();
+            note(&format!("Wrote {} station profiles to {}; start at {}", written.len().saturating_sub(1), out_dir, index));
+        }
+        Command::Validate { .. } | Command::Compare { .. } | Command::Operators { .. } | Command::Watch { .. } => unreachable!("handled before loading"),
+        #[cfg(feature = "async")]
+        Command::Daemon { .. } => unreachable!("handled before loading"),
+        #[cfg(feature = "async")]
+        Command::Departures { .. } => unreachable!("handled after loading"),
+        #[cfg(feature = "serve")]
+        Command::Serve { .. } => unreachable!("handled after loading"),
+        Command::Rank { ranking, out: None } => rank(ranking, records, graph, opts, format),
+        Command::Rank { ranking, out: Some(path) } => {
+            let file = File::create(&path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+            let rows = rank_csv(ranking, records, graph, opts, None, file)
+                .map_err(|e| CliError::Internal(format!("failed to write {}: {}", path, e)))?;
+            note(&format!("Wrote {} rows to {}", rows, path));
+        }
+        Command::Metric { name, out: None } => emit_metric(registry, &name, graph, opts, format)?,
+        Command::Metric { name, out: Some(path) } => {
+            let ranked = registry.rank(&name, graph, opts)?;
+            let file = File::create(&path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?;
+            let rows = ranked.write_csv(&name, file).map_err(|e| CliError::Internal(format!("failed to write {}: {}", path, e)))?;
+            note(&format!("Wrote {} rows to {}", rows, path));
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui => tui::run(graph, opts).map_err(|e| CliError::Internal(format!("terminal dashboard failed: {}", e)))?,
+        Command::Export { kind, out } => {
+            if matches!(kind, ExportKind::Geojson | ExportKind::Kepler) && coords.is_none() {
+                return Err(CliError::BadInput(format!("{:?} export needs station coordinates: pass --coords <station,lat,lon CSV>", kind)));
+            }
+            if let Some(feature) = kind.missing_feature() {
+                return Err(CliError::BadInput(format!("{:?} export needs a build with --features {}", kind, feature)));
+            }
+            if kind == ExportKind::Sqlite {
+                let Some(path) = &out else {
+                    return Err(CliError::BadInput("SQLite export writes a database file: pass --out <path>".into()));
+                };
+                #[cfg(feature = "sqlite")]
+                crate::export::to_sqlite(Path::new(path), records, graph, metadata)
+                    .map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+                note(&format!("Wrote SQLite database to {}", path));
+                return Ok(());
+            }
+            let writer: Box<dyn Write> = match &out {
+                Some(path) => Box::new(File::create(path).map_err(|e| CliError::BadInput(format!("cannot create {}: {}", path, e)))?),
+                None => Box::new(io::stdout()),
+            };
+            match write_export(kind, records, graph, coords, metadata, writer) {
+                // A closed pipe (e.g. `| head`) is not an error worth reporting
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result.map_err(|e| CliError::Internal(format!("failed to write export: {}", e)))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+// Checks that station coordinates were given when a KML file was asked for
+fn kml_coordinates<'a>(kml: Option<&str>, coords: Option<&'a Coordinates>) -> Result<Option<&'a Coordinates>, CliError> {
+    match (kml, coords) {
+        (Some(_), None) => Err(CliError::BadInput("KML output needs station coordinates: pass --coords <station,lat,lon CSV>".into())),
+        (Some(_), coords) => Ok(coords),
+        (None, _) => Ok(None),
+    }
+}
+
+// Emits a single ranking
+pub(crate) fn rank(ranking: Ranking, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) {
+    let section = ranking_section(ranking, records, graph, opts, None);
+    emit(format, &section.title, &section.rows);
+}
+
+// Computes a single ranking as a titled section
+// Closeness and betweenness save their progress to `checkpoints` if given
+pub(crate) fn ranking_section(ranking: Ranking, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, checkpoints: Option<&mut Checkpointer>) -> Section {
+    let n = opts.top_n;
+    match ranking {
+        Ranking::Closeness => Section::new(
+            format!("Top {} stations by closeness centrality:", n),
+            &graph.rank_stations_by_closeness_checkpointed(opts, &Diagnostics, checkpoints),
+        ),
+        Ranking::Betweenness => Section::new(
+            format!("Top {} stations (unweighted betweenness):", n),
+            &graph.rank_stations_by_betweenness_checkpointed(opts, &Diagnostics, checkpoints),
+        ),
+        Ranking::WorstRoutes => Section::new(format!("Top {} routes by average delay:", n), &graph.rank_routes_by_average_delay(opts)),
+        Ranking::BestRoutes => Section::new(format!("Top {} routes by **lowest** average delay:", n), &graph.rank_routes_by_lowest_delay(opts)),
+        Ranking::Congestion => Section::new(
+            format!("Top {} congested stations (trips/day × average delay):", n),
+            &congestion::rank_stations_by_congestion(records, opts),
+        ),
+        Ranking::PassengerDelay => Section::new(
+            format!("Top {} routes by passenger-minutes of delay (gravity-model riders × average delay):", n),
+            &flows::rank_routes_by_passenger_delay(records, graph, opts),
+        ),
+        Ranking::Forecast => Section::new(format!("Top {} routes by forecast next-week delay:", n), &forecast::rank_routes_by_forecast(records, opts)),
+        Ranking::Links => Section::new(format!("Top {} predicted new connections (Adamic-Adar):", n), &graph.predict_links(n)),
+    }
+}
+
+// Writes a single ranking as typed CSV rows
+// Output: number of rows written
+// Prints a delay histogram: as terminal bars for table output, as rows otherwise
+fn emit_histogram(title: &str, bins: &[HistogramBin], format: OutputFormat) {
+    if format == OutputFormat::Table {
+        if verbosity() != Verbosity::Quiet {
+            println!("{}", title);
+        }
+        // A closed pipe (e.g. `| head`) is not an error worth reporting
+        let _ = textplot::write_histogram(&mut io::stdout().lock(), bins, 50);
+    } else {
+        emit(format, title, bins);
+    }
+}
+
+// Prints a scenario's network-wide impact, then its top affected stations and centrality shifts
+fn emit_impact(report: &whatif::ImpactReport, opts: &RankOptions, format: OutputFormat) {
+    emit(format, "Network impact:", &[&report.summary]);
+    emit(format, &format!("Top {} affected stations (destinations lost and gained, change in mean least delay):", opts.top_n), &report.affected[..report.affected.len().min(opts.top_n)]);
+    emit(format, &format!("Top {} centrality shifts (baseline → scenario):", opts.top_n), &report.centrality_shifts[..report.centrality_shifts.len().min(opts.top_n)]);
+}
+
+// Prints the ranking of a registered custom metric
+fn emit_metric(registry: &MetricRegistry, name: &str, graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) -> Result<(), CliError> {
+    let ranked = registry.rank(name, graph, opts)?;
+    let title = registry.get(name).map(|m| m.title()).unwrap_or_default();
+    emit(format, &format!("Top {} by {}:", opts.top_n, title), &ranked.rows(name));
+    Ok(())
+}
+
+pub(crate) fn rank_csv<W: Write>(
+    ranking: Ranking,
+    records: &[TrainRecord],
+    graph: &TransitGraph,
+    opts: &RankOptions,
+    checkpoints: Option<&mut Checkpointer>,
+    writer: W,
+) -> Result<usize, csv::Error> {
+    let name = ranking.to_possible_value().map_or(String::new(), |v| v.get_name().to_string());
+    match ranking {
+        Ranking::Closeness => write_ranking_csv(&name, &graph.rank_stations_by_closeness_checkpointed(opts, &Diagnostics, checkpoints), writer),
+        Ranking::Betweenness => write_ranking_csv(&name, &graph.rank_stations_by_betweenness_checkpointed(opts, &Diagnostics, checkpoints), writer),
+        Ranking::WorstRoutes => write_ranking_csv(&name, &graph.rank_routes_by_average_delay(opts), writer),
+        Ranking::BestRoutes => write_ranking_csv(&name, &graph.rank_routes_by_lowest_delay(opts), writer),
+        Ranking::Congestion => write_ranking_csv(&name, &congestion::rank_stations_by_congestion(records, opts), writer),
+        Ranking::PassengerDelay => write_ranking_csv(&name, &flows::rank_routes_by_passenger_delay(records, graph, opts), writer),
+        Ranking::Forecast => write_ranking_csv(&name, &forecast::rank_routes_by_forecast(records, opts), writer),
+        Ranking::Links => write_ranking_csv(&name, &graph.predict_links(opts.top_n), writer),
+    }
+}
+
+// Emits every ranking and network metric in turn
+pub(crate) fn analyze(records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) {
+    // Rankings: closeness, betweenness, worst/best routes, forecast, congestion, passenger delay, predicted links (top N each)
+    for ranking in [
+        Ranking::Closeness, Ranking::Betweenness, Ranking::WorstRoutes, Ranking::BestRoutes,
+        Ranking::Forecast, Ranking::Congestion, Ranking::PassengerDelay, Ranking::Links,
+    ] {
+        rank(ranking, records, graph, opts, format);
+    }
+    // Mean path length and small-world coefficient against 10 random baselines
+    let small_world: Vec<_> = graph.small_world(10, opts.seed).into_iter().collect();
+    emit(format, "Small-world indicators (10 degree-preserving random baselines):", &small_world);
+    // The longest least-delay journey, from the all-pairs delay matrix
+    if let Some(diameter) = graph.distance_matrix().ok().and_then(|matrix| matrix.diameter()) {
+        emit(format, "Delay diameter (longest least-delay journey):", &[diameter]);
+    }
+    // Degree assortativity with per-degree mixing detail
+    if let Some(assortativity) = graph.degree_assortativity() {
+        emit(format, &format!("Degree mixing (assortativity {:.4}):", assortativity.coefficient), &assortativity.mixing);
+    }
+    // The delay / hops / transfers trade-offs between two key stations
+    if let (Ok(from), Ok(to)) = (graph.resolve_station("New York Penn Station"), graph.resolve_station("Newark Broad Street")) {
+        emit(format, &format!("Pareto-optimal journeys {} → {} (delay / hops / transfers):", from, to), &graph.pareto_paths(&from, &to, 30));
+    }
+    // The 5 smallest Laplacian eigenvalues and the spectral bisection
+    #[cfg(feature = "linalg")]
+    if let Some(spectrum) = graph.laplacian_spectrum(5) {
+        let (a, b) = spectrum.partition();
+        let summary = serde_json::json!({
+            "components": spectrum.components,
+            "component_size": spectrum.component_size,
+            "algebraic_connectivity": spectrum.algebraic_connectivity,
+            "eigenvalues": spectrum.eigenvalues,
+            "fiedler_partition": [a.len(), b.len()],
+        });
+        emit(format, "Laplacian spectrum of the largest component:", &[summary]);
+    }
+    // Delay distributions between two lines, two periods, and both directions of a route
+    let comparisons = [
+        (Sample::Line("Morristown Line".into()), Sample::Line("Northeast Corrdr".into())),
+        (Sample::Period("2018-01-01".into(), "2018-12-31".into()), Sample::Period("2019-01-01".into(), "2019-12-31".into())),
+        (
+            Sample::Route("Secaucus Upper Lvl".into(), "New York Penn Station".into()),
+            Sample::Route("New York Penn Station".into(), "Secaucus Upper Lvl".into()),
+        ),
+    ];
+    for (a, b) in comparisons {
+        let rows: Vec<_> = stats::compare(records, &a, &b).into_iter().collect();
+        emit(format, &format!("Delay comparison: {:?} vs {:?}", a, b), &rows);
+    }
+}
+
+// Emits headline numbers, the delay distribution test, and route and centrality shifts between two sides
+pub(crate) fn compare(sides: &[(&str, String, Vec<TrainRecord>, TransitGraph); 2], opts: &RankOptions, format: OutputFormat) {
+    let [(_, _, records_a, a), (_, _, records_b, b)] = sides;
+    let summaries: Vec<_> = sides.iter()
+        .map(|(side, source, records, graph)| comparison::summarize(side, source, records, graph, opts.on_time_threshold))
+        .collect();
+    emit(format, &format!("Datasets compared ({}):", describe_threshold(opts.on_time_threshold)), &summaries);
+    let delays = |records: &[TrainRecord]| records.iter().filter_map(|r| r.delay()).collect::<Vec<_>>();
+    let test: Vec<_> = stats::compare_delays(&delays(records_a), &delays(records_b)).into_iter().collect();
+    emit(format, "Delay distribution, A vs B:", &test);
+    emit(format, &format!("Top {} route delay shifts (B − A):", opts.top_n), &comparison::route_shifts(a, b, opts));
+    emit(format, &format!("Top {} centrality shifts (B − A):", opts.top_n), &comparison::centrality_shifts(a, b, opts));
+}
+
+// Exactly two (operator, source, records) sides, or bad input explaining why not
+fn two_operators(sides: Vec<(String, String, Vec<TrainRecord>)>, problem: &str) -> Result<[(String, String, Vec<TrainRecord>); 2], CliError> {
+    let found: Vec<String> = sides.iter().map(|(operator, source, _)| format!("{} in {}", operator, source)).collect();
+    let [a, b]: [_; 2] = sides.try_into().map_err(|_| CliError::BadInput(format!("{} (found {})", problem, found.join(", "))))?;
+    if a.0.eq_ignore_ascii_case(&b.0) {
+        return Err(CliError::BadInput(format!("both datasets are operator {:?}; tag them with --operator and --other-operator", a.0)));
+    }
+    Ok([a, b])
+}
+
+// Emits each operator's headline numbers, the test of their delay distributions, and their delays on shared stations
+// and corridors; A is the operator with more records (or --data), B the other
+fn compare_operators(sides: &[(String, String, Vec<TrainRecord>); 2], opts: &RankOptions, format: OutputFormat) {
+    let [(operator_a, _, records_a), (operator_b, _, records_b)] = sides;
+    let summaries: Vec<_> = sides.iter()
+        .map(|(operator, source, records)| comparison::summarize(operator, source, records, &TransitGraph::from_records(records), opts.on_time_threshold))
+        .collect();
+    emit(format, &format!("Operators compared ({}):", describe_threshold(opts.on_time_threshold)), &summaries);
+    let delays = |records: &[TrainRecord]| records.iter().filter_map(|r| r.delay()).collect::<Vec<_>>();
+    let test: Vec<_> = stats::compare_delays(&delays(records_a), &delays(records_b)).into_iter().collect();
+    emit(format, &format!("Delay distribution, {} vs {}:", operator_a, operator_b), &test);
+    let versus = format!("{} − {}", operator_b, operator_a);
+    emit(format, &format!("Top {} shared stations by departure delay difference ({}):", opts.top_n, versus), &operators::shared_stations(records_a, records_b, opts));
+    emit(format, &format!("Top {} shared corridors by delay difference ({}):", opts.top_n, versus), &operators::shared_corridors(records_a, records_b, opts));
+}
+
+// Emails the run's digest to every --email-to address through the --smtp server, with the HTML report attached
+#[cfg(feature = "email")]
+fn email_digest(url: &str, from: &str, to: Vec<String>, preset: report::Preset, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> Result<(), CliError> {
+    let server = mail::SmtpServer::parse(url).map_err(|e| CliError::BadInput(e.to_string()))?;
+    let digest = Digest::new(records, graph, metadata, &AnomalyThresholds::default());
+    let email = mail::Email {
+        from: from.to_string(),
+        subject: digest.subject.clone(),
+        text: digest.text(metadata),
+        html: Some(digest.html(metadata)),
+        attachments: vec![mail::Attachment {
+            name: "report.html".to_string(),
+            content_type: "text/html; charset=utf-8".to_string(),
+            data: report::render_html(preset, records, graph, metadata).into_bytes(),
+        }],
+        to,
+    };
+    server.send(&email).map_err(|e| CliError::BadInput(format!("cannot email the digest: {}", e)))?;
+    note(&format!("Emailed the digest ({} anomalies) to {}", digest.anomalies.len(), email.to.join(", ")));
+    Ok(())
+}
+
+// Serves the loaded dataset over HTTP on `addr` until the process is stopped, publishing the feed's updates (if any)
+// and reloading the dataset on SIGHUP
+#[cfg(feature = "serve")]
+fn serve(addr: &str, state: server::ServerState, feed: Option<(LiveFeed, Duration, Option<WebhookAlerter>)>) -> Result<(), CliError> {
+    let state = std::sync::Arc::new(state);
+    if let Some((feed, interval, alerter)) = feed {
+        let state = state.clone();
+        thread::spawn(move || publish_updates(feed, interval, alerter.as_ref(), &state));
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()
+        .map_err(|e| CliError::Internal(format!("cannot start the server runtime: {}", e)))?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| CliError::BadInput(format!("cannot listen on {}: {}", addr, e)))?;
+        let local = listener.local_addr().map_or_else(|_| addr.to_string(), |a| a.to_string());
+        note(&format!("Serving {} stations on http://{} (Ctrl-C to stop)", state.dataset().graph.stations().count(), local));
+        #[cfg(unix)]
+        tokio::spawn(reload_on_hangup(state.clone()));
+        server::serve(listener, state).await.map_err(|e| CliError::Internal(format!("server failed: {}", e)))
+    })
+}
+
+// Reloads the served dataset each time the process receives SIGHUP; a failed reload keeps serving the old dataset
+#[cfg(all(feature = "serve", unix))]
+async fn reload_on_hangup(state: std::sync::Arc<server::ServerState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut hangups) = signal(SignalKind::hangup()) else {
+        diagnostic(Verbosity::Normal, "cannot listen for SIGHUP; reload with POST /admin/reload instead");
+        return;
+    };
+    while hangups.recv().await.is_some() {
+        let state = state.clone();
+        match nonblocking::spawn_blocking(move || state.reload()).await.map_err(CliError::from).and_then(|r| r) {
+            Ok(report) => note(&format!("Reloaded {} records ({} stations) as generation {}", report.records, report.stations, report.generation)),
+            Err(e) => diagnostic(Verbosity::Normal, &format!("Reload failed, still serving the previous dataset: {}", e)),
+        }
+    }
+}
+
+// Scans the feed's directory every `interval`, publishing an update to /live clients after any change and posting its
+// anomalies to the alert webhook, if any; an unreadable directory is reported and retried, since the server keeps
+// answering requests either way
+#[cfg(feature = "serve")]
+fn publish_updates(mut feed: LiveFeed, interval: Duration, alerter: Option<&WebhookAlerter>, state: &server::ServerState) {
+    note(&format!("Watching {} every {:?} for /live updates", feed.watcher().dir().display(), interval));
+    loop {
+        match feed.poll() {
+            Ok((scan, update)) => {
+                report_scan(&scan);
+                if let Some(update) = update {
+                    alert(alerter, &update.anomalies);
+                    state.publish(update);
+                }
+            }
+            Err(e) => diagnostic(Verbosity::Normal, &format!("cannot read {}: {}", feed.watcher().dir().display(), e)),
+        }
+        thread::sleep(interval);
+    }
+}
+
+// Fetches `source` every `interval` into the ingestor's state directory, emitting what each fetch added; a failed fetch
+// or write is reported and retried at the next interval
+#[cfg(feature = "async")]
+fn run_daemon(source: &str, mut ingestor: daemon::Ingestor, interval: Duration, publisher: Option<&MqttPublisher>, opts: &RankOptions, format: OutputFormat) -> Result<(), CliError> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+        .map_err(|e| CliError::Internal(format!("cannot start the fetch runtime: {}", e)))?;
+    note(&format!("Fetching {} every {:?} into {} (Ctrl-C to stop)", source, interval, ingestor.dir().display()));
+    loop {
+        let started = Instant::now();
+        match runtime.block_on(nonblocking::load_data(source)) {
+            Ok(fetched) => match ingestor.ingest(fetched) {
+                Ok(report) => {
+                    emit(format, &format!("Fetched {} at {}:", source, chrono::Local::now().format("%Y-%m-%d %H:%M:%S")), &[&report]);
+                    if publisher.is_some() {
+                        publish_states(publisher, &ingestor.filtered_records(), opts);
+                    }
+                    diagnostic(Verbosity::Verbose, &format!("Refreshed {} in {:.1?}", report.snapshot.display(), started.elapsed()));
+                }
+                Err(e) => diagnostic(Verbosity::Normal, &format!("cannot write to {}: {}", ingestor.dir().display(), e)),
+            },
+            Err(e) => diagnostic(Verbosity::Normal, &format!("{}; retrying in {:?}", e, interval)),
+        }
+        thread::sleep(interval);
+    }
+}
+
+// Fetches a station's departure board, once or every `interval`, emitting each departure against its historical delays
+// and appending the departures to `save`; while polling, a failed fetch is reported and retried at the next interval
+#[cfg(feature = "async")]
+fn run_departures(station: &str, api_url: &str, graph: &TransitGraph, interval: Option<Duration>, save: Option<&str>, format: OutputFormat) -> Result<(), CliError> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+        .map_err(|e| CliError::Internal(format!("cannot start the fetch runtime: {}", e)))?;
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let client = match (env("NJT_RAIL_TOKEN"), env("NJT_RAIL_USERNAME"), env("NJT_RAIL_PASSWORD")) {
+        (Some(token), _, _) => departures::RailDataClient::with_token(api_url, token),
+        (None, Some(username), Some(password)) => runtime.block_on(departures::RailDataClient::login(api_url, &username, &password))?,
+        _ => return Err(CliError::BadInput("set NJT_RAIL_TOKEN, or NJT_RAIL_USERNAME and NJT_RAIL_PASSWORD, to use the rail data API".into())),
+    };
+    loop {
+        let fetched = runtime.block_on(client.departures(station)).map_err(CliError::from).and_then(|board| {
+            let records = board.to_records();
+            if let Some(path) = save {
+                append_records(path, &records).map_err(|e| CliError::BadInput(format!("cannot write {}: {}", path, e)))?;
+            }
+            Ok((board, records))
+        });
+        match (fetched, interval) {
+            (Ok((board, records)), _) => {
+                let title = format!("Departures from {} at {} against history:", board.station_name, chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+                emit(format, &title, &departures::compare_departures(&records, graph));
+            }
+            (Err(e), None) => return Err(e),
+            (Err(e), Some(interval)) => diagnostic(Verbosity::Normal, &format!("{}; retrying in {:?}", e, interval)),
+        }
+        let Some(interval) = interval else { return Ok(()) };
+        thread::sleep(interval);
+    }
+}
+
+// Appends records to a CSV file in the dataset's format, writing the header if the file is new or empty
+#[cfg(feature = "async")]
+fn append_records(path: &str, records: &[TrainRecord]) -> io::Result<()> {
+    let header = std::fs::metadata(path).map_or(true, |m| m.len() == 0);
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = csv::WriterBuilder::new().has_headers(header).from_writer(file);
+    for record in records {
+        writer.serialize(record).map_err(io::Error::other)?;
+    }
+    writer.flush()
+}
+
+// Scans the feed's directory every `interval`, re-emitting the headline metrics after any change along with the new
+// trips' delay anomalies, which are also posted to the alert webhook, if any; runs until interrupted
+pub(crate) fn watch_directory(
+    mut feed: LiveFeed,
+    interval: Duration,
+    alerter: Option<&WebhookAlerter>,
+    publisher: Option<&MqttPublisher>,
+    opts: &RankOptions,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let dir = feed.watcher().dir().display().to_string();
+    note(&format!("Watching {} every {:?} (Ctrl-C to stop)", dir, interval));
+    loop {
+        let started = Instant::now();
+        let (scan, update) = feed.poll().map_err(|e| CliError::BadInput(format!("cannot read {}: {}", dir, e)))?;
+        report_scan(&scan);
+        if let Some(update) = update {
+            emit(format, &format!("Snapshot of {} at {}:", dir, update.at.format("%Y-%m-%d %H:%M:%S")), &[&update.snapshot]);
+            if !update.anomalies.is_empty() {
+                emit(format, "Delay anomalies:", &update.anomalies);
+            }
+            alert(alerter, &update.anomalies);
+            let records = feed.records();
+            publish_states(publisher, &records, opts);
+            let graph = TransitGraph::from_records(&records);
+            rank(Ranking::WorstRoutes, &records, &graph, opts, format);
+            rank(Ranking::Closeness, &records, &graph, opts, format);
+            diagnostic(Verbosity::Verbose, &format!("Refreshed metrics in {:.1?}", started.elapsed()));
+        }
+        thread::sleep(interval);
+    }
+}
+
+// Reports the files a scan skipped, ingested and dropped
+fn report_scan(scan: &watch::ScanResult) {
+    for (path, error) in &scan.failed {
+        diagnostic(Verbosity::Normal, &format!("Skipping {} until it changes or parses: {}", path.display(), error));
+    }
+    for path in &scan.loaded {
+        diagnostic(Verbosity::Verbose, &format!("Ingested {}", path.display()));
+    }
+    for path in &scan.removed {
+        diagnostic(Verbosity::Verbose, &format!("Dropped {}", path.display()));
+    }
+}
+
+// Publishes every station's and line's delay state to the broker, if any; a failed publish is reported and the
+// watch goes on
+#[cfg_attr(not(feature = "mqtt"), allow(unused_variables))]
+fn publish_states(publisher: Option<&MqttPublisher>, records: &[TrainRecord], opts: &RankOptions) {
+    #[cfg(feature = "mqtt")]
+    if let Some(publisher) = publisher {
+        let states = crate::mqtt::delay_states(records, opts.on_time_threshold, forecast::ForecastParams::default().alpha);
+        match publisher.publish_blocking(&states) {
+            Ok(published) => diagnostic(Verbosity::Verbose, &format!("Published {} delay states to {}", published, publisher.broker())),
+            Err(e) => diagnostic(Verbosity::Normal, &e.to_string()),
+        }
+    }
+}
+
+// Logs each anomaly and posts them to the webhook, if any; a failed post is reported and the watch goes on
+#[cfg_attr(not(feature = "async"), allow(unused_variables))]
+fn alert(alerter: Option<&WebhookAlerter>, anomalies: &[live::DelayAnomaly]) {
+    for anomaly in anomalies {
+        diagnostic(Verbosity::Verbose, &format!(
+            "Delay alert on {}: {:.1} min over {} new trips vs {:.1} min before (z = {:.1})",
+            anomaly.name, anomaly.mean_delay, anomaly.trips, anomaly.baseline_delay, anomaly.z_score,
+        ));
+    }
+    #[cfg(feature = "async")]
+    if let Some(alerter) = alerter
+        && let Err(e) = alerter.send_blocking(anomalies)
+    {
+        diagnostic(Verbosity::Normal, &e.to_string());
+    }
+}
@@ -0,0 +1,120 @@
+// Typed Arrow tables: the route and station metrics for the Parquet export, and any result section as an IPC stream
+// (requires the `arrow` feature)
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use arrow_array::{ArrayRef, BooleanArray, Float32Array, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use serde::Serialize;
+use serde_json::Value;
+use crate::output::{cell_text, flatten_values, FlatRows};
+use crate::graph::TransitGraph;
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+use crate::routes::route_summaries;
+use crate::stations::station_summaries;
+
+// Every route with at least one trip, ordered by (from, to)
+// Columns: from, to, trips, mean_delay, median_delay, p90_delay, on_time_rate
+pub fn route_table(graph: &TransitGraph, opts: &RankOptions) -> Result<RecordBatch, ArrowError> {
+    let mut routes = route_summaries(graph, &RankOptions { min_trips: 1, ..*opts });
+    routes.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+    let schema = Schema::new(vec![
+        Field::new("from", DataType::Utf8, false),
+        Field::new("to", DataType::Utf8, false),
+        Field::new("trips", DataType::UInt64, false),
+        Field::new("mean_delay", DataType::Float32, false),
+        Field::new("median_delay", DataType::Float32, false),
+        Field::new("p90_delay", DataType::Float32, false),
+        Field::new("on_time_rate", DataType::Float32, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(routes.iter().map(|r| r.from.as_str()))),
+        Arc::new(StringArray::from_iter_values(routes.iter().map(|r| r.to.as_str()))),
+        Arc::new(UInt64Array::from_iter_values(routes.iter().map(|r| r.trips as u64))),
+        Arc::new(Float32Array::from_iter_values(routes.iter().map(|r| r.mean_delay))),
+        Arc::new(Float32Array::from_iter_values(routes.iter().map(|r| r.median_delay))),
+        Arc::new(Float32Array::from_iter_values(routes.iter().map(|r| r.p90_delay))),
+        Arc::new(Float32Array::from_iter_values(routes.iter().map(|r| r.on_time_rate))),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+// Every station, ordered by name
+// Columns: station, degree, trips, inbound_delay, line_count, lines, closeness (null when undefined), betweenness
+pub fn station_table(records: &[TrainRecord], graph: &TransitGraph) -> Result<RecordBatch, ArrowError> {
+    let mut stations = station_summaries(records, graph);
+    stations.sort_by(|a, b| a.station.cmp(&b.station));
+    let betweenness = graph.betweenness_centrality();
+    let schema = Schema::new(vec![
+        Field::new("station", DataType::Utf8, false),
+        Field::new("degree", DataType::UInt64, false),
+        Field::new("trips", DataType::UInt64, false),
+        Field::new("inbound_delay", DataType::Float32, true),
+        Field::new("line_count", DataType::UInt64, false),
+        Field::new("lines", DataType::Utf8, false),
+        Field::new("closeness", DataType::Float32, true),
+        Field::new("betweenness", DataType::Float32, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(stations.iter().map(|s| s.station.as_str()))),
+        Arc::new(UInt64Array::from_iter_values(stations.iter().map(|s| s.degree as u64))),
+        Arc::new(UInt64Array::from_iter_values(stations.iter().map(|s| s.trips as u64))),
+        Arc::new(Float32Array::from_iter(stations.iter().map(|s| s.inbound_delay.is_finite().then_some(s.inbound_delay)))),
+        Arc::new(UInt64Array::from_iter_values(stations.iter().map(|s| s.line_count as u64))),
+        Arc::new(StringArray::from_iter_values(stations.iter().map(|s| s.lines.as_str()))),
+        Arc::new(Float32Array::from_iter(stations.iter().map(|s| graph.closeness_centrality(&s.station)))),
+        Arc::new(Float32Array::from_iter_values(stations.iter().map(|s| betweenness.get(&s.station).copied().unwrap_or(0.0)))),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+// Converts serialized rows into one record batch, flattening nested fields into dotted columns as tables do
+// Logic: each column takes the narrowest type that holds every non-null value: Boolean, Int64, Float32 when every
+// float survives a round trip through f32 (most metrics are f32), Float64, otherwise Utf8; missing cells are null
+pub fn rows_table<T: Serialize>(title: &str, rows: &[T]) -> io::Result<RecordBatch> {
+    let FlatRows { columns, rows: flat } = flatten_values(rows)?;
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+    for column in &columns {
+        let cells: Vec<Option<&Value>> = flat.iter().map(|row| row.get(column).filter(|v| !v.is_null())).collect();
+        let array = column_array(&cells);
+        fields.push(Field::new(column, array.data_type().clone(), true));
+        arrays.push(array);
+    }
+    let metadata = HashMap::from([("title".to_string(), title.trim_end_matches(':').to_string())]);
+    let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+    if arrays.is_empty() {
+        return Ok(RecordBatch::new_empty(schema));
+    }
+    RecordBatch::try_new(schema, arrays).map_err(io::Error::other)
+}
+
+fn column_array(cells: &[Option<&Value>]) -> ArrayRef {
+    let present = || cells.iter().flatten();
+    if present().all(|v| v.is_boolean()) {
+        return Arc::new(BooleanArray::from_iter(cells.iter().map(|v| v.and_then(Value::as_bool))));
+    }
+    if present().all(|v| v.is_i64()) {
+        return Arc::new(Int64Array::from_iter(cells.iter().map(|v| v.and_then(Value::as_i64))));
+    }
+    if present().all(|v| v.is_number()) {
+        let floats = || cells.iter().map(|v| v.and_then(Value::as_f64));
+        if floats().flatten().all(|f| f == (f as f32) as f64) {
+            return Arc::new(Float32Array::from_iter(floats().map(|f| f.map(|f| f as f32))));
+        }
+        return Arc::new(Float64Array::from_iter(floats()));
+    }
+    Arc::new(StringArray::from_iter(cells.iter().map(|v| v.map(|v| cell_text(v, true)))))
+}
+
+// Writes one section as a complete Arrow IPC stream: schema, one batch, end-of-stream marker
+// Commands that print several sections write several streams back to back; readers take one per open_stream call
+pub fn write_ipc_stream<W: Write, T: Serialize>(out: &mut W, title: &str, rows: &[T]) -> io::Result<()> {
+    let batch = rows_table(title, rows)?;
+    let mut writer = StreamWriter::try_new(out, &batch.schema()).map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
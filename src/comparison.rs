@@ -0,0 +1,125 @@
+// Side-by-side comparison of two datasets or periods: headline numbers, route delay shifts, and centrality shifts
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::graph::{TransitGraph, Station};
+use crate::load::TrainRecord;
+use crate::metrics::{RankOptions, RouteStat};
+
+// Headline numbers for one side of a comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SideSummary {
+    pub side: String,       // "A" or "B", or the operator in an operator comparison
+    pub source: String,     // File and/or period the side was drawn from
+    pub records: usize,
+    pub stations: usize,
+    pub routes: usize,      // Distinct (from, to) segments with delay data
+    pub average_delay: f32, // Mean delay over all records with one, in minutes
+    pub on_time_rate: f32,  // Share of those records at or below the on-time threshold
+}
+
+// Change in delay and on-time performance for a route present on both sides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteShift {
+    pub from: Station,
+    pub to: Station,
+    pub delay_a: f32,
+    pub delay_b: f32,
+    pub delay_change: f32, // delay_b - delay_a; positive means B is worse
+    pub trips_a: usize,
+    pub trips_b: usize,
+    pub on_time_a: f32,
+    pub on_time_b: f32,
+    pub on_time_change: f32,
+}
+
+// Change in centrality for a station present on both sides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CentralityShift {
+    pub station: Station,
+    pub closeness_a: Option<f32>,
+    pub closeness_b: Option<f32>,
+    pub closeness_change: Option<f32>, // None unless the station reaches others on both sides
+    pub betweenness_a: f32,
+    pub betweenness_b: f32,
+    pub betweenness_change: f32,
+}
+
+// Summarizes one side's records and graph
+pub fn summarize(side: &str, source: &str, records: &[TrainRecord], graph: &TransitGraph, on_time_threshold: f32) -> SideSummary {
+    let delays: Vec<f32> = records.iter().filter_map(|r| r.delay()).collect();
+    let count = delays.len().max(1) as f32;
+    SideSummary {
+        side: side.to_string(),
+        source: source.to_string(),
+        records: records.len(),
+        stations: graph.all_stations().len(),
+        routes: graph.lines.len(),
+        average_delay: delays.iter().sum::<f32>() / count,
+        on_time_rate: delays.iter().filter(|&&d| d <= on_time_threshold).count() as f32 / count,
+    }
+}
+
+// Pairs up routes with at least `min_trips` trips on both sides
+// Output: top N routes by absolute change in average delay
+pub fn route_shifts(a: &TransitGraph, b: &TransitGraph, opts: &RankOptions) -> Vec<RouteShift> {
+    let all = RankOptions { top_n: usize::MAX, ..*opts };
+    let before: HashMap<(Station, Station), RouteStat> = a.rank_routes_by_average_delay(&all).into_iter()
+        .map(|r| ((r.from.clone(), r.to.clone()), r))
+        .collect();
+    let mut shifts: Vec<RouteShift> = b.rank_routes_by_average_delay(&all).into_iter()
+        .filter_map(|after| {
+            let before = before.get(&(after.from.clone(), after.to.clone()))?;
+            Some(RouteShift {
+                delay_a: before.average_delay,
+                delay_b: after.average_delay,
+                delay_change: after.average_delay - before.average_delay,
+                trips_a: before.trips,
+                trips_b: after.trips,
+                on_time_a: before.on_time_rate,
+                on_time_b: after.on_time_rate,
+                on_time_change: after.on_time_rate - before.on_time_rate,
+                from: after.from,
+                to: after.to,
+            })
+        })
+        .collect();
+    shifts.sort_by(|x, y| {
+        y.delay_change.abs().total_cmp(&x.delay_change.abs())
+            .then_with(|| (&x.from, &x.to).cmp(&(&y.from, &y.to)))
+    });
+    shifts.truncate(opts.top_n);
+    shifts
+}
+
+// Pairs up stations present in both graphs
+// Output: top N stations by absolute closeness change, then by absolute betweenness change
+pub fn centrality_shifts(a: &TransitGraph, b: &TransitGraph, opts: &RankOptions) -> Vec<CentralityShift> {
+    let (between_a, between_b) = (a.betweenness_centrality(), b.betweenness_centrality());
+    let stations_b = b.all_stations();
+    let mut shifts: Vec<CentralityShift> = a.all_stations().into_iter()
+        .filter(|s| stations_b.contains(s))
+        .map(|station| {
+            let (closeness_a, closeness_b) = (a.closeness_centrality(&station), b.closeness_centrality(&station));
+            let betweenness_a = between_a.get(&station).copied().unwrap_or(0.0);
+            let betweenness_b = between_b.get(&station).copied().unwrap_or(0.0);
+            CentralityShift {
+                closeness_change: closeness_a.zip(closeness_b).map(|(x, y)| y - x),
+                closeness_a,
+                closeness_b,
+                betweenness_a,
+                betweenness_b,
+                betweenness_change: betweenness_b - betweenness_a,
+                station,
+            }
+        })
+        .collect();
+    let key = |s: &CentralityShift| s.closeness_change.map_or(-1.0, f32::abs);
+    shifts.sort_by(|x, y| {
+        key(y).total_cmp(&key(x))
+            .then(y.betweenness_change.abs().total_cmp(&x.betweenness_change.abs()))
+            .then_with(|| x.station.cmp(&y.station))
+    });
+    shifts.truncate(opts.top_n);
+    shifts
+}
@@ -0,0 +1,88 @@
+// Reconstructs trips from stop records and scores stations by throughput and incurred delay
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::graph::Station;
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+
+// Key identifying a single run of a train: (service date, train ID)
+pub type TripKey = (String, String);
+
+// Throughput and congestion summary for one station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationThroughput {
+    pub station: Station,
+    pub trips: usize,           // Distinct trips serving the station over the whole dataset
+    pub days: usize,            // Distinct service days the station appears on
+    pub trips_per_day: f32,     // Average distinct trips per active day
+    pub average_delay: f32,     // Average delay of arrivals at the station, in minutes
+    pub congestion_score: f32,  // trips_per_day * average_delay: delay-minutes incurred there per day
+}
+
+// Groups records into trips keyed by (date, train_id), each ordered by stop sequence
+// Input: slice of TrainRecord structs
+// Output: map from trip key to its segments in travel order
+pub fn reconstruct_trips(records: &[TrainRecord]) -> HashMap<TripKey, Vec<&TrainRecord>> {
+    let mut trips: HashMap<TripKey, Vec<&TrainRecord>> = HashMap::new();
+    for r in records {
+        trips.entry((r.date.clone(), r.train_id.clone())).or_default().push(r);
+    }
+    for segments in trips.values_mut() {
+        // Stop sequences are stored as floats in the CSV ("6.0"); unparsable ones sort last
+        segments.sort_by(|a, b| {
+            let key = |r: &TrainRecord| r.stop_sequence.parse::<f32>().unwrap_or(f32::INFINITY);
+            key(a).total_cmp(&key(b))
+        });
+    }
+    trips
+}
+
+// Computes throughput and congestion for every station served by at least one trip
+// Logic: a trip serves every station it departs from or arrives at; delay is attributed to the arrival station
+pub fn station_throughput(records: &[TrainRecord]) -> Vec<StationThroughput> {
+    let mut served: HashMap<Station, HashSet<&TripKey>> = HashMap::new(); // Station -> trips serving it
+    let mut days: HashMap<Station, HashSet<&str>> = HashMap::new();       // Station -> active dates
+    let mut delays: HashMap<Station, (f32, usize)> = HashMap::new();      // Station -> (total delay, arrivals)
+    let trips = reconstruct_trips(records);
+    // Trips in key order, so each station's delay total is summed in the same order on every run
+    let mut ordered: Vec<(&TripKey, &Vec<&TrainRecord>)> = trips.iter().collect();
+    ordered.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, segments) in ordered {
+        for r in segments {
+            for station in [r.from_station(), r.to_station()] {
+                served.entry(station.clone()).or_default().insert(key);
+                days.entry(station).or_default().insert(key.0.as_str());
+            }
+            if let Some(delay) = r.delay() {
+                let entry = delays.entry(r.to_station()).or_insert((0.0, 0));
+                entry.0 += delay; // Accumulate delay
+                entry.1 += 1;     // Count arrivals
+            }
+        }
+    }
+    served.into_iter()
+        .map(|(station, trips)| {
+            let active_days = days.get(&station).map_or(1, |d| d.len().max(1));
+            let trips_per_day = trips.len() as f32 / active_days as f32;
+            let average_delay = delays.get(&station).map_or(0.0, |(total, count)| total / *count as f32);
+            StationThroughput {
+                station,
+                trips: trips.len(),
+                days: active_days,
+                trips_per_day,
+                average_delay,
+                congestion_score: trips_per_day * average_delay,
+            }
+        })
+        .collect()
+}
+
+// Returns top N stations by congestion score
+pub fn rank_stations_by_congestion(records: &[TrainRecord], opts: &RankOptions) -> Vec<StationThroughput> {
+    let mut stations = station_throughput(records);
+    stations.retain(|s| s.trips >= opts.min_trips); // Filter stations with too few trips
+    stations.sort_by(|a, b| b.congestion_score.total_cmp(&a.congestion_score).then_with(|| a.station.cmp(&b.station)));
+    stations.truncate(opts.top_n);
+    stations
+}
@@ -0,0 +1,169 @@
+// Continuous ingestion (`nj-delays daemon`): each fetch of the data source is merged into an accumulated dataset in a
+// state directory, and a snapshot of the headline numbers is written per day, keeping the most recent days
+// Layout of the state directory:
+//   records.csv               every distinct record fetched so far, in the dataset's CSV format (so `--data` can read it)
+//   snapshots/YYYY-MM-DD.json the day's latest snapshot, rewritten on each fetch that day
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use crate::graph::TransitGraph;
+use crate::live::{line_summaries, LineSummary};
+use crate::load::{load_data, RecordFilter, TrainRecord};
+use crate::metrics::{RankOptions, RouteStat};
+
+// File of accumulated records in the state directory
+pub const RECORDS_FILE: &str = "records.csv";
+
+// Directory of daily snapshots in the state directory
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+// Identifies a record across fetches: one stop of one train on one day
+type RecordKey = (String, String, String, String, String);
+
+fn record_key(record: &TrainRecord) -> RecordKey {
+    (record.date.clone(), record.train_id.clone(), record.stop_sequence.clone(), record.from_id.clone(), record.to_id.clone())
+}
+
+// Headline numbers over everything ingested up to one fetch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySnapshot {
+    pub date: NaiveDate,
+    pub at: DateTime<Local>, // When the fetch finished
+    pub records: usize,      // Accumulated records passing the filter
+    pub stations: usize,
+    pub routes: usize,
+    pub average_delay: f32,
+    pub on_time_rate: f32,
+    pub lines: Vec<LineSummary>,
+    pub worst_routes: Vec<RouteStat>, // Top --top routes by average delay
+}
+
+// What one fetch changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub fetched: usize, // Records in the source
+    pub added: usize,   // Of those, records not seen in an earlier fetch
+    pub total: usize,   // Accumulated records
+    pub snapshot: PathBuf,
+    pub rotated: usize, // Snapshots of older days removed
+}
+
+// The accumulated dataset of a state directory
+pub struct Ingestor {
+    dir: PathBuf,
+    keep_days: usize,
+    filter: RecordFilter,
+    opts: RankOptions,
+    known: HashSet<RecordKey>,
+    records: Vec<TrainRecord>,
+}
+
+impl Ingestor {
+    // Continues from the records already in `dir`, if any
+    // Input: snapshots of the last `keep_days` days (at least 1) are kept; the filter and options shape the snapshots
+    // Output: a load error if the accumulated records exist but cannot be read
+    pub fn open(dir: impl Into<PathBuf>, keep_days: usize, filter: RecordFilter, opts: RankOptions) -> Result<Self> {
+        let dir = dir.into();
+        let path = dir.join(RECORDS_FILE);
+        let records = if path.exists() { load_data(&path.to_string_lossy())? } else { Vec::new() };
+        let known = records.iter().map(record_key).collect();
+        Ok(Self { dir, keep_days: keep_days.max(1), filter, opts, known, records })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Every record ingested so far, in fetch order
+    pub fn records(&self) -> &[TrainRecord] {
+        &self.records
+    }
+
+    // Merges one fetch: appends its new records, rewrites today's snapshot, and removes snapshots beyond `keep_days`
+    // Logic: records are new if no earlier record has the same date, train, stop sequence and stop IDs; duplicates
+    // within the fetch count once
+    pub fn ingest(&mut self, fetched: Vec<TrainRecord>) -> io::Result<IngestReport> {
+        let count = fetched.len();
+        let added: Vec<TrainRecord> = fetched.into_iter().filter(|r| self.known.insert(record_key(r))).collect();
+        fs::create_dir_all(self.dir.join(SNAPSHOTS_DIR))?;
+        self.append(&added)?;
+        let added = added.len();
+        let snapshot = self.write_snapshot()?;
+        let rotated = self.rotate()?;
+        Ok(IngestReport { fetched: count, added, total: self.records.len(), snapshot, rotated })
+    }
+
+    // Appends records to the accumulated CSV, writing the header if the file is new
+    fn append(&mut self, added: &[TrainRecord]) -> io::Result<()> {
+        let path = self.dir.join(RECORDS_FILE);
+        let header = !path.exists();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut writer = csv::WriterBuilder::new().has_headers(header).from_writer(file);
+        for record in added {
+            writer.serialize(record).map_err(io::Error::other)?;
+        }
+        writer.flush()?;
+        self.records.extend_from_slice(added);
+        Ok(())
+    }
+
+    // Every record ingested so far that passes the filter
+    pub fn filtered_records(&self) -> Vec<TrainRecord> {
+        self.filter.apply(self.records.clone())
+    }
+
+    // The snapshot of the accumulated records
+    pub fn snapshot(&self) -> DailySnapshot {
+        let records = self.filtered_records();
+        let graph = TransitGraph::from_records(&records);
+        let delays: Vec<f32> = records.iter().filter_map(|r| r.delay()).collect();
+        let count = delays.len().max(1) as f32;
+        let at = Local::now();
+        DailySnapshot {
+            date: at.date_naive(),
+            at,
+            records: records.len(),
+            stations: graph.all_stations().len(),
+            routes: graph.lines.len(),
+            average_delay: delays.iter().sum::<f32>() / count,
+            on_time_rate: delays.iter().filter(|&&d| d <= self.opts.on_time_threshold).count() as f32 / count,
+            lines: line_summaries(&records, self.opts.on_time_threshold),
+            worst_routes: graph.rank_routes_by_average_delay(&self.opts),
+        }
+    }
+
+    // Writes today's snapshot, replacing an earlier one from today
+    // Logic: written to a temporary file and renamed into place, so readers never see a partial snapshot
+    fn write_snapshot(&self) -> io::Result<PathBuf> {
+        let snapshot = self.snapshot();
+        let path = self.dir.join(SNAPSHOTS_DIR).join(format!("{}.json", snapshot.date));
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, serde_json::to_vec_pretty(&snapshot)?)?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    // Removes the oldest daily snapshots beyond `keep_days`
+    // Output: the number removed
+    fn rotate(&self) -> io::Result<usize> {
+        let mut days: Vec<PathBuf> = Vec::new();
+        for entry in fs::read_dir(self.dir.join(SNAPSHOTS_DIR))? {
+            let path = entry?.path();
+            let dated = path.file_stem().and_then(|s| s.to_str()).is_some_and(|s| s.parse::<NaiveDate>().is_ok());
+            if dated && path.extension().is_some_and(|e| e == "json") {
+                days.push(path);
+            }
+        }
+        days.sort(); // ISO dates sort chronologically
+        let expired = days.len().saturating_sub(self.keep_days);
+        for path in &days[..expired] {
+            fs::remove_file(path)?;
+        }
+        Ok(expired)
+    }
+}
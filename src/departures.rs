@@ -0,0 +1,230 @@
+// Current departures from NJ Transit's public rail data API (the feed behind DepartureVision), turned into train
+// records so today's live delays can be set against the historical graph
+// The API answers a station's departure board (getTrainSchedule) to a token from getToken; both are form POSTs under
+// DEFAULT_API_URL. Each departure becomes one record from the board's station to the train's next stop, with the
+// seconds the API reports it running late as the delay and "estimated" as the status
+// The API names stations by two-letter codes, so records carry those as stop IDs; comparisons with a historical graph
+// match stations by name instead
+
+use chrono::{Datelike, Duration, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::graph::{Graph, Station, TransitGraph};
+use crate::load::TrainRecord;
+
+// Base URL of the rail data API's train data endpoints
+pub const DEFAULT_API_URL: &str = "https://raildata.njtransit.com/api/TrainData";
+
+// Formats of the API's timestamps, e.g. "16-Oct-2026 05:12:00 PM"; the last one is the dataset's own
+const TIME_FORMATS: [&str; 3] = ["%d-%b-%Y %I:%M:%S %p", "%m/%d/%Y %I:%M:%S %p", "%Y-%m-%d %H:%M:%S"];
+
+// One station's departure board as getTrainSchedule answers it; fields the analysis does not use are ignored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DepartureBoard {
+    #[serde(rename = "STATION_2CHAR", default)]
+    pub station_code: String,
+    #[serde(rename = "STATIONNAME", default)]
+    pub station_name: String,
+    #[serde(rename = "ITEMS", default, deserialize_with = "null_as_empty")]
+    pub departures: Vec<Departure>,
+}
+
+// One train on a departure board
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Departure {
+    #[serde(rename = "TRAIN_ID", default)]
+    pub train_id: String,
+    #[serde(rename = "LINE", default)]
+    pub line: String,
+    #[serde(rename = "DESTINATION", default)]
+    pub destination: String,
+    #[serde(rename = "SCHED_DEP_DATE", default)]
+    pub scheduled: String,
+    #[serde(rename = "STATUS", default)]
+    pub status: String, // Board text, e.g. "All Aboard", "in 5 Min", "Cancelled"
+    #[serde(rename = "SEC_LATE", default, deserialize_with = "seconds")]
+    pub seconds_late: Option<f32>, // Unset when the API has no estimate yet
+    #[serde(rename = "STOPS", default, deserialize_with = "null_as_empty")]
+    pub stops: Vec<DepartureStop>,
+}
+
+// A stop of a departing train, in running order; the list may start at stops upstream of the board's own station
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DepartureStop {
+    #[serde(rename = "STATION_2CHAR", default)]
+    pub station_code: String,
+    #[serde(rename = "STATIONNAME", default)]
+    pub station_name: String,
+    #[serde(rename = "DEPARTED", default)]
+    pub departed: String, // "YES" once the train has left the stop
+}
+
+// A live departure set against the historical delays of its route and station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepartureComparison {
+    pub train_id: String,
+    pub line: String,
+    pub from: String,
+    pub to: String,
+    pub scheduled: String,
+    pub live_delay: Option<f32>,       // Minutes the train is running late now
+    pub route_baseline: Option<f32>,   // Historical mean delay from `from` to `to`, if the graph has the route
+    pub station_baseline: Option<f32>, // Historical mean delay of every route leaving `from`
+    pub excess: Option<f32>,           // Live delay minus the route baseline, or the station baseline without one
+}
+
+// SEC_LATE comes as a number or a numeric string, empty when unknown
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(n) => n.as_f64().map(|n| n as f32),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn null_as_empty<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn parse_time(text: &str) -> Option<NaiveDateTime> {
+    TIME_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(text.trim(), format).ok())
+}
+
+impl DepartureBoard {
+    // One record per departure with a parseable scheduled time, from this station to the train's next stop (its
+    // destination if the API lists no stops)
+    // Logic: the next stop is the one after this station in the stop list, which may start upstream; if the list
+    // leaves this station out, it is the first stop the train has not yet departed
+    // Logic: the actual time is the scheduled time plus the seconds late; a cancelled train keeps a missing delay
+    pub fn to_records(&self) -> Vec<TrainRecord> {
+        self.departures.iter()
+            .filter_map(|d| {
+                let scheduled = parse_time(&d.scheduled)?;
+                let next = match d.stops.iter().position(|s| s.station_code == self.station_code) {
+                    Some(at) => d.stops.get(at + 1),
+                    None => d.stops.iter().find(|s| !s.departed.trim().eq_ignore_ascii_case("yes")),
+                };
+                let (to, to_id) = match next {
+                    Some(stop) => (stop.station_name.clone(), stop.station_code.clone()),
+                    None => (d.destination.clone(), String::new()),
+                };
+                let cancelled = d.status.trim().eq_ignore_ascii_case("cancelled");
+                let delay = d.seconds_late.filter(|_| !cancelled).map(|s| s / 60.0);
+                let actual = scheduled + Duration::seconds(delay.map_or(0, |d| (d * 60.0).round() as i64));
+                Some(TrainRecord {
+                    date: scheduled.format("%Y-%m-%d").to_string(),
+                    train_id: d.train_id.clone(),
+                    stop_sequence: String::new(),
+                    from: self.station_name.clone(),
+                    from_id: self.station_code.clone(),
+                    to,
+                    to_id,
+                    scheduled_time: scheduled.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    actual_time: if delay.is_some() { actual.format("%Y-%m-%d %H:%M:%S").to_string() } else { String::new() },
+                    delay_minutes: delay,
+                    status: if cancelled { "cancelled".into() } else { "estimated".into() },
+                    line: d.line.clone(),
+                    r#type: "NJ Transit".into(),
+                    month: scheduled.month().to_string(),
+                    year: scheduled.year().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+// Sets each live record against the historical graph: the mean delay of its route and of every route leaving its
+// origin, with stations matched by name (exactly, or by the best non-fuzzy match)
+pub fn compare_departures(live: &[TrainRecord], graph: &TransitGraph) -> Vec<DepartureComparison> {
+    live.iter()
+        .map(|record| {
+            let from = match_station(graph, &record.from);
+            let to = match_station(graph, &record.to);
+            let mean = |delays: Vec<f32>| (!delays.is_empty()).then(|| delays.iter().sum::<f32>() / delays.len() as f32);
+            let station_baseline = from.as_ref().and_then(|from| mean(graph.neighbors(from).map(|(_, d)| d).collect()));
+            let route_baseline = from.as_ref().zip(to.as_ref())
+                .and_then(|(from, to)| mean(graph.neighbors(from).filter(|(n, _)| *n == to).map(|(_, d)| d).collect()));
+            let live_delay = record.delay();
+            DepartureComparison {
+                train_id: record.train_id.clone(),
+                line: record.line.clone(),
+                from: record.from.clone(),
+                to: record.to.clone(),
+                scheduled: record.scheduled_time.clone(),
+                live_delay,
+                route_baseline,
+                station_baseline,
+                excess: live_delay.zip(route_baseline.or(station_baseline)).map(|(live, base)| live - base),
+            }
+        })
+        .collect()
+}
+
+fn match_station(graph: &TransitGraph, name: &str) -> Option<Station> {
+    graph.resolve_station(name).ok().or_else(|| graph.find_stations(name, 1).into_iter().find(|m| m.kind != "fuzzy").map(|m| m.station))
+}
+
+// A session with the rail data API
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct RailDataClient {
+    base: String,
+    token: String,
+}
+
+#[cfg(feature = "async")]
+impl RailDataClient {
+    // A client with a token already issued by getToken
+    pub fn with_token(base: impl Into<String>, token: impl Into<String>) -> Self {
+        Self { base: base.into().trim_end_matches('/').to_string(), token: token.into() }
+    }
+
+    // Signs in with the API's developer credentials
+    // Output: a fetch error if the API cannot be reached or does not issue a token
+    pub async fn login(base: &str, username: &str, password: &str) -> crate::Result<Self> {
+        let base = base.trim_end_matches('/');
+        let url = format!("{}/getToken", base);
+        let body = post_form(&url, &[("username", username), ("password", password)]).await?;
+        let token = serde_json::from_slice::<serde_json::Value>(&body).ok()
+            .and_then(|answer| answer["UserToken"].as_str().map(str::to_string))
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| crate::Error::Fetch { url: url.clone(), reason: "the credentials were not accepted".into() })?;
+        Ok(Self::with_token(base, token))
+    }
+
+    // The current departure board of a station
+    // Input: the station's two-letter code, e.g. NY for New York Penn Station
+    // Output: a fetch error if the API cannot be reached, rejects the request, or answers something other than a board
+    pub async fn departures(&self, station: &str) -> crate::Result<DepartureBoard> {
+        let url = format!("{}/getTrainSchedule", self.base);
+        let body = post_form(&url, &[("token", &self.token), ("station", station)]).await?;
+        serde_json::from_slice(&body).map_err(|e| crate::Error::Fetch { url, reason: format!("unexpected answer: {}", e) })
+    }
+}
+
+// POSTs a URL-encoded form, answering the response body
+#[cfg(feature = "async")]
+async fn post_form(url: &str, fields: &[(&str, &str)]) -> crate::Result<Vec<u8>> {
+    let failed = |e: reqwest::Error| crate::Error::Fetch { url: url.to_string(), reason: e.without_url().to_string() };
+    let body: Vec<String> = fields.iter().map(|(key, value)| format!("{}={}", key, form_encode(value))).collect();
+    let response = reqwest::Client::new()
+        .post(url)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(body.join("&"))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(failed)?;
+    Ok(response.bytes().await.map_err(failed)?.to_vec())
+}
+
+// Percent-encodes a form value (application/x-www-form-urlencoded)
+#[cfg(feature = "async")]
+fn form_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'*' => (b as char).to_string(),
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
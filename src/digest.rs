@@ -0,0 +1,122 @@
+// Email digest of a report run (`report --email-to`): the headline numbers, the worst routes, the on-time trend of the
+// last months, and the lines and routes whose latest day ran markedly later than the days before, as a plain-text and
+// an HTML body for one message
+// Sending is in `mail` (the `email` feature); this module only decides what the digest says
+
+use serde::{Deserialize, Serialize};
+use crate::comparison::{summarize, SideSummary};
+use crate::export::{escape, ReportMetadata};
+use crate::graph::TransitGraph;
+use crate::live::{delay_anomalies, AnomalyThresholds, DelayAnomaly};
+use crate::load::TrainRecord;
+use crate::metrics::{describe_threshold, RouteStat};
+use crate::report::{html_table, monthly_trend, GroupOnTime};
+use crate::textplot::sparkline;
+
+// Months of on-time trend in a digest
+pub const TREND_MONTHS: usize = 6;
+
+// What one digest reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Digest {
+    pub subject: String,
+    pub summary: SideSummary,
+    pub worst_routes: Vec<RouteStat>,   // Top --top routes by average delay
+    pub trend: Vec<GroupOnTime>,        // The last TREND_MONTHS months, oldest first
+    pub latest_day: Option<String>,     // Date the anomalies are about; None without records on two dates
+    pub anomalies: Vec<DelayAnomaly>,   // Highest z-score first
+}
+
+// Records on the latest date, and those on every earlier date
+// Logic: dates compare as text, which orders the dataset's YYYY-MM-DD dates chronologically
+fn split_latest_day(records: &[TrainRecord]) -> Option<(String, Vec<TrainRecord>, Vec<TrainRecord>)> {
+    let latest = records.iter().map(|r| r.date.trim()).filter(|d| !d.is_empty()).max()?.to_string();
+    let (recent, baseline): (Vec<TrainRecord>, Vec<TrainRecord>) = records.iter().cloned().partition(|r| r.date.trim() == latest);
+    (!baseline.is_empty()).then_some((latest, recent, baseline))
+}
+
+impl Digest {
+    // Summarizes a run's (filtered) records and graph with its report metadata
+    pub fn new(records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata, thresholds: &AnomalyThresholds) -> Self {
+        let opts = &metadata.parameters;
+        let summary = summarize("run", &metadata.dataset, records, graph, opts.on_time_threshold);
+        let mut trend = monthly_trend(records, opts.on_time_threshold);
+        trend.drain(..trend.len().saturating_sub(TREND_MONTHS));
+        let (latest_day, anomalies) = match split_latest_day(records) {
+            Some((day, recent, baseline)) => {
+                let anomalies = delay_anomalies(&baseline, &recent, opts, thresholds);
+                (Some(day), anomalies)
+            }
+            None => (None, Vec::new()),
+        };
+        let subject = format!(
+            "NJ Transit delay digest: {:.1}% on time, {:.1} min average{}",
+            summary.on_time_rate * 100.0,
+            summary.average_delay,
+            match anomalies.len() {
+                0 => String::new(),
+                1 => ", 1 anomaly".to_string(),
+                n => format!(", {} anomalies", n),
+            },
+        );
+        Self { subject, summary, worst_routes: graph.rank_routes_by_average_delay(opts), trend, latest_day, anomalies }
+    }
+
+    // The plain-text body
+    pub fn text(&self, metadata: &ReportMetadata) -> String {
+        let threshold = describe_threshold(metadata.parameters.on_time_threshold);
+        let mut lines = vec![
+            format!("{} records from {} ({} to {})", self.summary.records, metadata.dataset, metadata.first_date.as_deref().unwrap_or("?"), metadata.last_date.as_deref().unwrap_or("?")),
+            format!("Average delay {:.1} min; {:.1}% on time ({})", self.summary.average_delay, self.summary.on_time_rate * 100.0, threshold),
+            String::new(),
+            "Worst routes by average delay:".to_string(),
+        ];
+        for (i, route) in self.worst_routes.iter().enumerate() {
+            lines.push(format!("{:>3}. {} → {}: {:.1} min over {} trips", i + 1, route.from.name, route.to.name, route.average_delay, route.trips));
+        }
+        let rates: Vec<Option<f32>> = self.trend.iter().map(|m| Some(m.on_time_rate)).collect();
+        lines.push(String::new());
+        lines.push(format!("On-time trend {}:", sparkline(&rates)));
+        for month in &self.trend {
+            lines.push(format!("  {}: {:.1}% on time, {:.1} min average over {} trips", month.group, month.on_time_rate * 100.0, month.average_delay, month.trips));
+        }
+        lines.push(String::new());
+        match &self.latest_day {
+            Some(day) if self.anomalies.is_empty() => lines.push(format!("No delay anomalies on {}.", day)),
+            Some(day) => {
+                lines.push(format!("Delay anomalies on {} vs the days before:", day));
+                for a in &self.anomalies {
+                    lines.push(format!("  {}: {:.1} min over {} trips vs {:.1} min before (z = {:.1})", a.name, a.mean_delay, a.trips, a.baseline_delay, a.z_score));
+                }
+            }
+            None => lines.push("Anomalies need records on at least two dates.".to_string()),
+        }
+        lines.push(String::new());
+        lines.push(format!("Generated {} by nj-delays {}; the full report is attached.", metadata.generated_at, metadata.tool_version));
+        lines.join("\n")
+    }
+
+    // The HTML body, with the same sections as tables
+    pub fn html(&self, metadata: &ReportMetadata) -> String {
+        let table = |rows: serde_json::Result<serde_json::Value>| match rows {
+            Ok(serde_json::Value::Array(rows)) => html_table(&rows),
+            _ => String::new(),
+        };
+        let anomalies = match &self.latest_day {
+            Some(day) => format!("<h2>Delay anomalies on {}</h2>\n{}", escape(day), table(serde_json::to_value(&self.anomalies))),
+            None => "<h2>Delay anomalies</h2>\n<p>Anomalies need records on at least two dates.</p>".to_string(),
+        };
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head><body>\n<h1>{title}</h1>\n<p>{meta}</p>\n\
+             <h2>Worst routes</h2>\n{routes}\n<h2>On-time trend</h2>\n{trend}\n{anomalies}\n<p>The full report is attached.</p>\n</body></html>\n",
+            title = escape(&self.subject),
+            meta = escape(&format!(
+                "{} records from {} · average delay {:.1} min · {:.1}% on time ({}) · generated {}",
+                self.summary.records, metadata.dataset, self.summary.average_delay, self.summary.on_time_rate * 100.0,
+                describe_threshold(metadata.parameters.on_time_threshold), metadata.generated_at,
+            )),
+            routes = table(serde_json::to_value(&self.worst_routes)),
+            trend = table(serde_json::to_value(&self.trend)),
+        )
+    }
+}
@@ -0,0 +1,136 @@
+// All-pairs least delays as one flat row-major `Vec<f32>` over u16 station indices, NaN where a station is not
+// reachable: 4 bytes per pair (about 100 KB for the 160-station network, 16 GB at the u16 limit) instead of a map per
+// station holding Station keys, so diameter, closeness and isochrone queries over every pair stay in memory
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::graph::{Station, TransitGraph, Weight};
+use crate::metrics::{index_graph, SearchContext};
+
+// Position of a station in a `DistanceMatrix`, in station order
+pub type StationIndex = u16;
+
+// Largest network a matrix can index
+pub const MAX_STATIONS: usize = StationIndex::MAX as usize + 1;
+
+// Least delay from every station to every station
+#[derive(Debug, Clone)]
+pub struct DistanceMatrix {
+    stations: Vec<Station>, // Sorted; index i is row and column i
+    departs: Vec<bool>,     // Whether the station has outgoing segments, i.e. counts as a closeness target
+    distances: Vec<f32>,    // Row-major n × n minutes; NaN if unreachable, 0 on the diagonal
+}
+
+// The longest least-delay journey in the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diameter {
+    pub from: Station,
+    pub to: Station,
+    pub delay: f32, // Minutes of accumulated delay along the least-delay path
+}
+
+impl<W: Weight> TransitGraph<W> {
+    // All-pairs least delays; see `DistanceMatrix::new`
+    pub fn distance_matrix(&self) -> Result<DistanceMatrix> {
+        DistanceMatrix::new(self)
+    }
+}
+
+impl DistanceMatrix {
+    // One Dijkstra per station, rows filled in parallel with per-thread search buffers
+    // Output: an InvalidParameter error if the graph has more stations than a u16 can index
+    pub fn new<W: Weight>(graph: &TransitGraph<W>) -> Result<Self> {
+        let (nodes, lists) = index_graph(graph);
+        let n = nodes.len();
+        if n > MAX_STATIONS {
+            return Err(Error::InvalidParameter {
+                name: "distance matrix",
+                reason: format!("{} stations exceed the {} a matrix can index", n, MAX_STATIONS),
+            });
+        }
+        let mut distances = vec![f32::NAN; n * n];
+        distances.par_chunks_mut(n.max(1)).enumerate().for_each_init(
+            || SearchContext::new(n),
+            |search, (from, row)| {
+                search.dijkstra(&lists, from, None);
+                for (cell, distance) in row.iter_mut().zip(search.distances()) {
+                    if let Some(distance) = distance {
+                        *cell = distance.minutes();
+                    }
+                }
+            },
+        );
+        let departs = lists.iter().map(|edges| !edges.is_empty()).collect();
+        Ok(Self { stations: nodes.into_iter().cloned().collect(), departs, distances })
+    }
+
+    // Number of stations (rows and columns)
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    pub fn index_of(&self, station: &Station) -> Option<StationIndex> {
+        self.stations.binary_search(station).ok().map(|i| i as StationIndex)
+    }
+
+    pub fn station(&self, index: StationIndex) -> &Station {
+        &self.stations[index as usize]
+    }
+
+    // Least delays from one station to each station in index order, NaN where unreachable
+    pub fn row(&self, from: StationIndex) -> &[f32] {
+        let n = self.len();
+        &self.distances[from as usize * n..(from as usize + 1) * n]
+    }
+
+    // Least delay from one station to another, or None if it is not reachable
+    pub fn get(&self, from: StationIndex, to: StationIndex) -> Option<f32> {
+        Some(self.row(from)[to as usize]).filter(|d| !d.is_nan())
+    }
+
+    // The reachable pair with the largest least delay; ties go to the pair that sorts first
+    // Output: None if no station reaches another
+    pub fn diameter(&self) -> Option<Diameter> {
+        let n = self.len();
+        let mut longest: Option<(usize, f32)> = None;
+        for (cell, &delay) in self.distances.iter().enumerate() {
+            if cell / n != cell % n && !delay.is_nan() && longest.is_none_or(|(_, best)| delay > best) {
+                longest = Some((cell, delay));
+            }
+        }
+        longest.map(|(cell, delay)| Diameter { from: self.stations[cell / n].clone(), to: self.stations[cell % n].clone(), delay })
+    }
+
+    // Closeness of a station: reachable targets over their total least delay, with the same targets (stations with
+    // outgoing segments) and summation order as `closeness_centrality`, so the scores are identical
+    pub fn closeness(&self, station: StationIndex) -> Option<f32> {
+        let (mut total_delay, mut reachable) = (0.0, 0);
+        for (other, &delay) in self.row(station).iter().enumerate() {
+            if other == station as usize || delay.is_nan() || !self.departs[other] {
+                continue;
+            }
+            total_delay += delay;
+            reachable += 1;
+        }
+        (total_delay != 0.0 && reachable != 0).then(|| reachable as f32 / total_delay)
+    }
+
+    // Stations reachable from `from` within `budget` minutes of accumulated delay, with that delay, nearest first
+    // (ties in station order); the origin is included at 0
+    pub fn within(&self, from: StationIndex, budget: f32) -> Vec<(Station, f32)> {
+        let mut reachable: Vec<(usize, f32)> = self.row(from).iter().copied().enumerate()
+            .filter(|&(_, delay)| delay <= budget) // NaN compares false, so unreachable stations drop out
+            .collect();
+        reachable.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        reachable.into_iter().map(|(i, delay)| (self.stations[i].clone(), delay)).collect()
+    }
+}
@@ -0,0 +1,34 @@
+// Crate-wide error type: everything the library can fail on, for callers to match on instead of parsing messages
+
+use crate::graph::{join_names, Station};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // A data or coordinates file could not be opened or a row could not be parsed
+    #[error("failed to load {path}: {source}")]
+    Load { path: String, #[source] source: csv::Error },
+    // A remote data source could not be downloaded
+    #[error("failed to fetch {url}: {reason}")]
+    Fetch { url: String, reason: String },
+    // An alert could not be delivered to a webhook (named by its host only, since webhook paths carry secrets)
+    #[error("failed to notify {url}: {reason}")]
+    Notify { url: String, reason: String },
+    // A computation handed to a worker thread did not finish because its runtime is shutting down
+    #[error("the computation was cancelled")]
+    Cancelled,
+    // A station name not in the graph, with the closest names as suggestions
+    #[error("unknown station {station:?}{}", did_you_mean(suggestions))]
+    UnknownStation { station: String, suggestions: Vec<Station> },
+    // No segment has a delay to weight it, so there is nothing to analyze
+    #[error("the graph is empty: no delays among {records} records")]
+    EmptyGraph { records: usize },
+    // An argument outside the range an analysis accepts
+    #[error("invalid {name}: {reason}")]
+    InvalidParameter { name: &'static str, reason: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn did_you_mean(suggestions: &[Station]) -> String {
+    if suggestions.is_empty() { String::new() } else { format!("; did you mean {}?", join_names(suggestions, ", ")) }
+}
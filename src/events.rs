@@ -0,0 +1,169 @@
+// Train records from an event stream instead of a file: each message on a NATS subject or Kafka topic is one JSON
+// train record with the fields of a CSV row (`delay_minutes` a number or null). A stream has no end, so consumption
+// stops after a number of records or once none arrives for a while, and the streaming pipeline then reports as for a file
+// Sources are written as nats://HOST[:PORT]/SUBJECT or kafka://BROKER[,BROKER...]/TOPIC[?group=GROUP]
+
+use std::fmt;
+use std::time::Duration;
+use crate::error::{Error, Result};
+use crate::load::TrainRecord;
+
+// Kafka consumer group unless the source names one
+pub const DEFAULT_GROUP: &str = "nj-delays";
+
+// Where train-record events come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventSource {
+    Nats { server: String, subject: String },
+    Kafka { brokers: String, topic: String, group: String },
+}
+
+// When to stop consuming
+#[derive(Debug, Clone, Copy)]
+pub struct EventLimits {
+    pub max_records: Option<usize>, // Stop after this many records; unbounded if None
+    pub idle: Duration,             // Stop once no message arrives for this long
+}
+
+// What a consumer read
+#[derive(Debug, Clone, Default)]
+pub struct EventStats {
+    pub records: usize,              // Messages parsed as train records
+    pub skipped: usize,              // Messages that were not a train record
+    pub first_error: Option<String>, // Why the first skipped message failed to parse
+}
+
+impl EventStats {
+    // Parses one message, passing it on as a record or counting it as skipped; for consumers of other brokers
+    pub fn add(&mut self, payload: &[u8], on_record: &mut impl FnMut(TrainRecord)) {
+        match parse_event(payload) {
+            Ok(record) => {
+                self.records += 1;
+                on_record(record);
+            }
+            Err(e) => {
+                self.skipped += 1;
+                self.first_error.get_or_insert_with(|| e.to_string());
+            }
+        }
+    }
+}
+
+impl EventLimits {
+    // Whether a consumer that has read `records` records should stop
+    pub fn reached(&self, records: usize) -> bool {
+        self.max_records.is_some_and(|max| records >= max)
+    }
+}
+
+// One message as a train record
+pub fn parse_event(payload: &[u8]) -> serde_json::Result<TrainRecord> {
+    serde_json::from_slice(payload)
+}
+
+impl EventSource {
+    // The event source a --data value names, or None for files and URLs
+    // Output: an invalid-parameter error for a nats:// or kafka:// source without a host or subject/topic
+    pub fn parse(source: &str) -> Result<Option<Self>> {
+        let (scheme, rest) = match source.split_once("://") {
+            Some((scheme @ ("nats" | "kafka"), rest)) => (scheme, rest),
+            _ => return Ok(None),
+        };
+        let invalid = || Error::InvalidParameter {
+            name: "event source",
+            reason: format!("{:?} is not {}://HOST/{}", source, scheme, if scheme == "nats" { "SUBJECT" } else { "TOPIC[?group=GROUP]" }),
+        };
+        let (host, path) = rest.split_once('/').filter(|(host, path)| !host.is_empty() && !path.is_empty()).ok_or_else(invalid)?;
+        Ok(Some(if scheme == "nats" {
+            EventSource::Nats { server: format!("nats://{}", host), subject: path.to_string() }
+        } else {
+            let (topic, group) = match path.split_once("?group=") {
+                Some((topic, group)) if !topic.is_empty() && !group.is_empty() => (topic, group),
+                Some(_) => return Err(invalid()),
+                None => (path, DEFAULT_GROUP),
+            };
+            EventSource::Kafka { brokers: host.to_string(), topic: topic.to_string(), group: group.to_string() }
+        }))
+    }
+
+    // Reads records until a limit is reached, passing each to `on_record` as it arrives
+    // Output: what was read, or a fetch error if the broker cannot be reached or the subscription fails; messages that
+    // are not train records are skipped and counted
+    #[cfg_attr(not(all(feature = "nats", feature = "kafka")), allow(unused_variables, unused_mut))]
+    pub fn consume(&self, limits: &EventLimits, mut on_record: impl FnMut(TrainRecord)) -> Result<EventStats> {
+        match self {
+            #[cfg(feature = "nats")]
+            EventSource::Nats { server, subject } => consume_nats(server, subject, limits, &mut on_record),
+            #[cfg(not(feature = "nats"))]
+            EventSource::Nats { .. } => Err(self.missing_feature("nats")),
+            #[cfg(feature = "kafka")]
+            EventSource::Kafka { brokers, topic, group } => consume_kafka(brokers, topic, group, limits, &mut on_record),
+            #[cfg(not(feature = "kafka"))]
+            EventSource::Kafka { .. } => Err(self.missing_feature("kafka")),
+        }
+    }
+
+    #[cfg(not(all(feature = "nats", feature = "kafka")))]
+    fn missing_feature(&self, feature: &str) -> Error {
+        Error::InvalidParameter { name: "event source", reason: format!("reading {} needs the `{}` feature", self, feature) }
+    }
+}
+
+impl fmt::Display for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventSource::Nats { server, subject } => write!(f, "{}/{}", server, subject),
+            EventSource::Kafka { brokers, topic, group } => write!(f, "kafka://{}/{}?group={}", brokers, topic, group),
+        }
+    }
+}
+
+// Subscribes to `subject` and reads messages on a single-threaded runtime until a limit is reached
+#[cfg(feature = "nats")]
+fn consume_nats(server: &str, subject: &str, limits: &EventLimits, on_record: &mut impl FnMut(TrainRecord)) -> Result<EventStats> {
+    use futures_util::StreamExt;
+    let failed = |reason: String| Error::Fetch { url: format!("{}/{}", server, subject), reason };
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| failed(e.to_string()))?;
+    runtime.block_on(async {
+        let client = async_nats::connect(server).await.map_err(|e| failed(e.to_string()))?;
+        let mut subscriber = client.subscribe(subject.to_string()).await.map_err(|e| failed(e.to_string()))?;
+        let mut stats = EventStats::default();
+        while !limits.reached(stats.records) {
+            match tokio::time::timeout(limits.idle, subscriber.next()).await {
+                Ok(Some(message)) => stats.add(&message.payload, on_record),
+                Ok(None) | Err(_) => break, // Subscription closed, or idle for too long
+            }
+        }
+        Ok(stats)
+    })
+}
+
+// Joins `group` on `topic` (from the earliest offset the group has not committed) and polls until a limit is reached
+#[cfg(feature = "kafka")]
+fn consume_kafka(brokers: &str, topic: &str, group: &str, limits: &EventLimits, on_record: &mut impl FnMut(TrainRecord)) -> Result<EventStats> {
+    use std::time::Instant;
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::{ClientConfig, Message};
+    let failed = |e: rdkafka::error::KafkaError| Error::Fetch { url: format!("kafka://{}/{}", brokers, topic), reason: e.to_string() };
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("group.id", group)
+        .set("auto.offset.reset", "earliest")
+        .create()
+        .map_err(failed)?;
+    consumer.subscribe(&[topic]).map_err(failed)?;
+    let mut stats = EventStats::default();
+    let mut last_message = Instant::now();
+    while !limits.reached(stats.records) {
+        match consumer.poll(limits.idle.min(Duration::from_millis(100))) {
+            Some(Ok(message)) => {
+                stats.add(message.payload().unwrap_or_default(), on_record);
+                last_message = Instant::now();
+            }
+            Some(Err(e)) => return Err(failed(e)),
+            None if last_message.elapsed() >= limits.idle => break,
+            None => {}
+        }
+    }
+    Ok(stats)
+}
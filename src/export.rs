@@ -0,0 +1,803 @@
+// Writes the graph and its metrics to files for other tools: CSV tables, GraphML, GeoJSON, kepler.gl arcs, D3 node-link JSON, Prometheus metrics, Vega-Lite chart specs, a JSON report, Parquet tables, an Excel workbook, and a SQLite database
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::congestion::StationThroughput;
+use crate::flows::SegmentFlow;
+use crate::forecast::RouteForecast;
+use crate::graph::{Station, TransitGraph};
+use crate::load::{Coordinates, RecordFilter, TrainRecord};
+use crate::metrics::{RankOptions, RouteStat, StationScore};
+use crate::topology::LinkPrediction;
+use crate::report::{line_on_time, monthly_trend, Preset};
+use crate::stats::histogram;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportKind {
+    /// CSV: per-route average delay and trip count
+    Routes,
+    /// CSV: per-station closeness and betweenness centrality
+    Stations,
+    /// GraphML: the delay graph with centralities, coordinates, mean delay and trips, for yEd and NetworkX
+    Graphml,
+    /// GeoJSON: station points and delay-weighted route lines for web maps (requires --coords)
+    Geojson,
+    /// CSV: one arc per route between station coordinates, weighted by delay, for kepler.gl's arc layer (requires --coords)
+    Kepler,
+    /// JSON: {nodes, links} with centrality and delay attributes, the shape D3 force-directed layouts consume
+    D3,
+    /// Prometheus text format: per-line average delay, on-time ratio, trips and cancellations, for textfile collectors
+    Prometheus,
+    /// JSON: a Vega-Lite spec of the monthly trend, delay histogram and worst-route ranking, data inlined
+    VegaLite,
+    /// JSON: every ranking and metric in one document, with dataset, filter and parameter metadata
+    Report,
+    /// Parquet: per-route trips, mean/median/p90 delay and on-time rate, typed (requires the `parquet` feature)
+    RoutesParquet,
+    /// Parquet: per-station degree, trips, lines, inbound delay and centrality, typed (requires the `parquet` feature)
+    StationsParquet,
+    /// Excel: one sheet per analysis (worst routes, lines, stations, monthly trend) plus run metadata (requires the `xlsx` feature)
+    Xlsx,
+    /// SQLite: stations, routes, per-route stats and centrality as relational tables (requires --out and the `sqlite` feature)
+    Sqlite,
+}
+
+impl ExportKind {
+    // The cargo feature this export needs, if this build was compiled without it
+    pub fn missing_feature(self) -> Option<&'static str> {
+        match self {
+            ExportKind::RoutesParquet | ExportKind::StationsParquet if !cfg!(feature = "parquet") => Some("parquet"),
+            ExportKind::Xlsx if !cfg!(feature = "xlsx") => Some("xlsx"),
+            ExportKind::Sqlite if !cfg!(feature = "sqlite") => Some("sqlite"),
+            _ => None,
+        }
+    }
+}
+
+// Where a report's numbers came from and how they were computed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportMetadata {
+    pub generated_at: String,        // RFC 3339 local time the report was produced
+    pub tool_version: &'static str,
+    pub dataset: String,             // Path of the records file
+    pub filters: RecordFilter,
+    pub parameters: RankOptions,
+    pub records: usize,              // Records after filtering
+    pub first_date: Option<String>,  // Earliest service date in the records
+    pub last_date: Option<String>,   // Latest service date in the records
+}
+
+impl ReportMetadata {
+    pub fn new(dataset: &str, filters: &RecordFilter, parameters: &RankOptions, records: &[TrainRecord]) -> Self {
+        Self {
+            generated_at: chrono::Local::now().to_rfc3339(),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            dataset: dataset.to_string(),
+            filters: filters.clone(),
+            parameters: *parameters,
+            records: records.len(),
+            first_date: records.iter().map(|r| &r.date).min().cloned(),
+            last_date: records.iter().map(|r| &r.date).max().cloned(),
+        }
+    }
+}
+
+// Writes the requested export
+// Input: export kind, records and graph, optional station coordinates (GraphML, GeoJSON), report metadata, and destination
+pub fn write_export<W: Write>(
+    kind: ExportKind,
+    records: &[TrainRecord],
+    graph: &TransitGraph,
+    coords: Option<&Coordinates>,
+    metadata: &ReportMetadata,
+    mut writer: W,
+) -> io::Result<()> {
+    match kind {
+        ExportKind::Routes | ExportKind::Stations => export_csv(graph, kind, writer).map_err(io::Error::from),
+        ExportKind::Graphml => to_graphml(graph, coords, writer),
+        ExportKind::Geojson => match coords {
+            Some(coords) => to_geojson(graph, coords, writer),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "GeoJSON export needs station coordinates (--coords)")),
+        },
+        ExportKind::Kepler => match coords {
+            Some(coords) => to_kepler_csv(graph, coords, writer).map_err(io::Error::from),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "kepler.gl export needs station coordinates (--coords)")),
+        },
+        ExportKind::D3 => to_d3_json(graph, writer),
+        ExportKind::Prometheus => to_prometheus(records, metadata, writer),
+        ExportKind::VegaLite => {
+            serde_json::to_writer_pretty(&mut writer, &vega_lite(records, graph, &metadata.parameters))?;
+            writeln!(writer)?;
+            writer.flush()
+        }
+        ExportKind::Report => {
+            serde_json::to_writer_pretty(&mut writer, &report_json(records, graph, metadata))?;
+            writeln!(writer)?;
+            writer.flush()
+        }
+        ExportKind::RoutesParquet | ExportKind::StationsParquet => to_parquet(kind, records, graph, metadata, writer),
+        ExportKind::Xlsx => to_xlsx(records, graph, metadata, writer),
+        ExportKind::Sqlite => Err(io::Error::new(io::ErrorKind::InvalidInput, "SQLite export writes a database file, not a stream (use to_sqlite)")),
+    }
+}
+
+// Writes the requested metric table as CSV with a header row
+pub fn export_csv<W: Write>(graph: &TransitGraph, kind: ExportKind, writer: W) -> Result<(), csv::Error> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    match kind {
+        ExportKind::Routes => {
+            let mut routes = graph.get_route_average_delays();
+            routes.sort_by(|a, b| a.0.cmp(&b.0));
+            wtr.write_record(["from", "to", "average_delay", "trips"])?;
+            for ((from, to), avg, count) in routes {
+                wtr.write_record([from.name, to.name, format!("{:.4}", avg), count.to_string()])?;
+            }
+        }
+        ExportKind::Stations => {
+            let betweenness = graph.betweenness_centrality();
+            let mut stations: Vec<_> = graph.all_stations().into_iter().collect();
+            stations.sort();
+            wtr.write_record(["station", "closeness", "betweenness"])?;
+            for station in stations {
+                let closeness = graph.closeness_centrality(&station).map_or(String::new(), |c| format!("{:.6}", c));
+                let between = betweenness.get(&station).copied().unwrap_or(0.0);
+                wtr.write_record([station.name, closeness, format!("{:.4}", between)])?;
+            }
+        }
+        ExportKind::Graphml | ExportKind::Geojson | ExportKind::Kepler | ExportKind::D3 | ExportKind::Prometheus | ExportKind::VegaLite | ExportKind::Report | ExportKind::RoutesParquet | ExportKind::StationsParquet | ExportKind::Xlsx | ExportKind::Sqlite => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a CSV table").into());
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Writes the graph as directed GraphML, one edge per aggregated (from, to) route
+// Node data: closeness (omitted if undefined), betweenness, lat/lon (if known); edge data: mean_delay, trips, lines
+// Logic: stations and routes are written in name order so the output is stable across runs
+pub fn to_graphml<W: Write>(graph: &TransitGraph, coords: Option<&Coordinates>, mut out: W) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">"#)?;
+    for (id, domain, name, kind) in [
+        ("closeness", "node", "closeness", "double"),
+        ("betweenness", "node", "betweenness", "double"),
+        ("lat", "node", "lat", "double"),
+        ("lon", "node", "lon", "double"),
+        ("mean_delay", "edge", "mean_delay", "double"),
+        ("trips", "edge", "trips", "int"),
+        ("lines", "edge", "lines", "string"),
+    ] {
+        writeln!(out, r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#, id, domain, name, kind)?;
+    }
+    writeln!(out, r#"  <graph id="nj-transit" edgedefault="directed">"#)?;
+    let betweenness = graph.betweenness_centrality();
+    let mut stations: Vec<_> = graph.all_stations().into_iter().collect();
+    stations.sort();
+    for station in &stations {
+        writeln!(out, r#"    <node id="{}">"#, escape(&station.name))?;
+        if let Some(closeness) = graph.closeness_centrality(station) {
+            writeln!(out, r#"      <data key="closeness">{}</data>"#, closeness)?;
+        }
+        writeln!(out, r#"      <data key="betweenness">{}</data>"#, betweenness.get(station).copied().unwrap_or(0.0))?;
+        if let Some((lat, lon)) = coords.and_then(|c| c.get(&station.name)) {
+            writeln!(out, r#"      <data key="lat">{}</data>"#, lat)?;
+            writeln!(out, r#"      <data key="lon">{}</data>"#, lon)?;
+        }
+        writeln!(out, "    </node>")?;
+    }
+    let mut routes = graph.get_route_average_delays();
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    for (i, ((from, to), mean_delay, trips)) in routes.iter().enumerate() {
+        let mut lines: Vec<&String> = graph.lines.get(&(from.clone(), to.clone())).into_iter().flatten().collect();
+        lines.sort();
+        let lines: Vec<&str> = lines.into_iter().map(String::as_str).collect();
+        writeln!(out, r#"    <edge id="e{}" source="{}" target="{}">"#, i, escape(&from.name), escape(&to.name))?;
+        writeln!(out, r#"      <data key="mean_delay">{}</data>"#, mean_delay)?;
+        writeln!(out, r#"      <data key="trips">{}</data>"#, trips)?;
+        writeln!(out, r#"      <data key="lines">{}</data>"#, escape(&lines.join(", ")))?;
+        writeln!(out, "    </edge>")?;
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    out.flush()
+}
+
+// Builds one JSON document holding every ranking and network metric
+// Output: {"metadata": {...}, "sections": {"<slug>": {"title", "rows"}, ...}}
+// Logic: the full report preset's sections, plus degree mixing and (with linalg) the Laplacian spectrum
+pub fn report_json(records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> Value {
+    let opts = &metadata.parameters;
+    let mut sections = Map::new();
+    for section in Preset::Full.sections() {
+        sections.insert(section.slug().to_string(), json!({ "title": section.title(opts), "rows": section.rows(records, graph, opts) }));
+    }
+    if let Some(assortativity) = graph.degree_assortativity() {
+        sections.insert("degree-assortativity".to_string(), json!({ "title": "Degree assortativity and mixing", "rows": [assortativity] }));
+    }
+    #[cfg(feature = "linalg")]
+    if let Some(spectrum) = graph.laplacian_spectrum(5) {
+        sections.insert("laplacian-spectrum".to_string(), json!({ "title": "Laplacian spectrum of the largest component", "rows": [spectrum] }));
+    }
+    json!({ "metadata": metadata, "sections": sections })
+}
+
+// One entry of any ranking in a shared, typed layout for spreadsheets and BI tools
+// Station rankings fill `station`; route and link rankings fill `from` and `to`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankingRow {
+    pub ranking: String,
+    pub rank: usize,              // 1-based position in the ranking
+    pub station: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub score: f32,               // The value the ranking is ordered by
+    pub trips: Option<usize>,     // Trips behind the score, where the ranking counts them
+}
+
+// What a ranking entry is about
+pub enum Subject<'a> {
+    Station(&'a str),
+    Route(&'a str, &'a str), // (from, to), or the station pair of a predicted link
+}
+
+// A ranking entry that can be written as a RankingRow
+pub trait Ranked {
+    fn subject(&self) -> Subject<'_>;
+    // The value the ranking is ordered by
+    fn score(&self) -> f32;
+    // Trips behind the score, if the ranking counts them
+    fn trips(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl Ranked for StationScore {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Station(&self.station.name)
+    }
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+impl Ranked for RouteStat {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Route(&self.from.name, &self.to.name)
+    }
+    fn score(&self) -> f32 {
+        self.average_delay
+    }
+    fn trips(&self) -> Option<usize> {
+        Some(self.trips)
+    }
+}
+
+impl Ranked for StationThroughput {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Station(&self.station.name)
+    }
+    fn score(&self) -> f32 {
+        self.congestion_score
+    }
+    fn trips(&self) -> Option<usize> {
+        Some(self.trips)
+    }
+}
+
+impl Ranked for SegmentFlow {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Route(&self.from.name, &self.to.name)
+    }
+    fn score(&self) -> f32 {
+        self.passenger_delay
+    }
+    fn trips(&self) -> Option<usize> {
+        Some(self.trips)
+    }
+}
+
+impl Ranked for RouteForecast {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Route(&self.from.name, &self.to.name)
+    }
+    fn score(&self) -> f32 {
+        self.ewma
+    }
+}
+
+impl Ranked for LinkPrediction {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Route(&self.a.name, &self.b.name)
+    }
+    fn score(&self) -> f32 {
+        self.adamic_adar
+    }
+}
+
+// Converts ranking entries, in order, to typed rows
+pub fn ranking_rows<T: Ranked>(ranking: &str, entries: &[T]) -> Vec<RankingRow> {
+    entries.iter().enumerate()
+        .map(|(i, entry)| {
+            let (station, from, to) = match entry.subject() {
+                Subject::Station(station) => (Some(station.to_string()), None, None),
+                Subject::Route(from, to) => (None, Some(from.to_string()), Some(to.to_string())),
+            };
+            RankingRow { ranking: ranking.to_string(), rank: i + 1, station, from, to, score: entry.score(), trips: entry.trips() }
+        })
+        .collect()
+}
+
+// Writes ranking entries as CSV: ranking,rank,station,from,to,score,trips
+// Output: number of rows written
+pub fn write_ranking_csv<W: Write, T: Ranked>(ranking: &str, entries: &[T], writer: W) -> Result<usize, csv::Error> {
+    let rows = ranking_rows(ranking, entries);
+    let mut wtr = csv::Writer::from_writer(writer);
+    for row in &rows {
+        wtr.serialize(row)?;
+    }
+    if rows.is_empty() {
+        wtr.write_record(["ranking", "rank", "station", "from", "to", "score", "trips"])?; // Header only
+    }
+    wtr.flush()?;
+    Ok(rows.len())
+}
+
+// Writes a GeoJSON FeatureCollection: a Point per station and a LineString per route, in name order
+// Station properties: name, closeness, betweenness; route properties: from, to, mean_delay, trips, lines
+// Logic: stations without coordinates are left out, along with every route touching them and self-loops
+pub fn to_geojson<W: Write>(graph: &TransitGraph, coords: &Coordinates, mut out: W) -> io::Result<()> {
+    let position = |station: &Station| coords.get(&station.name).map(|(lat, lon)| json!([lon, lat])); // GeoJSON is [lon, lat]
+    let betweenness = graph.betweenness_centrality();
+    let mut stations: Vec<_> = graph.all_stations().into_iter().collect();
+    stations.sort();
+    let mut features: Vec<Value> = stations.iter()
+        .filter_map(|station| {
+            Some(json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": position(station)? },
+                "properties": {
+                    "kind": "station",
+                    "name": station,
+                    "closeness": graph.closeness_centrality(station),
+                    "betweenness": betweenness.get(station).copied().unwrap_or(0.0),
+                },
+            }))
+        })
+        .collect();
+    let mut routes = graph.get_route_average_delays();
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    for ((from, to), mean_delay, trips) in routes {
+        let (Some(start), Some(end)) = (position(&from), position(&to)) else { continue };
+        if from == to {
+            continue;
+        }
+        let mut lines: Vec<&String> = graph.lines.get(&(from.clone(), to.clone())).into_iter().flatten().collect();
+        lines.sort();
+        features.push(json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": [start, end] },
+            "properties": { "kind": "route", "from": from, "to": to, "mean_delay": mean_delay, "trips": trips, "lines": lines },
+        }));
+    }
+    serde_json::to_writer(&mut out, &json!({ "type": "FeatureCollection", "features": features }))?;
+    writeln!(out)?;
+    out.flush()
+}
+
+// Writes the graph as D3 node-link JSON: {"nodes": [...], "links": [...]}, in name order
+// Node fields: id, closeness (null if undefined), betweenness, degree; link fields: source, target (node ids), mean_delay, trips, lines
+// Logic: self-loops are left out, since force layouts draw them as zero-length links
+pub fn to_d3_json<W: Write>(graph: &TransitGraph, mut out: W) -> io::Result<()> {
+    let betweenness = graph.betweenness_centrality();
+    let mut stations: Vec<_> = graph.all_stations().into_iter().collect();
+    stations.sort();
+    let mut routes = graph.get_route_average_delays();
+    routes.retain(|((from, to), _, _)| from != to);
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for ((from, to), _, _) in &routes {
+        *degree.entry(from.as_str()).or_default() += 1;
+        *degree.entry(to.as_str()).or_default() += 1;
+    }
+    let nodes: Vec<Value> = stations.iter()
+        .map(|station| json!({
+            "id": station,
+            "closeness": graph.closeness_centrality(station),
+            "betweenness": betweenness.get(station).copied().unwrap_or(0.0),
+            "degree": degree.get(station.as_str()).copied().unwrap_or(0),
+        }))
+        .collect();
+    let links: Vec<Value> = routes.iter()
+        .map(|((from, to), mean_delay, trips)| {
+            let mut lines: Vec<&String> = graph.lines.get(&(from.clone(), to.clone())).into_iter().flatten().collect();
+            lines.sort();
+            json!({ "source": from, "target": to, "mean_delay": mean_delay, "trips": trips, "lines": lines })
+        })
+        .collect();
+    serde_json::to_writer(&mut out, &json!({ "nodes": nodes, "links": links }))?;
+    writeln!(out)?;
+    out.flush()
+}
+
+// Writes current aggregate metrics in the Prometheus text exposition format, one gauge family per metric
+// Per line (label `line`, names trimmed): average delay, on-time ratio, trips with a delay, and cancelled trips;
+// plus the network-wide record count and the on-time threshold the ratios use
+// Logic: values are snapshots of the loaded records, so every family is a gauge; lines are in name order. A line with
+// cancellations but no delayed trips (cancelled records often carry no delay) has trips and cancellations only
+pub fn to_prometheus<W: Write>(records: &[TrainRecord], metadata: &ReportMetadata, mut out: W) -> io::Result<()> {
+    let threshold = metadata.parameters.on_time_threshold;
+    let on_time = line_on_time(records, threshold);
+    let mut cancelled: HashMap<&str, usize> = HashMap::new();
+    for r in records.iter().filter(|r| r.status.trim().eq_ignore_ascii_case("cancelled")) {
+        *cancelled.entry(r.line.trim()).or_default() += 1;
+    }
+    let mut lines: Vec<&str> = on_time.iter().map(|l| l.group.as_str()).chain(cancelled.keys().copied()).collect();
+    lines.sort();
+    lines.dedup();
+    let families = [
+        ("njtransit_line_average_delay_minutes", "Mean delay of trips on the line, in minutes"),
+        ("njtransit_line_on_time_ratio", "Share of trips on the line at or below the on-time threshold"),
+        ("njtransit_line_trips", "Trips on the line with a recorded delay"),
+        ("njtransit_line_cancellations", "Trips on the line with status cancelled"),
+    ];
+    // One value per family for each line, in family order; None where the line has no delayed trip to measure
+    let values: Vec<[Option<f32>; 4]> = lines.iter()
+        .map(|&line| {
+            let stats = on_time.iter().find(|l| l.group == line);
+            [
+                stats.map(|l| l.average_delay),
+                stats.map(|l| l.on_time_rate),
+                Some(stats.map_or(0, |l| l.trips) as f32),
+                Some(cancelled.get(line).copied().unwrap_or(0) as f32),
+            ]
+        })
+        .collect();
+    for (i, (name, help)) in families.iter().enumerate() {
+        writeln!(out, "# HELP {} {}", name, help)?;
+        writeln!(out, "# TYPE {} gauge", name)?;
+        for (line, value) in lines.iter().zip(&values) {
+            if let Some(value) = value[i] {
+                writeln!(out, "{}{{line=\"{}\"}} {}", name, prometheus_label(line), value)?;
+            }
+        }
+    }
+    writeln!(out, "# HELP njtransit_records Train records loaded after filters")?;
+    writeln!(out, "# TYPE njtransit_records gauge")?;
+    writeln!(out, "njtransit_records {}", records.len())?;
+    writeln!(out, "# HELP njtransit_on_time_threshold_minutes Delay at or below which a trip counts as on time")?;
+    writeln!(out, "# TYPE njtransit_on_time_threshold_minutes gauge")?;
+    writeln!(out, "njtransit_on_time_threshold_minutes {}", threshold)?;
+    out.flush()
+}
+
+// Escapes a Prometheus label value: backslash, double quote, and newline
+pub(crate) fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+const VEGA_LITE_SCHEMA: &str = "https://vega.github.io/schema/vega-lite/v5.json";
+const VEGA_HISTOGRAM_BIN_MINUTES: f32 = 2.0;
+const VEGA_HISTOGRAM_MAX_MINUTES: f32 = 60.0;
+
+// Standalone Vega-Lite specs, each with its data inlined: ("monthly-trend" | "delay-histogram" | "worst-routes", spec)
+pub fn vega_lite_specs(records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions) -> Vec<(&'static str, Value)> {
+    let months: Vec<Value> = monthly_trend(records, opts.on_time_threshold).into_iter()
+        .map(|m| json!({ "month": m.group, "average_delay": m.average_delay, "on_time_rate": m.on_time_rate, "trips": m.trips }))
+        .collect();
+    let trend = json!({
+        "$schema": VEGA_LITE_SCHEMA,
+        "title": "Average delay by month",
+        "data": { "values": months },
+        "mark": { "type": "line", "point": true },
+        "encoding": {
+            "x": { "field": "month", "type": "temporal", "timeUnit": "yearmonth", "title": "Month" },
+            "y": { "field": "average_delay", "type": "quantitative", "title": "Average delay (minutes)" },
+            "tooltip": [{ "field": "month" }, { "field": "average_delay", "format": ".2f" }, { "field": "on_time_rate", "format": ".1%" }, { "field": "trips" }],
+        },
+    });
+    // Bins are precomputed so large datasets stay small; the overflow bin is drawn one bin wide and labelled "60+"
+    let delays: Vec<f32> = records.iter().filter_map(|r| r.delay()).collect();
+    let bins: Vec<Value> = histogram(&delays, VEGA_HISTOGRAM_BIN_MINUTES, VEGA_HISTOGRAM_MAX_MINUTES)
+        .unwrap_or_default() // The constant bin width and maximum are valid
+        .into_iter()
+        .map(|b| {
+            let label = if b.upper.is_finite() { format!("{}–{}", b.lower, b.upper) } else { format!("{}+", b.lower) };
+            let upper = if b.upper.is_finite() { b.upper } else { b.lower + VEGA_HISTOGRAM_BIN_MINUTES };
+            json!({ "lower": b.lower, "upper": upper, "label": label, "trips": b.count })
+        })
+        .collect();
+    let distribution = json!({
+        "$schema": VEGA_LITE_SCHEMA,
+        "title": "Delay distribution",
+        "data": { "values": bins },
+        "mark": "bar",
+        "encoding": {
+            "x": { "field": "lower", "type": "quantitative", "bin": "binned", "title": "Delay (minutes)" },
+            "x2": { "field": "upper" },
+            "y": { "field": "trips", "type": "quantitative", "title": "Trips" },
+            "tooltip": [{ "field": "label", "title": "Delay" }, { "field": "trips" }],
+        },
+    });
+    let routes: Vec<Value> = graph.rank_routes_by_average_delay(opts).into_iter()
+        .map(|r| json!({ "route": format!("{} → {}", r.from, r.to), "average_delay": r.average_delay, "trips": r.trips, "on_time_rate": r.on_time_rate }))
+        .collect();
+    let ranking = json!({
+        "$schema": VEGA_LITE_SCHEMA,
+        "title": format!("Top {} routes by average delay", routes.len()),
+        "data": { "values": routes },
+        "mark": "bar",
+        "encoding": {
+            "y": { "field": "route", "type": "nominal", "sort": "-x", "title": null },
+            "x": { "field": "average_delay", "type": "quantitative", "title": "Average delay (minutes)" },
+            "tooltip": [{ "field": "route" }, { "field": "average_delay", "format": ".2f" }, { "field": "trips" }, { "field": "on_time_rate", "format": ".1%" }],
+        },
+    });
+    vec![("monthly-trend", trend), ("delay-histogram", distribution), ("worst-routes", ranking)]
+}
+
+// All three charts stacked in one Vega-Lite spec; each entry of `vconcat` also works on its own
+pub fn vega_lite(records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions) -> Value {
+    let charts: Vec<Value> = vega_lite_specs(records, graph, opts).into_iter()
+        .map(|(name, mut spec)| {
+            if let Some(spec) = spec.as_object_mut() {
+                spec.shift_remove("$schema"); // Only the top level carries the schema
+                spec.insert("name".to_string(), json!(name));
+            }
+            spec
+        })
+        .collect();
+    json!({ "$schema": VEGA_LITE_SCHEMA, "vconcat": charts })
+}
+
+// Writes one CSV row per route with both endpoints' coordinates, in (from, to) order
+// kepler.gl recognizes the from_lat/from_lng and to_lat/to_lng column pairs and offers an arc layer sized or colored by mean_delay
+// Logic: as in the GeoJSON export, routes touching a station without coordinates and self-loops are left out
+pub fn to_kepler_csv<W: Write>(graph: &TransitGraph, coords: &Coordinates, out: W) -> Result<(), csv::Error> {
+    let mut wtr = csv::Writer::from_writer(out);
+    wtr.write_record(["from", "to", "from_lat", "from_lng", "to_lat", "to_lng", "mean_delay", "trips", "lines"])?;
+    let mut routes = graph.get_route_average_delays();
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    for ((from, to), mean_delay, trips) in routes {
+        let (Some(start), Some(end)) = (coords.get(&from.name), coords.get(&to.name)) else { continue };
+        if from == to {
+            continue;
+        }
+        let mut lines: Vec<&str> = graph.lines.get(&(from.clone(), to.clone())).into_iter().flatten().map(String::as_str).collect();
+        lines.sort();
+        wtr.write_record([
+            from.as_str(), to.as_str(),
+            &start.0.to_string(), &start.1.to_string(), &end.0.to_string(), &end.1.to_string(),
+            &format!("{:.4}", mean_delay), &trips.to_string(), &lines.join("; "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Escapes text for use in XML attributes and element content
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Writes the route or station metric table as one Parquet file, with the run metadata as file key/value metadata
+// Logic: the file is assembled in memory, since the Parquet writer needs a `Send` sink and these tables are small
+#[cfg(feature = "parquet")]
+pub fn to_parquet<W: Write>(kind: ExportKind, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata, mut writer: W) -> io::Result<()> {
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::metadata::KeyValue;
+    use parquet::file::properties::WriterProperties;
+    use crate::columnar::{route_table, station_table};
+
+    let table = match kind {
+        ExportKind::RoutesParquet => route_table(graph, &metadata.parameters),
+        ExportKind::StationsParquet => station_table(records, graph),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a Parquet table")),
+    }
+    .map_err(io::Error::other)?;
+    let about = serde_json::to_string(metadata).map_err(io::Error::other)?;
+    let properties = WriterProperties::builder()
+        .set_key_value_metadata(Some(vec![KeyValue::new("nj_delays.metadata".to_string(), about)]))
+        .build();
+    let mut buffer = Vec::new();
+    let mut parquet = ArrowWriter::try_new(&mut buffer, table.schema(), Some(properties)).map_err(io::Error::other)?;
+    parquet.write(&table).map_err(io::Error::other)?;
+    parquet.close().map_err(io::Error::other)?;
+    writer.write_all(&buffer)?;
+    writer.flush()
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn to_parquet<W: Write>(_kind: ExportKind, _records: &[TrainRecord], _graph: &TransitGraph, _metadata: &ReportMetadata, _writer: W) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Parquet export needs a build with --features parquet"))
+}
+
+// Writes an Excel workbook: worst routes, per-line on-time performance, station centrality, the monthly trend, and the run metadata
+// Each sheet has a bold frozen header row, numbers stored as numbers (rates as percentages), and fitted column widths
+#[cfg(feature = "xlsx")]
+pub fn to_xlsx<W: Write>(records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata, mut writer: W) -> io::Result<()> {
+    use rust_xlsxwriter::Workbook;
+    use crate::report::Section;
+    use crate::stations::station_summaries;
+
+    let opts = &metadata.parameters;
+    let betweenness = graph.betweenness_centrality();
+    let mut stations = station_summaries(records, graph);
+    stations.sort_by(|a, b| a.station.cmp(&b.station));
+    let stations: Vec<Value> = stations.iter()
+        .map(|s| json!({
+            "station": s.station,
+            "degree": s.degree,
+            "trips": s.trips,
+            "inbound_delay": s.inbound_delay,
+            "closeness": graph.closeness_centrality(&s.station),
+            "betweenness": betweenness.get(&s.station).copied().unwrap_or(0.0),
+            "lines": s.lines,
+        }))
+        .collect();
+    let (names, values) = crate::output::flatten_rows(std::slice::from_ref(metadata), true)?;
+    let about: Vec<Value> = names.iter().zip(values.iter().flatten())
+        .map(|(setting, value)| json!({ "setting": setting, "value": value }))
+        .collect();
+    let sheets = [
+        ("Worst routes", Section::WorstRoutes.rows(records, graph, opts)),
+        ("Lines", Section::LineOnTime.rows(records, graph, opts)),
+        ("Stations", stations),
+        ("Monthly trend", Section::MonthlyTrend.rows(records, graph, opts)),
+        ("About", about),
+    ];
+    let mut workbook = Workbook::new();
+    for (name, rows) in &sheets {
+        write_sheet(workbook.add_worksheet(), name, rows).map_err(io::Error::other)?;
+    }
+    writer.write_all(&workbook.save_to_buffer().map_err(io::Error::other)?)?;
+    writer.flush()
+}
+
+#[cfg(not(feature = "xlsx"))]
+pub fn to_xlsx<W: Write>(_records: &[TrainRecord], _graph: &TransitGraph, _metadata: &ReportMetadata, _writer: W) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Excel export needs a build with --features xlsx"))
+}
+
+// Fills one worksheet from flattened rows; all-numeric columns are written as numbers
+// Formats: "rate" columns as percentages, whole-number columns without decimals, everything else to two places
+#[cfg(feature = "xlsx")]
+fn write_sheet(sheet: &mut rust_xlsxwriter::Worksheet, name: &str, rows: &[Value]) -> Result<(), rust_xlsxwriter::XlsxError> {
+    use rust_xlsxwriter::{Format, FormatBorder};
+
+    sheet.set_name(name)?;
+    let (columns, cells) = crate::output::flatten_rows(rows, true)?;
+    let header = Format::new().set_bold().set_border_bottom(FormatBorder::Thin);
+    let formats: Vec<Option<Format>> = columns.iter().enumerate()
+        .map(|(c, column)| {
+            let values: Vec<&str> = cells.iter().map(|r| r[c].as_str()).filter(|v| !v.is_empty()).collect();
+            if values.is_empty() || values.iter().any(|v| v.parse::<f64>().is_err()) {
+                None
+            } else if column.contains("rate") {
+                Some(Format::new().set_num_format("0.0%"))
+            } else if values.iter().all(|v| !v.contains(['.', 'e', 'E'])) {
+                Some(Format::new().set_num_format("0"))
+            } else {
+                Some(Format::new().set_num_format("0.00"))
+            }
+        })
+        .collect();
+    for (c, column) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, c as u16, column, &header)?;
+    }
+    for (r, row) in cells.iter().enumerate() {
+        let r = r as u32 + 1;
+        for (c, text) in row.iter().enumerate() {
+            match (&formats[c], text.parse::<f64>()) {
+                (Some(format), Ok(number)) => sheet.write_number_with_format(r, c as u16, number, format)?,
+                _ => sheet.write_string(r, c as u16, text)?,
+            };
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    Ok(())
+}
+
+// Schema of the SQLite export; station and route ids are stable within one file only
+#[cfg(feature = "sqlite")]
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE stations (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        degree INTEGER NOT NULL,
+        trips INTEGER NOT NULL,
+        inbound_delay REAL,
+        lines TEXT NOT NULL
+    );
+    CREATE TABLE routes (
+        id INTEGER PRIMARY KEY,
+        from_station INTEGER NOT NULL REFERENCES stations(id),
+        to_station INTEGER NOT NULL REFERENCES stations(id),
+        UNIQUE (from_station, to_station)
+    );
+    CREATE TABLE route_stats (
+        route_id INTEGER PRIMARY KEY REFERENCES routes(id),
+        trips INTEGER NOT NULL,
+        mean_delay REAL,
+        median_delay REAL,
+        p90_delay REAL,
+        on_time_rate REAL
+    );
+    CREATE TABLE centrality (
+        station_id INTEGER NOT NULL REFERENCES stations(id),
+        measure TEXT NOT NULL,
+        score REAL,
+        PRIMARY KEY (station_id, measure)
+    );
+";
+
+// Writes stations, routes, per-route stats, centrality scores, and the run metadata into a new SQLite database
+// Input: database path (replaced if it exists), records and graph, and the report metadata
+// Logic: one transaction; stations get ids in name order, routes in (from, to) order, and every other table refers to those ids
+#[cfg(feature = "sqlite")]
+pub fn to_sqlite(path: &std::path::Path, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> rusqlite::Result<()> {
+    use rusqlite::{params, Connection};
+    use crate::routes::route_summaries;
+    use crate::stations::station_summaries;
+
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    }
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SQLITE_SCHEMA)?;
+
+    let (keys, values) = crate::output::flatten_rows(std::slice::from_ref(metadata), true)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    for (key, value) in keys.iter().zip(values.iter().flatten()) {
+        tx.execute("INSERT INTO metadata (key, value) VALUES (?1, ?2)", params![key, value])?;
+    }
+
+    let mut summaries = station_summaries(records, graph);
+    summaries.sort_by(|a, b| a.station.cmp(&b.station));
+    let mut station_ids: HashMap<Station, i64> = HashMap::new();
+    for (id, s) in (1..).zip(&summaries) {
+        let inbound = s.inbound_delay.is_finite().then_some(s.inbound_delay);
+        tx.execute(
+            "INSERT INTO stations (id, name, degree, trips, inbound_delay, lines) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, s.station.name, s.degree as i64, s.trips as i64, inbound, s.lines],
+        )?;
+        station_ids.insert(s.station.clone(), id);
+    }
+
+    let every_route = RankOptions { min_trips: 1, ..metadata.parameters };
+    let mut routes = route_summaries(graph, &every_route);
+    routes.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+    for (id, r) in (1i64..).zip(&routes) {
+        let (Some(from), Some(to)) = (station_ids.get(&r.from), station_ids.get(&r.to)) else { continue };
+        tx.execute("INSERT INTO routes (id, from_station, to_station) VALUES (?1, ?2, ?3)", params![id, from, to])?;
+        tx.execute(
+            "INSERT INTO route_stats (route_id, trips, mean_delay, median_delay, p90_delay, on_time_rate) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, r.trips as i64, r.mean_delay, r.median_delay, r.p90_delay, r.on_time_rate],
+        )?;
+    }
+
+    let all = RankOptions { top_n: usize::MAX, ..metadata.parameters };
+    for (measure, scores) in [("closeness", graph.rank_stations_by_closeness(&all)), ("betweenness", graph.rank_stations_by_betweenness(&all))] {
+        for s in scores {
+            let Some(id) = station_ids.get(&s.station) else { continue };
+            tx.execute("INSERT INTO centrality (station_id, measure, score) VALUES (?1, ?2, ?3)", params![id, measure, s.score])?;
+        }
+    }
+    tx.commit()
+}
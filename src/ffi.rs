@@ -0,0 +1,119 @@
+// C API for embedding the engine in non-Rust applications; see include/nj_delays.h for the declarations
+// Safety contract for every function: string arguments are NUL-terminated UTF-8 (or null), graph handles come from
+// `nj_graph_from_csv` and are not used after `nj_graph_free`, and returned strings are released with `nj_string_free`
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use serde_json::json;
+use crate::error::{Error, Result};
+use crate::graph::TransitGraph;
+use crate::load::load_data;
+use crate::metrics::RankOptions;
+
+thread_local! {
+    // Message of the last failed call on this thread, for `nj_last_error`
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Reads a string argument, or an invalid-parameter error naming it if it is null or not UTF-8
+unsafe fn read_str<'a>(s: *const c_char, name: &'static str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(Error::InvalidParameter { name, reason: "null pointer".to_string() });
+    }
+    unsafe { CStr::from_ptr(s) }.to_str().map_err(|e| Error::InvalidParameter { name, reason: e.to_string() })
+}
+
+// Hands a JSON value to the caller as an owned C string, or records the error and returns null
+fn json_result(result: Result<serde_json::Value>) -> *mut c_char {
+    match result {
+        Ok(value) => CString::new(value.to_string()).map(CString::into_raw).unwrap_or(ptr::null_mut()),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+// Builds the delay graph from a CSV file of train records
+// Output: an owned graph handle, or null on failure (see `nj_last_error`)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nj_graph_from_csv(path: *const c_char) -> *mut TransitGraph {
+    let graph = unsafe { read_str(path, "path") }.and_then(load_data).and_then(|records| TransitGraph::try_from_records(&records));
+    match graph {
+        Ok(graph) => Box::into_raw(Box::new(graph)),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+// Releases a graph handle; null is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nj_graph_free(graph: *mut TransitGraph) {
+    if !graph.is_null() {
+        drop(unsafe { Box::from_raw(graph) });
+    }
+}
+
+// Least-delay path between two stations (names or stop IDs) as JSON: {"delay": minutes, "stations": [names]}
+// Output: an owned string, "null" if no path exists, or null on failure (see `nj_last_error`)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nj_shortest_path_json(graph: *const TransitGraph, from: *const c_char, to: *const c_char) -> *mut c_char {
+    let Some(graph) = (unsafe { graph.as_ref() }) else {
+        return json_result(Err(Error::InvalidParameter { name: "graph", reason: "null pointer".to_string() }));
+    };
+    json_result((|| {
+        let from = graph.resolve_station(unsafe { read_str(from, "from") }?)?;
+        let to = graph.resolve_station(unsafe { read_str(to, "to") }?)?;
+        Ok(match graph.shortest_path(&from, &to) {
+            Some((delay, stations)) => json!({ "delay": delay, "stations": stations }),
+            None => serde_json::Value::Null,
+        })
+    })())
+}
+
+// Top-N ranking as a JSON array; `kind` is closeness, betweenness, worst-routes or best-routes
+// Routes need at least `min_trips` observations, as with the CLI's --min-trips
+// Output: an owned string, or null on failure (see `nj_last_error`)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nj_rankings_json(graph: *const TransitGraph, kind: *const c_char, top_n: usize, min_trips: usize) -> *mut c_char {
+    let Some(graph) = (unsafe { graph.as_ref() }) else {
+        return json_result(Err(Error::InvalidParameter { name: "graph", reason: "null pointer".to_string() }));
+    };
+    json_result((|| {
+        let opts = RankOptions { top_n, min_trips, ..Default::default() };
+        let value = match unsafe { read_str(kind, "kind") }?.trim() {
+            "closeness" => json!(graph.rank_stations_by_closeness(&opts)),
+            "betweenness" => json!(graph.rank_stations_by_betweenness(&opts)),
+            "worst-routes" => json!(graph.rank_routes_by_average_delay(&opts)),
+            "best-routes" => json!(graph.rank_routes_by_lowest_delay(&opts)),
+            other => return Err(Error::InvalidParameter {
+                name: "kind",
+                reason: format!("{:?} is not one of closeness, betweenness, worst-routes, best-routes", other),
+            }),
+        };
+        Ok(value)
+    })())
+}
+
+// Message of the last failed call on this thread, or null if none; valid until the next failing call on the thread
+#[unsafe(no_mangle)]
+pub extern "C" fn nj_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+// Releases a string returned by this API; null is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nj_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
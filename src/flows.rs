@@ -0,0 +1,176 @@
+// Passenger flows: a gravity-model origin-destination matrix assigned onto least-delay paths, so a segment's delay can
+// be weighted by the riders it holds up rather than by the trains that run it
+// A station's mass is its trip volume (records departing or arriving there). Demand between two stations is the
+// product of their masses over the number of segments on the least-delay path between them raised to `beta`, scaled
+// so the whole matrix sums to `passengers`, and every pair's demand rides each segment of that one path (all-or-nothing
+// assignment). The flows are estimates for comparing segments with each other, not ridership counts
+
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::graph::{FastMap, Station, TransitGraph};
+use crate::load::TrainRecord;
+use crate::metrics::{index_graph, RankOptions, SearchContext};
+
+// Passengers the demand matrix is scaled to unless set
+pub const DEFAULT_PASSENGERS: f32 = 100_000.0;
+
+// Distance-decay exponent unless set: doubling the segments between two stations quarters their demand
+pub const DEFAULT_BETA: f32 = 2.0;
+
+// How demand is modelled
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FlowOptions {
+    pub passengers: f32, // Total demand over every origin-destination pair
+    pub beta: f32,       // Distance-decay exponent on the segments of the path; 0 ignores distance
+}
+
+impl Default for FlowOptions {
+    fn default() -> Self {
+        Self { passengers: DEFAULT_PASSENGERS, beta: DEFAULT_BETA }
+    }
+}
+
+// The riders assigned to one segment and the delay they meet there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentFlow {
+    pub from: Station,
+    pub to: Station,
+    pub passengers: f32,      // Estimated riders whose least-delay path uses the segment
+    pub average_delay: f32,   // Mean delay over the segment's trips
+    pub trips: usize,
+    pub passenger_delay: f32, // passengers × average_delay, in passenger-minutes
+}
+
+// The network-wide outcome of an assignment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowSummary {
+    pub od_pairs: usize,                // Origin-destination pairs with demand
+    pub passengers: f32,
+    pub segments_used: usize,
+    pub average_journey_delay: f32,     // Least delay of a journey, averaged over passengers
+    pub passenger_weighted_delay: f32,  // Segment delay averaged over the riders on each segment
+    pub train_weighted_delay: f32,      // Segment delay averaged over trips, for comparison
+}
+
+// An assignment: the summary and every segment carrying riders, most passenger-minutes of delay first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowAssignment {
+    #[serde(flatten)]
+    pub summary: FlowSummary,
+    pub segments: Vec<SegmentFlow>,
+}
+
+// The gravity-model demand of one origin-destination pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OdDemand {
+    pub origin: Station,
+    pub destination: Station,
+    pub passengers: f32,
+    pub segments: usize,  // On the least-delay path
+    pub least_delay: f32,
+}
+
+// Builds the gravity-model demand between every pair of stations and assigns it onto least-delay paths
+// Output: an invalid-parameter error for a negative or non-finite passenger total or exponent, or if no two stations
+// with trips are joined by a path
+pub fn assign_flows(records: &[TrainRecord], graph: &TransitGraph, opts: &FlowOptions) -> Result<FlowAssignment> {
+    let mut load: FastMap<(&Station, &Station), f64> = FastMap::default();
+    let (mut journey_delay, mut od_pairs) = (0.0f64, 0);
+    let demand = gravity(records, graph, opts, |path, pair, delay| {
+        for leg in path.windows(2) {
+            *load.entry((leg[0], leg[1])).or_default() += pair;
+        }
+        journey_delay += pair * delay as f64;
+        od_pairs += 1;
+    })?;
+    let scale = opts.passengers as f64 / demand;
+    let delays: FastMap<(Station, Station), (f32, usize)> = graph.get_route_average_delays().into_iter()
+        .map(|(route, delay, trips)| (route, (delay, trips)))
+        .collect();
+    let mut segments: Vec<SegmentFlow> = load.into_iter()
+        .filter_map(|((from, to), riders)| {
+            let route = (from.clone(), to.clone());
+            let &(average_delay, trips) = delays.get(&route)?;
+            let (from, to) = route;
+            let passengers = (riders * scale) as f32;
+            Some(SegmentFlow { from, to, passengers, average_delay, trips, passenger_delay: passengers * average_delay })
+        })
+        .collect();
+    segments.sort_by(|a, b| b.passenger_delay.total_cmp(&a.passenger_delay).then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to))));
+    let riders: f32 = segments.iter().map(|s| s.passengers).sum();
+    let (train_delay, trips) = delays.values().fold((0.0, 0), |(total, count), &(delay, trips)| (total + delay * trips as f32, count + trips));
+    Ok(FlowAssignment {
+        summary: FlowSummary {
+            od_pairs,
+            passengers: opts.passengers,
+            segments_used: segments.len(),
+            average_journey_delay: (journey_delay / demand) as f32,
+            passenger_weighted_delay: if riders > 0.0 { segments.iter().map(|s| s.passenger_delay).sum::<f32>() / riders } else { 0.0 },
+            train_weighted_delay: train_delay / trips.max(1) as f32,
+        },
+        segments,
+    })
+}
+
+// The demand of every origin-destination pair joined by a path, most passengers first
+// Output: as for `assign_flows`
+pub fn od_demand(records: &[TrainRecord], graph: &TransitGraph, opts: &FlowOptions) -> Result<Vec<OdDemand>> {
+    let mut pairs = Vec::new();
+    let demand = gravity(records, graph, opts, |path, pair, delay| pairs.push((path[0], path[path.len() - 1], pair, path.len() - 1, delay)))?;
+    let scale = opts.passengers as f64 / demand;
+    let mut pairs: Vec<OdDemand> = pairs.into_iter()
+        .map(|(origin, destination, pair, segments, least_delay)| OdDemand {
+            origin: origin.clone(),
+            destination: destination.clone(),
+            passengers: (pair * scale) as f32,
+            segments,
+            least_delay,
+        })
+        .collect();
+    pairs.sort_by(|a, b| b.passengers.total_cmp(&a.passengers).then_with(|| (&a.origin, &a.destination).cmp(&(&b.origin, &b.destination))));
+    Ok(pairs)
+}
+
+// Runs the gravity model, passing every origin-destination pair joined by a path to `visit` with its least-delay path,
+// unscaled demand and least delay
+// Output: the total unscaled demand, or the errors of `assign_flows`
+fn gravity<'g>(records: &[TrainRecord], graph: &'g TransitGraph, opts: &FlowOptions, mut visit: impl FnMut(&[&'g Station], f64, f32)) -> Result<f64> {
+    if !(opts.passengers >= 0.0 && opts.passengers.is_finite()) {
+        return Err(Error::InvalidParameter { name: "passengers", reason: format!("{} is not a non-negative number", opts.passengers) });
+    }
+    if !(opts.beta >= 0.0 && opts.beta.is_finite()) {
+        return Err(Error::InvalidParameter { name: "beta", reason: format!("{} is not a non-negative exponent", opts.beta) });
+    }
+    let (nodes, lists) = index_graph(graph);
+    let index: FastMap<&Station, usize> = nodes.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let mut mass = vec![0.0f64; nodes.len()];
+    for record in records {
+        for id in [&record.from_id, &record.to_id] {
+            if let Some(&i) = graph.station(id).and_then(|s| index.get(s)) {
+                mass[i] += 1.0;
+            }
+        }
+    }
+    let mut demand = 0.0f64;
+    let mut search = SearchContext::new(nodes.len());
+    for origin in (0..nodes.len()).filter(|&i| mass[i] > 0.0) {
+        search.dijkstra(&lists, origin, None);
+        for destination in (0..nodes.len()).filter(|&j| j != origin && mass[j] > 0.0) {
+            let (Some(delay), Some(path)) = (search.distances()[destination], search.path_to(destination)) else { continue };
+            let pair = mass[origin] * mass[destination] / ((path.len() - 1) as f64).powf(opts.beta as f64);
+            let stations: Vec<&Station> = path.iter().map(|&i| nodes[i]).collect();
+            visit(&stations, pair, delay);
+            demand += pair;
+        }
+    }
+    if demand == 0.0 {
+        return Err(Error::InvalidParameter { name: "network", reason: "no two stations with trips are joined by a path".into() });
+    }
+    Ok(demand)
+}
+
+// Returns top N routes by passenger-minutes of delay under the default demand model; empty if nothing can be assigned
+pub fn rank_routes_by_passenger_delay(records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions) -> Vec<SegmentFlow> {
+    let Ok(assignment) = assign_flows(records, graph, &FlowOptions::default()) else { return Vec::new() };
+    assignment.segments.into_iter().filter(|s| s.trips >= opts.min_trips).take(opts.top_n).collect()
+}
@@ -0,0 +1,188 @@
+// Forecasts next-week average delay per route from its historical daily series (EWMA and Holt-Winters)
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::graph::Station;
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+
+// Number of seasons in the weekly cycle (one per weekday)
+const SEASON_LENGTH: usize = 7;
+
+// Days of history a route needs before it is forecast in a ranking
+pub const DEFAULT_MIN_DAYS: usize = 5;
+
+// Smoothing parameters shared by the EWMA and Holt-Winters models
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ForecastParams {
+    pub alpha: f32, // Level smoothing factor
+    pub beta: f32,  // Trend smoothing factor (Holt-Winters only)
+    pub gamma: f32, // Weekday seasonal smoothing factor (Holt-Winters only)
+    pub holdout: f32, // Fraction of the series held out for backtesting
+}
+
+impl Default for ForecastParams {
+    fn default() -> Self {
+        Self { alpha: 0.3, beta: 0.1, gamma: 0.2, holdout: 0.2 }
+    }
+}
+
+// One day of a route's delay history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DailyDelay {
+    pub date: NaiveDate, // Service date
+    pub average: f32,    // Average delay observed on that date, in minutes
+}
+
+// Backtest error of one-step-ahead forecasts over the held-out tail of a series
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BacktestError {
+    pub mae: f32,     // Mean absolute error in minutes
+    pub rmse: f32,    // Root mean squared error in minutes
+    pub points: usize, // Number of held-out observations scored
+}
+
+// Next-week forecast for one route, with backtest error for each model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteForecast {
+    pub from: Station,
+    pub to: Station,
+    pub observations: usize, // Number of days with data for this route
+    pub trips: usize,        // Trips with a delay over those days
+    pub ewma: f32,           // EWMA forecast of next week's average delay
+    pub holt_winters: f32,   // Holt-Winters forecast of next week's average delay
+    pub ewma_error: Option<BacktestError>, // None when the series is too short to backtest
+    pub holt_winters_error: Option<BacktestError>,
+}
+
+// Builds a daily average delay series for every (from, to) route
+// Input: slice of TrainRecord structs
+// Output: map from route to its days with data, sorted by date
+// Logic: Skip records without a delay or a parsable date, average delays per route per day
+pub fn daily_route_series(records: &[TrainRecord]) -> HashMap<(Station, Station), Vec<DailyDelay>> {
+    daily_route_sums(records).into_iter()
+        .map(|(route, days)| {
+            let series = days.into_iter()
+                .map(|(date, (total, count))| DailyDelay { date, average: total / count as f32 })
+                .collect();
+            (route, series)
+        })
+        .collect()
+}
+
+// Total delay and trips per route per day, for `daily_route_series`
+fn daily_route_sums(records: &[TrainRecord]) -> HashMap<(Station, Station), BTreeMap<NaiveDate, (f32, usize)>> {
+    let mut sums: HashMap<(Station, Station), BTreeMap<NaiveDate, (f32, usize)>> = HashMap::new();
+    for r in records {
+        let (Some(delay), Ok(date)) = (r.delay(), NaiveDate::parse_from_str(&r.date, "%Y-%m-%d")) else {
+            continue;
+        };
+        let entry = sums.entry((r.from_station(), r.to_station())).or_default().entry(date).or_insert((0.0, 0));
+        entry.0 += delay; // Accumulate delay
+        entry.1 += 1;     // Count trips
+    }
+    sums
+}
+
+// Exponentially weighted moving average of the series; the flat forecast for every future day
+// Returns None for an empty series
+pub fn ewma(series: &[DailyDelay], alpha: f32) -> Option<f32> {
+    let (first, rest) = series.split_first()?;
+    let mut level = first.average;
+    for day in rest {
+        level = alpha * day.average + (1.0 - alpha) * level;
+    }
+    Some(level)
+}
+
+// Additive Holt-Winters forecast averaged over the next seven days
+// Seasonality is indexed by weekday rather than position, since routes are not observed every day
+// Returns None for an empty series
+pub fn holt_winters(series: &[DailyDelay], params: &ForecastParams) -> Option<f32> {
+    let first = series.first()?;
+    let mut level = first.average;
+    let mut trend = 0.0;
+    let mut seasonal = [0.0f32; SEASON_LENGTH];
+    for day in &series[1..] {
+        let s = day.date.weekday().num_days_from_monday() as usize;
+        let previous_level = level;
+        level = params.alpha * (day.average - seasonal[s]) + (1.0 - params.alpha) * (level + trend);
+        trend = params.beta * (level - previous_level) + (1.0 - params.beta) * trend;
+        seasonal[s] = params.gamma * (day.average - level) + (1.0 - params.gamma) * seasonal[s];
+    }
+    // Average the forecast over one step per weekday following the last observation
+    let last = series[series.len() - 1].date;
+    let total: f32 = (1..=SEASON_LENGTH)
+        .map(|h| {
+            let s = (last + chrono::Duration::days(h as i64)).weekday().num_days_from_monday() as usize;
+            level + trend * h as f32 + seasonal[s]
+        })
+        .sum();
+    Some(total / SEASON_LENGTH as f32)
+}
+
+// Scores one-step-ahead forecasts of `model` over the last `holdout` fraction of the series
+// Returns None when there are not enough points for both a training prefix and a holdout
+pub fn backtest<F>(series: &[DailyDelay], holdout: f32, model: F) -> Option<BacktestError>
+where
+    F: Fn(&[DailyDelay]) -> Option<f32>,
+{
+    let held = ((series.len() as f32 * holdout).round() as usize).max(1);
+    if series.len() < held + 2 {
+        return None;
+    }
+    let start = series.len() - held;
+    let mut abs_total = 0.0;
+    let mut sq_total = 0.0;
+    for i in start..series.len() {
+        let predicted = model(&series[..i])?;
+        let err = predicted - series[i].average;
+        abs_total += err.abs();
+        sq_total += err * err;
+    }
+    Some(BacktestError {
+        mae: abs_total / held as f32,
+        rmse: (sq_total / held as f32).sqrt(),
+        points: held,
+    })
+}
+
+// Forecasts next-week average delay for every route with at least `min_days` days of data
+// Output: Vec of RouteForecast, unsorted
+pub fn forecast_routes(records: &[TrainRecord], params: &ForecastParams, min_days: usize) -> Vec<RouteForecast> {
+    let mut forecasts = Vec::new();
+    for ((from, to), days) in daily_route_sums(records) {
+        if days.len() < min_days.max(1) {
+            continue;
+        }
+        let trips = days.values().map(|(_, count)| count).sum();
+        let series: Vec<DailyDelay> = days.into_iter()
+            .map(|(date, (total, count))| DailyDelay { date, average: total / count as f32 })
+            .collect();
+        let (Some(ewma_forecast), Some(hw_forecast)) = (ewma(&series, params.alpha), holt_winters(&series, params)) else {
+            continue;
+        };
+        forecasts.push(RouteForecast {
+            from,
+            to,
+            observations: series.len(),
+            trips,
+            ewma: ewma_forecast,
+            holt_winters: hw_forecast,
+            ewma_error: backtest(&series, params.holdout, |s| ewma(s, params.alpha)),
+            holt_winters_error: backtest(&series, params.holdout, |s| holt_winters(s, params)),
+        });
+    }
+    forecasts
+}
+
+// Returns top N routes by forecast next-week delay among those with `DEFAULT_MIN_DAYS` days of history and at least
+// `min_trips` trips
+pub fn rank_routes_by_forecast(records: &[TrainRecord], opts: &RankOptions) -> Vec<RouteForecast> {
+    let mut forecasts = forecast_routes(records, &ForecastParams::default(), DEFAULT_MIN_DAYS);
+    forecasts.retain(|f| f.trips >= opts.min_trips);
+    forecasts.sort_by(|a, b| b.ewma.total_cmp(&a.ewma).then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to))));
+    forecasts.truncate(opts.top_n);
+    forecasts
+}
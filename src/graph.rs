@@ -1,31 +1,382 @@
 // Defines the transit graph structure and builds it from the records.
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Add;
+use std::sync::OnceLock;
+use std::time::Duration;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::cache::{PathCache, DEFAULT_PATH_CACHE_CAPACITY};
+use crate::error::{Error, Result};
 use crate::load::TrainRecord;
-// Type alias for station name
-pub type Station = String;
+use crate::metrics::NodeIndex;
+// NJ Transit stop ID, normalized by `station_id` ("105.0" in the data becomes "105")
+pub type StationId = String;
+
+// Maps and sets of the graph and metrics hot paths, hashed with aHash instead of std's SipHash: every algorithm does
+// lookups keyed by stations, and DoS-resistant hashing buys nothing on our own data. Swap the hasher here to change it
+pub type FastMap<K, V> = HashMap<K, V, ahash::RandomState>;
+pub type FastSet<T> = HashSet<T, ahash::RandomState>;
+
+// A station: identified by its stop ID, displayed by name
+// Equality and hashing use the ID only, so spelling variants of one stop are one node
+#[derive(Debug, Clone)]
+pub struct Station {
+    pub id: StationId,
+    pub name: String, // Display name; the most common spelling in the data when a graph is built
+}
+
+impl Station {
+    pub fn new(id: impl Into<StationId>, name: impl Into<String>) -> Self {
+        Self { id: id.into(), name: name.into() }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for Station {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Station {}
+
+impl Hash for Station {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+// Ordered by name for listings, with the ID breaking ties between distinct stops of the same name
+impl Ord for Station {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.id == other.id {
+            return Ordering::Equal;
+        }
+        self.name.cmp(&other.name).then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Station {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Station {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl PartialEq<str> for Station {
+    fn eq(&self, other: &str) -> bool {
+        self.name == other
+    }
+}
+
+impl PartialEq<&str> for Station {
+    fn eq(&self, other: &&str) -> bool {
+        self.name == *other
+    }
+}
+
+// Serialized as the display name so tables, CSV and JSON keep one readable station column
+impl Serialize for Station {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+// A bare name carries no stop ID, so the name stands in for it
+impl<'de> Deserialize<'de> for Station {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self { id: name.clone(), name })
+    }
+}
+
+// Normalizes a stop ID from the data: trims padding and drops the ".0" of IDs stored as floats
+pub fn station_id(raw: &str) -> StationId {
+    let raw = raw.trim();
+    match raw.parse::<f64>() {
+        Ok(n) if n.fract() == 0.0 && n.abs() < 1e15 => format!("{}", n as i64),
+        _ => raw.to_string(),
+    }
+}
+
+// Joins station names with a separator, e.g. " → " for a path
+pub fn join_names(stations: &[Station], separator: &str) -> String {
+    stations.iter().map(Station::as_str).collect::<Vec<_>>().join(separator)
+}
+
 // Type alias for a weighted edge between stations with delay as weight
 pub type WeightedEdge = (Station, Station, f32);
-// Represents a transit network graph with stations and delays as weighted edges
+
+// An edge weight the algorithms can add up and compare: delay minutes (f32, f64), a travel time (Duration),
+// or a composite struct of several criteria with its own PartialOrd (e.g. delay first, then transfers)
+// Send + Sync so rankings can score stations on several threads
+pub trait Weight: Copy + PartialOrd + Add<Output = Self> + fmt::Debug + Send + Sync {
+    const ZERO: Self;
+    // The weight in minutes, for metrics that sum or divide weights (closeness); a composite gives its primary criterion
+    fn minutes(&self) -> f32;
+    // Whether the weight compares with every other; false for NaN, which is never stored as an edge
+    fn is_ordered(&self) -> bool {
+        self.partial_cmp(self).is_some()
+    }
+}
+
+impl Weight for f32 {
+    const ZERO: Self = 0.0;
+    fn minutes(&self) -> f32 {
+        *self
+    }
+}
+
+impl Weight for f64 {
+    const ZERO: Self = 0.0;
+    fn minutes(&self) -> f32 {
+        *self as f32
+    }
+}
+
+impl Weight for Duration {
+    const ZERO: Self = Duration::ZERO;
+    fn minutes(&self) -> f32 {
+        self.as_secs_f32() / 60.0
+    }
+}
+
+// A directed, weighted graph the metrics can run on, so other backends (an interned-index graph, petgraph,
+// a time-expanded graph) and other weights (travel time, multi-criteria) reuse the same algorithms as `TransitGraph`
+pub trait Graph {
+    type Node: Clone + Eq + Hash + Ord; // Ord breaks ties between equal delays in the Dijkstra heap
+    type Weight: Weight;
+    // Every node, including ones that are only ever arrived at
+    fn nodes(&self) -> impl Iterator<Item = &Self::Node>;
+    // Outgoing edges of a node with their weights; parallel edges may repeat a neighbor
+    // The algorithms skip an edge whose weight makes an unordered (NaN) distance instead of panicking, but ordered weights are expected
+    fn neighbors(&self, node: &Self::Node) -> impl Iterator<Item = (&Self::Node, Self::Weight)>;
+    // Weight of the cheapest edge from one node to another, if there is one
+    fn weight(&self, from: &Self::Node, to: &Self::Node) -> Option<Self::Weight> {
+        self.neighbors(from).filter(|(n, _)| *n == to).map(|(_, w)| w).min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+}
+
+// Represents a transit network graph with stations and weighted edges, delay minutes unless built with another weight
 #[derive(Debug)]
-pub struct TransitGraph {
-    pub nodes: HashMap<Station, Vec<(Station, f32)>>, // Map from station to list of destination stations with delay
+pub struct TransitGraph<W = f32> {
+    pub(crate) nodes: FastMap<Station, Vec<(Station, W)>>, // Map from station to list of destination stations with weight
+    pub lines: FastMap<(Station, Station), HashSet<String>>, // Lines observed running each (from, to) segment
+    pub(crate) by_id: FastMap<StationId, Station>, // Every station with an edge, keyed by stop ID
+    pub(crate) path_cache: PathCache<W>, // Memoized `shortest_path` answers; valid because edges never change after building
+    pub(crate) node_index: OnceLock<NodeIndex<W>>, // Dense index for point-to-point searches, built on the first one
 }
 impl TransitGraph {
-    // Constructs a TransitGraph from a slice of TrainRecords
+    // Constructs a delay-weighted TransitGraph from a slice of TrainRecords
     // Input: slice of TrainRecord structs
-    // Output: TransitGraph with nodes populated by delay-weighted edges
-    // Logic: Filter records with delay data, then insert edges into graph map
+    // Output: TransitGraph with one edge per record with a finite delay
     pub fn from_records(records: &[TrainRecord]) -> Self {
-        let mut nodes: HashMap<Station, Vec<(Station, f32)>> = HashMap::new(); // Initialize graph
-        // Iterate over records with valid delay data
-        for r in records.iter().filter(|r| r.delay_minutes.is_some()) {
-            let from = r.from.clone(); // Source station
-            let to = r.to.clone();     // Destination station
-            let delay = r.delay_minutes.unwrap(); // Extract delay value
-            // Insert or update edge from -> to with delay
-            nodes.entry(from.clone()).or_default().push((to.clone(), delay));
+        Self::from_records_with(records, TrainRecord::delay)
+    }
+
+    // Like `from_records`, but an error if no record has a delay, since every metric over an empty graph is meaningless
+    pub fn try_from_records(records: &[TrainRecord]) -> Result<Self> {
+        let graph = Self::from_records(records);
+        if graph.nodes.is_empty() {
+            return Err(Error::EmptyGraph { records: records.len() });
+        }
+        Ok(graph)
+    }
+}
+
+impl<W: Weight> TransitGraph<W> {
+    // Constructs a TransitGraph weighted by any function of a record (travel time, a composite of criteria, ...)
+    // Input: slice of TrainRecord structs, the weight of a record or None to leave it out
+    // Output: TransitGraph with one edge per weighted record
+    pub fn from_records_with(records: &[TrainRecord], weight: impl Fn(&TrainRecord) -> Option<W>) -> Self {
+        let mut builder = GraphBuilder::new();
+        for r in records {
+            builder.add(r, weight(r));
+        }
+        builder.build()
+    }
+
+    // Every station in the graph, including ones only ever arrived at, in no particular order
+    pub fn stations(&self) -> impl Iterator<Item = &Station> {
+        self.by_id.values()
+    }
+
+    // Every observed segment as (from, to, weight), one per weighted record
+    pub fn edges(&self) -> impl Iterator<Item = (&Station, &Station, W)> {
+        self.nodes.iter().flat_map(|(from, neighbors)| neighbors.iter().map(move |(to, w)| (from, to, *w)))
+    }
+
+    // Weights observed on the direct segment from -> to, in record order
+    pub fn observations<'a>(&'a self, from: &Station, to: &'a Station) -> impl Iterator<Item = W> + 'a {
+        self.nodes.get(from).into_iter().flatten().filter(move |(n, _)| n == to).map(|(_, w)| *w)
+    }
+
+    // Looks up a station by stop ID (raw as in the data, or normalized)
+    pub fn station(&self, id: &str) -> Option<&Station> {
+        self.by_id.get(&station_id(id))
+    }
+}
+
+// Maps every stop ID in the records to a station named by its most common spelling (ties go to the first alphabetically)
+pub(crate) fn station_index(records: &[TrainRecord]) -> FastMap<StationId, Station> {
+    let mut spellings: FastMap<StationId, FastMap<&str, usize>> = FastMap::default();
+    for r in records {
+        *spellings.entry(r.from_station_id()).or_default().entry(r.from.trim()).or_default() += 1;
+        *spellings.entry(r.to_station_id()).or_default().entry(r.to.trim()).or_default() += 1;
+    }
+    spellings.into_iter()
+        .map(|(id, names)| {
+            let name = names.into_iter().max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0))).map(|(n, _)| n).unwrap_or_default();
+            (id.clone(), Station::new(id, name))
+        })
+        .collect()
+}
+
+// Builds a graph one record at a time, so a streaming loader can feed it without keeping the records
+// Logic: stations are interned as indices while counting each stop ID's spellings; edges and lines are kept by index
+// and only become `Station`s, named by their most common spelling, in `build`
+#[derive(Debug)]
+pub struct GraphBuilder<W = f32> {
+    ids: FastMap<StationId, usize>,                      // Stop ID -> station index
+    raw_ids: FastMap<String, usize>,                     // Stop ID as written in the data -> station index, so each spelling is normalized once
+    stop_ids: Vec<StationId>,                            // Station index -> stop ID
+    spellings: Vec<FastMap<String, usize>>,              // Per station index, how often each name was seen
+    edges: Vec<Vec<(usize, W)>>,                         // Per station index, outgoing (target, weight) in record order
+    lines: FastMap<(usize, usize), HashSet<String>>,     // Lines observed per (from, to) index pair
+}
+
+impl<W: Weight> Default for GraphBuilder<W> {
+    fn default() -> Self {
+        Self {
+            ids: FastMap::default(),
+            raw_ids: FastMap::default(),
+            stop_ids: Vec::new(),
+            spellings: Vec::new(),
+            edges: Vec::new(),
+            lines: FastMap::default(),
+        }
+    }
+}
+
+impl<W: Weight> GraphBuilder<W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Index of a stop ID as written in the data ("105.0"), counting this spelling of its name
+    fn intern(&mut self, raw_id: &str, name: &str) -> usize {
+        let index = match self.raw_ids.get(raw_id) {
+            Some(&index) => index,
+            None => {
+                let id = station_id(raw_id);
+                let index = match self.ids.get(&id) {
+                    Some(&index) => index,
+                    None => {
+                        self.ids.insert(id.clone(), self.stop_ids.len());
+                        self.stop_ids.push(id);
+                        self.spellings.push(FastMap::default());
+                        self.edges.push(Vec::new());
+                        self.stop_ids.len() - 1
+                    }
+                };
+                self.raw_ids.insert(raw_id.to_string(), index);
+                index
+            }
+        };
+        match self.spellings[index].get_mut(name) {
+            Some(count) => *count += 1,
+            None => { self.spellings[index].insert(name.to_string(), 1); }
+        }
+        index
+    }
+
+    // Adds one record: its station spellings always count toward names, and it becomes an edge if it has a weight
+    // Unordered (NaN) weights are left out, so shortest paths are well defined
+    pub fn add(&mut self, record: &TrainRecord, weight: Option<W>) {
+        let from = self.intern(&record.from_id, record.from.trim());
+        let to = self.intern(&record.to_id, record.to.trim());
+        let Some(w) = weight.filter(Weight::is_ordered) else { return };
+        self.edges[from].push((to, w));
+        // Record which line served this segment (line names are space-padded in the source data)
+        let served = self.lines.entry((from, to)).or_default();
+        if !served.contains(record.line.trim()) {
+            served.insert(record.line.trim().to_string());
+        }
+    }
+
+    // The graph of every weighted record added; stations seen only on unweighted records are left out
+    // Names are the most common spelling per stop ID (ties go to the first alphabetically), as in `from_records`
+    pub fn build(self) -> TransitGraph<W> {
+        let stations: Vec<Station> = self.stop_ids.into_iter()
+            .zip(&self.spellings)
+            .map(|(id, names)| {
+                let name = names.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0))).map(|(n, _)| n.as_str()).unwrap_or_default();
+                Station::new(id, name)
+            })
+            .collect();
+        let mut nodes: FastMap<Station, Vec<(Station, W)>> = FastMap::default();
+        for (from, edges) in self.edges.into_iter().enumerate().filter(|(_, edges)| !edges.is_empty()) {
+            nodes.insert(stations[from].clone(), edges.into_iter().map(|(to, w)| (stations[to].clone(), w)).collect());
         }
+        let lines: FastMap<(Station, Station), HashSet<String>> = self.lines.into_iter()
+            .map(|((from, to), served)| ((stations[from].clone(), stations[to].clone()), served))
+            .collect();
+        TransitGraph::from_parts(nodes, lines)
+    }
+}
+
+impl<W: Weight> TransitGraph<W> {
+    // A graph of these edges and lines, indexing every station with a segment
+    // Stations seen only on records without a weight have no edge to be a node through
+    fn from_parts(nodes: FastMap<Station, Vec<(Station, W)>>, lines: FastMap<(Station, Station), HashSet<String>>) -> Self {
+        let by_id: FastMap<StationId, Station> = lines.keys()
+            .flat_map(|(from, to)| [from, to])
+            .map(|station| (station.id.clone(), station.clone()))
+            .collect();
+        TransitGraph { nodes, lines, by_id, path_cache: PathCache::new(DEFAULT_PATH_CACHE_CAPACITY), node_index: OnceLock::new() }
+    }
+
+    // A copy keeping only the segments `keep` accepts (with their observations and lines); stations left without a
+    // segment are dropped
+    pub fn retain_segments(&self, keep: impl Fn(&Station, &Station) -> bool) -> Self {
+        let nodes = self.nodes.iter()
+            .map(|(from, edges)| (from.clone(), edges.iter().filter(|(to, _)| keep(from, to)).cloned().collect::<Vec<_>>()))
+            .filter(|(_, edges)| !edges.is_empty())
+            .collect();
+        let lines = self.lines.iter().filter(|((from, to), _)| keep(from, to)).map(|(k, v)| (k.clone(), v.clone())).collect();
+        Self::from_parts(nodes, lines)
+    }
+
+    // A copy with extra (from, to, weight) segments, each served by `line`; their stations join the graph if new
+    pub fn with_segments(&self, segments: &[(Station, Station, W)], line: &str) -> Self {
+        let (mut nodes, mut lines) = (self.nodes.clone(), self.lines.clone());
+        for (from, to, weight) in segments.iter().filter(|(_, _, w)| w.is_ordered()) {
+            nodes.entry(from.clone()).or_default().push((to.clone(), *weight));
+            lines.entry((from.clone(), to.clone())).or_default().insert(line.to_string());
+        }
+        Self::from_parts(nodes, lines)
+    }
+}
+
+impl<W: Weight> Graph for TransitGraph<W> {
+    type Node = Station;
+    type Weight = W;
+
+    fn nodes(&self) -> impl Iterator<Item = &Station> {
+        self.stations()
+    }
 
-        Self { nodes } // Return constructed graph
+    fn neighbors(&self, node: &Station) -> impl Iterator<Item = (&Station, W)> {
+        self.nodes.get(node).into_iter().flatten().map(|(to, w)| (to, *w))
     }
 }
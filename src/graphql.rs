@@ -0,0 +1,220 @@
+// GraphQL schema over the served dataset (POST /graphql), so a dashboard asks for exactly the fields it shows in one
+// query, e.g. a station with its closeness and its most delayed outbound routes:
+//   { station(name: "Newark Penn Station") { name closeness outbound(first: 3) { to { name } meanDelay trips } } }
+// Resolvers run their computations on the blocking pool, as the REST routes do; errors carry the CLI's error kind
+
+use std::sync::Arc;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema, SimpleObject};
+use crate::cli::CliError;
+use crate::graph::Station;
+use crate::metrics::{RankOptions, StationScore};
+use crate::nonblocking::spawn_blocking;
+use crate::routes::{route_summaries, sort_routes, station_routes, RouteColumn, RouteSummary};
+use crate::routing::PathSegment;
+use crate::server::{Dataset, ServerState};
+
+// The schema served at /graphql
+pub type DelaySchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+// Builds the schema over the shared state
+pub fn schema(state: Arc<ServerState>) -> DelaySchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription).data(state).finish()
+}
+
+// Root of every query
+pub struct Query;
+
+// A station, with its routes and centrality resolved on request
+pub struct StationNode(Station);
+
+// A route with its delay statistics; `from` and `to` resolve to stations, so queries can keep walking the network
+pub struct RouteNode(RouteSummary);
+
+// A station's score in a ranking
+#[derive(SimpleObject)]
+pub struct ScoreNode {
+    pub station: String,
+    pub score: f32,
+}
+
+// A least-delay path and its segments
+#[derive(SimpleObject)]
+pub struct PathNode {
+    pub delay: f32,
+    pub stations: Vec<String>,
+    pub segments: Vec<SegmentNode>,
+}
+
+// One segment of a path
+#[derive(SimpleObject)]
+pub struct SegmentNode {
+    pub from: String,
+    pub to: String,
+    pub delay: f32,
+    pub cumulative: f32,
+}
+
+impl From<PathSegment> for SegmentNode {
+    fn from(segment: PathSegment) -> Self {
+        Self { from: segment.from.name, to: segment.to.name, delay: segment.delay, cumulative: segment.cumulative }
+    }
+}
+
+// A CLI error as a GraphQL error, with `kind` and `code` extensions as in the JSON error object
+fn graphql_error(error: CliError) -> async_graphql::Error {
+    async_graphql::Error::new(error.to_string()).extend_with(|_, extensions| {
+        extensions.set("kind", error.kind());
+        extensions.set("code", error.exit_code());
+    })
+}
+
+// Runs a resolver's computation on the blocking pool against the current dataset
+async fn compute<T, F>(ctx: &Context<'_>, f: F) -> async_graphql::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Dataset) -> Result<T, CliError> + Send + 'static,
+{
+    let data = ctx.data::<Arc<ServerState>>()?.dataset();
+    spawn_blocking(move || f(&data)).await.map_err(|e| graphql_error(e.into()))?.map_err(graphql_error)
+}
+
+// The command-line options with a `first:` argument as the number of entries
+fn options(data: &Dataset, first: Option<usize>) -> RankOptions {
+    RankOptions { top_n: first.unwrap_or(data.opts.top_n), ..data.opts }
+}
+
+fn scores(ranking: Vec<StationScore>) -> Vec<ScoreNode> {
+    ranking.into_iter().map(|s| ScoreNode { station: s.station.name, score: s.score }).collect()
+}
+
+#[Object]
+impl Query {
+    // Every station in station order, or the best matches for `find` by fuzzy name search
+    async fn stations(&self, ctx: &Context<'_>, find: Option<String>, first: Option<usize>) -> async_graphql::Result<Vec<StationNode>> {
+        compute(ctx, move |data| {
+            let graph = &data.graph;
+            Ok(match &find {
+                Some(name) => graph.find_stations(name, options(data, first).top_n).into_iter().map(|m| StationNode(m.station)).collect(),
+                None => {
+                    let mut stations: Vec<Station> = graph.stations().cloned().collect();
+                    stations.sort();
+                    stations.into_iter().take(first.unwrap_or(usize::MAX)).map(StationNode).collect()
+                }
+            })
+        })
+        .await
+    }
+
+    // One station by name or stop ID
+    async fn station(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<StationNode> {
+        compute(ctx, move |data| Ok(StationNode(data.graph.resolve_station(&name)?))).await
+    }
+
+    // The least-delay path between two stations
+    async fn path(&self, ctx: &Context<'_>, from: String, to: String) -> async_graphql::Result<PathNode> {
+        compute(ctx, move |data| {
+            let graph = &data.graph;
+            let (from, to) = (graph.resolve_station(&from)?, graph.resolve_station(&to)?);
+            let (delay, stations) = graph.shortest_path(&from, &to).ok_or(CliError::NoPath { from: from.name, to: to.name })?;
+            let segments = graph.path_segments(&stations).into_iter().map(Into::into).collect();
+            Ok(PathNode { delay, stations: stations.into_iter().map(|s| s.name).collect(), segments })
+        })
+        .await
+    }
+
+    // Stations by delay-weighted closeness centrality
+    async fn closeness(&self, ctx: &Context<'_>, first: Option<usize>) -> async_graphql::Result<Vec<ScoreNode>> {
+        compute(ctx, move |data| Ok(scores(data.graph.rank_stations_by_closeness(&options(data, first))))).await
+    }
+
+    // Stations by unweighted betweenness centrality
+    async fn betweenness(&self, ctx: &Context<'_>, first: Option<usize>) -> async_graphql::Result<Vec<ScoreNode>> {
+        compute(ctx, move |data| Ok(scores(data.graph.rank_stations_by_betweenness(&options(data, first))))).await
+    }
+
+    // Routes meeting --min-trips by mean delay, worst first unless `worstFirst: false`
+    async fn routes(&self, ctx: &Context<'_>, first: Option<usize>, worst_first: Option<bool>) -> async_graphql::Result<Vec<RouteNode>> {
+        compute(ctx, move |data| {
+            let opts = options(data, first);
+            let mut routes = route_summaries(&data.graph, &opts);
+            sort_routes(&mut routes, RouteColumn::MeanDelay, worst_first.unwrap_or(true));
+            Ok(routes.into_iter().take(opts.top_n).map(RouteNode).collect())
+        })
+        .await
+    }
+}
+
+#[Object]
+impl StationNode {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    // None if the station reaches no other station
+    async fn closeness(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<f32>> {
+        let station = self.0.clone();
+        compute(ctx, move |data| Ok(data.graph.closeness_centrality(&station))).await
+    }
+
+    // Routes leaving the station that meet --min-trips, most delayed first
+    async fn outbound(&self, ctx: &Context<'_>, first: Option<usize>) -> async_graphql::Result<Vec<RouteNode>> {
+        self.routes(ctx, first, true).await
+    }
+
+    // Routes arriving at the station that meet --min-trips, most delayed first
+    async fn inbound(&self, ctx: &Context<'_>, first: Option<usize>) -> async_graphql::Result<Vec<RouteNode>> {
+        self.routes(ctx, first, false).await
+    }
+}
+
+impl StationNode {
+    async fn routes(&self, ctx: &Context<'_>, first: Option<usize>, outbound: bool) -> async_graphql::Result<Vec<RouteNode>> {
+        let station = self.0.clone();
+        compute(ctx, move |data| {
+            let opts = options(data, first);
+            let (leaving, arriving) = station_routes(&data.graph, &station, &opts);
+            let mut routes = if outbound { leaving } else { arriving };
+            sort_routes(&mut routes, RouteColumn::MeanDelay, true);
+            Ok(routes.into_iter().take(opts.top_n).map(RouteNode).collect())
+        })
+        .await
+    }
+}
+
+#[Object]
+impl RouteNode {
+    async fn from(&self) -> StationNode {
+        StationNode(self.0.from.clone())
+    }
+
+    async fn to(&self) -> StationNode {
+        StationNode(self.0.to.clone())
+    }
+
+    async fn trips(&self) -> usize {
+        self.0.trips
+    }
+
+    // Average delay in minutes
+    async fn mean_delay(&self) -> f32 {
+        self.0.mean_delay
+    }
+
+    async fn median_delay(&self) -> f32 {
+        self.0.median_delay
+    }
+
+    // 90th percentile delay in minutes
+    async fn p90_delay(&self) -> f32 {
+        self.0.p90_delay
+    }
+
+    // Share of trips at or below the on-time threshold
+    async fn on_time_rate(&self) -> f32 {
+        self.0.on_time_rate
+    }
+}
@@ -0,0 +1,71 @@
+// Average delay by scheduled hour × weekday, for the whole network or any subset of records (a line, a route)
+
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDateTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use crate::load::TrainRecord;
+
+pub const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+// Trips and delay observed in one (weekday, hour) window
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct HeatCell {
+    pub trips: usize,
+    pub total_delay: f32,
+}
+
+impl HeatCell {
+    pub fn average_delay(&self) -> Option<f32> {
+        (self.trips > 0).then(|| self.total_delay / self.trips as f32)
+    }
+}
+
+// 7 × 24 grid of delay windows, Monday first, hour 0 first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelayHeatmap {
+    pub cells: [[HeatCell; 24]; 7],
+}
+
+// One weekday's row of the matrix: the weekday, then average delay per hour ("00".."23"), blank when no trips
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapRow {
+    pub weekday: String,
+    #[serde(flatten)]
+    pub hours: BTreeMap<String, Option<f32>>,
+}
+
+impl DelayHeatmap {
+    // Buckets records with a delay by the weekday and hour of their scheduled time
+    // Records with an unparseable scheduled time are skipped
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a TrainRecord>) -> Self {
+        let mut cells = [[HeatCell::default(); 24]; 7];
+        for r in records {
+            let Some(delay) = r.delay() else { continue };
+            let Ok(scheduled) = NaiveDateTime::parse_from_str(&r.scheduled_time, "%Y-%m-%d %H:%M:%S") else { continue };
+            let cell = &mut cells[scheduled.weekday().num_days_from_monday() as usize][scheduled.hour() as usize];
+            cell.trips += 1;
+            cell.total_delay += delay;
+        }
+        DelayHeatmap { cells }
+    }
+
+    // Total trips across every window
+    pub fn trips(&self) -> usize {
+        self.cells.iter().flatten().map(|c| c.trips).sum()
+    }
+
+    // Highest average delay of any window, if any window has trips
+    pub fn max_average(&self) -> Option<f32> {
+        self.cells.iter().flatten().filter_map(HeatCell::average_delay).reduce(f32::max)
+    }
+
+    // The matrix as one row per weekday, ready for table/JSON/CSV output
+    pub fn rows(&self) -> Vec<HeatmapRow> {
+        WEEKDAYS.iter().zip(&self.cells)
+            .map(|(day, hours)| HeatmapRow {
+                weekday: day.to_string(),
+                hours: hours.iter().enumerate().map(|(h, c)| (format!("{:02}", h), c.average_delay())).collect(),
+            })
+            .collect()
+    }
+}
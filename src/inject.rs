@@ -0,0 +1,184 @@
+// Delay injection: scales the delays of chosen lines or segments, optionally only for trains scheduled in a window of
+// the day ("Northeast Corrdr +30% during the PM peak"), rebuilds the network from the changed records, and compares it
+// with the baseline on least delays and centrality
+// Only late trains are scaled: a multiplier stretches or shrinks lateness, and early or on-the-dot trains stay as they
+// were. A record matched by several injections gets every multiplier
+// Least delays run over each segment's smallest observed delay, so scaling only some of a segment's trips moves them
+// only when it changes that smallest one; the segments' average delays show every scaled trip
+
+use std::fmt;
+use std::str::FromStr;
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::comparison::{route_shifts, RouteShift};
+use crate::graph::TransitGraph;
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+use crate::whatif::{impact, ImpactReport};
+
+// Which trips an injection scales
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InjectionTarget {
+    All,
+    Line(String),            // Matched case-insensitively after trimming
+    Segment(String, String), // (from, to) by station name or stop ID, case-insensitively
+}
+
+// Scheduled times of day [start, end), wrapping past midnight when end is not after start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+// One synthetic change: the target's late trains (in the window, if any) run `factor` times as late
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Injection {
+    pub target: InjectionTarget,
+    pub factor: f32,
+    pub window: Option<TimeWindow>,
+}
+
+// The injected network against the baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectionReport {
+    pub records_changed: usize,
+    #[serde(flatten)]
+    pub impact: ImpactReport,
+    pub route_shifts: Vec<RouteShift>, // Segments with at least `min_trips` trips by change in average delay (A = baseline)
+}
+
+impl TimeWindow {
+    // Weekday rush hours as NJ Transit prices them
+    pub const AM_PEAK: TimeWindow = TimeWindow { start: hm(6, 0), end: hm(10, 0) };
+    pub const PM_PEAK: TimeWindow = TimeWindow { start: hm(16, 0), end: hm(19, 0) };
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end { self.start <= time && time < self.end } else { time >= self.start || time < self.end }
+    }
+}
+
+const fn hm(hour: u32, minute: u32) -> NaiveTime {
+    match NaiveTime::from_hms_opt(hour, minute, 0) {
+        Some(time) => time,
+        None => panic!("invalid time"),
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = Error;
+
+    // "am-peak", "pm-peak", or "HH:MM-HH:MM"
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidParameter { name: "time window", reason: format!("{:?} is not am-peak, pm-peak or HH:MM-HH:MM", s) };
+        match s.trim().to_ascii_lowercase().as_str() {
+            "am-peak" => Ok(Self::AM_PEAK),
+            "pm-peak" => Ok(Self::PM_PEAK),
+            range => {
+                let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+                let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+                Ok(Self { start: time(start)?, end: time(end)? })
+            }
+        }
+    }
+}
+
+impl FromStr for Injection {
+    type Err = Error;
+
+    // TARGET:CHANGE[@WINDOW], where TARGET is a line name, FROM>TO for a segment, or * for every trip; CHANGE is +N%,
+    // -N% or xF; WINDOW is as `TimeWindow` parses it. E.g. "Northeast Corrdr:+30%@pm-peak", "Newark Penn>Secaucus:x2"
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidParameter { name: "injection", reason };
+        let (body, window) = match s.rsplit_once('@') {
+            Some((body, window)) => (body, Some(window.parse()?)),
+            None => (s, None),
+        };
+        let (target, change) = body.rsplit_once(':').ok_or_else(|| invalid(format!("{:?} is not TARGET:CHANGE[@WINDOW]", s)))?;
+        let change = change.trim();
+        let factor = if let Some(percent) = change.strip_suffix('%') {
+            percent.trim().parse::<f32>().map(|p| 1.0 + p / 100.0)
+        } else {
+            change.strip_prefix(['x', 'X', '*']).unwrap_or("").trim().parse::<f32>()
+        };
+        let factor = factor.ok().filter(|f| *f >= 0.0 && f.is_finite())
+            .ok_or_else(|| invalid(format!("{:?} is not +N%, -N% (at most -100%) or xF", change)))?;
+        let target = match target.trim() {
+            "" => return Err(invalid(format!("{:?} names no line or segment", s))),
+            "*" => InjectionTarget::All,
+            target => match target.split_once('>') {
+                Some((from, to)) => InjectionTarget::Segment(from.trim().to_string(), to.trim().to_string()),
+                None => InjectionTarget::Line(target.to_string()),
+            },
+        };
+        Ok(Self { target, factor, window })
+    }
+}
+
+impl fmt::Display for Injection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            InjectionTarget::All => write!(f, "every trip")?,
+            InjectionTarget::Line(line) => write!(f, "{}", line)?,
+            InjectionTarget::Segment(from, to) => write!(f, "{} → {}", from, to)?,
+        }
+        write!(f, " {:+.0}%", (self.factor - 1.0) * 100.0)?;
+        if let Some(window) = &self.window {
+            write!(f, " {}-{}", window.start.format("%H:%M"), window.end.format("%H:%M"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Injection {
+    // Whether the injection applies to a record; with a window, records without a parseable scheduled time never match
+    pub fn matches(&self, record: &TrainRecord) -> bool {
+        let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+        let targeted = match &self.target {
+            InjectionTarget::All => true,
+            InjectionTarget::Line(line) => same(&record.line, line),
+            InjectionTarget::Segment(from, to) => {
+                (same(&record.from, from) || same(&record.from_id, from)) && (same(&record.to, to) || same(&record.to_id, to))
+            }
+        };
+        targeted && self.window.is_none_or(|window| {
+            NaiveDateTime::parse_from_str(record.scheduled_time.trim(), "%Y-%m-%d %H:%M:%S").is_ok_and(|t| window.contains(t.time()))
+        })
+    }
+}
+
+// The records with every injection applied
+// Output: the changed copy, and how many records' delays changed
+pub fn inject(records: &[TrainRecord], injections: &[Injection]) -> (Vec<TrainRecord>, usize) {
+    let mut changed = 0;
+    let injected = records.iter()
+        .map(|record| {
+            let factor: f32 = injections.iter().filter(|i| i.matches(record)).map(|i| i.factor).product();
+            match record.delay_minutes {
+                Some(delay) if delay > 0.0 && factor != 1.0 => {
+                    changed += 1;
+                    TrainRecord { delay_minutes: Some(delay * factor), ..record.clone() }
+                }
+                _ => record.clone(),
+            }
+        })
+        .collect();
+    (injected, changed)
+}
+
+// Applies the injections and compares the rebuilt network with the baseline graph of the same records
+// Output: an invalid-parameter error if no injection matches a late trip, or a network is too large to compare
+pub fn run(records: &[TrainRecord], baseline: &TransitGraph, injections: &[Injection], opts: &RankOptions) -> Result<InjectionReport> {
+    let (injected, records_changed) = inject(records, injections);
+    if records_changed == 0 {
+        return Err(Error::InvalidParameter { name: "injection", reason: "no late trip matches any injection".into() });
+    }
+    let scenario = injections.iter().map(Injection::to_string).collect::<Vec<_>>().join("; ");
+    let modified = TransitGraph::from_records(&injected);
+    Ok(InjectionReport {
+        records_changed,
+        impact: impact(scenario, baseline, &modified, &[])?,
+        route_shifts: route_shifts(baseline, &modified, &RankOptions { top_n: usize::MAX, ..*opts }),
+    })
+}
@@ -0,0 +1,102 @@
+// KML documents of journey analyses (least-delay paths and delay isochrones) for Google Earth and Google My Maps
+
+use std::io::{self, Write};
+use crate::export::escape;
+use crate::graph::{join_names, Station, TransitGraph};
+use crate::load::Coordinates;
+use crate::routing::Reachable;
+
+// Line and icon colors, as KML's aabbggrr
+const PATH_COLORS: [&str; 4] = ["ff2b39c0", "ffa56e3b", "ff3c9c27", "ff0e7ee6"];
+const NEAR: &str = "ff3c9c27"; // Within a third of the budget
+const MID: &str = "ff0fc4f1";  // Within two thirds
+const FAR: &str = "ff2b39c0";  // Up to the budget
+
+fn write_header<W: Write>(out: &mut W, name: &str, colors: &[&str]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(out, "<Document>")?;
+    writeln!(out, "  <name>{}</name>", escape(name))?;
+    for color in colors {
+        writeln!(out, r#"  <Style id="c{}"><LineStyle><color>{}</color><width>4</width></LineStyle><IconStyle><color>{}</color></IconStyle></Style>"#, color, color, color)?;
+    }
+    Ok(())
+}
+
+fn write_footer<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "</Document>")?;
+    writeln!(out, "</kml>")?;
+    out.flush()
+}
+
+fn write_point<W: Write>(out: &mut W, name: &str, description: &str, color: &str, (lat, lon): (f64, f64)) -> io::Result<()> {
+    writeln!(
+        out,
+        "    <Placemark><name>{}</name><description>{}</description><styleUrl>#c{}</styleUrl><Point><coordinates>{},{},0</coordinates></Point></Placemark>",
+        escape(name), escape(description), color, lon, lat,
+    )
+}
+
+fn write_line<W: Write>(out: &mut W, name: &str, description: &str, color: &str, points: &[(f64, f64)]) -> io::Result<()> {
+    let coordinates: Vec<String> = points.iter().map(|(lat, lon)| format!("{},{},0", lon, lat)).collect(); // KML is lon,lat
+    writeln!(
+        out,
+        "    <Placemark><name>{}</name><description>{}</description><styleUrl>#c{}</styleUrl><LineString><tessellate>1</tessellate><coordinates>{}</coordinates></LineString></Placemark>",
+        escape(name), escape(description), color, coordinates.join(" "),
+    )
+}
+
+// Writes least-delay paths as KML, one folder per path holding its route line and a placemark per stop
+// Input: paths as (total delay, stations) from `k_shortest_paths` and friends
+// Logic: stations without coordinates are left out of both the line and the stops, so the line joins the known ones
+pub fn write_paths<W: Write>(paths: &[(f32, Vec<Station>)], graph: &TransitGraph, coords: &Coordinates, mut out: W) -> io::Result<()> {
+    let name = match paths.first().map(|(_, stations)| stations) {
+        Some(stations) if !stations.is_empty() => format!("{} → {}", stations[0], stations[stations.len() - 1]),
+        _ => "Paths".to_string(),
+    };
+    write_header(&mut out, &name, &PATH_COLORS)?;
+    for (i, (delay, stations)) in paths.iter().enumerate() {
+        let color = PATH_COLORS[i % PATH_COLORS.len()];
+        writeln!(out, "  <Folder><name>Path {}: {:.2} minutes, {} stops</name>", i + 1, delay, stations.len())?;
+        let points: Vec<(f64, f64)> = stations.iter().filter_map(|s| coords.get(&s.name).copied()).collect();
+        write_line(&mut out, &format!("Path {}", i + 1), &join_names(stations, " → "), color, &points)?;
+        let mut cumulative = 0.0;
+        for (j, station) in stations.iter().enumerate() {
+            if j > 0 {
+                cumulative += graph.segment_delay(&stations[j - 1], station).unwrap_or(0.0);
+            }
+            if let Some(&position) = coords.get(&station.name) {
+                write_point(&mut out, &station.name, &format!("Stop {}: {:.2} minutes of delay so far", j + 1, cumulative), color, position)?;
+            }
+        }
+        writeln!(out, "  </Folder>")?;
+    }
+    write_footer(&mut out)
+}
+
+// Writes a delay isochrone as KML: a placemark per reachable station and the shortest-path tree as lines
+// Stations and tree edges are colored by how much of the budget they use: green, amber, red by thirds
+pub fn write_isochrone<W: Write>(reachable: &[Reachable], budget: f32, coords: &Coordinates, mut out: W) -> io::Result<()> {
+    let origin = reachable.first().map(|r| r.station.as_str()).unwrap_or("?");
+    write_header(&mut out, &format!("Within {} minutes of delay from {}", budget, origin), &[NEAR, MID, FAR])?;
+    let color = |delay: f32| match delay / budget.max(f32::EPSILON) {
+        share if share <= 1.0 / 3.0 => NEAR,
+        share if share <= 2.0 / 3.0 => MID,
+        _ => FAR,
+    };
+    writeln!(out, "  <Folder><name>Stations</name>")?;
+    for r in reachable {
+        if let Some(&position) = coords.get(&r.station.name) {
+            write_point(&mut out, &r.station.name, &format!("{:.2} minutes of delay, {} segments", r.delay, r.hops), color(r.delay), position)?;
+        }
+    }
+    writeln!(out, "  </Folder>")?;
+    writeln!(out, "  <Folder><name>Least-delay paths</name>")?;
+    for r in reachable {
+        let Some(previous) = &r.previous else { continue };
+        let (Some(&start), Some(&end)) = (coords.get(&previous.name), coords.get(&r.station.name)) else { continue };
+        write_line(&mut out, &format!("{} → {}", previous, r.station), &format!("{:.2} minutes from {}", r.delay, origin), color(r.delay), &[start, end])?;
+    }
+    writeln!(out, "  </Folder>")?;
+    write_footer(&mut out)
+}
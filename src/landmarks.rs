@@ -0,0 +1,189 @@
+// Landmark (ALT) preprocessing for point-to-point queries: least delays to and from a few landmark stations bound the
+// remaining delay from any station to the target by the triangle inequality, and A* uses the bound to search toward it
+// Build the index once per graph (two Dijkstra runs per landmark), then answer many queries: interactive or served
+// routing settles a fraction of the stations a plain Dijkstra does
+
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
+use crate::graph::{FastMap, Station, TransitGraph};
+use crate::metrics::{index_graph, SearchContext};
+
+// Landmarks chosen unless a count is given; more tighten the bounds at two Dijkstra runs and 8 bytes per station each
+pub const DEFAULT_LANDMARKS: usize = 8;
+
+// Stations, least segment delays and landmark distances of one graph, ready for A* queries
+#[derive(Debug, Clone)]
+pub struct LandmarkIndex {
+    stations: Vec<Station>,         // Sorted; every index below refers to this order
+    edges: Vec<Vec<(usize, f32)>>,  // Least delay of each (from, to) segment, by index
+    landmarks: Vec<usize>,          // Landmark indices, in the order chosen
+    from_landmark: Vec<Vec<f32>>,   // Per landmark, least delay from it to each index (infinite if unreachable)
+    to_landmark: Vec<Vec<f32>>,     // Per landmark, least delay from each index to it
+}
+
+// Work done by one query, for comparing against plain Dijkstra
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub settled: usize, // Stations taken off the queue with their final delay
+    pub pushed: usize,  // Queue insertions
+}
+
+impl TransitGraph {
+    // Landmark index over this graph's least segment delays; see `LandmarkIndex::new`
+    pub fn landmark_index(&self, landmarks: usize) -> LandmarkIndex {
+        LandmarkIndex::new(self, landmarks)
+    }
+}
+
+impl LandmarkIndex {
+    // Chooses up to `landmarks` landmarks and records the least delay to and from each
+    // Logic: farthest-point selection: each new landmark is the station farthest (in either direction) from every
+    // landmark so far, stations no landmark reaches first, so landmarks spread to the network's ends and every
+    // component. A negative delay would break the bounds, so a graph with one gets no landmarks and queries fall back
+    // to Dijkstra
+    pub fn new(graph: &TransitGraph, landmarks: usize) -> Self {
+        let (nodes, lists) = index_graph(graph);
+        let stations: Vec<Station> = nodes.into_iter().cloned().collect();
+        let n = stations.len();
+        // Parallel segments (one per record) collapse to their least delay; A* only ever takes that one
+        let edges: Vec<Vec<(usize, f32)>> = lists.iter()
+            .map(|targets| {
+                let mut least: FastMap<usize, f32> = FastMap::default();
+                for &(to, delay) in targets {
+                    least.entry(to).and_modify(|d| *d = d.min(delay)).or_insert(delay);
+                }
+                let mut targets: Vec<(usize, f32)> = least.into_iter().collect();
+                targets.sort_by_key(|&(to, _)| to);
+                targets
+            })
+            .collect();
+        let mut reverse: Vec<Vec<(usize, f32)>> = vec![Vec::new(); n];
+        for (from, targets) in edges.iter().enumerate() {
+            for &(to, delay) in targets {
+                reverse[to].push((from, delay));
+            }
+        }
+        let mut index = Self { stations, edges, landmarks: Vec::new(), from_landmark: Vec::new(), to_landmark: Vec::new() };
+        if n == 0 || index.edges.iter().flatten().any(|&(_, delay)| delay < 0.0) {
+            return index;
+        }
+        let mut search = SearchContext::new(n);
+        let mut distances = |lists: &[Vec<(usize, f32)>], start: usize| {
+            search.dijkstra(lists, start, None);
+            search.distances().iter().map(|d| d.unwrap_or(f32::INFINITY)).collect::<Vec<f32>>()
+        };
+        // Nearest landmark per station in either direction; seeded from index 0, which is not itself a landmark
+        let mut nearest: Vec<f32> = distances(&index.edges, 0).into_iter().zip(distances(&reverse, 0)).map(|(a, b)| a.min(b)).collect();
+        let connected: Vec<bool> = (0..n).map(|i| !index.edges[i].is_empty() || !reverse[i].is_empty()).collect();
+        while index.landmarks.len() < landmarks.min(n) {
+            let Some(next) = (0..n)
+                .filter(|&i| connected[i] && !index.landmarks.contains(&i))
+                .max_by(|&a, &b| nearest[a].total_cmp(&nearest[b]).then(b.cmp(&a)))
+            else {
+                break;
+            };
+            let from = distances(&index.edges, next);
+            let to = distances(&reverse, next);
+            for i in 0..n {
+                nearest[i] = if index.landmarks.is_empty() { from[i].min(to[i]) } else { nearest[i].min(from[i]).min(to[i]) };
+            }
+            index.landmarks.push(next);
+            index.from_landmark.push(from);
+            index.to_landmark.push(to);
+        }
+        index
+    }
+
+    // The landmark stations, in the order chosen
+    pub fn landmarks(&self) -> Vec<&Station> {
+        self.landmarks.iter().map(|&i| &self.stations[i]).collect()
+    }
+
+    // Lower bound on the least delay from one station to another; infinite if the landmarks prove no path exists
+    pub fn lower_bound(&self, from: &Station, to: &Station) -> Option<f32> {
+        Some(self.bound(self.position(from)?, self.position(to)?))
+    }
+
+    // Least-delay path between two stations by A* with landmark bounds; same result as `TransitGraph::shortest_path`
+    // up to float rounding and the choice among equally delayed paths
+    pub fn shortest_path(&self, start: &Station, end: &Station) -> Option<(f32, Vec<Station>)> {
+        self.shortest_path_with_stats(start, end).0
+    }
+
+    // Like `shortest_path`, also reporting how much of the graph the query searched
+    pub fn shortest_path_with_stats(&self, start: &Station, end: &Station) -> (Option<(f32, Vec<Station>)>, QueryStats) {
+        let mut stats = QueryStats::default();
+        let (Some(s), Some(t)) = (self.position(start), self.position(end)) else {
+            return (None, stats);
+        };
+        let n = self.stations.len();
+        let mut delay = vec![f32::INFINITY; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+        let mut settled = vec![false; n];
+        let mut bounds: Vec<Option<f32>> = vec![None; n]; // Computed on first reach; most stations are never reached
+        let mut heap = BinaryHeap::new();
+        delay[s] = 0.0;
+        heap.push(Reverse((key(0.0, self.bound(s, t)), s)));
+        stats.pushed += 1;
+        while let Some(Reverse((_, node))) = heap.pop() {
+            if settled[node] {
+                continue; // A stale entry; the node was settled through a shorter path
+            }
+            settled[node] = true;
+            stats.settled += 1;
+            if node == t {
+                break;
+            }
+            for &(next, weight) in &self.edges[node] {
+                let through = delay[node] + weight;
+                if settled[next] || through >= delay[next] {
+                    continue;
+                }
+                let bound = *bounds[next].get_or_insert_with(|| self.bound(next, t));
+                if bound.is_infinite() {
+                    continue; // The landmarks show `next` cannot reach the target
+                }
+                delay[next] = through;
+                previous[next] = Some(node);
+                heap.push(Reverse((key(through, bound), next)));
+                stats.pushed += 1;
+            }
+        }
+        if !delay[t].is_finite() {
+            return (None, stats);
+        }
+        let mut path = vec![t];
+        let mut node = t;
+        while let Some(prev) = previous[node] {
+            path.push(prev);
+            node = prev;
+        }
+        path.reverse();
+        (Some((delay[t], path.into_iter().map(|i| self.stations[i].clone()).collect())), stats)
+    }
+
+    fn position(&self, station: &Station) -> Option<usize> {
+        self.stations.binary_search(station).ok()
+    }
+
+    // Largest triangle-inequality bound over the landmarks: d(L, t) - d(L, v) and d(v, L) - d(t, L) both bound d(v, t)
+    // Logic: a difference of two infinities says nothing and is skipped; an infinite difference means v cannot reach t
+    fn bound(&self, v: usize, t: usize) -> f32 {
+        let mut bound: f32 = 0.0;
+        for (from, to) in self.from_landmark.iter().zip(&self.to_landmark) {
+            for difference in [from[t] - from[v], to[v] - to[t]] {
+                if !difference.is_nan() {
+                    bound = bound.max(difference);
+                }
+            }
+        }
+        bound
+    }
+}
+
+// Queue key of a reached station: its delay so far plus the bound on the rest
+fn key(delay: f32, bound: f32) -> NotNan<f32> {
+    NotNan::new(delay + bound).unwrap_or_default()
+}
@@ -0,0 +1,196 @@
+// Live updates for dashboards: after each change to a watched directory, per-line delay summaries over everything
+// ingested, and anomalies: lines and routes whose newly ingested trips run markedly later than their earlier ones
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use crate::graph::{StationId, TransitGraph};
+use crate::load::{RecordFilter, TrainRecord};
+use crate::metrics::RankOptions;
+use crate::stats::percentile;
+use crate::watch::{DirectoryWatcher, ScanResult, Snapshot};
+
+// How many standard errors above its earlier mean a line's or route's new mean delay must be to raise an alert, unless set
+pub const ANOMALY_Z_SCORE: f32 = 3.0;
+
+// What counts as an anomaly: new trips later than earlier ones by both margins
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnomalyThresholds {
+    pub z_score: f32,    // Standard errors above the earlier mean
+    pub min_excess: f32, // Minutes above the earlier mean, so small but consistent shifts on busy lines stay quiet
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self { z_score: ANOMALY_Z_SCORE, min_excess: 0.0 }
+    }
+}
+
+// Whether an anomaly is about a whole line or one (from, to) route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyScope {
+    Line,
+    Route,
+}
+
+// Delay statistics of one line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineSummary {
+    pub line: String,
+    pub trips: usize,      // Records with a delay
+    pub mean_delay: f32,   // Average delay in minutes
+    pub p90_delay: f32,
+    pub on_time_rate: f32, // Share of trips at or below the on-time threshold
+}
+
+// A line or route whose newly ingested trips are later than its earlier trips by more than chance explains
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelayAnomaly {
+    pub scope: AnomalyScope,
+    pub name: String,        // Line name, or "From → To" for a route
+    pub trips: usize,        // New trips with a delay
+    pub mean_delay: f32,     // Their average delay in minutes
+    pub baseline_delay: f32, // Average delay of the earlier trips
+    pub z_score: f32,        // Standard errors between the two means (infinite if earlier delays never varied)
+}
+
+// One message of the live feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveUpdate {
+    pub at: DateTime<Local>,
+    pub snapshot: Snapshot,
+    pub lines: Vec<LineSummary>,      // By line name
+    pub anomalies: Vec<DelayAnomaly>, // Highest z-score first; empty on the first scan
+}
+
+// Summarizes every line with at least one delay, by line name (trimmed)
+pub fn line_summaries(records: &[TrainRecord], on_time_threshold: f32) -> Vec<LineSummary> {
+    delays_by_line(records).into_iter()
+        .filter_map(|(line, (_, mut delays))| {
+            delays.sort_by(f32::total_cmp);
+            let trips = delays.len();
+            Some(LineSummary {
+                line: line.to_string(),
+                trips,
+                mean_delay: delays.iter().sum::<f32>() / trips as f32,
+                p90_delay: percentile(&delays, 0.9)?,
+                on_time_rate: delays.iter().filter(|&&d| d <= on_time_threshold).count() as f32 / trips as f32,
+            })
+        })
+        .collect()
+}
+
+// Lines and routes whose delays in `recent` exceed their delays in `baseline` by both thresholds
+// Logic: z = (recent mean − baseline mean) / (baseline standard deviation / √recent trips); each line or route needs
+// at least `min_trips` recent trips and two baseline trips. Only increases are reported, highest z-score first
+pub fn delay_anomalies(baseline: &[TrainRecord], recent: &[TrainRecord], opts: &RankOptions, thresholds: &AnomalyThresholds) -> Vec<DelayAnomaly> {
+    let mut anomalies = anomalies_of(AnomalyScope::Line, &delays_by_line(baseline), delays_by_line(recent), opts, thresholds);
+    anomalies.extend(anomalies_of(AnomalyScope::Route, &delays_by_route(baseline), delays_by_route(recent), opts, thresholds));
+    anomalies.sort_by(|a, b| b.z_score.total_cmp(&a.z_score).then_with(|| (a.scope, &a.name).cmp(&(b.scope, &b.name))));
+    anomalies
+}
+
+fn anomalies_of<K: Ord, N: ToString>(
+    scope: AnomalyScope,
+    baseline: &BTreeMap<K, (N, Vec<f32>)>,
+    recent: BTreeMap<K, (N, Vec<f32>)>,
+    opts: &RankOptions,
+    thresholds: &AnomalyThresholds,
+) -> Vec<DelayAnomaly> {
+    recent.into_iter()
+        .filter_map(|(key, (name, delays))| {
+            let earlier = baseline.get(&key).map(|(_, d)| d).filter(|d| d.len() >= 2)?;
+            if delays.len() < opts.min_trips.max(1) {
+                return None;
+            }
+            let (mean_delay, baseline_delay) = (mean(&delays), mean(earlier));
+            let sd = (earlier.iter().map(|d| (d - baseline_delay).powi(2)).sum::<f32>() / (earlier.len() - 1) as f32).sqrt();
+            let excess = mean_delay - baseline_delay;
+            let z_score = if sd > 0.0 { excess / (sd / (delays.len() as f32).sqrt()) } else if excess > 0.0 { f32::INFINITY } else { 0.0 };
+            (z_score >= thresholds.z_score && excess >= thresholds.min_excess)
+                .then(|| DelayAnomaly { scope, name: name.to_string(), trips: delays.len(), mean_delay, baseline_delay, z_score })
+        })
+        .collect()
+}
+
+// Delays per route, keyed by stop IDs and named by the first record's station names
+fn delays_by_route(records: &[TrainRecord]) -> BTreeMap<(StationId, StationId), (String, Vec<f32>)> {
+    let mut by_route: BTreeMap<(StationId, StationId), (String, Vec<f32>)> = BTreeMap::new();
+    for record in records {
+        if let Some(delay) = record.delay() {
+            by_route.entry((record.from_station_id(), record.to_station_id()))
+                .or_insert_with(|| (format!("{} → {}", record.from.trim(), record.to.trim()), Vec::new()))
+                .1.push(delay);
+        }
+    }
+    by_route
+}
+
+fn delays_by_line(records: &[TrainRecord]) -> BTreeMap<&str, (&str, Vec<f32>)> {
+    let mut by_line: BTreeMap<&str, (&str, Vec<f32>)> = BTreeMap::new();
+    for record in records {
+        if let Some(delay) = record.delay() {
+            let line = record.line.trim();
+            by_line.entry(line).or_insert((line, Vec::new())).1.push(delay);
+        }
+    }
+    by_line
+}
+
+fn mean(delays: &[f32]) -> f32 {
+    delays.iter().sum::<f32>() / delays.len() as f32
+}
+
+// A watched directory turned into live updates
+pub struct LiveFeed {
+    watcher: DirectoryWatcher,
+    filter: RecordFilter,
+    opts: RankOptions,
+    thresholds: AnomalyThresholds,
+}
+
+impl LiveFeed {
+    pub fn new(dir: impl Into<PathBuf>, filter: RecordFilter, opts: RankOptions) -> Self {
+        Self { watcher: DirectoryWatcher::new(dir), filter, opts, thresholds: AnomalyThresholds::default() }
+    }
+
+    // Flags anomalies by these thresholds instead of the defaults
+    pub fn with_thresholds(mut self, thresholds: AnomalyThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    pub fn watcher(&self) -> &DirectoryWatcher {
+        &self.watcher
+    }
+
+    // Every ingested record that passes the filter, in file-name order
+    pub fn records(&self) -> Vec<TrainRecord> {
+        self.filter.apply(self.watcher.records())
+    }
+
+    // Scans the directory once
+    // Output: the scan, and an update if the dataset changed; Err only if the directory cannot be listed
+    // Logic: the files loaded in this scan are the recent trips, every other ingested file the baseline
+    pub fn poll(&mut self) -> io::Result<(ScanResult, Option<LiveUpdate>)> {
+        let scan = self.watcher.scan()?;
+        if !scan.changed() {
+            return Ok((scan, None));
+        }
+        let records = self.records();
+        let records_of = |paths: Vec<&Path>| paths.into_iter().flat_map(|path| self.watcher.file_records(path).iter().cloned()).collect();
+        let recent = self.filter.apply(records_of(scan.loaded.iter().map(PathBuf::as_path).collect()));
+        let baseline = self.filter.apply(records_of(self.watcher.paths().into_iter().filter(|path| !scan.loaded.iter().any(|p| p == path)).collect()));
+        let graph = TransitGraph::from_records(&records);
+        let update = LiveUpdate {
+            at: Local::now(),
+            snapshot: self.watcher.snapshot(&records, &graph, self.opts.on_time_threshold),
+            lines: line_summaries(&records, self.opts.on_time_threshold),
+            anomalies: delay_anomalies(&baseline, &recent, &self.opts, &self.thresholds),
+        };
+        Ok((scan, Some(update)))
+    }
+}
@@ -1,11 +1,18 @@
 //  Loads and deserializes the dataset
 
-use serde::Deserialize;
-use std::error::Error;
+use std::collections::HashMap;
+use std::io::Read;
+use std::mem;
+use std::sync::mpsc;
+use std::thread;
+use serde::{Deserialize, Serialize};
 use csv::ReaderBuilder;
+use chrono::NaiveDate;
+use crate::error::{Error, Result};
+use crate::graph::{station_id, Station, StationId};
 
 // Represents a single train record from the dataset with metadata including delay and routing
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrainRecord {
     pub date: String,// Date of the train record
     pub train_id: String,// Identifier for the train
@@ -24,16 +31,162 @@ pub struct TrainRecord {
     pub year: String,// Year of the record
 }
 
+impl TrainRecord {
+    // Delay in minutes if recorded and finite; a NaN or infinite delay in the CSV counts as missing
+    pub fn delay(&self) -> Option<f32> {
+        self.delay_minutes.filter(|d| d.is_finite())
+    }
+
+    // Normalized stop ID of the departure station
+    pub fn from_station_id(&self) -> StationId {
+        station_id(&self.from_id)
+    }
+
+    // Normalized stop ID of the arrival station
+    pub fn to_station_id(&self) -> StationId {
+        station_id(&self.to_id)
+    }
+
+    // Departure station as named on this record
+    pub fn from_station(&self) -> Station {
+        Station::new(self.from_station_id(), self.from.trim())
+    }
+
+    // Arrival station as named on this record
+    pub fn to_station(&self) -> Station {
+        Station::new(self.to_station_id(), self.to.trim())
+    }
+}
+
 // Loads and parses CSV data into a vector of TrainRecord structs
 // Input: path to CSV file as &str
-// Output: Result with either vector of TrainRecord or error
+// Output: Result with either vector of TrainRecord or a load error naming the file
 // Logic: Build CSV reader, iterate through records, deserialize each line into TrainRecord and collect
-pub fn load_data(path: &str) -> Result<Vec<TrainRecord>, Box<dyn Error>> {
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_path(path)?; 
+pub fn load_data(path: &str) -> Result<Vec<TrainRecord>> {
+    let rdr = ReaderBuilder::new().has_headers(true).from_path(path).map_err(|source| Error::Load { path: path.to_string(), source })?;
+    deserialize_records(rdr, path)
+}
+
+// Parses train records from any reader (e.g. a downloaded body); `source` names it in load errors
+pub fn read_data<R: Read>(reader: R, source: &str) -> Result<Vec<TrainRecord>> {
+    deserialize_records(ReaderBuilder::new().has_headers(true).from_reader(reader), source)
+}
+
+// Records per message from the parsing thread, and messages it may run ahead of the consumer
+// Batching keeps channel overhead per record negligible; the bound caps memory when the consumer is the slower side
+const PARSE_BATCH: usize = 1024;
+const PARSE_AHEAD: usize = 16;
+
+// Reads a CSV file one record at a time, handing each to `f` instead of collecting them, so a file larger than memory
+// can be aggregated in one pass
+// Output: the number of records read, or a load error naming the file at the first unreadable row
+// Logic: a scoped thread parses and sends batches over a bounded channel while `f` runs on the calling thread, so
+// parsing overlaps whatever `f` does with each record (building the graph, aggregating)
+pub fn stream_data(path: &str, mut f: impl FnMut(TrainRecord)) -> Result<usize> {
+    let failed = |source| Error::Load { path: path.to_string(), source };
+    let rdr = ReaderBuilder::new().has_headers(true).from_path(path).map_err(failed)?;
+    let (sender, receiver) = mpsc::sync_channel::<std::result::Result<Vec<TrainRecord>, csv::Error>>(PARSE_AHEAD);
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut batch = Vec::with_capacity(PARSE_BATCH);
+            for result in rdr.into_deserialize() {
+                match result {
+                    Ok(record) => batch.push(record),
+                    Err(e) => {
+                        // Records before the bad row first, then the error; a dropped receiver just ends the thread
+                        let _ = sender.send(Ok(batch)).and_then(|_| sender.send(Err(e)));
+                        return;
+                    }
+                }
+                if batch.len() == PARSE_BATCH && sender.send(Ok(mem::replace(&mut batch, Vec::with_capacity(PARSE_BATCH)))).is_err() {
+                    return; // The consumer stopped
+                }
+            }
+            let _ = sender.send(Ok(batch));
+        });
+        let mut count = 0;
+        for batch in receiver {
+            for record in batch.map_err(failed)? {
+                f(record);
+                count += 1;
+            }
+        }
+        Ok(count)
+    })
+}
+
+fn deserialize_records<R: Read>(mut rdr: csv::Reader<R>, source: &str) -> Result<Vec<TrainRecord>> {
+    let failed = |source_error| Error::Load { path: source.to_string(), source: source_error };
     let mut records = Vec::new(); 
     for result in rdr.deserialize(){ 
-        let record: TrainRecord = result?; // Deserialize line into TrainRecord struct
+        let record: TrainRecord = result.map_err(failed)?; // Deserialize line into TrainRecord struct
         records.push(record) // Append to records vector
     }
     Ok(records) 
 }
+
+// Station name -> (latitude, longitude) in decimal degrees
+pub type Coordinates = HashMap<String, (f64, f64)>;
+
+// One row of a station coordinates file
+#[derive(Debug, Deserialize)]
+struct CoordinateRow {
+    station: String,
+    lat: f64,
+    lon: f64,
+}
+
+// Loads station coordinates from a CSV with `station,lat,lon` columns
+// The train records carry no locations, so map-based exports take them from a separate file
+pub fn load_coordinates(path: &str) -> Result<Coordinates> {
+    let failed = |source| Error::Load { path: path.to_string(), source };
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_path(path).map_err(failed)?;
+    let mut coords = Coordinates::new();
+    for result in rdr.deserialize() {
+        let row: CoordinateRow = result.map_err(failed)?;
+        coords.insert(row.station.trim().to_string(), (row.lat, row.lon));
+    }
+    Ok(coords)
+}
+
+// Restricts records to a subset of lines, a service type, and/or a date range; empty fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordFilter {
+    pub lines: Vec<String>,          // Line names, matched case-insensitively after trimming
+    pub r#type: Option<String>,      // Service type, matched case-insensitively
+    pub from_date: Option<NaiveDate>, // Inclusive start of the date range
+    pub to_date: Option<NaiveDate>,   // Inclusive end of the date range
+}
+
+impl RecordFilter {
+    // True if the filter has no criteria and keeps every record
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.r#type.is_none() && self.from_date.is_none() && self.to_date.is_none()
+    }
+
+    // True if the record satisfies every criterion; records with unparsable dates fail any date bound
+    pub fn matches(&self, record: &TrainRecord) -> bool {
+        let line = record.line.trim();
+        if !self.lines.is_empty() && !self.lines.iter().any(|l| l.trim().eq_ignore_ascii_case(line)) {
+            return false;
+        }
+        if let Some(kind) = &self.r#type
+            && !kind.trim().eq_ignore_ascii_case(record.r#type.trim())
+        {
+            return false;
+        }
+        if self.from_date.is_none() && self.to_date.is_none() {
+            return true;
+        }
+        let Ok(date) = NaiveDate::parse_from_str(&record.date, "%Y-%m-%d") else { return false };
+        self.from_date.is_none_or(|d| date >= d) && self.to_date.is_none_or(|d| date <= d)
+    }
+
+    // Keeps only matching records
+    pub fn apply(&self, mut records: Vec<TrainRecord>) -> Vec<TrainRecord> {
+        if !self.is_empty() {
+            records.retain(|r| self.matches(r));
+        }
+        records
+    }
+}
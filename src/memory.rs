@@ -0,0 +1,191 @@
+// Estimated memory footprint of the loaded records, the graph and the metric intermediates (--mem-stats), so a user
+// moving to multi-year data can see which representation dominates and what the columnar or streaming modes would save
+// Estimates count allocated capacity: struct slots, string contents, and hash-table control bytes and empty buckets;
+// allocator headers and fragmentation are not counted
+
+use std::collections::HashSet;
+use std::mem::{size_of, size_of_val};
+use serde::{Deserialize, Serialize};
+use crate::graph::{Station, TransitGraph, Weight};
+use crate::load::TrainRecord;
+
+// One component's estimated footprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    pub component: String,
+    pub items: usize,          // Records, edges, map entries or nodes, as the component name says
+    pub struct_bytes: usize,   // Fixed-size parts: struct and tuple sizes times occupied slots
+    pub string_bytes: usize,   // Heap bytes holding string contents
+    pub overhead_bytes: usize, // Hash-table control bytes and empty buckets, spare Vec capacity
+    pub total_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub(crate) fn new(component: &str, items: usize, struct_bytes: usize, string_bytes: usize, overhead_bytes: usize) -> Self {
+        Self {
+            component: component.to_string(),
+            items,
+            struct_bytes,
+            string_bytes,
+            overhead_bytes,
+            total_bytes: struct_bytes + string_bytes + overhead_bytes,
+        }
+    }
+}
+
+// Heap bytes of a station's ID and name
+pub(crate) fn station_bytes(station: &Station) -> usize {
+    station.id.capacity() + station.name.capacity()
+}
+
+// Empty-bucket and control-byte overhead of a hash table holding `len` entries of `slot` bytes with room for `capacity`
+// Logic: hashbrown (behind std's and the aHash-keyed maps alike) allocates a power-of-two number of buckets, at most
+// 7/8 full, plus one control byte per bucket and a 16-byte group of trailing control bytes
+pub(crate) fn table_overhead(capacity: usize, len: usize, slot: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    let buckets = match capacity {
+        0..4 => 4,
+        4..8 => 8,
+        _ => (capacity * 8 / 7).next_power_of_two(),
+    };
+    buckets.saturating_sub(len) * slot + buckets + 16
+}
+
+// Records held in memory (the slice; spare capacity of the Vec holding it is not visible here)
+pub fn records_memory(records: &[TrainRecord]) -> MemoryUsage {
+    let strings: usize = records.iter()
+        .map(|r| {
+            [&r.date, &r.train_id, &r.stop_sequence, &r.from, &r.from_id, &r.to, &r.to_id, &r.scheduled_time, &r.actual_time,
+             &r.status, &r.line, &r.r#type, &r.month, &r.year]
+                .iter()
+                .map(|s| s.capacity())
+                .sum::<usize>()
+        })
+        .sum();
+    MemoryUsage::new("records", records.len(), size_of_val(records), strings, 0)
+}
+
+impl<W: Weight> TransitGraph<W> {
+    // Footprint of the graph's adjacency map, segment line sets, stop-ID index, node index and path cache
+    pub fn memory_usage(&self) -> Vec<MemoryUsage> {
+        // Adjacency: one Station key per source, and one Station clone (with its own strings) per edge
+        let edge_slot = size_of::<(Station, W)>();
+        let (mut edges, mut edge_structs, mut edge_strings, mut edge_spare) = (0, 0, 0, 0);
+        for (from, targets) in &self.nodes {
+            edges += targets.len();
+            edge_structs += targets.len() * edge_slot;
+            edge_spare += (targets.capacity() - targets.len()) * edge_slot;
+            edge_strings += station_bytes(from) + targets.iter().map(|(to, _)| station_bytes(to)).sum::<usize>();
+        }
+        let node_slot = size_of::<(Station, Vec<(Station, W)>)>();
+        let adjacency = MemoryUsage::new(
+            "graph edges",
+            edges,
+            self.nodes.len() * node_slot + edge_structs,
+            edge_strings,
+            table_overhead(self.nodes.capacity(), self.nodes.len(), node_slot) + edge_spare,
+        );
+
+        let line_slot = size_of::<((Station, Station), HashSet<String>)>();
+        let (mut line_structs, mut line_strings, mut line_overhead) = (0, 0, 0);
+        for ((from, to), served) in &self.lines {
+            line_structs += served.len() * size_of::<String>();
+            line_strings += station_bytes(from) + station_bytes(to) + served.iter().map(String::capacity).sum::<usize>();
+            line_overhead += table_overhead(served.capacity(), served.len(), size_of::<String>());
+        }
+        let lines = MemoryUsage::new(
+            "graph segment lines",
+            self.lines.len(),
+            self.lines.len() * line_slot + line_structs,
+            line_strings,
+            table_overhead(self.lines.capacity(), self.lines.len(), line_slot) + line_overhead,
+        );
+
+        let id_slot = size_of::<(String, Station)>();
+        let index = MemoryUsage::new(
+            "graph station index",
+            self.by_id.len(),
+            self.by_id.len() * id_slot,
+            self.by_id.iter().map(|(id, station)| id.capacity() + station_bytes(station)).sum(),
+            table_overhead(self.by_id.capacity(), self.by_id.len(), id_slot),
+        );
+        // Dense node index of point-to-point searches, once one has run: a Station clone per node and an edge per edge
+        let (nodes, edges) = self.node_index.get().map_or((0, 0), |index| (index.nodes.len(), index.lists.iter().map(Vec::len).sum()));
+        let node_index = MemoryUsage::new(
+            "graph node index",
+            nodes,
+            nodes * (size_of::<Station>() + size_of::<Vec<(usize, W)>>()) + edges * size_of::<(usize, W)>(),
+            self.node_index.get().map_or(0, |index| index.nodes.iter().map(station_bytes).sum()),
+            0,
+        );
+        vec![adjacency, lines, index, node_index, self.path_cache.memory_usage()]
+    }
+
+    // Peak extra memory of the metric computations, which is freed when each finishes
+    // Closeness and betweenness index the graph (node references and dense adjacency) and keep one set of search
+    // buffers per worker thread; betweenness returns a map with one Station clone per node
+    pub fn metric_memory(&self) -> Vec<MemoryUsage> {
+        let n = self.by_id.len();
+        let edges: usize = self.nodes.values().map(Vec::len).sum();
+        let adjacency = MemoryUsage::new(
+            "metrics: indexed adjacency",
+            edges,
+            n * (size_of::<&Station>() + size_of::<Vec<(usize, W)>>()) + edges * size_of::<(usize, W)>(),
+            0,
+            0,
+        );
+        let threads = rayon::current_num_threads();
+        // Per node: distance, predecessor, hops, path count, dependency, predecessor list, BFS queue and order slots
+        let per_node = size_of::<Option<W>>() + size_of::<Option<usize>>() + size_of::<i32>() + 2 * size_of::<f32>()
+            + size_of::<Vec<usize>>() + 2 * size_of::<usize>();
+        let search = MemoryUsage::new(&format!("metrics: search buffers ({} threads)", threads), n, threads * n * per_node, 0, 0);
+        let result_slot = size_of::<(Station, f32)>();
+        let betweenness = MemoryUsage::new(
+            "metrics: betweenness scores",
+            n,
+            n * result_slot,
+            self.by_id.values().map(station_bytes).sum(),
+            table_overhead(n, n, result_slot),
+        );
+        // All-pairs least delays (`DistanceMatrix`): one f32 per pair plus the station list
+        let matrix = MemoryUsage::new(
+            "metrics: distance matrix",
+            n * n,
+            n * n * size_of::<f32>() + n * (size_of::<Station>() + size_of::<bool>()),
+            self.by_id.values().map(station_bytes).sum(),
+            0,
+        );
+        vec![adjacency, search, betweenness, matrix]
+    }
+
+    // What the delayed records would take as a columnar `RecordTable`: three 4-byte columns per row plus dictionaries
+    pub fn columnar_memory(&self) -> MemoryUsage {
+        let rows: usize = self.nodes.values().map(Vec::len).sum();
+        let lines: HashSet<&String> = self.lines.values().flatten().collect();
+        let structs = rows * 3 * size_of::<u32>()
+            + self.by_id.len() * size_of::<Station>()
+            + self.lines.len() * size_of::<(u32, u32)>()
+            + lines.len() * size_of::<String>();
+        let strings = self.by_id.values().map(station_bytes).sum::<usize>() + lines.iter().map(|l| l.capacity()).sum::<usize>();
+        MemoryUsage::new("alternative: columnar table", rows, structs, strings, 0)
+    }
+}
+
+// Every estimate for a run: the records and graph held, then a total, then metric peaks and the columnar alternative
+pub fn estimate<W: Weight>(records: &[TrainRecord], graph: &TransitGraph<W>) -> Vec<MemoryUsage> {
+    let mut rows = vec![records_memory(records)];
+    rows.extend(graph.memory_usage());
+    let held = MemoryUsage::new(
+        "total held",
+        records.len(),
+        rows.iter().map(|r| r.struct_bytes).sum(),
+        rows.iter().map(|r| r.string_bytes).sum(),
+        rows.iter().map(|r| r.overhead_bytes).sum(),
+    );
+    rows.push(held);
+    rows.extend(graph.metric_memory());
+    rows.push(graph.columnar_memory());
+    rows
+}
@@ -1,234 +1,740 @@
 // Contains algorithms to compute graph metrics like shortest paths, closeness, betweenness, and delay ranking
 
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
 use std::cmp::Reverse;
-use ordered_float::NotNan;
-use crate::graph::{TransitGraph, Station};
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use rayon::prelude::*;
+use crate::cache::PathCache;
+use crate::checkpoint::{Checkpoint, Checkpointer};
+use crate::graph::{FastMap, Graph, TransitGraph, Station, Weight};
+use crate::pipeline::{Observer, Silent};
 use std::collections::{HashSet, VecDeque};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
-impl TransitGraph {
+// Runtime parameters shared by every ranking, passed down from the CLI
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RankOptions {
+    pub top_n: usize,           // Number of entries to print
+    pub min_trips: usize,       // Minimum observations for a route (or station) to be ranked
+    pub on_time_threshold: f32, // Delay in minutes at or below which a trip counts as on time
+    #[serde(default = "default_seed")]
+    pub seed: u64,              // Seed for sampled algorithms (e.g. random baselines), so reruns match
+    #[serde(default)]
+    pub path_algorithm: PathAlgorithm, // Single-source shortest-path search used by closeness rankings
+    #[serde(default)]
+    pub prune: bool,            // Rank closeness and betweenness for the top N only; see the `topk` module
+}
+
+impl Default for RankOptions {
+    fn default() -> Self {
+        Self { top_n: 10, min_trips: 5, on_time_threshold: DELAY_PROFILES[0].1, seed: DEFAULT_SEED, path_algorithm: PathAlgorithm::Dijkstra, prune: false }
+    }
+}
+
+// How single-source shortest paths are computed for all-pairs workloads such as closeness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathAlgorithm {
+    #[default]
+    Dijkstra,      // Binary-heap Dijkstra, each search on one thread
+    DeltaStepping, // Distance buckets whose edges are relaxed in parallel when a bucket is large; same distances
+}
+
+// Seed used unless one is given, so default output is the same on every run
+pub const DEFAULT_SEED: u64 = 42;
+
+fn default_seed() -> u64 {
+    DEFAULT_SEED
+}
+
+// Named definitions of "on time": (profile name, threshold in minutes); the first is the default
+// fra-6min follows the FRA/NJ Transit convention of counting trains up to 6 minutes late as on time
+pub const DELAY_PROFILES: [(&str, f32); 2] = [("fra-6min", 6.0), ("strict-2min", 2.0)];
+
+// Parses a delay threshold given as a profile name or a number of minutes
+pub fn parse_delay_threshold(s: &str) -> Result<f32, String> {
+    if let Some((_, minutes)) = DELAY_PROFILES.iter().find(|(name, _)| name.eq_ignore_ascii_case(s.trim())) {
+        return Ok(*minutes);
+    }
+    let names: Vec<&str> = DELAY_PROFILES.iter().map(|(name, _)| *name).collect();
+    match s.trim().parse::<f32>() {
+        Ok(minutes) if minutes.is_finite() && minutes >= 0.0 => Ok(minutes),
+        _ => Err(format!("expected minutes or one of {}", names.join(", "))),
+    }
+}
+
+// Describes a threshold for titles, naming its profile when it matches one
+pub fn describe_threshold(minutes: f32) -> String {
+    match DELAY_PROFILES.iter().find(|(_, m)| *m == minutes) {
+        Some((name, _)) => format!("on time ≤ {} min, {}", minutes, name),
+        None => format!("on time ≤ {} min", minutes),
+    }
+}
+
+// A station with its score in a ranking
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationScore {
+    pub station: Station,
+    pub score: f32,
+}
+
+// Aggregated delay statistics for one (from, to) route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteStat {
+    pub from: Station,
+    pub to: Station,
+    pub average_delay: f32, // Mean delay in minutes
+    pub trips: usize,
+    pub on_time_rate: f32,  // Share of trips within the on-time threshold, in [0, 1]
+}
+
+impl<W: Weight> TransitGraph<W> {
     // Returns a set of all unique stations in the graph
     pub fn all_stations(&self) -> HashSet<Station> {
-        let mut stations = HashSet::new();
-        for (from, neighbors) in &self.nodes {
-            stations.insert(from.clone());
-            for (to, _) in neighbors {
-                stations.insert(to.clone());
+        self.stations().cloned().collect()
+    }
+
+    // Least-weight path from start to end; see the generic `shortest_path`
+    // Answers are memoized in the graph's LRU `path_cache`, so repeating a query does not rerun Dijkstra
+    // A miss searches the graph's `node_index`, which is built once, rather than re-indexing every node per query
+    pub fn shortest_path(&self, start: &Station, end: &Station) -> Option<(W, Vec<Station>)> {
+        self.path_cache.get_or_compute(start, end, || {
+            if start == end {
+                return Some((W::ZERO, vec![start.clone()]));
             }
+            let index = self.node_index();
+            let (s, e) = (index.position(start)?, index.position(end)?);
+            path_between(&index.lists, s, e, |i| index.nodes[i].clone())
+        })
+    }
+
+    // Dense index of the graph's stations and edges, built on first use and kept for the graph's lifetime
+    pub(crate) fn node_index(&self) -> &NodeIndex<W> {
+        self.node_index.get_or_init(|| {
+            let (nodes, lists) = index_graph(self);
+            NodeIndex { nodes: nodes.into_iter().cloned().collect(), lists }
+        })
+    }
+
+    // The graph's shortest-path cache, for its hit rate or to resize or clear it
+    pub fn path_cache(&self) -> &PathCache<W> {
+        &self.path_cache
+    }
+
+    // Closeness centrality of a station; see the generic `closeness_centrality`
+    pub fn closeness_centrality(&self, station: &Station) -> Option<f32> {
+        closeness_centrality(self, station)
+    }
+
+    // Ranks stations by closeness centrality and returns top N
+    pub fn rank_stations_by_closeness(&self, opts: &RankOptions) -> Vec<StationScore> {
+        self.rank_stations_by_closeness_observed(opts, &Silent)
+    }
+
+    // Like `rank_stations_by_closeness`, reporting progress per station as the "closeness" metric
+    // Logic: the graph is indexed once, then stations are scored in parallel, one single-source Dijkstra each in the
+    // worker's reused search buffers; progress may arrive from any thread
+    // With delta-stepping, each search also relaxes large buckets in parallel (rayon nests inside the per-station map)
+    pub fn rank_stations_by_closeness_observed(&self, opts: &RankOptions, observer: &dyn Observer) -> Vec<StationScore> {
+        self.rank_stations_by_closeness_checkpointed(opts, observer, None)
+    }
+
+    // Like `rank_stations_by_closeness_observed`, saving finished scores to `checkpoints` and continuing from them
+    // Logic: with checkpoints, stations are scored in chunks of CHECKPOINT_SOURCES (each chunk in parallel) and a save is
+    // considered after each; without, all at once. Scores do not depend on each other, so the ranking is the same
+    // With `opts.prune`, `top_k_closeness` ranks instead, without checkpoints
+    pub fn rank_stations_by_closeness_checkpointed(
+        &self,
+        opts: &RankOptions,
+        observer: &dyn Observer,
+        mut checkpoints: Option<&mut Checkpointer>,
+    ) -> Vec<StationScore> {
+        if opts.prune {
+            return self.top_k_closeness(opts, observer).scores; // Short enough not to checkpoint
         }
-        stations
-    }
-
-// Computes the shortest path (by total delay) from start to end station using Dijkstra’s algorithm.
-// Input: station names for `start` and `end`.
-// Output: Option containing a tuple of (total delay, list of stations along the shortest path).
-pub fn shortest_path(&self, start: &Station, end: &Station) -> Option<(f32, Vec<Station>)> {
-    let mut distances: HashMap<Station, f32> = HashMap::new(); 
-    let mut previous: HashMap<Station, Station> = HashMap::new();
-    let mut heap = BinaryHeap::new(); 
-
-    // Insert the starting station into the heap with 0 delay
-    heap.push(Reverse((NotNan::new(0.0).unwrap(), start.clone())));
-    distances.insert(start.clone(), 0.0);
-
-    // Main loop: extract the station with the shortest known delay
-    while let Some(Reverse((wrapped_dist, station))) = heap.pop() {
-        let dist = wrapped_dist.into_inner(); 
-
-        // If we've reached the destination, reconstruct and return the full path
-        if &station == end {
-            let mut path = vec![end.clone()];
-            let mut current = end.clone();
-            // Walk backwards using `previous` map to reconstruct path from end to start
-            while let Some(prevstation) = previous.get(&current) {
-                path.push(prevstation.clone());
-                current = prevstation.clone();
-            }
-            path.reverse(); // Reverse the path so it's from start → end
-            return Some((dist, path)); 
-        }
-
-        // If this station has neighbors, explore them
-        if let Some(neighbors) = self.nodes.get(&station) {
-            for (neighbor, weight) in neighbors {
-                let new_dist = dist + *weight; // Calculate total delay to neighbor through current station
-                // Check if this new path is better than any previously known path
-                let is_better = match distances.get(neighbor) {
-                    None => true, 
-                    Some(&current_dist) => new_dist < current_dist, // Found a shorter path
-                };
-                // If it's better, update our records and push neighbor onto the heap
-                if is_better {
-                    distances.insert(neighbor.clone(), new_dist);
-                    previous.insert(neighbor.clone(), station.clone());
-                    heap.push(Reverse((NotNan::new(new_dist).unwrap(), neighbor.clone()))); 
-                }
+        let (stations, lists) = index_graph(self);
+        let n = stations.len();
+        let delta = match opts.path_algorithm {
+            PathAlgorithm::Dijkstra => None,
+            PathAlgorithm::DeltaStepping => default_delta(&lists),
+        };
+        let (mut scores, start) = resume(&checkpoints, observer, "closeness", n, None);
+        let done = AtomicUsize::new(start);
+        let chunk = if checkpoints.is_some() { CHECKPOINT_SOURCES } else { n.max(1) };
+        for first in (start..n).step_by(chunk) {
+            let sources = first..(first + chunk).min(n);
+            let chunk_scores: Vec<Option<f32>> = sources.clone().into_par_iter()
+                .map_init(|| SearchContext::new(n), |search, i| {
+                    let score = closeness_of(&lists, i, delta, search);
+                    observer.on_metric_progress("closeness", done.fetch_add(1, AtomicOrdering::Relaxed) + 1, n);
+                    score
+                })
+                .collect();
+            scores[sources.clone()].copy_from_slice(&chunk_scores);
+            save_if_due(&mut checkpoints, observer, "closeness", sources.end, &scores);
+        }
+        finish(checkpoints);
+        let mut results: Vec<StationScore> = scores.into_iter().enumerate()
+            .filter_map(|(i, score)| Some(StationScore { station: stations[i].clone(), score: score? }))
+            .collect();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.station.cmp(&b.station)));
+        results.truncate(opts.top_n);
+        results
+    }
+
+    // Betweenness centrality of every station; see the generic `betweenness_centrality`
+    pub fn betweenness_centrality(&self) -> HashMap<Station, f32> {
+        betweenness_centrality(self)
+    }
+
+    // Ranks and returns top N stations by betweenness centrality
+    pub fn rank_stations_by_betweenness(&self, opts: &RankOptions) -> Vec<StationScore> {
+        self.rank_stations_by_betweenness_observed(opts, &Silent)
+    }
+
+    // Like `rank_stations_by_betweenness`, reporting progress per source station as the "betweenness" metric
+    pub fn rank_stations_by_betweenness_observed(&self, opts: &RankOptions, observer: &dyn Observer) -> Vec<StationScore> {
+        self.rank_stations_by_betweenness_checkpointed(opts, observer, None)
+    }
+
+    // Like `rank_stations_by_betweenness_observed`, saving progress to `checkpoints`; see `betweenness_centrality_checkpointed`
+    // With `opts.prune`, `top_k_betweenness` ranks instead, without checkpoints
+    pub fn rank_stations_by_betweenness_checkpointed(
+        &self,
+        opts: &RankOptions,
+        observer: &dyn Observer,
+        checkpoints: Option<&mut Checkpointer>,
+    ) -> Vec<StationScore> {
+        if opts.prune {
+            return self.top_k_betweenness(opts, observer).scores;
+        }
+        let mut scores: Vec<StationScore> = betweenness_centrality_checkpointed(self, observer, checkpoints).into_iter()
+            .map(|(station, score)| StationScore { station, score })
+            .collect();
+        scores.retain(|s| s.score.is_finite());
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.station.cmp(&b.station)));
+        scores.truncate(opts.top_n);
+        scores
+    }
+}
+
+impl TransitGraph {
+
+    // Computes average delay per route in the network
+    // Output: Vec of ((from, to), avg_delay, trip_count)
+    pub fn get_route_average_delays(&self) -> Vec<((Station, Station), f32, usize)> {
+        let mut totalroutes: FastMap<(Station, Station), (f32, usize)> = FastMap::default();
+        for (from, to, delay) in self.edges() {
+            let entry = totalroutes.entry((from.clone(), to.clone())).or_insert((0.0, 0));
+            entry.0 += delay; // Accumulate delay
+            entry.1 += 1;     // Count trips
+        }
+        totalroutes.into_iter().map(|((from, to), (total_delay, count))| {
+                ((from, to), total_delay / count as f32, count) // Compute average
+            })
+            .collect()
+    }
+
+    // Computes the share of trips on each route arriving within `threshold` minutes of schedule
+    // Output: map from (from, to) to on-time fraction in [0, 1]
+    pub fn get_route_on_time_rates(&self, threshold: f32) -> HashMap<(Station, Station), f32> {
+        let mut counts: FastMap<(Station, Station), (usize, usize)> = FastMap::default();
+        for (from, to, delay) in self.edges() {
+            let entry = counts.entry((from.clone(), to.clone())).or_insert((0, 0));
+            if delay <= threshold {
+                entry.0 += 1; // Count on-time trips
             }
+            entry.1 += 1; // Count trips
         }
+        counts.into_iter().map(|(route, (on_time, total))| (route, on_time as f32 / total as f32)).collect()
+    }
+
+    // Returns top N routes with highest average delay
+    pub fn rank_routes_by_average_delay(&self, opts: &RankOptions) -> Vec<RouteStat> {
+        let mut averages = self.get_route_average_delays().into_iter().filter(|(_, _, count)| *count >= opts.min_trips).collect::<Vec<_>>(); // Filter routes with too few trips
+        averages.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.route_stats(averages, opts)
+    }
+
+    // Returns top N routes with the lowest average delay
+    pub fn rank_routes_by_lowest_delay(&self, opts: &RankOptions) -> Vec<RouteStat> {
+        let mut averages = self.get_route_average_delays().into_iter().filter(|(_, _, count)| *count >= opts.min_trips).collect::<Vec<_>>(); // Filter routes with too few trips
+        averages.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        self.route_stats(averages, opts)
+    }
+
+    // Attaches on-time rates to the first N sorted routes
+    fn route_stats(&self, averages: Vec<((Station, Station), f32, usize)>, opts: &RankOptions) -> Vec<RouteStat> {
+        let on_time = self.get_route_on_time_rates(opts.on_time_threshold);
+        averages.into_iter()
+            .take(opts.top_n)
+            .map(|((from, to), average_delay, trips)| {
+                let on_time_rate = on_time.get(&(from.clone(), to.clone())).copied().unwrap_or(0.0);
+                RouteStat { from, to, average_delay, trips, on_time_rate }
+            })
+            .collect()
+    }
+}
+
+// A weight in the Dijkstra heap; only ordered weights are pushed, so comparing them never falls back to Equal in practice
+#[derive(Clone, Copy, PartialEq)]
+struct HeapWeight<W>(W);
+
+impl<W: Weight> Eq for HeapWeight<W> {}
+
+impl<W: Weight> PartialOrd for HeapWeight<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    None 
+impl<W: Weight> Ord for HeapWeight<W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
 }
 
+// Outgoing (target index, weight) edges per node index
+type Adjacency<W> = Vec<Vec<(usize, W)>>;
 
-    // Calculates closeness centrality for a given station
-    // Returns None if station is isolated or unreachable from others
-    // Closeness is defined as the number of reachable nodes divided by the sum of shortest-path delays to them
-    pub fn closeness_centrality(&self, station: &Station) -> Option<f32> {
-        let mut total_delay = 0.0; 
-        let mut reachable = 0;    
-        let n = self.nodes.len(); 
-        // Loop through all other stations in the graph
-        for other in self.nodes.keys() {
-            if other == station {
-                continue; // Skip calculating distance to itself
+// Dense view of any graph for the algorithms below: nodes in sorted order and, per index, its outgoing edges by index
+// Output: borrowed nodes (nothing is cloned) and adjacency lists in the graph's own neighbor order; edges to nodes the
+// graph does not list are dropped
+pub(crate) fn index_graph<G: Graph>(graph: &G) -> (Vec<&G::Node>, Adjacency<G::Weight>) {
+    let mut nodes: Vec<&G::Node> = graph.nodes().collect();
+    nodes.sort();
+    let index: FastMap<&G::Node, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let lists = nodes.iter()
+        .map(|n| graph.neighbors(n).filter_map(|(to, w)| index.get(to).map(|&j| (j, w))).collect())
+        .collect();
+    (nodes, lists)
+}
+
+// An `index_graph` view that owns its stations, so a graph can keep it for repeated queries
+#[derive(Debug)]
+pub(crate) struct NodeIndex<W> {
+    pub(crate) nodes: Vec<Station>, // Sorted, as `index_graph` orders them
+    pub(crate) lists: Adjacency<W>,
+}
+
+impl<W> NodeIndex<W> {
+    pub(crate) fn position(&self, station: &Station) -> Option<usize> {
+        self.nodes.binary_search(station).ok()
+    }
+}
+
+// Bucket width for delta-stepping over these edges: their mean weight, which keeps buckets a few edges wide
+// Output: None if any weight is negative or unordered, since buckets are then not settled in distance order
+fn default_delta<W: Weight>(lists: &[Vec<(usize, W)>]) -> Option<f32> {
+    let (mut total, mut count) = (0.0, 0);
+    for &(_, weight) in lists.iter().flatten() {
+        if weight.partial_cmp(&W::ZERO).is_none_or(Ordering::is_lt) {
+            return None;
+        }
+        total += weight.minutes() as f64;
+        count += 1;
+    }
+    Some(if total > 0.0 { (total / count as f64) as f32 } else { 1.0 })
+}
+
+// Position of a node in `index_graph` order
+fn position<N: Ord>(nodes: &[&N], node: &N) -> Option<usize> {
+    nodes.binary_search(&node).ok()
+}
+
+// Reusable scratch buffers for repeated searches over one indexed graph: Dijkstra's distances, predecessors and heap,
+// and the breadth-first state of Brandes' algorithm. All-pairs and centrality workloads keep one per thread, so a
+// search allocates nothing once the buffers have grown
+// Logic: each search records the indices it wrote and the next one resets only those, so a short point-to-point
+// search on a large graph does not pay for clearing every node's slot
+pub(crate) struct SearchContext<W> {
+    distances: Vec<Option<W>>,     // Least distance found per index (None if unreached)
+    previous: Vec<Option<usize>>,  // Previous index on a least path
+    heap: BinaryHeap<Reverse<(HeapWeight<W>, usize)>>,
+    reached: Vec<usize>,           // Indices the last Dijkstra wrote
+    hops: Vec<i32>,                // BFS distance from the source, -1 if unvisited
+    sigma: Vec<f32>,               // Number of shortest paths from the source
+    delta: Vec<f32>,               // Dependency of the source on each node
+    preds: Vec<Vec<usize>>,        // Predecessors on shortest paths
+    queue: VecDeque<usize>,
+    order: Vec<usize>,             // BFS visitation order of the last source
+    buckets: BTreeMap<usize, Vec<usize>>, // Delta-stepping: unsettled indices by distance bucket (may hold stale entries)
+    settled: Vec<usize>,           // Delta-stepping: indices settled in the current bucket
+}
+
+// Frontier size from which a delta-stepping phase relaxes edges on the thread pool; below it, threads cost more
+const PARALLEL_FRONTIER: usize = 256;
+
+// A delta-stepping relaxation request: (target index, tentative distance, source index)
+type Request<W> = (usize, W, usize);
+
+impl<W: Weight> SearchContext<W> {
+    // Buffers for a graph of `n` nodes
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            distances: vec![None; n],
+            previous: vec![None; n],
+            heap: BinaryHeap::new(),
+            reached: Vec::new(),
+            hops: vec![-1; n],
+            sigma: vec![0.0; n],
+            delta: vec![0.0; n],
+            preds: vec![Vec::new(); n],
+            queue: VecDeque::new(),
+            order: Vec::with_capacity(n),
+            buckets: BTreeMap::new(),
+            settled: Vec::new(),
+        }
+    }
+
+    // Least distance per index found by the last `dijkstra`
+    pub(crate) fn distances(&self) -> &[Option<W>] {
+        &self.distances
+    }
+
+    // Index path from the last search's start to `end`, or None if it was not reached
+    pub(crate) fn path_to(&self, end: usize) -> Option<Vec<usize>> {
+        self.distances[end]?;
+        let mut path = vec![end];
+        while let Some(prev) = self.previous[*path.last()?] {
+            path.push(prev);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Dijkstra over index adjacency from `start`, stopping early once `end` is settled if given
+    // Output: in the buffers, per index, the least distance and the previous index on a least path
+    // Logic: ties in the heap go to the lower index, i.e. the node that sorts first, as with a node-keyed heap
+    pub(crate) fn dijkstra(&mut self, lists: &[Vec<(usize, W)>], start: usize, end: Option<usize>) {
+        self.dijkstra_until(lists, start, |node, _| Some(node) != end);
+    }
+
+    // Dijkstra like `dijkstra`, passing each node with its final distance to `settled` as it leaves the heap (in
+    // distance order, the start first) and stopping as soon as `settled` returns false
+    // Output: true if the search ran to completion, i.e. the distances in the buffers are final for every node
+    pub(crate) fn dijkstra_until(&mut self, lists: &[Vec<(usize, W)>], start: usize, mut settled: impl FnMut(usize, W) -> bool) -> bool {
+        self.reset();
+        self.heap.clear();
+        // Insert the starting node into the heap with 0 delay
+        self.heap.push(Reverse((HeapWeight(W::ZERO), start)));
+        self.distances[start] = Some(W::ZERO);
+        self.reached.push(start);
+        // Main loop: extract the node with the shortest known delay
+        while let Some(Reverse((HeapWeight(dist), node))) = self.heap.pop() {
+            if self.distances[node].is_some_and(|best| best < dist) {
+                continue; // A stale heap entry; the node was settled with a lower distance
             }
-            // Try computing shortest path from station to `other`
-            if let Some((delay, _path)) = self.shortest_path(station, other) {
-                total_delay += delay; 
-                reachable += 1;      
+            if !settled(node, dist) {
+                return false;
+            }
+            // Explore the node's neighbors
+            for &(neighbor, weight) in &lists[node] {
+                let new_dist = dist + weight; // Calculate total delay to neighbor through current node
+                if !new_dist.is_ordered() {
+                    continue; // A NaN weight cannot be ordered; skip the edge
+                }
+                // If this path is better than any previously known one, update our records and push the neighbor
+                match self.distances[neighbor] {
+                    Some(current) if new_dist >= current => continue,
+                    Some(_) => {}
+                    None => self.reached.push(neighbor),
+                }
+                self.distances[neighbor] = Some(new_dist);
+                self.previous[neighbor] = Some(node);
+                self.heap.push(Reverse((HeapWeight(new_dist), neighbor)));
             }
         }
+        true
+    }
 
-        // If no reachable nodes or no delay accumulated, return None (undefined closeness)
-        if total_delay == 0.0 || reachable == 0 {
-            if reachable == 0 { return None } 
-            None // Covers cases where delays exist but all are zero
-        } else {
-            // Compute closeness as the number of reachable nodes divided by total delay
-            Some(reachable as f32 / total_delay) // Higher value means more central (lower delay to more stations)
+    // Clears the distances and predecessors the last search wrote
+    fn reset(&mut self) {
+        for i in self.reached.drain(..) {
+            self.distances[i] = None;
+            self.previous[i] = None;
         }
     }
 
-    // Ranks stations by closeness centrality and prints top N
-    pub fn rank_stations_by_closeness(&self, top_n: usize) {
-        let mut results: Vec<(Station, f32)> = vec![];
-        for station in self.nodes.keys() {
-            if let Some(score) = self.closeness_centrality(station) {
-                results.push((station.clone(), score));
+    // Delta-stepping (Meyer and Sanders) over index adjacency from `start`, for non-negative weights
+    // Input: the bucket width in minutes; edges at most `delta` long are light, longer ones heavy
+    // Output: the same least distances as `dijkstra`, in the same buffers; on ties the predecessor may differ
+    // Logic: nodes wait in buckets of width `delta` by tentative distance; the lowest bucket is emptied by relaxing
+    // light edges until no node falls back into it, then the heavy edges of everything it settled are relaxed once.
+    // Each phase first generates relaxation requests (in parallel on large frontiers) and then applies them in order,
+    // so results do not depend on the number of threads
+    pub(crate) fn delta_stepping(&mut self, lists: &[Vec<(usize, W)>], start: usize, delta: f32) {
+        self.reset();
+        self.buckets.clear();
+        self.distances[start] = Some(W::ZERO);
+        self.reached.push(start);
+        self.buckets.insert(0, vec![start]);
+        while let Some((bucket, mut frontier)) = self.buckets.pop_first() {
+            self.settled.clear();
+            loop {
+                frontier.sort_unstable();
+                frontier.dedup();
+                // Drop stale entries: nodes that have since moved to a lower bucket and were settled there
+                frontier.retain(|&u| self.distances[u].is_some_and(|d| bucket_of(d, delta) == bucket));
+                if frontier.is_empty() {
+                    break;
+                }
+                let requests = self.requests(lists, &frontier, delta, true);
+                self.settled.append(&mut frontier);
+                self.relax(requests, delta);
+                frontier = self.buckets.remove(&bucket).unwrap_or_default();
             }
+            self.settled.sort_unstable();
+            self.settled.dedup();
+            let requests = self.requests(lists, &self.settled, delta, false);
+            self.relax(requests, delta);
         }
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        println!("Top {} stations by closeness centrality:", top_n);
-        for (i, (station, score)) in results.iter().take(top_n).enumerate() {
-            println!("{:>2}. {:<30} {:.4}", i + 1, station, score);
+    }
+
+    // Relaxation requests along the light (or heavy) edges of `nodes` that would improve their target's distance
+    fn requests(&self, lists: &[Vec<(usize, W)>], nodes: &[usize], delta: f32, light: bool) -> Vec<Request<W>> {
+        let distances = &self.distances;
+        let edges = |&u: &usize| {
+            let from = distances[u].unwrap_or(W::ZERO);
+            lists[u].iter()
+                .filter(move |(_, weight)| (weight.minutes() <= delta) == light)
+                .map(move |&(v, weight)| (v, from + weight, u))
+                .filter(|&(v, dist, _)| dist.is_ordered() && distances[v].is_none_or(|current| dist < current))
+        };
+        if nodes.len() >= PARALLEL_FRONTIER {
+            nodes.par_iter().flat_map_iter(edges).collect()
+        } else {
+            nodes.iter().flat_map(edges).collect()
         }
     }
 
-    // Computes unweighted betweenness centrality for all stations
-    // Betweenness measures how often a station appears on shortest paths between other stations
-    // Returns a HashMap mapping each station to its centrality score
-    pub fn betweenness_centrality(&self) -> HashMap<Station, f32> {
-        let all: Vec<Station> = self.all_stations().into_iter().collect(); // Collect all unique stations
-        // Initialize centrality map with zero for each station
-        let mut centrality: HashMap<Station, f32> =
-            all.iter().map(|v| (v.clone(), 0.0)).collect();
-        // Iterate over each station as the source
-        for s in &all {
-            let mut stack: Vec<Station> = Vec::new(); // Stack for storing visitation order
-            let mut preds: HashMap<Station, Vec<Station>> = HashMap::new(); // Predecessors in shortest paths
-            let mut sigma: HashMap<Station, f32> = all.iter().map(|v| (v.clone(), 0.0)).collect(); // Num of shortest paths to each node
-            let mut dist: HashMap<Station, i32> = all.iter().map(|v| (v.clone(), -1)).collect(); // Distance from source
-            let mut queue: VecDeque<Station> = VecDeque::new(); // Queue for BFS
-            sigma.insert(s.clone(), 1.0); // There's one path to the source
-            dist.insert(s.clone(), 0);    // Distance to self is 0
-            queue.push_back(s.clone());   // Start BFS from source
-            // BFS traversal from source to discover shortest paths
-            while let Some(v) = queue.pop_front() {
-                stack.push(v.clone());
-                let d_v = dist[&v];
-                // For each neighbor of v
-                for (w, _) in self.nodes.get(&v).into_iter().flatten() {
-                    if dist[w] < 0 {
-                        // First time visiting w
-                        dist.insert(w.clone(), d_v + 1);
-                        queue.push_back(w.clone());
-                    }
-                    if dist[w] == d_v + 1 {
-                        // If w is reachable via shortest path through v
-                        let sv = sigma[&v];
-                        let entry = sigma.get_mut(w).unwrap();
-                        *entry += sv; // Accumulate path counts
-                        preds.entry(w.clone()).or_default().push(v.clone());
-                    }
-                }
+    // Applies requests in order, moving each improved node into the bucket of its new distance
+    fn relax(&mut self, requests: Vec<Request<W>>, delta: f32) {
+        for (v, dist, u) in requests {
+            match self.distances[v] {
+                Some(current) if dist >= current => continue,
+                Some(_) => {}
+                None => self.reached.push(v),
             }
-            // Dependency accumulation
-            let mut delta: HashMap<Station, f32> = all.iter().map(|v| (v.clone(), 0.0)).collect();
-            // Back-propagate dependencies from the stack
-            while let Some(w) = stack.pop() {
-                for v in preds.get(&w).into_iter().flatten() {
-                    let sig_w = sigma[&w];
-                    if sig_w > 0.0 {
-                        // Distribute dependency based on path counts
-                        let c = (sigma[v] / sig_w) * (1.0 + delta[&w]);
-                        delta.entry(v.clone()).and_modify(|x| *x += c);
-                    }
+            self.distances[v] = Some(dist);
+            self.previous[v] = Some(u);
+            self.buckets.entry(bucket_of(dist, delta)).or_default().push(v);
+        }
+    }
+
+    // One source of Brandes' algorithm: a BFS counting shortest paths by hops, then dependencies back-propagated
+    // in reverse visitation order and added to `centrality` (every node but the source)
+    pub(crate) fn accumulate_betweenness(&mut self, lists: &[Vec<(usize, W)>], s: usize, centrality: &mut [f32]) {
+        for &v in &self.order {
+            self.hops[v] = -1;
+            self.sigma[v] = 0.0;
+            self.delta[v] = 0.0;
+            self.preds[v].clear();
+        }
+        self.order.clear();
+        self.sigma[s] = 1.0; // There's one path to the source
+        self.hops[s] = 0;    // Distance to self is 0
+        self.queue.push_back(s); // Start BFS from source
+        // BFS traversal from source to discover shortest paths
+        while let Some(v) = self.queue.pop_front() {
+            self.order.push(v);
+            // For each neighbor of v
+            for &(w, _) in &lists[v] {
+                if self.hops[w] < 0 {
+                    // First time visiting w
+                    self.hops[w] = self.hops[v] + 1;
+                    self.queue.push_back(w);
                 }
-                if w != *s {
-                    let contrib = delta[&w];
-                    // Only add finite and non-negative contributions
-                    if contrib.is_finite() && contrib >= 0.0 {
-                        centrality.entry(w.clone()).and_modify(|x| *x += contrib);
-                    }
+                if self.hops[w] == self.hops[v] + 1 {
+                    // If w is reachable via shortest path through v
+                    self.sigma[w] += self.sigma[v]; // Accumulate path counts
+                    self.preds[w].push(v);
                 }
             }
         }
+        // Back-propagate dependencies, farthest nodes first
+        for &w in self.order.iter().rev() {
+            for &v in &self.preds[w] {
+                if self.sigma[w] > 0.0 {
+                    // Distribute dependency based on path counts
+                    self.delta[v] += (self.sigma[v] / self.sigma[w]) * (1.0 + self.delta[w]);
+                }
+            }
+            // Only add finite and non-negative contributions
+            if w != s && self.delta[w].is_finite() && self.delta[w] >= 0.0 {
+                centrality[w] += self.delta[w];
+            }
+        }
+    }
+}
 
-        centrality
+// Delta-stepping bucket of a tentative distance
+fn bucket_of<W: Weight>(distance: W, delta: f32) -> usize {
+    (distance.minutes() / delta) as usize
+}
+
+// Computes the shortest path (by total weight, delay for `TransitGraph`) from start to end node using Dijkstra’s algorithm.
+// Input: any `Graph` and the `start` and `end` nodes.
+// Output: Option containing a tuple of (total weight, list of nodes along the shortest path).
+pub fn shortest_path<G: Graph>(graph: &G, start: &G::Node, end: &G::Node) -> Option<(G::Weight, Vec<G::Node>)> {
+    if start == end {
+        return Some((G::Weight::ZERO, vec![start.clone()]));
     }
+    let (nodes, lists) = index_graph(graph);
+    let (s, e) = (position(&nodes, start)?, position(&nodes, end)?);
+    path_between(&lists, s, e, |i| nodes[i].clone())
+}
 
+// Dijkstra from index `s` stopping at `e`, naming the path's nodes with `node`
+fn path_between<W: Weight, N>(lists: &[Vec<(usize, W)>], s: usize, e: usize, node: impl Fn(usize) -> N) -> Option<(W, Vec<N>)> {
+    let mut search = SearchContext::new(lists.len());
+    search.dijkstra(lists, s, Some(e));
+    let dist = search.distances()[e]?;
+    // Walk back along `previous` from end to start, cloning only the nodes on the path
+    Some((dist, search.path_to(e)?.into_iter().map(node).collect()))
+}
 
-    // Ranks and prints top N stations by betweenness centrality
-    pub fn rank_stations_by_betweenness(&self, top_n: usize) {
-        let mut scores: Vec<(Station, f32)> = self.betweenness_centrality().into_iter().collect();
-        scores.retain(|(_, sc)| sc.is_finite());
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        println!("Top {} stations (unweighted betweenness):", top_n);
-        for (i, (st, sc)) in scores.into_iter().take(top_n).enumerate() {
-            println!("{:>2}. {:<30} {:.4}", i + 1, st, sc);
-        }
+// Least total weight from one node to every node it reaches, itself included (Dijkstra without a target)
+pub fn shortest_distances<G: Graph>(graph: &G, start: &G::Node) -> HashMap<G::Node, G::Weight> {
+    let (nodes, lists) = index_graph(graph);
+    let Some(s) = position(&nodes, start) else {
+        return HashMap::from([(start.clone(), G::Weight::ZERO)]);
+    };
+    let mut search = SearchContext::new(nodes.len());
+    search.dijkstra(&lists, s, None);
+    search.distances().iter().enumerate().filter_map(|(i, d)| Some((nodes[i].clone(), (*d)?))).collect()
+}
+
+// Calculates closeness centrality for a given node
+// Returns None if the node is isolated or reaches others only with zero total delay
+// Closeness is defined as the number of reachable nodes divided by the sum of shortest-path delays to them
+pub fn closeness_centrality<G: Graph>(graph: &G, node: &G::Node) -> Option<f32> {
+    let (nodes, lists) = index_graph(graph);
+    closeness_of(&lists, position(&nodes, node)?, None, &mut SearchContext::new(nodes.len()))
+}
+
+// Least total weight from one node to every node it reaches, like `shortest_distances`, by delta-stepping with
+// buckets `delta` minutes wide (None picks the mean edge weight)
+// Output: None if `delta` is not positive or the graph has a negative weight, which delta-stepping cannot handle
+pub fn delta_stepping_distances<G: Graph>(graph: &G, start: &G::Node, delta: Option<f32>) -> Option<HashMap<G::Node, G::Weight>> {
+    let (nodes, lists) = index_graph(graph);
+    let mean = default_delta(&lists)?;
+    let delta = Some(delta.unwrap_or(mean)).filter(|d| *d > 0.0 && d.is_finite())?;
+    let Some(s) = position(&nodes, start) else {
+        return Some(HashMap::from([(start.clone(), G::Weight::ZERO)]));
+    };
+    let mut search = SearchContext::new(nodes.len());
+    search.delta_stepping(&lists, s, delta);
+    Some(search.distances().iter().enumerate().filter_map(|(i, d)| Some((nodes[i].clone(), (*d)?))).collect())
+}
+
+// Closeness of one index over index adjacency, so a ranking indexes the graph once for every station
+// Logic: one single-source search in the caller's buffers, delta-stepping if a bucket width is given and Dijkstra
+// otherwise; only nodes with outgoing edges count as targets, so terminal-only stations do not pull scores down
+fn closeness_of<W: Weight>(lists: &[Vec<(usize, W)>], node: usize, delta: Option<f32>, search: &mut SearchContext<W>) -> Option<f32> {
+    match delta {
+        Some(delta) => search.delta_stepping(lists, node, delta),
+        None => search.dijkstra(lists, node, None),
     }
+    closeness_from(lists, node, search.distances())
+}
 
-    // Computes average delay per route in the network
-    // Output: Vec of ((from, to), avg_delay, trip_count)
-    pub fn get_route_average_delays(&self) -> Vec<((Station, Station), f32, usize)> {
-        let mut totalroutes: HashMap<(Station, Station), (f32, usize)> = HashMap::new();
-        for (from, neighbors) in &self.nodes {
-            for (to, delay) in neighbors {
-                let entry = totalroutes.entry((from.clone(), to.clone())).or_insert((0.0, 0));
-                entry.0 += *delay; // Accumulate delay
-                entry.1 += 1;      // Count trips
-            }
+// Closeness of `node` from its finished single-source distances; see `closeness_of`
+pub(crate) fn closeness_from<W: Weight>(lists: &[Vec<(usize, W)>], node: usize, distances: &[Option<W>]) -> Option<f32> {
+    let mut total_delay = 0.0;
+    let mut reachable = 0;
+    // Sum in index (node) order so the float total is the same on every run
+    for (other, distance) in distances.iter().enumerate() {
+        let Some(distance) = distance else { continue };
+        if other == node || lists[other].is_empty() {
+            continue; // Skip itself and nodes that are only ever arrived at
         }
-        totalroutes.into_iter().map(|((from, to), (total_delay, count))| {
-                ((from, to), total_delay / count as f32, count) // Compute average
-            })
-            .collect()
+        total_delay += distance.minutes();
+        reachable += 1;
     }
 
-    // Prints top N routes with highest average delay
-    pub fn rank_routes_by_average_delay(&self, n: usize) {
-        let mut averages = self.get_route_average_delays();
-        let mut averages = self.get_route_average_delays().into_iter().filter(|(_, _, count)| *count >= 5).collect::<Vec<_>>(); // Filter routes with at least 5 trips
-        averages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        println!("Top {} routes by average delay:", n);
-        for (i, ((from, to), avg, count)) in averages.into_iter().take(n).enumerate() {
-            println!(
-                "{:>2}. {} → {} : {:.2} minutes ({} trips)", i + 1, from, to, avg, count
-            );
+    // If no reachable nodes or no delay accumulated, closeness is undefined
+    if total_delay == 0.0 || reachable == 0 {
+        None
+    } else {
+        Some(reachable as f32 / total_delay) // Higher value means more central (lower delay to more nodes)
+    }
+}
+
+// Computes unweighted betweenness centrality for all nodes (Brandes' algorithm)
+// Betweenness measures how often a node appears on shortest paths between other nodes
+// Returns a HashMap mapping each node to its centrality score
+pub fn betweenness_centrality<G: Graph>(graph: &G) -> HashMap<G::Node, f32> {
+    betweenness_centrality_observed(graph, &Silent)
+}
+
+// Like `betweenness_centrality`, reporting each finished source node to the observer as the "betweenness" metric
+// Logic: runs on index adjacency with one SearchContext reused across sources; nodes are cloned once, into the result
+pub fn betweenness_centrality_observed<G: Graph>(graph: &G, observer: &dyn Observer) -> HashMap<G::Node, f32> {
+    betweenness_centrality_checkpointed(graph, observer, None)
+}
+
+// Like `betweenness_centrality_observed`, saving the running sums to `checkpoints` and continuing from them
+// Logic: sources run in index order and each adds to the sums, so a resumed run adds the remaining sources in the same
+// order to the saved sums (f32 values survive the JSON round trip exactly) and ends with the same scores
+pub fn betweenness_centrality_checkpointed<G: Graph>(
+    graph: &G,
+    observer: &dyn Observer,
+    mut checkpoints: Option<&mut Checkpointer>,
+) -> HashMap<G::Node, f32> {
+    let (nodes, lists) = index_graph(graph); // Sorted, so sources run in a fixed order and scores accumulate identically on every run
+    let n = nodes.len();
+    let (sums, start) = resume(&checkpoints, observer, "betweenness", n, Some(0.0));
+    let mut centrality: Vec<f32> = sums.into_iter().map(|sum| sum.unwrap_or(0.0)).collect();
+    let mut search = SearchContext::new(n);
+    // Iterate over each node as the source
+    for s in start..n {
+        search.accumulate_betweenness(&lists, s, &mut centrality);
+        observer.on_metric_progress("betweenness", s + 1, n);
+        if checkpoints.as_ref().is_some_and(|c| c.due()) {
+            let sums: Vec<Option<f32>> = centrality.iter().copied().map(Some).collect();
+            save_if_due(&mut checkpoints, observer, "betweenness", s + 1, &sums);
         }
     }
+    finish(checkpoints);
+
+    nodes.into_iter().cloned().zip(centrality).collect()
+}
+
+// Sources scored between checkpoint saves in a checkpointed closeness ranking: enough to keep every thread busy
+const CHECKPOINT_SOURCES: usize = 256;
 
-    // Prints top N routes with the lowest average delay
-    pub fn rank_routes_by_lowest_delay(&self, n: usize) {
-        let mut averages = self.get_route_average_delays().into_iter().filter(|(_, _, count)| *count >= 5).collect::<Vec<_>>(); // Filter routes with at least 5 trips
-        averages.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-        println!("Top {} routes by **lowest** average delay:", n);
-        for (i, ((from, to), avg, count)) in averages.into_iter().take(n).enumerate() {
-            println!(
-                "{:>2}. {} → {} : {:.2} minutes ({} trips)", i + 1, from, to, avg, count
-            );
+// Per-station state and first source to process: a matching checkpoint's, reported to the observer, or `initial`
+// for every station and source 0
+fn resume(
+    checkpoints: &Option<&mut Checkpointer>,
+    observer: &dyn Observer,
+    metric: &str,
+    n: usize,
+    initial: Option<f32>,
+) -> (Vec<Option<f32>>, usize) {
+    match checkpoints.as_ref().and_then(|c| c.resume_from(metric, n)) {
+        Some(checkpoint) => {
+            observer.on_metric_resumed(metric, checkpoint.sources_done, n);
+            (checkpoint.values, checkpoint.sources_done)
         }
+        None => (vec![initial; n], 0),
+    }
+}
+
+// Saves progress if a checkpoint is due, reporting the outcome to the observer
+fn save_if_due(checkpoints: &mut Option<&mut Checkpointer>, observer: &dyn Observer, metric: &str, done: usize, values: &[Option<f32>]) {
+    let Some(checkpointer) = checkpoints.as_deref_mut().filter(|c| c.due()) else { return };
+    let checkpoint = Checkpoint { metric: metric.to_string(), sources_done: done, values: values.to_vec() };
+    observer.on_checkpoint_saved(metric, done, values.len(), &checkpointer.save(&checkpoint));
+}
+
+// Removes the checkpoint of a finished computation; one left behind is only found again by an identical run, which
+// would then skip straight to the end, so failing to remove it is not worth reporting
+fn finish(checkpoints: Option<&mut Checkpointer>) {
+    if let Some(checkpointer) = checkpoints {
+        let _ = checkpointer.finish();
     }
 }
@@ -0,0 +1,205 @@
+// Station and line delay state published to an MQTT broker: one retained JSON message per topic after each ingest, so
+// home-automation and signage subscribers get a station's or line's current value as soon as they subscribe
+// Topics are PREFIX/station/SLUG and PREFIX/line/SLUG, where SLUG is the lowercased name with every run of characters
+// other than letters and digits turned into one '-', so no MQTT wildcard or level separator ends up in a topic
+// The expected delay is the EWMA of the daily average delays: it follows the latest ingest without one bad day
+// swinging it as far as that day's own average (publishing needs the `mqtt` feature)
+
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::forecast::{ewma, DailyDelay};
+use crate::load::TrainRecord;
+
+// Topic prefix when the broker URL names none
+pub const DEFAULT_TOPIC_PREFIX: &str = "njtransit";
+
+// Port of a broker URL without one
+pub const DEFAULT_PORT: u16 = 1883;
+
+// Whether a state is about a station (its departures) or a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateKind {
+    Station,
+    Line,
+}
+
+// The delay state of one station or line, as published
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelayState {
+    pub kind: StateKind,
+    pub name: String,
+    pub topic: String,               // Below the prefix: station/SLUG or line/SLUG
+    pub expected_delay: f32,         // EWMA of the daily average delays in minutes; the plain mean without dated trips
+    pub latest_date: Option<String>, // Latest service date with a delay
+    pub latest_delay: Option<f32>,   // Average delay on that date
+    pub latest_trips: usize,
+    pub trips: usize,                // Trips with a delay, over every date
+    pub on_time_rate: f32,           // Share of those at or below the on-time threshold
+}
+
+// A station's or line's delays with their service dates, under the first name seen
+type NamedDelays = (String, Vec<(Option<NaiveDate>, f32)>);
+
+// The topic-safe form of a name; empty if it has no letters or digits
+pub fn topic_slug(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// The state of every station (by its departures) and every line with a delay, stations first, each by topic
+// Logic: names with the same slug share a topic and are merged under the first name seen
+pub fn delay_states(records: &[TrainRecord], on_time_threshold: f32, alpha: f32) -> Vec<DelayState> {
+    let mut groups: BTreeMap<(StateKind, String), NamedDelays> = BTreeMap::new();
+    for record in records {
+        let Some(delay) = record.delay() else { continue };
+        let date = NaiveDate::parse_from_str(record.date.trim(), "%Y-%m-%d").ok();
+        for (kind, name) in [(StateKind::Station, record.from.trim()), (StateKind::Line, record.line.trim())] {
+            let slug = topic_slug(name);
+            if !slug.is_empty() {
+                groups.entry((kind, slug)).or_insert_with(|| (name.to_string(), Vec::new())).1.push((date, delay));
+            }
+        }
+    }
+    groups.into_iter()
+        .map(|((kind, slug), (name, delays))| {
+            let mut days: BTreeMap<NaiveDate, (f32, usize)> = BTreeMap::new();
+            for (date, delay) in delays.iter().filter_map(|(date, delay)| Some((date.as_ref()?, delay))) {
+                let day = days.entry(*date).or_default();
+                day.0 += delay;
+                day.1 += 1;
+            }
+            let series: Vec<DailyDelay> = days.iter().map(|(&date, &(total, count))| DailyDelay { date, average: total / count as f32 }).collect();
+            let trips = delays.len();
+            let mean = delays.iter().map(|(_, d)| d).sum::<f32>() / trips as f32;
+            let latest = days.last_key_value();
+            DelayState {
+                kind,
+                topic: format!("{}/{}", match kind { StateKind::Station => "station", StateKind::Line => "line" }, slug),
+                name,
+                expected_delay: ewma(&series, alpha).unwrap_or(mean),
+                latest_date: latest.map(|(date, _)| date.format("%Y-%m-%d").to_string()),
+                latest_delay: latest.map(|(_, &(total, count))| total / count as f32),
+                latest_trips: latest.map_or(0, |(_, &(_, count))| count),
+                trips,
+                on_time_rate: delays.iter().filter(|(_, d)| *d <= on_time_threshold).count() as f32 / trips as f32,
+            }
+        })
+        .collect()
+}
+
+// Publishes delay states to one broker (publishing needs the `mqtt` feature)
+#[derive(Clone)]
+pub struct MqttPublisher {
+    host: String,
+    port: u16,
+    prefix: String,
+    credentials: Option<(String, String)>,
+}
+
+// Never prints the password
+impl std::fmt::Debug for MqttPublisher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttPublisher")
+            .field("broker", &self.broker())
+            .field("prefix", &self.prefix)
+            .field("user", &self.credentials.as_ref().map(|(user, _)| user))
+            .finish()
+    }
+}
+
+impl MqttPublisher {
+    // Parses mqtt://HOST[:PORT][/PREFIX]; the prefix may have several levels, e.g. mqtt://broker/home/trains
+    // Output: an invalid-parameter error for another scheme, a missing host, a bad port or a wildcard in the prefix
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidParameter { name: "MQTT broker", reason };
+        let rest = url.strip_prefix("mqtt://").ok_or_else(|| invalid("expected mqtt://HOST[:PORT][/PREFIX]".into()))?;
+        let (address, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid(format!("bad port {:?}", port)))?),
+            None => (address, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(invalid("the URL names no host".into()));
+        }
+        let prefix = match prefix.trim_matches('/') {
+            "" => DEFAULT_TOPIC_PREFIX,
+            prefix if prefix.contains(['+', '#']) => return Err(invalid(format!("wildcards in the topic prefix {:?}", prefix))),
+            prefix => prefix,
+        };
+        Ok(Self { host: host.to_string(), port, prefix: prefix.to_string(), credentials: None })
+    }
+
+    // Signs in to the broker with a user name and password
+    pub fn with_credentials(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+
+    // The broker's address, safe to print
+    pub fn broker(&self) -> String {
+        format!("mqtt://{}:{}", self.host, self.port)
+    }
+
+    // The full topic of a state
+    pub fn topic(&self, state: &DelayState) -> String {
+        format!("{}/{}", self.prefix, state.topic)
+    }
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttPublisher {
+    // Publishes every state as a retained JSON message at least once, waiting for the broker to acknowledge each
+    // Output: the number of messages published, or a notify error naming the broker if it cannot be reached, refuses
+    // the connection, or does not acknowledge every message within a minute
+    pub async fn publish(&self, states: &[DelayState]) -> Result<usize> {
+        use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+        let failed = |reason: String| Error::Notify { url: self.broker(), reason };
+        if states.is_empty() {
+            return Ok(0);
+        }
+        let mut options = MqttOptions::new(format!("nj-delays-{}", std::process::id()), &self.host, self.port);
+        options.set_keep_alive(std::time::Duration::from_secs(30));
+        if let Some((user, password)) = &self.credentials {
+            options.set_credentials(user, password);
+        }
+        let (client, mut events) = AsyncClient::new(options, states.len().max(10));
+        let exchange = async {
+            for state in states {
+                let payload = serde_json::to_vec(state).map_err(|e| failed(e.to_string()))?;
+                client.publish(self.topic(state), QoS::AtLeastOnce, true, payload).await.map_err(|e| failed(e.to_string()))?;
+            }
+            let mut acknowledged = 0;
+            while acknowledged < states.len() {
+                match events.poll().await.map_err(|e| failed(e.to_string()))? {
+                    Event::Incoming(Packet::PubAck(_)) => acknowledged += 1,
+                    Event::Incoming(Packet::ConnAck(ack)) if ack.code != rumqttc::ConnectReturnCode::Success => {
+                        return Err(failed(format!("the broker refused the connection: {:?}", ack.code)));
+                    }
+                    _ => {}
+                }
+            }
+            let _ = client.disconnect().await;
+            let _ = tokio::time::timeout(std::time::Duration::from_secs(1), events.poll()).await;
+            Ok(acknowledged)
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(60), exchange).await
+            .unwrap_or_else(|_| Err(failed("timed out waiting for the broker".into())))
+    }
+
+    // `publish` for callers without a runtime, on a single-threaded one of its own
+    pub fn publish_blocking(&self, states: &[DelayState]) -> Result<usize> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+            .map_err(|e| Error::Notify { url: self.broker(), reason: e.to_string() })?;
+        runtime.block_on(self.publish(states))
+    }
+}
@@ -0,0 +1,69 @@
+// Async front end for hosts running on a tokio runtime (the server mode, notebooks, services embedding the crate)
+// Loading awaits downloads; parsing, graph building and long computations run on the blocking pool via spawn_blocking,
+// so they never stall the runtime's worker threads
+
+use std::sync::Arc;
+use crate::error::{Error, Result};
+use crate::graph::TransitGraph;
+use crate::load::{self, TrainRecord};
+use crate::metrics::{RankOptions, StationScore};
+
+// Runs a blocking closure on tokio's blocking pool; a panic in it is resumed in the caller, as if called directly
+pub async fn spawn_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => Ok(value),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(_) => Err(Error::Cancelled),
+    }
+}
+
+// Maps a public S3 object to its HTTPS URL; other sources are returned unchanged
+// Only unsigned (publicly readable) objects can be fetched this way; there is no AWS credential support
+pub fn resolve_url(source: &str) -> String {
+    match source.strip_prefix("s3://").and_then(|rest| rest.split_once('/')) {
+        Some((bucket, key)) => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+        None => source.to_string(),
+    }
+}
+
+// Loads train records from a local path, an http(s):// URL or an s3://bucket/key of a public object
+// Input: the source as given on a command line or in a request
+// Output: the records, or a fetch error (HTTP status or network failure) or load error naming the source
+pub async fn load_data(source: &str) -> Result<Vec<TrainRecord>> {
+    let source = source.to_string();
+    if !["http://", "https://", "s3://"].iter().any(|scheme| source.starts_with(scheme)) {
+        return spawn_blocking(move || load::load_data(&source)).await?;
+    }
+    let url = resolve_url(&source);
+    let failed = |e: reqwest::Error| Error::Fetch { url: url.clone(), reason: e.to_string() };
+    let body = reqwest::get(&url).await.and_then(reqwest::Response::error_for_status).map_err(failed)?.bytes().await.map_err(failed)?;
+    spawn_blocking(move || load::read_data(body.as_ref(), &source)).await?
+}
+
+// Builds the delay graph off the runtime; an error if no record has a delay, as with `TransitGraph::try_from_records`
+pub async fn build_graph(records: Vec<TrainRecord>) -> Result<TransitGraph> {
+    spawn_blocking(move || TransitGraph::try_from_records(&records)).await?
+}
+
+// Runs any computation on a shared graph off the runtime, e.g. `compute(graph, |g| g.small_world(10, seed))`
+pub async fn compute<T, F>(graph: Arc<TransitGraph>, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&TransitGraph) -> T + Send + 'static,
+{
+    spawn_blocking(move || f(&graph)).await
+}
+
+// Closeness ranking off the runtime; one Dijkstra per station pair, so it takes seconds on the full network
+pub async fn rank_stations_by_closeness(graph: Arc<TransitGraph>, opts: RankOptions) -> Result<Vec<StationScore>> {
+    compute(graph, move |g| g.rank_stations_by_closeness(&opts)).await
+}
+
+// Betweenness ranking off the runtime
+pub async fn rank_stations_by_betweenness(graph: Arc<TransitGraph>, opts: RankOptions) -> Result<Vec<StationScore>> {
+    compute(graph, move |g| g.rank_stations_by_betweenness(&opts)).await
+}
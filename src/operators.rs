@@ -0,0 +1,139 @@
+// Cross-operator comparison: each record's `type` names the operator that ran it (NJ Transit, Amtrak, ...), so one
+// mixed dataset, or two datasets tagged with an operator each, can be compared on the stations and corridors both
+// operators serve
+// Stations are paired by name (trimmed, case-insensitive) rather than stop ID, since another operator's dataset may
+// number stops differently; a corridor is one (from, to) segment both operators run
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+
+// Operator of records whose `type` is blank
+pub const UNKNOWN_OPERATOR: &str = "Unknown";
+
+// The operator that ran a record
+pub fn operator_of(record: &TrainRecord) -> &str {
+    match record.r#type.trim() {
+        "" => UNKNOWN_OPERATOR,
+        operator => operator,
+    }
+}
+
+// Tags every record with `operator`, replacing its `type`
+pub fn tag_operator(records: &mut [TrainRecord], operator: &str) {
+    for record in records {
+        record.r#type = operator.to_string();
+    }
+}
+
+// Splits records by operator
+// Output: (operator, records) with the most records first; ties by operator name
+pub fn split_operators(records: Vec<TrainRecord>) -> Vec<(String, Vec<TrainRecord>)> {
+    let mut by_operator: BTreeMap<String, Vec<TrainRecord>> = BTreeMap::new();
+    for record in records {
+        by_operator.entry(operator_of(&record).to_string()).or_default().push(record);
+    }
+    let mut operators: Vec<(String, Vec<TrainRecord>)> = by_operator.into_iter().collect();
+    operators.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    operators
+}
+
+// Departure delays at a station both operators serve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedStation {
+    pub station: String,
+    pub trips_a: usize,
+    pub delay_a: f32, // Mean delay of operator A's departures, in minutes
+    pub on_time_a: f32,
+    pub trips_b: usize,
+    pub delay_b: f32,
+    pub on_time_b: f32,
+    pub delay_difference: f32, // delay_b - delay_a; positive means B runs later here
+}
+
+// Delays on a segment both operators run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedCorridor {
+    pub from: String,
+    pub to: String,
+    pub trips_a: usize,
+    pub delay_a: f32,
+    pub on_time_a: f32,
+    pub trips_b: usize,
+    pub delay_b: f32,
+    pub on_time_b: f32,
+    pub delay_difference: f32, // delay_b - delay_a
+}
+
+// Trips, mean delay and on-time rate of a set of delays
+type DelayStats = (usize, f32, f32);
+
+// Stations where both operators have at least `min_trips` departures with a delay
+// Output: top N by absolute delay difference, then by name; named as operator A's records spell them
+pub fn shared_stations(a: &[TrainRecord], b: &[TrainRecord], opts: &RankOptions) -> Vec<SharedStation> {
+    let key = |r: &TrainRecord| (name_key(&r.from), r.from.trim().to_string());
+    let mut shared: Vec<SharedStation> = pair(a, b, key, opts)
+        .into_iter()
+        .map(|(station, (trips_a, delay_a, on_time_a), (trips_b, delay_b, on_time_b))| SharedStation {
+            station, trips_a, delay_a, on_time_a, trips_b, delay_b, on_time_b, delay_difference: delay_b - delay_a,
+        })
+        .collect();
+    shared.sort_by(|x, y| y.delay_difference.abs().total_cmp(&x.delay_difference.abs()).then_with(|| x.station.cmp(&y.station)));
+    shared.truncate(opts.top_n);
+    shared
+}
+
+// Segments both operators run with at least `min_trips` trips with a delay each
+// Output: top N by absolute delay difference, then by route
+pub fn shared_corridors(a: &[TrainRecord], b: &[TrainRecord], opts: &RankOptions) -> Vec<SharedCorridor> {
+    let key = |r: &TrainRecord| ((name_key(&r.from), name_key(&r.to)), (r.from.trim().to_string(), r.to.trim().to_string()));
+    let mut shared: Vec<SharedCorridor> = pair(a, b, key, opts)
+        .into_iter()
+        .map(|((from, to), (trips_a, delay_a, on_time_a), (trips_b, delay_b, on_time_b))| SharedCorridor {
+            from, to, trips_a, delay_a, on_time_a, trips_b, delay_b, on_time_b, delay_difference: delay_b - delay_a,
+        })
+        .collect();
+    shared.sort_by(|x, y| {
+        y.delay_difference.abs().total_cmp(&x.delay_difference.abs()).then_with(|| (&x.from, &x.to).cmp(&(&y.from, &y.to)))
+    });
+    shared.truncate(opts.top_n);
+    shared
+}
+
+// Groups both sides' delays by `key` (a grouping key and the display name) and keeps the groups with enough trips on
+// both sides, named as on side A
+fn pair<K, N>(a: &[TrainRecord], b: &[TrainRecord], key: impl Fn(&TrainRecord) -> (K, N), opts: &RankOptions) -> Vec<(N, DelayStats, DelayStats)>
+where
+    K: std::hash::Hash + Eq,
+{
+    let group = |records: &[TrainRecord]| {
+        let mut groups: HashMap<K, (N, Vec<f32>)> = HashMap::new();
+        for record in records {
+            if let Some(delay) = record.delay() {
+                let (k, name) = key(record);
+                groups.entry(k).or_insert_with(|| (name, Vec::new())).1.push(delay);
+            }
+        }
+        groups
+    };
+    let min_trips = opts.min_trips.max(1);
+    let mut b = group(b);
+    group(a).into_iter()
+        .filter_map(|(k, (name, delays_a))| {
+            let (_, delays_b) = b.remove(&k)?;
+            (delays_a.len() >= min_trips && delays_b.len() >= min_trips)
+                .then(|| (name, delay_stats(&delays_a, opts.on_time_threshold), delay_stats(&delays_b, opts.on_time_threshold)))
+        })
+        .collect()
+}
+
+fn delay_stats(delays: &[f32], on_time_threshold: f32) -> DelayStats {
+    let trips = delays.len();
+    let on_time = delays.iter().filter(|&&d| d <= on_time_threshold).count();
+    (trips, delays.iter().sum::<f32>() / trips as f32, on_time as f32 / trips as f32)
+}
+
+fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
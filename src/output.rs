@@ -0,0 +1,315 @@
+// Renders every ranking and metric through one serializer as a bordered table, JSON, CSV, or Markdown
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+use clap::ValueEnum;
+use comfy_table::{presets, Cell, CellAlignment, Color, Table};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// Output format selected by the global --output flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable bordered tables
+    #[default]
+    Table,
+    /// One JSON document per section: {"title": ..., "rows": [...]}
+    Json,
+    /// Header plus rows per section; sections are separated by a blank line
+    Csv,
+    /// GitHub-flavored Markdown: a heading and a pipe table per section
+    Markdown,
+    /// Arrow IPC stream per section with typed columns, for Polars, pandas and pyarrow (requires the `arrow` feature)
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+// How much the CLI prints besides results, selected by -q / -v / -vv
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,   // Results only: no section titles or notes
+    Normal,  // Results with titles and human-readable notes
+    Verbose, // Plus timings and filter counts on stderr
+    Debug,   // Plus per-section row counts and every skipped row on stderr
+}
+
+// Process-wide verbosity, set once from the command line
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub(crate) fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+// When terminal tables color delay cells, selected by --color
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color only when stdout is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// How terminal tables are drawn, set once from the command line
+#[derive(Debug, Clone, Copy)]
+pub struct TableStyle {
+    pub max_width: usize,     // Longest cell in characters before it is cut with "…"; 0 for no limit
+    pub color: ColorChoice,
+    pub delay_threshold: f32, // Delays at or below this are green, up to twice it yellow, beyond that red
+}
+
+impl TableStyle {
+    pub const DEFAULT: TableStyle = TableStyle { max_width: 40, color: ColorChoice::Auto, delay_threshold: 6.0 };
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static TABLE_STYLE: RwLock<TableStyle> = RwLock::new(TableStyle::DEFAULT);
+
+pub(crate) fn set_table_style(style: TableStyle) {
+    *TABLE_STYLE.write().unwrap_or_else(|e| e.into_inner()) = style;
+}
+
+pub(crate) fn table_style() -> TableStyle {
+    *TABLE_STYLE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+// Prints a human-readable note to stdout unless running quietly
+pub(crate) fn note(message: &str) {
+    if verbosity() >= Verbosity::Normal {
+        println!("{}", message);
+    }
+}
+
+// Prints a diagnostic to stderr if the verbosity is at least `level`, keeping stdout machine-readable
+pub(crate) fn diagnostic(level: Verbosity, message: &str) {
+    if verbosity() >= level {
+        eprintln!("[{}] {}", if level >= Verbosity::Debug { "debug" } else { "info" }, message);
+    }
+}
+
+// Writes one titled section of rows to stdout in the given format
+// Quiet mode drops the table title so only the rows (and their header) remain
+pub fn emit<T: Serialize>(format: OutputFormat, title: &str, rows: &[T]) {
+    diagnostic(Verbosity::Debug, &format!("{} rows in section {:?}", rows.len(), title));
+    let title = if verbosity() == Verbosity::Quiet { "" } else { title };
+    let stdout = io::stdout();
+    let color = match table_style().color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => stdout.is_terminal(),
+    };
+    let mut out = stdout.lock();
+    // A closed pipe (e.g. `| head`) is not an error worth reporting
+    let _ = match format {
+        OutputFormat::Table => write_table(&mut out, title, rows, color),
+        _ => write_section(&mut out, format, title, rows),
+    };
+}
+
+// Writes one titled section of rows in the given format
+// Input: writer, format, section title, and rows of any serializable struct
+// Logic: JSON serializes the rows directly; table and CSV flatten each row into dotted columns first
+pub fn write_section<W: Write, T: Serialize>(out: &mut W, format: OutputFormat, title: &str, rows: &[T]) -> io::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct Section<'a, T> {
+                title: &'a str,
+                rows: &'a [T],
+            }
+            serde_json::to_writer(&mut *out, &Section { title, rows })?;
+            writeln!(out)
+        }
+        OutputFormat::Csv => {
+            let (columns, cells) = flatten_rows(rows, true)?;
+            let mut wtr = csv::Writer::from_writer(&mut *out);
+            wtr.write_record(&columns)?;
+            for row in cells {
+                wtr.write_record(&row)?;
+            }
+            wtr.flush()?;
+            drop(wtr);
+            writeln!(out)
+        }
+        OutputFormat::Markdown => {
+            let (columns, cells) = flatten_rows(rows, false)?;
+            if !title.is_empty() {
+                writeln!(out, "### {}\n", title.trim_end_matches(':'))?;
+            }
+            if cells.is_empty() {
+                return writeln!(out, "_(none)_\n");
+            }
+            let numeric: Vec<bool> = (0..columns.len())
+                .map(|c| cells.iter().all(|r| r[c].is_empty() || r[c].parse::<f64>().is_ok()))
+                .collect();
+            // Pipes inside cells would end the cell early
+            let line = |cells: &[String]| -> String {
+                cells.iter().map(|cell| cell.replace('|', "\\|")).collect::<Vec<_>>().join(" | ")
+            };
+            let rule: Vec<&str> = numeric.iter().map(|&n| if n { "---:" } else { "---" }).collect();
+            writeln!(out, "| # | {} |", line(&columns))?;
+            writeln!(out, "|---:| {} |", rule.join(" | "))?;
+            for (i, row) in cells.iter().enumerate() {
+                writeln!(out, "| {} | {} |", i + 1, line(row))?;
+            }
+            writeln!(out)
+        }
+        OutputFormat::Table => write_table(out, title, rows, false),
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => crate::columnar::write_ipc_stream(out, title, rows),
+    }
+}
+
+// Writes one titled section as a bordered table, numbering rows and right-aligning numeric columns
+// Cells longer than the style's max width are cut; with `color`, delay columns are shaded by severity
+fn write_table<W: Write, T: Serialize>(out: &mut W, title: &str, rows: &[T], color: bool) -> io::Result<()> {
+    let style = table_style();
+    let (columns, cells) = flatten_rows(rows, false)?;
+    if !title.is_empty() {
+        writeln!(out, "{}", title)?;
+    }
+    if cells.is_empty() {
+        return writeln!(out, "  (none)");
+    }
+    let numeric: Vec<bool> = (0..columns.len())
+        .map(|c| cells.iter().all(|r| r[c].is_empty() || r[c].parse::<f64>().is_ok()))
+        .collect();
+    let delay: Vec<bool> = columns.iter().zip(&numeric).map(|(name, &n)| n && name.contains("delay")).collect();
+    let mut table = Table::new();
+    table.load_preset(presets::UTF8_FULL_CONDENSED);
+    if color {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    table.set_header(std::iter::once("#").chain(columns.iter().map(String::as_str)));
+    for (i, row) in cells.iter().enumerate() {
+        let mut line = vec![Cell::new(i + 1).set_alignment(CellAlignment::Right)];
+        for (c, text) in row.iter().enumerate() {
+            let mut cell = Cell::new(truncate(text, style.max_width));
+            if numeric[c] {
+                cell = cell.set_alignment(CellAlignment::Right);
+            }
+            if color && delay[c] && let Ok(minutes) = text.parse::<f32>() {
+                cell = cell.fg(severity_color(minutes, style.delay_threshold));
+            }
+            line.push(cell);
+        }
+        table.add_row(line);
+    }
+    writeln!(out, "{}", table)
+}
+
+// Cuts text to at most `max` characters, marking the cut with "…"; 0 means no limit
+fn truncate(text: &str, max: usize) -> String {
+    if max == 0 || text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+// Green when on time, yellow up to twice the threshold, red beyond
+fn severity_color(minutes: f32, threshold: f32) -> Color {
+    if minutes <= threshold {
+        Color::Green
+    } else if minutes <= 2.0 * threshold {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+// Flattens rows into a shared column list and string cells
+// Nested objects become dotted columns ("welch.p_value"), arrays of scalars are joined with " → "
+// Precise cells keep full float precision (CSV); otherwise floats are rounded for display
+pub(crate) fn flatten_rows<T: Serialize>(rows: &[T], precise: bool) -> io::Result<(Vec<String>, Vec<Vec<String>>)> {
+    let FlatRows { columns, rows: flat_rows } = flatten_values(rows)?;
+    let cells = flat_rows.iter()
+        .map(|flat| columns.iter().map(|c| flat.get(c).map(|v| cell_text(v, precise)).unwrap_or_default()).collect())
+        .collect();
+    Ok((columns, cells))
+}
+
+// Rows flattened to scalar JSON values under a shared column list
+pub(crate) struct FlatRows {
+    pub columns: Vec<String>,          // Every column seen, in first-seen order
+    pub rows: Vec<Map<String, Value>>, // Column -> value; a column a row lacks is absent
+}
+
+// Flattens rows like `flatten_rows` but keeps the JSON types of the cells
+pub(crate) fn flatten_values<T: Serialize>(rows: &[T]) -> io::Result<FlatRows> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut flat_rows: Vec<Map<String, Value>> = Vec::new();
+    for row in rows {
+        let value = serde_json::to_value(row).map_err(io::Error::other)?;
+        let mut flat = Map::new();
+        flatten_value("", value, &mut flat);
+        for key in flat.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        flat_rows.push(flat);
+    }
+    Ok(FlatRows { columns, rows: flat_rows })
+}
+
+fn flatten_value(prefix: &str, value: Value, out: &mut Map<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, inner) in map {
+                let name = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten_value(&name, inner, out);
+            }
+        }
+        other => {
+            let name = if prefix.is_empty() { "value".to_string() } else { prefix.to_string() };
+            out.insert(name, other);
+        }
+    }
+}
+
+// Formats a scalar cell
+// Precise floats use the shortest representation of their original f32 where possible, since most metrics are f32
+pub(crate) fn cell_text(value: &Value, precise: bool) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            _ if n.is_i64() || n.is_u64() => n.to_string(),
+            Some(f) if !precise => format!("{:.4}", f), // The precision the rankings have always printed
+            Some(f) if f == (f as f32) as f64 => (f as f32).to_string(),
+            _ => n.to_string(),
+        },
+        Value::Array(items) => items.iter().map(|v| cell_text(v, precise)).collect::<Vec<_>>().join(" → "),
+        other => other.to_string(),
+    }
+}
+
+// Returns the rows on a 1-based page and the total number of pages, or None if the page is out of range
+pub(crate) fn paginate<T>(rows: &[T], page: usize, per_page: usize) -> Option<(&[T], usize)> {
+    let per_page = per_page.max(1);
+    let pages = rows.len().div_ceil(per_page).max(1);
+    if page == 0 || page > pages {
+        return None;
+    }
+    let start = (page - 1) * per_page;
+    Some((&rows[start.min(rows.len())..(start + per_page).min(rows.len())], pages))
+}
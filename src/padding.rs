@@ -0,0 +1,114 @@
+// Schedule padding recommendations: how many minutes each line's segments would need added to their scheduled running
+// time for a target share of trips to arrive on time, and the runtime that adds up to per line
+// A segment's padding is its observed delay at the target percentile (the delay the target share of trips stay at or
+// under) less the on-time threshold, rounded up to whole minutes as timetables are written: padding shifts every
+// arrival earlier by that much, so the target share then falls within the threshold. Each segment is padded for its own
+// arrivals; since padding upstream also makes later arrivals on the same run earlier, a line's total is an upper bound
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::graph::{station_id, Station, StationId, TransitGraph};
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+
+// Share of trips to bring on time unless set
+pub const DEFAULT_TARGET: f32 = 0.9;
+
+// The padding one segment of one line needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentPadding {
+    pub line: String,
+    pub from: Station,
+    pub to: Station,
+    pub trips: usize,
+    pub on_time_rate: f32,
+    pub target_delay: f32,  // Delay the target share of trips stay at or under
+    pub padding: f32,       // Whole minutes to add to the scheduled running time
+    pub on_time_after: f32, // On-time rate with the padding
+}
+
+// The padding of one line's segments added up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinePadding {
+    pub line: String,
+    pub segments: usize,
+    pub padded_segments: usize,
+    pub total_padding: f32,  // Minutes added over every segment, in both directions
+    pub on_time_rate: f32,   // Over the trips of the segments considered
+    pub on_time_after: f32,
+}
+
+// Padding recommendations for a target on-time rate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaddingReport {
+    pub target: f32,
+    pub threshold: f32,
+    pub lines: Vec<LinePadding>,       // Most total padding first
+    pub segments: Vec<SegmentPadding>, // Most padding first
+}
+
+// Recommends the padding of every segment of every line with at least `min_trips` delayed trips; a stop's records from
+// itself to itself are not a segment to pad
+// Output: an invalid-parameter error for a target outside (0, 1]
+pub fn recommend_padding(records: &[TrainRecord], graph: &TransitGraph, target: f32, opts: &RankOptions) -> Result<PaddingReport> {
+    if !(target > 0.0 && target <= 1.0) {
+        return Err(Error::InvalidParameter { name: "target", reason: format!("{} is not an on-time rate in (0, 1]", target) });
+    }
+    let threshold = opts.on_time_threshold;
+    let mut delays: BTreeMap<(&str, StationId, StationId), Vec<f32>> = BTreeMap::new();
+    for record in records {
+        let (from, to) = (station_id(&record.from_id), station_id(&record.to_id));
+        if let Some(delay) = record.delay().filter(|_| from != to) {
+            delays.entry((record.line.trim(), from, to)).or_default().push(delay);
+        }
+    }
+    let on_time = |delays: &[f32], padding: f32| delays.iter().filter(|&&d| d - padding <= threshold).count();
+    let mut segments: Vec<SegmentPadding> = delays.into_iter()
+        .filter(|(_, delays)| delays.len() >= opts.min_trips.max(1))
+        .filter_map(|((line, from, to), mut delays)| {
+            delays.sort_by(f32::total_cmp);
+            let trips = delays.len();
+            let target_delay = delays[((target * trips as f32).ceil() as usize).clamp(1, trips) - 1];
+            let padding = (target_delay - threshold).max(0.0).ceil();
+            Some(SegmentPadding {
+                line: line.to_string(),
+                from: graph.station(&from)?.clone(),
+                to: graph.station(&to)?.clone(),
+                trips,
+                on_time_rate: on_time(&delays, 0.0) as f32 / trips as f32,
+                target_delay,
+                padding,
+                on_time_after: on_time(&delays, padding) as f32 / trips as f32,
+            })
+        })
+        .collect();
+    let mut lines: BTreeMap<&str, LinePadding> = BTreeMap::new();
+    for segment in &segments {
+        let line = lines.entry(&segment.line).or_insert_with(|| LinePadding {
+            line: segment.line.clone(), segments: 0, padded_segments: 0, total_padding: 0.0, on_time_rate: 0.0, on_time_after: 0.0,
+        });
+        line.segments += 1;
+        line.padded_segments += usize::from(segment.padding > 0.0);
+        line.total_padding += segment.padding;
+        // Trip-weighted sums for now, divided below
+        line.on_time_rate += segment.on_time_rate * segment.trips as f32;
+        line.on_time_after += segment.on_time_after * segment.trips as f32;
+    }
+    let trips = |line: &str| segments.iter().filter(|s| s.line == line).map(|s| s.trips).sum::<usize>() as f32;
+    let mut lines: Vec<LinePadding> = lines.into_values()
+        .map(|mut line| {
+            let trips = trips(&line.line);
+            line.on_time_rate /= trips;
+            line.on_time_after /= trips;
+            line
+        })
+        .collect();
+    lines.sort_by(|a, b| b.total_padding.total_cmp(&a.total_padding).then_with(|| a.line.cmp(&b.line)));
+    segments.sort_by(|a, b| {
+        b.padding.total_cmp(&a.padding)
+            .then(b.target_delay.total_cmp(&a.target_delay))
+            .then_with(|| (&a.line, &a.from, &a.to).cmp(&(&b.line, &b.from, &b.to)))
+    });
+    Ok(PaddingReport { target, threshold, lines, segments })
+}
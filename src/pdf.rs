@@ -0,0 +1,212 @@
+// Minimal PDF writer for paginated reports: text in the built-in Helvetica fonts, filled rectangles, and lines
+// No fonts are embedded, so text is limited to the WinAnsi character set; other characters are transliterated
+
+use std::fmt::Write as _;
+
+pub const PAGE_WIDTH: f32 = 612.0;  // US Letter, in points
+pub const PAGE_HEIGHT: f32 = 792.0;
+pub const MARGIN: f32 = 54.0;
+
+// Average Helvetica glyph width as a fraction of the font size, for laying out columns
+const CHAR_WIDTH: f32 = 0.52;
+
+// A document being laid out top to bottom, starting a new page whenever the cursor runs out of room
+pub struct PdfDocument {
+    pages: Vec<String>, // Content stream of each page
+    y: f32,             // Baseline of the next line on the current page, from the bottom edge
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        PdfDocument { pages: vec![String::new()], y: PAGE_HEIGHT - MARGIN }
+    }
+
+    // Width of the printable area between the margins
+    pub fn width(&self) -> f32 {
+        PAGE_WIDTH - 2.0 * MARGIN
+    }
+
+    // Starts a new page unless `height` points still fit above the bottom margin
+    pub fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN + 20.0 {
+            self.pages.push(String::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    // Moves the cursor down without drawing
+    pub fn skip(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn page(&mut self) -> &mut String {
+        self.pages.last_mut().expect("a document always has a page")
+    }
+
+    // Draws one line of text with its baseline at (x, y)
+    pub fn text_at(&mut self, x: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        let encoded = encode(text);
+        let _ = writeln!(self.page(), "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET", font, size, x, y, encoded);
+    }
+
+    // Draws a filled rectangle from its lower-left corner, in an RGB color with components in [0, 1]
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, rgb: (f32, f32, f32)) {
+        let _ = writeln!(self.page(), "{:.3} {:.3} {:.3} rg {:.2} {:.2} {:.2} {:.2} re f", rgb.0, rgb.1, rgb.2, x, y, width, height);
+    }
+
+    // Draws a straight line in gray (0 black, 1 white)
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, gray: f32) {
+        let _ = writeln!(self.page(), "{:.3} G {:.2} w {:.2} {:.2} m {:.2} {:.2} l S", gray, width, from.0, from.1, to.0, to.1);
+    }
+
+    // Draws a line of text at the left margin and advances the cursor
+    pub fn paragraph(&mut self, size: f32, bold: bool, text: &str) {
+        self.reserve(size * 1.4);
+        self.y -= size * 1.4;
+        let y = self.y;
+        self.text_at(MARGIN, y, size, bold, text);
+    }
+
+    // Draws a table with a bold header row, repeating the header on every page it spans
+    // Logic: column widths follow the longest cell; the font shrinks (down to 5 pt) until the table fits the page width,
+    // and cells that still do not fit are cut with "..."
+    pub fn table(&mut self, columns: &[String], cells: &[Vec<String>]) {
+        let longest: Vec<usize> = (0..columns.len())
+            .map(|c| cells.iter().map(|r| r[c].chars().count()).chain([columns[c].chars().count()]).max().unwrap_or(0).clamp(3, 40))
+            .collect();
+        let total: usize = longest.iter().sum::<usize>() + 2 * columns.len();
+        let size = (self.width() / (total as f32 * CHAR_WIDTH)).clamp(5.0, 9.0);
+        let scale = (self.width() / (total as f32 * CHAR_WIDTH * size)).min(1.0);
+        let widths: Vec<f32> = longest.iter().map(|&n| (n + 2) as f32 * CHAR_WIDTH * size * scale).collect();
+        let numeric: Vec<bool> = (0..columns.len())
+            .map(|c| cells.iter().all(|r| r[c].is_empty() || r[c].parse::<f64>().is_ok()))
+            .collect();
+        let row_height = size * 1.5;
+        let header = |doc: &mut PdfDocument| {
+            doc.y -= row_height;
+            doc.row(columns, &widths, &numeric, size, true);
+            let y = doc.y - size * 0.4;
+            doc.line((MARGIN, y), (MARGIN + widths.iter().sum::<f32>(), y), 0.6, 0.3);
+        };
+        self.reserve(row_height * 2.0);
+        header(self);
+        for row in cells {
+            let page = self.pages.len();
+            self.reserve(row_height);
+            if self.pages.len() != page {
+                header(self);
+            }
+            self.y -= row_height;
+            self.row(row, &widths, &numeric, size, false);
+        }
+    }
+
+    fn row(&mut self, cells: &[String], widths: &[f32], numeric: &[bool], size: f32, bold: bool) {
+        let mut x = MARGIN;
+        let y = self.y;
+        for (c, cell) in cells.iter().enumerate() {
+            let fits = ((widths[c] / (CHAR_WIDTH * size)) as usize).saturating_sub(1).max(3);
+            let text = if cell.chars().count() > fits {
+                format!("{}...", cell.chars().take(fits - 3).collect::<String>())
+            } else {
+                cell.clone()
+            };
+            let offset = if numeric[c] && !bold { widths[c] - (text.chars().count() as f32 + 1.0) * CHAR_WIDTH * size } else { 0.0 };
+            self.text_at(x + offset, y, size, bold, &text);
+            x += widths[c];
+        }
+    }
+
+    // Draws a labelled horizontal bar chart, bars scaled against `max`
+    pub fn bar_chart(&mut self, title: &str, bars: &[(String, f32)], max: f32, unit: &str) {
+        let row = 13.0;
+        self.reserve(24.0 + row * bars.len() as f32);
+        self.paragraph(10.0, true, title);
+        self.skip(4.0);
+        let (label_width, area) = (150.0, self.width() - 200.0);
+        for (label, value) in bars {
+            self.y -= row;
+            let y = self.y;
+            let width = area * (value / max.max(f32::EPSILON)).clamp(0.0, 1.0);
+            let label: String = if label.chars().count() > 36 { format!("{}...", label.chars().take(33).collect::<String>()) } else { label.clone() };
+            self.text_at(MARGIN, y + 2.0, 7.5, false, &label);
+            self.rect(MARGIN + label_width, y, width, row - 3.0, (0.23, 0.43, 0.65));
+            self.text_at(MARGIN + label_width + width + 4.0, y + 2.0, 7.5, false, &format!("{:.1}{}", value, unit));
+        }
+    }
+
+    // Serializes the document, numbering each page in its footer
+    pub fn finish(mut self) -> Vec<u8> {
+        let count = self.pages.len();
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            let footer = format!("Page {} of {}", i + 1, count);
+            let _ = writeln!(page, "BT /F1 8.0 Tf {:.2} {:.2} Td ({}) Tj ET", PAGE_WIDTH - MARGIN - 50.0, MARGIN / 2.0, footer);
+        }
+        // Objects: 1 catalog, 2 page tree, 3-4 fonts, then a page and its content stream for each page
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        let kids: Vec<String> = (0..count).map(|i| format!("{} 0 R", 5 + 2 * i)).collect();
+        objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), count).into_bytes());
+        for font in ["Helvetica", "Helvetica-Bold"] {
+            objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font).into_bytes());
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, 6 + 2 * i,
+            ).into_bytes());
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.len()).into_bytes();
+            stream.extend_from_slice(page.as_bytes());
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+        let mut out: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset); // Entries are exactly 20 bytes
+        }
+        let _ = write!(table, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+impl Default for PdfDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Encodes text as a PDF string body in WinAnsi: escapes delimiters, writes non-ASCII as octal, and transliterates the rest
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                encoded.push('\\');
+                encoded.push(c);
+            }
+            ' '..='~' => encoded.push(c),
+            '→' => encoded.push_str("->"),
+            '≤' => encoded.push_str("<="),
+            '≥' => encoded.push_str(">="),
+            '…' => encoded.push_str("\\205"),
+            '–' => encoded.push_str("\\226"),
+            '—' => encoded.push_str("\\227"),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(encoded, "\\{:03o}", c as u32);
+            }
+            _ => encoded.push('?'),
+        }
+    }
+    encoded
+}
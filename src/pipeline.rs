@@ -0,0 +1,119 @@
+// The load → filter → build pipeline with observer hooks, so the CLI's diagnostics, a progress bar, a GUI or a server
+// can follow the same events while the library itself prints nothing
+
+use std::io;
+use std::time::{Duration, Instant};
+use crate::error::{Error, Result};
+use crate::events::{EventLimits, EventSource};
+use crate::graph::{GraphBuilder, TransitGraph};
+use crate::load::{stream_data, RecordFilter, TrainRecord};
+use crate::stream::{StreamAggregator, StreamSummary};
+
+// Receives pipeline and long-computation events; every method defaults to doing nothing, so implement only what you need
+// Sync because parallel computations may report progress from several threads
+pub trait Observer: Sync {
+    // Records were read from `source`, before any filtering
+    fn on_records_loaded(&self, _source: &str, _records: &[TrainRecord], _elapsed: Duration) {}
+    // A non-empty filter kept `kept` of `total` records
+    fn on_records_filtered(&self, _filter: &RecordFilter, _kept: usize, _total: usize) {}
+    // The graph was built from the (filtered) records
+    fn on_graph_built(&self, _graph: &TransitGraph, _records: &[TrainRecord], _elapsed: Duration) {}
+    // A streaming pass read `read` records from `source`, folded `kept` of them (the rest failed the filter) into its
+    // aggregates and built the graph; no records are kept to pass along
+    fn on_records_streamed(&self, _source: &str, _read: usize, _kept: usize, _graph: &TransitGraph, _elapsed: Duration) {}
+    // An event stream delivered `skipped` messages that were not train records; `first_error` says why the first failed
+    fn on_events_skipped(&self, _source: &str, _skipped: usize, _first_error: &str) {}
+    // A metric finished `done` of its `total` steps (e.g. source stations); called at least once with done == total
+    fn on_metric_progress(&self, _metric: &str, _done: usize, _total: usize) {}
+    // A metric continued from a checkpoint with `done` of its `total` steps already finished
+    fn on_metric_resumed(&self, _metric: &str, _done: usize, _total: usize) {}
+    // A top-k metric skipped or cut short `cut` of its `total` steps, because they could not change the top k
+    fn on_metric_pruned(&self, _metric: &str, _cut: usize, _total: usize) {}
+    // A metric tried to save its progress after `done` of `total` steps; a failed save does not stop the computation
+    fn on_checkpoint_saved(&self, _metric: &str, _done: usize, _total: usize, _result: &io::Result<()>) {}
+}
+
+// An observer that ignores every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Silent;
+
+impl Observer for Silent {}
+
+// Loads a dataset, applies the filter and builds its graph, reporting each stage to the observer
+// Input: path to the CSV, the record filter, the observer
+// Output: the filtered records with their graph, or a load or empty-graph error
+// Logic: parsing runs on a reader thread while this one adds each matching record to the graph as it arrives, so
+// building overlaps loading; the graph-built event times only what is left after the last record (naming stations)
+pub fn load_graph(path: &str, filter: &RecordFilter, observer: &dyn Observer) -> Result<(Vec<TrainRecord>, TransitGraph)> {
+    let started = Instant::now();
+    let mut loaded = Vec::new();
+    let mut builder = GraphBuilder::new();
+    stream_data(path, |record| {
+        if filter.matches(&record) {
+            builder.add(&record, record.delay());
+        }
+        loaded.push(record);
+    })?;
+    observer.on_records_loaded(path, &loaded, started.elapsed());
+    let total = loaded.len();
+    let records = filter.apply(loaded);
+    if !filter.is_empty() {
+        observer.on_records_filtered(filter, records.len(), total);
+    }
+    let build = Instant::now();
+    let graph = builder.build();
+    if graph.edges().next().is_none() {
+        return Err(Error::EmptyGraph { records: records.len() });
+    }
+    observer.on_graph_built(&graph, &records, build.elapsed());
+    Ok((records, graph))
+}
+
+// Streams a dataset through the filter into the aggregator without keeping the records, then builds its graph
+// Input: path to the CSV, the record filter, an aggregator configured with the on-time threshold and histogram bins,
+// the observer
+// Output: the running totals with the graph, or a load or empty-graph error
+pub fn stream_graph(path: &str, filter: &RecordFilter, mut aggregator: StreamAggregator, observer: &dyn Observer) -> Result<(StreamSummary, TransitGraph)> {
+    let started = Instant::now();
+    let read = stream_data(path, |record| {
+        if filter.matches(&record) {
+            aggregator.add(&record);
+        }
+    })?;
+    let kept = aggregator.records();
+    let (summary, graph) = aggregator.finish();
+    if graph.edges().next().is_none() {
+        return Err(Error::EmptyGraph { records: kept });
+    }
+    observer.on_records_streamed(path, read, kept, &graph, started.elapsed());
+    Ok((summary, graph))
+}
+
+// Consumes an event stream through the filter into the aggregator until a limit is reached, then builds its graph
+// Output: the summary and graph as for `stream_graph`, a fetch error if the broker is unreachable, or an empty-graph
+// error if no consumed record had a delay
+pub fn stream_events(
+    source: &EventSource,
+    limits: &EventLimits,
+    filter: &RecordFilter,
+    mut aggregator: StreamAggregator,
+    observer: &dyn Observer,
+) -> Result<(StreamSummary, TransitGraph)> {
+    let started = Instant::now();
+    let stats = source.consume(limits, |record| {
+        if filter.matches(&record) {
+            aggregator.add(&record);
+        }
+    })?;
+    let name = source.to_string();
+    if let Some(error) = &stats.first_error {
+        observer.on_events_skipped(&name, stats.skipped, error);
+    }
+    let kept = aggregator.records();
+    let (summary, graph) = aggregator.finish();
+    if graph.edges().next().is_none() {
+        return Err(Error::EmptyGraph { records: kept });
+    }
+    observer.on_records_streamed(&name, stats.records, kept, &graph, started.elapsed());
+    Ok((summary, graph))
+}
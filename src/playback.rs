@@ -0,0 +1,88 @@
+// Delay playback: the network's per-route delay state in one frame per day or hour, for watching delay waves move through the system
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use chrono::{NaiveDateTime, Timelike};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::graph::Station;
+use crate::load::{Coordinates, TrainRecord};
+
+// Length of one playback frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FrameStep {
+    /// One frame per service date
+    #[default]
+    Day,
+    /// One frame per scheduled hour
+    Hour,
+}
+
+// Mean delay on one route within one frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameRoute {
+    pub from: Station,
+    pub to: Station,
+    pub mean_delay: f32,
+    pub trips: usize,
+}
+
+// The delay state of every route that ran during one time window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub time: String, // "2019-01-07" for daily frames, "2019-01-07 08:00" for hourly ones
+    pub routes: Vec<FrameRoute>,
+}
+
+// Groups records with a delay into frames, in time order; windows without trips get no frame
+// Routes within a frame are in (from, to) order; self-loops are left out
+pub fn delay_frames(records: &[TrainRecord], step: FrameStep) -> Vec<Frame> {
+    let mut windows: BTreeMap<(String, Station, Station), (usize, f32)> = BTreeMap::new(); // (time, from, to) -> (trips, total delay)
+    for r in records {
+        let Some(delay) = r.delay() else { continue };
+        if r.from_station_id() == r.to_station_id() {
+            continue;
+        }
+        let time = match step {
+            FrameStep::Day => r.date.clone(),
+            FrameStep::Hour => match NaiveDateTime::parse_from_str(&r.scheduled_time, "%Y-%m-%d %H:%M:%S") {
+                Ok(t) => format!("{} {:02}:00", t.date(), t.hour()),
+                Err(_) => continue,
+            },
+        };
+        let entry = windows.entry((time, r.from_station(), r.to_station())).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += delay;
+    }
+    let mut frames: Vec<Frame> = Vec::new();
+    for ((time, from, to), (trips, total)) in windows {
+        let route = FrameRoute { from, to, mean_delay: total / trips as f32, trips };
+        match frames.last_mut() {
+            Some(frame) if frame.time == time => frame.routes.push(route),
+            _ => frames.push(Frame { time, routes: vec![route] }),
+        }
+    }
+    frames
+}
+
+// Writes frames as GeoJSON Lines: one FeatureCollection per line, with the frame's `time` as a top-level member
+// Each route is a LineString between its stations' [lon, lat] with from, to, mean_delay and trips properties;
+// routes touching a station without coordinates are left out
+pub fn write_geojson_frames<W: Write>(frames: &[Frame], coords: &Coordinates, mut out: W) -> io::Result<()> {
+    let position = |station: &Station| coords.get(&station.name).map(|(lat, lon)| json!([lon, lat]));
+    for frame in frames {
+        let features: Vec<Value> = frame.routes.iter()
+            .filter_map(|r| {
+                Some(json!({
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [position(&r.from)?, position(&r.to)?] },
+                    "properties": { "from": r.from, "to": r.to, "mean_delay": r.mean_delay, "trips": r.trips },
+                }))
+            })
+            .collect();
+        serde_json::to_writer(&mut out, &json!({ "type": "FeatureCollection", "time": frame.time, "features": features }))?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
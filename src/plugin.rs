@@ -0,0 +1,148 @@
+// User-defined metrics: implement `Metric`, register it, and it is ranked, reported and exported like the built-in ones
+// The CLI picks up a registry through `cli::execute_with`, so a downstream binary only has to register its metrics
+
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::export::{ranking_rows, write_ranking_csv, Ranked, RankingRow, Subject};
+use crate::graph::{Station, TransitGraph};
+use crate::metrics::{RankOptions, StationScore};
+
+// A route with its score in a custom ranking
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteScore {
+    pub from: Station,
+    pub to: Station,
+    pub score: f32,
+}
+
+impl Ranked for RouteScore {
+    fn subject(&self) -> Subject<'_> {
+        Subject::Route(&self.from.name, &self.to.name)
+    }
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+// Scores a metric computed: one per station or one per route, in any order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MetricResult {
+    Stations(Vec<StationScore>),
+    Routes(Vec<RouteScore>),
+}
+
+impl MetricResult {
+    pub fn len(&self) -> usize {
+        match self {
+            MetricResult::Stations(scores) => scores.len(),
+            MetricResult::Routes(scores) => scores.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Sorts best-ranked first and keeps the top N; NaN scores are dropped and ties go to the station or route name
+    pub fn ranked(self, opts: &RankOptions, lowest_first: bool) -> Self {
+        let order = |a: f32, b: f32| if lowest_first { a.total_cmp(&b) } else { b.total_cmp(&a) };
+        match self {
+            MetricResult::Stations(mut scores) => {
+                scores.retain(|s| !s.score.is_nan());
+                scores.sort_by(|a, b| order(a.score, b.score).then_with(|| a.station.cmp(&b.station)));
+                scores.truncate(opts.top_n);
+                MetricResult::Stations(scores)
+            }
+            MetricResult::Routes(mut scores) => {
+                scores.retain(|s| !s.score.is_nan());
+                scores.sort_by(|a, b| order(a.score, b.score).then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to))));
+                scores.truncate(opts.top_n);
+                MetricResult::Routes(scores)
+            }
+        }
+    }
+
+    // Typed rows (rank, station or route, score) as in `export::ranking_rows`
+    pub fn rows(&self, name: &str) -> Vec<RankingRow> {
+        match self {
+            MetricResult::Stations(scores) => ranking_rows(name, scores),
+            MetricResult::Routes(scores) => ranking_rows(name, scores),
+        }
+    }
+
+    // Writes the scores as ranking CSV; see `export::write_ranking_csv`
+    pub fn write_csv<W: std::io::Write>(&self, name: &str, writer: W) -> std::result::Result<usize, csv::Error> {
+        match self {
+            MetricResult::Stations(scores) => write_ranking_csv(name, scores, writer),
+            MetricResult::Routes(scores) => write_ranking_csv(name, scores, writer),
+        }
+    }
+}
+
+// A custom station or route score
+// Send + Sync so a registry can be shared with the async front end and worker threads
+pub trait Metric: Send + Sync {
+    // Identifier on the command line (`metric <name>`), in report file names and in the ranking column of exports
+    fn name(&self) -> &str;
+    // Section heading; the name unless overridden
+    fn title(&self) -> String {
+        self.name().to_string()
+    }
+    // Whether a lower score ranks first (e.g. a delay); by default the highest score does
+    fn lowest_first(&self) -> bool {
+        false
+    }
+    // Scores every station or route of the graph
+    fn compute(&self, graph: &TransitGraph) -> MetricResult;
+}
+
+// The custom metrics available to a run, in registration order
+#[derive(Default)]
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn Metric>>,
+}
+
+impl MetricRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a metric, replacing any registered under the same name
+    pub fn register(&mut self, metric: impl Metric + 'static) -> &mut Self {
+        self.metrics.retain(|m| m.name() != metric.name());
+        self.metrics.push(Box::new(metric));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Metric> {
+        self.metrics.iter().find(|m| m.name() == name).map(|m| m.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Metric> {
+        self.metrics.iter().map(|m| m.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    // Computes a metric by name and ranks it, or an invalid-parameter error listing the registered names
+    pub fn rank(&self, name: &str, graph: &TransitGraph, opts: &RankOptions) -> Result<MetricResult> {
+        let Some(metric) = self.get(name) else {
+            let names: Vec<&str> = self.iter().map(|m| m.name()).collect();
+            let reason = if names.is_empty() {
+                format!("{:?} is not registered; no custom metrics are", name)
+            } else {
+                format!("{:?} is not one of {}", name, names.join(", "))
+            };
+            return Err(Error::InvalidParameter { name: "metric", reason });
+        };
+        Ok(metric.compute(graph).ranked(opts, metric.lowest_first()))
+    }
+}
+
+impl std::fmt::Debug for MetricRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter().map(|m| m.name())).finish()
+    }
+}
@@ -0,0 +1,26 @@
+// The supported public surface in one import: `use nj_delays::prelude::*;`
+// Everything re-exported here follows semver; other public items (output rendering, export writers, the CLI) may change
+// in minor releases. `Result` is left out so a glob import does not shadow the standard one; use `nj_delays::Result`
+
+pub use crate::error::Error;
+pub use crate::load::{load_coordinates, load_data, read_data, stream_data, Coordinates, RecordFilter, TrainRecord};
+pub use crate::graph::{station_id, Graph, GraphBuilder, Station, StationId, TransitGraph, Weight};
+pub use crate::plugin::{Metric, MetricRegistry, MetricResult, RouteScore};
+pub use crate::pipeline::{load_graph, stream_graph, Observer, Silent};
+pub use crate::stream::{StreamAggregator, StreamSummary};
+pub use crate::cache::CacheStats;
+pub use crate::metrics::{betweenness_centrality, closeness_centrality, shortest_path, RankOptions, RouteStat, StationScore, DEFAULT_SEED};
+pub use crate::routing::{ParetoPath, PathSegment, Reachable};
+pub use crate::landmarks::{LandmarkIndex, QueryStats};
+pub use crate::distances::{Diameter, DistanceMatrix, StationIndex};
+pub use crate::routes::{RouteSummary, RouteTrend};
+pub use crate::stations::{StationDetail, StationSummary};
+pub use crate::congestion::StationThroughput;
+pub use crate::forecast::{ForecastParams, RouteForecast};
+pub use crate::topology::{Assortativity, LinkPrediction, SmallWorld};
+pub use crate::stats::{Comparison, HistogramBin, Sample};
+pub use crate::comparison::{CentralityShift, RouteShift, SideSummary};
+pub use crate::heatmap::DelayHeatmap;
+pub use crate::search::StationMatch;
+pub use crate::profiles::StationProfile;
+pub use crate::validate::{Issue, Severity, ValidationSummary};
@@ -0,0 +1,265 @@
+// Per-station profile pages: one HTML or Markdown page per station plus an index, a browsable static site of the network
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::export::{escape, ReportMetadata};
+use crate::graph::{Station, StationId, TransitGraph};
+use crate::load::TrainRecord;
+use crate::metrics::{describe_threshold, RankOptions};
+use crate::output::{write_section, OutputFormat};
+use crate::report::{bar_chart, html_table, line_chart, monthly_trend, GroupOnTime, HTML_TEMPLATE};
+use crate::routes::{route_summaries, sort_routes, RouteColumn, RouteSummary};
+use crate::stations::{busiest_hours, station_summaries, worst_days, DayDelay, HourlyActivity, StationDetail};
+
+// Everything shown on one station's page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationProfile {
+    pub slug: String,                 // File name stem, unique within the site
+    pub detail: StationDetail,
+    pub monthly: Vec<GroupOnTime>,    // Arrivals at the station by month
+    pub outbound: Vec<RouteSummary>,  // Worst routes leaving the station, top N
+    pub inbound: Vec<RouteSummary>,   // Worst routes arriving at the station, top N
+    pub hours: Vec<HourlyActivity>,   // Busiest arrival hours, top N
+    pub days: Vec<DayDelay>,          // Worst arrival days, top N
+    pub neighbors: Vec<Station>,      // Directly connected stations, sorted by name
+}
+
+// Builds a profile for every station in the graph, sorted by name
+// Logic: centrality is computed once for the whole network and ranked, and records are grouped by arrival station,
+// so the cost is one closeness/betweenness pass rather than one per station
+pub fn station_profiles(records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions) -> Vec<StationProfile> {
+    let betweenness = graph.betweenness_centrality();
+    let closeness: HashMap<&Station, f32> = graph.stations()
+        .filter_map(|s| graph.closeness_centrality(s).map(|c| (s, c)))
+        .collect();
+    let adjacency = graph.undirected_adjacency();
+    let mut arrivals: HashMap<StationId, Vec<TrainRecord>> = HashMap::new();
+    for r in records {
+        arrivals.entry(r.to_station_id()).or_default().push(r.clone());
+    }
+    let every_route = RankOptions { min_trips: 1, ..*opts };
+    let mut routes = route_summaries(graph, &every_route);
+    sort_routes(&mut routes, RouteColumn::MeanDelay, true);
+    let mut used = BTreeSet::new();
+    station_summaries(records, graph).into_iter()
+        .map(|summary| {
+            let station = summary.station.clone();
+            let score = betweenness.get(&station).copied().unwrap_or(0.0);
+            let close = closeness.get(&station).copied();
+            let detail = StationDetail {
+                summary,
+                closeness: close,
+                closeness_rank: close.map(|c| 1 + closeness.values().filter(|&&other| other > c).count()),
+                betweenness: score,
+                betweenness_rank: 1 + betweenness.values().filter(|&&b| b > score).count(),
+            };
+            let here = arrivals.get(&station.id).map(Vec::as_slice).unwrap_or_default();
+            let top = |rows: Vec<RouteSummary>| rows.into_iter().take(opts.top_n).collect::<Vec<_>>();
+            let mut neighbors: Vec<Station> = adjacency.get(&station).into_iter().flatten().cloned().collect();
+            neighbors.sort();
+            StationProfile {
+                slug: unique_slug(&station.name, &mut used),
+                monthly: monthly_trend(here, opts.on_time_threshold),
+                outbound: top(routes.iter().filter(|r| r.from == station).cloned().collect()),
+                inbound: top(routes.iter().filter(|r| r.to == station).cloned().collect()),
+                hours: busiest_hours(here, &station).into_iter().take(opts.top_n).collect(),
+                days: worst_days(here, &station).into_iter().take(opts.top_n).collect(),
+                neighbors,
+                detail,
+            }
+        })
+        .collect()
+}
+
+// Lower-case ASCII letters and digits joined by single dashes; a numeric suffix keeps names that collapse together apart
+fn unique_slug(name: &str, used: &mut BTreeSet<String>) -> String {
+    let words: Vec<String> = name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect();
+    let base = if words.is_empty() { "station".to_string() } else { words.join("-") };
+    let mut slug = base.clone();
+    let mut n = 2;
+    // "index" is taken by the station list
+    while slug == "index" || used.contains(&slug) {
+        slug = format!("{}-{}", base, n);
+        n += 1;
+    }
+    used.insert(slug.clone());
+    slug
+}
+
+// Writes index.{html,md} and one page per station into `dir`, created if missing
+// Output: the files written, index first
+pub fn write_profiles(dir: &Path, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata, markdown: bool) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let profiles = station_profiles(records, graph, &metadata.parameters);
+    let slugs: HashMap<&Station, &str> = profiles.iter().map(|p| (&p.detail.summary.station, p.slug.as_str())).collect();
+    let extension = if markdown { "md" } else { "html" };
+    let index = dir.join(format!("index.{}", extension));
+    fs::write(&index, if markdown { markdown_index(&profiles, metadata) } else { html_index(&profiles, metadata) })?;
+    let mut written = vec![index];
+    for profile in &profiles {
+        let path = dir.join(format!("{}.{}", profile.slug, extension));
+        let page = if markdown { markdown_page(profile, &slugs, metadata) } else { html_page(profile, &slugs, metadata) };
+        fs::write(&path, page)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// Dataset line shown under every page title
+fn describe_source(metadata: &ReportMetadata) -> String {
+    format!(
+        "{} records from {} ({} to {}) · generated {} · {}",
+        metadata.records,
+        metadata.dataset,
+        metadata.first_date.as_deref().unwrap_or("?"),
+        metadata.last_date.as_deref().unwrap_or("?"),
+        metadata.generated_at,
+        describe_threshold(metadata.parameters.on_time_threshold),
+    )
+}
+
+// Page titles and section headings shared by both formats
+fn section_titles(profile: &StationProfile, opts: &RankOptions) -> [String; 6] {
+    let station = &profile.detail.summary.station;
+    [
+        "Centrality and volume".to_string(),
+        format!("Arrivals at {} by month ({})", station, describe_threshold(opts.on_time_threshold)),
+        format!("Worst {} outbound routes", profile.outbound.len()),
+        format!("Worst {} inbound routes", profile.inbound.len()),
+        format!("Busiest {} arrival hours", profile.hours.len()),
+        format!("Worst {} arrival days", profile.days.len()),
+    ]
+}
+
+// Station list with links to every page, busiest first
+fn html_index(profiles: &[StationProfile], metadata: &ReportMetadata) -> String {
+    let mut table = String::from("<table>\n<tr><th>Station</th><th>Degree</th><th>Trips</th><th>Inbound delay</th><th>Betweenness rank</th><th>Lines</th></tr>\n");
+    for p in by_trips(profiles) {
+        let s = &p.detail.summary;
+        table.push_str(&format!(
+            "<tr><td><a href=\"{}.html\">{}</a></td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+            p.slug, escape(&s.station.name), s.degree, s.trips, s.inbound_delay, p.detail.betweenness_rank, escape(&s.lines),
+        ));
+    }
+    table.push_str("</table>");
+    HTML_TEMPLATE
+        .replace("{{title}}", &escape(&format!("NJ Transit stations ({})", profiles.len())))
+        .replace("{{meta}}", &escape(&describe_source(metadata)))
+        .replace("{{charts}}", "")
+        .replace("{{sections}}", &table)
+}
+
+// One station's page: a monthly delay chart and an hourly arrivals chart, then a table per section
+fn html_page(profile: &StationProfile, slugs: &HashMap<&Station, &str>, metadata: &ReportMetadata) -> String {
+    let opts = &metadata.parameters;
+    let station = &profile.detail.summary.station;
+    let months: Vec<(String, f32)> = profile.monthly.iter().map(|m| (m.group.clone(), m.average_delay)).collect();
+    let hours: Vec<(String, f32)> = profile.hours.iter().map(|h| (format!("{:02}:00", h.hour), h.arrivals as f32)).collect();
+    let busiest = hours.iter().map(|(_, v)| *v).fold(0.0f32, f32::max);
+    let charts = [
+        line_chart("Average arrival delay by month (minutes)", &months),
+        bar_chart("Arrivals in the busiest hours", &hours, busiest),
+    ].join("\n");
+    let links: Vec<String> = profile.neighbors.iter()
+        .map(|n| match slugs.get(n) {
+            Some(slug) => format!("<a href=\"{}.html\">{}</a>", slug, escape(&n.name)),
+            None => escape(&n.name),
+        })
+        .collect();
+    let mut sections = format!("<p><a href=\"index.html\">All stations</a> · Connects to: {}</p>\n", links.join(", "));
+    let titles = section_titles(profile, opts);
+    let tables = [
+        html_table(&values(std::slice::from_ref(&profile.detail))),
+        html_table(&values(&profile.monthly)),
+        html_table(&values(&profile.outbound)),
+        html_table(&values(&profile.inbound)),
+        html_table(&values(&profile.hours)),
+        html_table(&values(&profile.days)),
+    ];
+    for (title, table) in titles.iter().zip(tables) {
+        sections.push_str(&format!("<h2>{}</h2>\n{}\n", escape(title), table));
+    }
+    HTML_TEMPLATE
+        .replace("{{title}}", &escape(&station.name))
+        .replace("{{meta}}", &escape(&describe_source(metadata)))
+        .replace("{{charts}}", &charts)
+        .replace("{{sections}}", &sections)
+}
+
+// Row of the Markdown station list; the station cell is a link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexRow {
+    station: String,
+    degree: usize,
+    trips: usize,
+    inbound_delay: f32,
+    betweenness_rank: usize,
+    lines: String,
+}
+
+// Station list with links to every page, busiest first
+fn markdown_index(profiles: &[StationProfile], metadata: &ReportMetadata) -> String {
+    let rows: Vec<IndexRow> = by_trips(profiles).into_iter()
+        .map(|p| {
+            let s = &p.detail.summary;
+            IndexRow {
+                station: format!("[{}]({}.md)", s.station, p.slug),
+                degree: s.degree,
+                trips: s.trips,
+                inbound_delay: s.inbound_delay,
+                betweenness_rank: p.detail.betweenness_rank,
+                lines: s.lines.clone(),
+            }
+        })
+        .collect();
+    let mut out: Vec<u8> = Vec::new();
+    let document = |out: &mut Vec<u8>| -> io::Result<()> {
+        writeln!(out, "# NJ Transit stations ({})\n", profiles.len())?;
+        writeln!(out, "{}\n", describe_source(metadata))?;
+        write_section(out, OutputFormat::Markdown, "Stations by trips", &rows)
+    };
+    document(&mut out).expect("writing to memory cannot fail");
+    String::from_utf8(out).expect("tables are built from UTF-8 strings")
+}
+
+// One station's page: links to the index and neighbours, then a table per section
+fn markdown_page(profile: &StationProfile, slugs: &HashMap<&Station, &str>, metadata: &ReportMetadata) -> String {
+    let links: Vec<String> = profile.neighbors.iter()
+        .map(|n| match slugs.get(n) {
+            Some(slug) => format!("[{}]({}.md)", n, slug),
+            None => n.name.clone(),
+        })
+        .collect();
+    let titles = section_titles(profile, &metadata.parameters);
+    let mut out: Vec<u8> = Vec::new();
+    let document = |out: &mut Vec<u8>| -> io::Result<()> {
+        writeln!(out, "# {}\n", profile.detail.summary.station)?;
+        writeln!(out, "{}\n", describe_source(metadata))?;
+        writeln!(out, "[All stations](index.md) · Connects to: {}\n", links.join(", "))?;
+        write_section(out, OutputFormat::Markdown, &titles[0], std::slice::from_ref(&profile.detail))?;
+        write_section(out, OutputFormat::Markdown, &titles[1], &profile.monthly)?;
+        write_section(out, OutputFormat::Markdown, &titles[2], &profile.outbound)?;
+        write_section(out, OutputFormat::Markdown, &titles[3], &profile.inbound)?;
+        write_section(out, OutputFormat::Markdown, &titles[4], &profile.hours)?;
+        write_section(out, OutputFormat::Markdown, &titles[5], &profile.days)
+    };
+    document(&mut out).expect("writing to memory cannot fail");
+    String::from_utf8(out).expect("tables are built from UTF-8 strings")
+}
+
+// Profiles ordered by trips, most first, then by name
+fn by_trips(profiles: &[StationProfile]) -> Vec<&StationProfile> {
+    let mut sorted: Vec<&StationProfile> = profiles.iter().collect();
+    sorted.sort_by(|a, b| b.detail.summary.trips.cmp(&a.detail.summary.trips).then(a.detail.summary.station.cmp(&b.detail.summary.station)));
+    sorted
+}
+
+fn values<T: Serialize>(rows: &[T]) -> Vec<serde_json::Value> {
+    rows.iter().filter_map(|r| serde_json::to_value(r).ok()).collect()
+}
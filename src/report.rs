@@ -0,0 +1,437 @@
+// Report presets: curated bundles of analyses written to one file per section, or as one self-contained HTML, Markdown, or PDF document
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{NaiveDateTime, Timelike};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::comparison::summarize;
+use crate::congestion::rank_stations_by_congestion;
+use crate::forecast::rank_routes_by_forecast;
+use crate::graph::TransitGraph;
+use crate::load::TrainRecord;
+use crate::metrics::{describe_threshold, RankOptions};
+use crate::export::{escape, ReportMetadata};
+use crate::output::{flatten_rows, write_section, OutputFormat};
+use crate::pdf::PdfDocument;
+use crate::plugin::MetricRegistry;
+
+// Bundle of analyses selected by `report --preset`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Route rankings, line on-time performance, and the monthly trend; no all-pairs centrality
+    #[default]
+    Quick,
+    /// What riders feel: line on-time performance, the worst routes, peak hours, and next week's forecast
+    Commuter,
+    /// Every ranking, trend, and network metric
+    Full,
+}
+
+// One analysis that can appear in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    LineOnTime,
+    MonthlyTrend,
+    HourlyProfile,
+    WorstRoutes,
+    BestRoutes,
+    Congestion,
+    Forecast,
+    Closeness,
+    Betweenness,
+    Links,
+    SmallWorld,
+}
+
+impl Preset {
+    // Sections in the order they are written
+    pub fn sections(self) -> &'static [Section] {
+        use Section::*;
+        match self {
+            Preset::Quick => &[LineOnTime, MonthlyTrend, WorstRoutes, BestRoutes, Congestion],
+            Preset::Commuter => &[LineOnTime, HourlyProfile, WorstRoutes, Forecast],
+            Preset::Full => &[
+                LineOnTime, MonthlyTrend, HourlyProfile, WorstRoutes, BestRoutes, Congestion,
+                Forecast, Closeness, Betweenness, Links, SmallWorld,
+            ],
+        }
+    }
+}
+
+impl Section {
+    // File name stem
+    pub fn slug(self) -> &'static str {
+        match self {
+            Section::LineOnTime => "line-on-time",
+            Section::MonthlyTrend => "monthly-trend",
+            Section::HourlyProfile => "hourly-profile",
+            Section::WorstRoutes => "worst-routes",
+            Section::BestRoutes => "best-routes",
+            Section::Congestion => "congestion",
+            Section::Forecast => "forecast",
+            Section::Closeness => "closeness",
+            Section::Betweenness => "betweenness",
+            Section::Links => "predicted-links",
+            Section::SmallWorld => "small-world",
+        }
+    }
+
+    // Section heading
+    pub fn title(self, opts: &RankOptions) -> String {
+        let n = opts.top_n;
+        match self {
+            Section::LineOnTime => format!("On-time performance by line ({}):", describe_threshold(opts.on_time_threshold)),
+            Section::MonthlyTrend => format!("Monthly delay trend ({}):", describe_threshold(opts.on_time_threshold)),
+            Section::HourlyProfile => format!("Delay by scheduled hour of day ({}):", describe_threshold(opts.on_time_threshold)),
+            Section::WorstRoutes => format!("Top {} routes by average delay:", n),
+            Section::BestRoutes => format!("Top {} routes by lowest average delay:", n),
+            Section::Congestion => format!("Top {} congested stations (trips/day × average delay):", n),
+            Section::Forecast => format!("Top {} routes by forecast next-week delay:", n),
+            Section::Closeness => format!("Top {} stations by closeness centrality:", n),
+            Section::Betweenness => format!("Top {} stations (unweighted betweenness):", n),
+            Section::Links => format!("Top {} predicted new connections (Adamic-Adar):", n),
+            Section::SmallWorld => "Small-world indicators (10 degree-preserving random baselines):".to_string(),
+        }
+    }
+
+    // Computes the section's rows as JSON values
+    pub fn rows(self, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions) -> Vec<Value> {
+        fn values<T: Serialize>(rows: Vec<T>) -> Vec<Value> {
+            rows.iter().filter_map(|r| serde_json::to_value(r).ok()).collect()
+        }
+        match self {
+            Section::LineOnTime => values(line_on_time(records, opts.on_time_threshold)),
+            Section::MonthlyTrend => values(monthly_trend(records, opts.on_time_threshold)),
+            Section::HourlyProfile => values(hourly_profile(records, opts.on_time_threshold)),
+            Section::WorstRoutes => values(graph.rank_routes_by_average_delay(opts)),
+            Section::BestRoutes => values(graph.rank_routes_by_lowest_delay(opts)),
+            Section::Congestion => values(rank_stations_by_congestion(records, opts)),
+            Section::Forecast => values(rank_routes_by_forecast(records, opts)),
+            Section::Closeness => values(graph.rank_stations_by_closeness(opts)),
+            Section::Betweenness => values(graph.rank_stations_by_betweenness(opts)),
+            Section::Links => values(graph.predict_links(opts.top_n)),
+            Section::SmallWorld => values(graph.small_world(10, opts.seed).into_iter().collect()),
+        }
+    }
+
+    // Computes the section and writes it
+    // Output: number of rows written
+    pub fn write<W: Write>(self, out: &mut W, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) -> io::Result<usize> {
+        let rows = self.rows(records, graph, opts);
+        write_section(out, format, &self.title(opts), &rows)?;
+        Ok(rows.len())
+    }
+}
+
+// Delay and on-time performance over a group of records (a line, a month, an hour)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupOnTime {
+    pub group: String,
+    pub trips: usize,       // Records with a delay
+    pub average_delay: f32, // Minutes
+    pub on_time_rate: f32,  // Share of trips at or below the threshold
+}
+
+// Groups delayed records by a key, sorted by key
+fn group_on_time<K: Ord>(records: &[TrainRecord], threshold: f32, key: impl Fn(&TrainRecord) -> Option<K>, label: impl Fn(&K) -> String) -> Vec<GroupOnTime> {
+    let mut groups: BTreeMap<K, (usize, f32, usize)> = BTreeMap::new(); // Key -> (trips, total delay, on-time trips)
+    for r in records {
+        let (Some(delay), Some(k)) = (r.delay(), key(r)) else { continue };
+        let entry = groups.entry(k).or_insert((0, 0.0, 0));
+        entry.0 += 1;
+        entry.1 += delay;
+        entry.2 += (delay <= threshold) as usize;
+    }
+    groups.iter()
+        .map(|(k, (trips, total, on_time))| GroupOnTime {
+            group: label(k),
+            trips: *trips,
+            average_delay: total / *trips as f32,
+            on_time_rate: *on_time as f32 / *trips as f32,
+        })
+        .collect()
+}
+
+// On-time performance per line (names trimmed), worst first
+pub fn line_on_time(records: &[TrainRecord], threshold: f32) -> Vec<GroupOnTime> {
+    let mut lines = group_on_time(records, threshold, |r| Some(r.line.trim().to_string()), String::clone);
+    lines.sort_by(|a, b| a.on_time_rate.total_cmp(&b.on_time_rate).then_with(|| a.group.cmp(&b.group)));
+    lines
+}
+
+// Delay and on-time performance per calendar month ("YYYY-MM"), in date order
+pub fn monthly_trend(records: &[TrainRecord], threshold: f32) -> Vec<GroupOnTime> {
+    group_on_time(records, threshold, |r| r.date.get(..7).map(str::to_string), String::clone)
+}
+
+// Delay and on-time performance per scheduled hour of day ("08:00"), in hour order
+pub fn hourly_profile(records: &[TrainRecord], threshold: f32) -> Vec<GroupOnTime> {
+    let hour = |r: &TrainRecord| NaiveDateTime::parse_from_str(&r.scheduled_time, "%Y-%m-%d %H:%M:%S").ok().map(|t| t.hour());
+    group_on_time(records, threshold, hour, |h| format!("{:02}:00", h))
+}
+
+// One file written by a report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportFile {
+    pub section: String,
+    pub path: String,
+    pub rows: usize,
+}
+
+// Writes every section of a preset into `dir`, one file each, named NN-slug.ext
+// Output: the files written, in order
+pub fn write_report(preset: Preset, dir: &Path, records: &[TrainRecord], graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) -> io::Result<Vec<ReportFile>> {
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for (i, section) in preset.sections().iter().enumerate() {
+        let path: PathBuf = dir.join(format!("{:02}-{}.{}", i + 1, section.slug(), extension(format)));
+        let mut out = BufWriter::new(File::create(&path)?);
+        let rows = section.write(&mut out, records, graph, opts, format)?;
+        out.flush()?;
+        written.push(ReportFile { section: section.slug().to_string(), path: path.display().to_string(), rows });
+    }
+    Ok(written)
+}
+
+// Writes one section per registered custom metric into `dir`, numbered on from `first` (after a preset's sections)
+// Output: the files written, in registration order
+pub fn write_metric_sections(registry: &MetricRegistry, first: usize, dir: &Path, graph: &TransitGraph, opts: &RankOptions, format: OutputFormat) -> io::Result<Vec<ReportFile>> {
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for (i, metric) in registry.iter().enumerate() {
+        let ranked = metric.compute(graph).ranked(opts, metric.lowest_first());
+        let path: PathBuf = dir.join(format!("{:02}-{}.{}", first + i, metric.name(), extension(format)));
+        let mut out = BufWriter::new(File::create(&path)?);
+        let rows = ranked.rows(metric.name());
+        write_section(&mut out, format, &metric.title(), &rows)?;
+        out.flush()?;
+        written.push(ReportFile { section: metric.name().to_string(), path: path.display().to_string(), rows: rows.len() });
+    }
+    Ok(written)
+}
+
+// File extension for a report section in an output format
+fn extension(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Table => "txt",
+        OutputFormat::Json => "json",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Markdown => "md",
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => "arrows",
+    }
+}
+
+
+// Page skeleton for HTML reports; {{...}} placeholders are filled by `render_html`
+pub(crate) const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 60rem; color: #222; }
+h1 { margin-bottom: 0.2rem; }
+.meta { color: #666; font-size: 0.9rem; }
+.charts { display: flex; flex-wrap: wrap; gap: 1rem; }
+table { border-collapse: collapse; margin: 0.5rem 0 1.5rem; font-size: 0.9rem; }
+th, td { padding: 0.2rem 0.6rem; border-bottom: 1px solid #ddd; text-align: left; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+svg text { font-size: 11px; fill: #333; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<p class="meta">{{meta}}</p>
+<div class="charts">
+{{charts}}
+</div>
+{{sections}}
+</body>
+</html>
+"#;
+
+// Renders a preset as one HTML page with inline SVG charts; needs no server, scripts, or external files
+// Logic: an overview of line on-time performance and the monthly delay trend, then one table per section
+pub fn render_html(preset: Preset, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> String {
+    let opts = &metadata.parameters;
+    let lines: Vec<(String, f32)> = line_on_time(records, opts.on_time_threshold).into_iter()
+        .map(|l| (l.group, l.on_time_rate * 100.0))
+        .collect();
+    let months: Vec<(String, f32)> = monthly_trend(records, opts.on_time_threshold).into_iter()
+        .map(|m| (m.group, m.average_delay))
+        .collect();
+    let charts = [
+        bar_chart(&format!("On-time % by line ({})", describe_threshold(opts.on_time_threshold)), &lines, 100.0),
+        line_chart("Average delay by month (minutes)", &months),
+    ].join("\n");
+    let sections: String = preset.sections().iter()
+        .map(|section| format!("<h2>{}</h2>\n{}", escape(section.title(opts).trim_end_matches(':')), html_table(&section.rows(records, graph, opts))))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let meta = format!(
+        "{} records from {} ({} to {}) · generated {} · top {}, at least {} trips, {}",
+        metadata.records,
+        metadata.dataset,
+        metadata.first_date.as_deref().unwrap_or("?"),
+        metadata.last_date.as_deref().unwrap_or("?"),
+        metadata.generated_at,
+        opts.top_n,
+        opts.min_trips,
+        describe_threshold(opts.on_time_threshold),
+    );
+    HTML_TEMPLATE
+        .replace("{{title}}", &escape(&format!("NJ Transit delay report ({:?})", preset)))
+        .replace("{{meta}}", &escape(&meta))
+        .replace("{{charts}}", &charts)
+        .replace("{{sections}}", &sections)
+}
+
+// Renders rows as an HTML table with the same flattened columns as the terminal tables
+pub(crate) fn html_table(rows: &[Value]) -> String {
+    let Ok((columns, cells)) = flatten_rows(rows, false) else { return String::new() };
+    if cells.is_empty() {
+        return "<p>(none)</p>".to_string();
+    }
+    let mut html = String::from("<table>\n<tr><th>#</th>");
+    for column in &columns {
+        html.push_str(&format!("<th>{}</th>", escape(column)));
+    }
+    html.push_str("</tr>\n");
+    for (i, row) in cells.iter().enumerate() {
+        html.push_str(&format!("<tr><td class=\"num\">{}</td>", i + 1));
+        for cell in row {
+            let class = if cell.parse::<f64>().is_ok() { " class=\"num\"" } else { "" };
+            html.push_str(&format!("<td{}>{}</td>", class, escape(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>");
+    html
+}
+
+// Horizontal bar chart as inline SVG; bars are scaled against `max`
+pub(crate) fn bar_chart(title: &str, bars: &[(String, f32)], max: f32) -> String {
+    let (label_width, bar_area, row) = (150.0, 300.0, 18.0);
+    let height = 24.0 + row * bars.len() as f32;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" role="img"><text x="0" y="14" font-weight="bold">{}</text>"#,
+        label_width + bar_area + 50.0, height, escape(title),
+    );
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = 24.0 + row * i as f32;
+        let width = bar_area * (value / max.max(f32::EPSILON)).clamp(0.0, 1.0);
+        svg.push_str(&format!(
+            r##"<text x="{}" y="{}" text-anchor="end">{}</text><rect x="{}" y="{}" width="{:.1}" height="{}" fill="#3b6ea5"/><text x="{:.1}" y="{}">{:.1}</text>"##,
+            label_width - 6.0, y + 12.0, escape(label), label_width, y + 2.0, width, row - 4.0, label_width + width + 4.0, y + 12.0, value,
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// Line chart as inline SVG with the first, middle, and last x labels and a zero-based y axis
+pub(crate) fn line_chart(title: &str, points: &[(String, f32)]) -> String {
+    let (width, height, left, top, bottom) = (460.0, 220.0, 40.0, 24.0, 30.0);
+    let plot_width = width - left - 10.0;
+    let plot_height = height - top - bottom;
+    let max = points.iter().map(|(_, v)| *v).fold(0.0f32, f32::max).max(f32::EPSILON);
+    let x = |i: usize| left + plot_width * i as f32 / (points.len().max(2) - 1) as f32;
+    let y = |v: f32| top + plot_height * (1.0 - v / max);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" role="img"><text x="0" y="14" font-weight="bold">{}</text>"#,
+        width, height, escape(title),
+    );
+    svg.push_str(&format!(
+        r##"<line x1="{left}" y1="{top}" x2="{left}" y2="{base}" stroke="#999"/><line x1="{left}" y1="{base}" x2="{right}" y2="{base}" stroke="#999"/><text x="{tx}" y="{ty}" text-anchor="end">{max:.1}</text><text x="{tx}" y="{base}" text-anchor="end">0</text>"##,
+        left = left, top = top, base = top + plot_height, right = left + plot_width, tx = left - 4.0, ty = top + 4.0, max = max,
+    ));
+    let polyline: Vec<String> = points.iter().enumerate().map(|(i, (_, v))| format!("{:.1},{:.1}", x(i), y(*v))).collect();
+    svg.push_str(&format!(r##"<polyline points="{}" fill="none" stroke="#c0392b" stroke-width="2"/>"##, polyline.join(" ")));
+    let mut labelled: Vec<usize> = vec![0, points.len() / 2, points.len().saturating_sub(1)];
+    labelled.dedup();
+    for i in labelled.into_iter().filter(|&i| i < points.len()) {
+        svg.push_str(&format!(r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#, x(i), height - 10.0, escape(&points[i].0)));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// One name/value pair in a report's parameter table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Setting {
+    setting: String,
+    value: String,
+}
+
+// Renders a preset as one Markdown document: headline numbers, the parameters used, then a table per section
+// Paste-ready for GitHub issues and wikis; numbers are rounded as in terminal tables
+pub fn render_markdown(preset: Preset, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> String {
+    let opts = &metadata.parameters;
+    let summary = summarize("", &metadata.dataset, records, graph, opts.on_time_threshold);
+    let mut out: Vec<u8> = Vec::new();
+    let document = |out: &mut Vec<u8>| -> io::Result<()> {
+        writeln!(out, "# NJ Transit delay report ({:?})\n", preset)?;
+        writeln!(out, "- **Records:** {} from {} to {}", summary.records, metadata.first_date.as_deref().unwrap_or("?"), metadata.last_date.as_deref().unwrap_or("?"))?;
+        writeln!(out, "- **Network:** {} stations, {} routes", summary.stations, summary.routes)?;
+        writeln!(out, "- **Average delay:** {:.2} min", summary.average_delay)?;
+        writeln!(out, "- **On time:** {:.1}% ({})\n", summary.on_time_rate * 100.0, describe_threshold(opts.on_time_threshold))?;
+        // Every metadata field, dotted like table columns ("filters.lines", "parameters.top_n")
+        let (names, values) = flatten_rows(std::slice::from_ref(metadata), false)?;
+        let settings: Vec<Setting> = names.into_iter().zip(values.into_iter().flatten())
+            .map(|(setting, value)| Setting { setting, value })
+            .collect();
+        write_section(out, OutputFormat::Markdown, "Parameters", &settings)?;
+        for section in preset.sections() {
+            write_section(out, OutputFormat::Markdown, &section.title(opts), &section.rows(records, graph, opts))?;
+        }
+        Ok(())
+    };
+    document(&mut out).expect("writing to memory cannot fail");
+    String::from_utf8(out).expect("tables are built from UTF-8 strings")
+}
+
+// Renders a preset as a paginated PDF: headline numbers and parameters, two bar charts, then a table per section
+// For readers who will not open a terminal or a spreadsheet; tables shrink their font to fit the page width
+pub fn render_pdf(preset: Preset, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> Vec<u8> {
+    let opts = &metadata.parameters;
+    let summary = summarize("", &metadata.dataset, records, graph, opts.on_time_threshold);
+    let mut doc = PdfDocument::new();
+    doc.paragraph(18.0, true, &format!("NJ Transit delay report ({:?})", preset));
+    doc.skip(6.0);
+    for line in [
+        format!("{} records from {} to {}", summary.records, metadata.first_date.as_deref().unwrap_or("?"), metadata.last_date.as_deref().unwrap_or("?")),
+        format!("{} stations, {} routes", summary.stations, summary.routes),
+        format!("Average delay {:.2} min; {:.1}% on time ({})", summary.average_delay, summary.on_time_rate * 100.0, describe_threshold(opts.on_time_threshold)),
+        format!("Source {}, generated {}, top {}, at least {} trips per route", metadata.dataset, metadata.generated_at, opts.top_n, opts.min_trips),
+    ] {
+        doc.paragraph(9.0, false, &line);
+    }
+    doc.skip(12.0);
+    let lines: Vec<(String, f32)> = line_on_time(records, opts.on_time_threshold).into_iter()
+        .map(|l| (l.group, l.on_time_rate * 100.0))
+        .collect();
+    doc.bar_chart("On-time % by line", &lines, 100.0, "%");
+    doc.skip(12.0);
+    let worst: Vec<(String, f32)> = graph.rank_routes_by_average_delay(opts).into_iter()
+        .map(|r| (format!("{} → {}", r.from, r.to), r.average_delay))
+        .collect();
+    let max = worst.iter().map(|(_, d)| *d).fold(0.0, f32::max);
+    doc.bar_chart(&format!("Top {} routes by average delay (minutes)", worst.len()), &worst, max, "");
+    for section in preset.sections() {
+        doc.skip(12.0);
+        doc.reserve(60.0);
+        doc.paragraph(11.0, true, section.title(opts).trim_end_matches(':'));
+        let Ok((columns, cells)) = flatten_rows(&section.rows(records, graph, opts), false) else { continue };
+        if cells.is_empty() {
+            doc.paragraph(9.0, false, "(none)");
+        } else {
+            doc.table(&columns, &cells);
+        }
+    }
+    doc.finish()
+}
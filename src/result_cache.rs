@@ -0,0 +1,123 @@
+// On-disk cache of computed results, keyed on a hash of the input files and every parameter that shapes the result,
+// so re-running an analysis on unchanged data returns at once and recomputes only when the data or the options change
+// Entries are JSON files named by key in the cache directory (~/.cache/nj-delays by default); any can be deleted
+// Keys and entries carry the build fingerprint from build.rs, so a rebuilt tool recomputes rather than serving results
+// of code that may have changed
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash3_128;
+
+// Directory under the user cache directory that holds the entries
+pub const CACHE_DIR_NAME: &str = "nj-delays";
+
+// Bytes hashed per read of an input file
+const HASH_BUFFER: usize = 1 << 16;
+
+// Version and build fingerprint of this binary; the version alone is the same for every build of a release
+const BUILD: &str = concat!(env!("CARGO_PKG_VERSION"), "+", env!("NJ_DELAYS_BUILD"));
+
+// Hash of the inputs and parameters of one computation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey(u128);
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+// A stored result with what produced it; the parameters are kept for inspecting entries by hand
+#[derive(Serialize, Deserialize)]
+struct Entry<P, T> {
+    tool_version: String,
+    parameters: P,
+    value: T,
+}
+
+// A directory of cached results
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    // A cache in `dir`, created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // The cache in the user cache directory: $XDG_CACHE_HOME/nj-delays, else ~/.cache/nj-delays; None without a home
+    pub fn user() -> Option<Self> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(Self::new(base.join(CACHE_DIR_NAME)))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Key of a computation over the contents of `inputs` with `parameters` and this build of the tool
+    // Output: an error if an input cannot be read (the caller then computes without the cache)
+    pub fn key(inputs: &[&Path], parameters: &impl Serialize) -> io::Result<CacheKey> {
+        let mut hasher = XxHash3_128::new();
+        hasher.write(BUILD.as_bytes());
+        hasher.write(&serde_json::to_vec(parameters)?);
+        let mut buffer = vec![0; HASH_BUFFER];
+        for input in inputs {
+            // Lengths separate the inputs, so moving bytes from one file to the next changes the key
+            hasher.write(&fs::metadata(input)?.len().to_le_bytes());
+            let mut file = File::open(input)?;
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.write(&buffer[..read]);
+            }
+        }
+        Ok(CacheKey(hasher.finish_128()))
+    }
+
+    // File holding the entry for `key`
+    pub fn path(&self, key: CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    // The cached value for `key`, or None if there is none, another build wrote it, or it cannot be read as a `T` (a
+    // stale or damaged entry)
+    pub fn get<T: DeserializeOwned>(&self, key: CacheKey) -> Option<T> {
+        let file = File::open(self.path(key)).ok()?;
+        let entry: Entry<serde_json::Value, T> = serde_json::from_reader(BufReader::new(file)).ok()?;
+        (entry.tool_version == BUILD).then_some(entry.value)
+    }
+
+    // Stores a value under `key`, replacing any entry; returns the entry's file
+    // Logic: written to a temporary file and renamed into place, so a concurrent reader never sees half an entry
+    pub fn put<P: Serialize, T: Serialize>(&self, key: CacheKey, parameters: &P, value: &T) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let partial = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        let entry = Entry { tool_version: BUILD.to_string(), parameters, value };
+        let written = File::create(&partial).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, &entry)?;
+            // Dropping the writer would discard a failed write of its last buffer, leaving a truncated entry
+            writer.flush()
+        });
+        match written.and_then(|()| fs::rename(&partial, &path)) {
+            Ok(()) => Ok(path),
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
+}
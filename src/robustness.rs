@@ -0,0 +1,172 @@
+// Robustness of the network to station failures: stations are taken out one at a time, in a random order or most
+// central first, and after each removal the giant component and the average least delay of the journeys left are measured
+// The giant component is the largest set of stations joined by segments in either direction, as a share of the
+// original stations. The targeted attack recomputes betweenness on what is left after every removal, so it keeps
+// striking the stations the remaining journeys run through; the random curve averages several seeded orders
+// The robustness index is the mean giant-component share over the removals (Schneider et al., 2011): the further it
+// falls below the random curve's, the more fragile the network is to targeted disruption. Mean least delay tends to
+// fall as the network breaks up, since only shorter journeys stay joined, so it reads alongside the giant component
+
+use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use crate::distances::StationIndex;
+use crate::error::{Error, Result};
+use crate::graph::{Station, TransitGraph};
+use crate::topology::{connected_components, index_adjacency};
+
+// Random removal orders averaged unless set
+pub const DEFAULT_TRIALS: usize = 10;
+
+// Share of the stations removed unless set
+pub const DEFAULT_FRACTION: f32 = 0.5;
+
+// How a robustness experiment runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RobustnessOptions {
+    pub fraction: f32, // Share of the stations to remove, in (0, 1]
+    pub trials: usize, // Random removal orders to average
+    pub seed: u64,     // So reruns draw the same orders
+}
+
+impl Default for RobustnessOptions {
+    fn default() -> Self {
+        Self { fraction: DEFAULT_FRACTION, trials: DEFAULT_TRIALS, seed: crate::metrics::DEFAULT_SEED }
+    }
+}
+
+// The network after a number of removals under each strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobustnessPoint {
+    pub removed: usize,
+    pub removed_fraction: f32,
+    pub random_giant: f32,                 // Mean giant-component share over the random orders
+    pub targeted_giant: f32,
+    pub random_delay: Option<f32>,         // Mean least delay over the pairs still joined, averaged over the orders with any
+    pub targeted_delay: Option<f32>,
+    pub targeted_station: Option<Station>, // Station the targeted attack took out at this step; None before any removal
+}
+
+// How fragile the network is under each strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobustnessSummary {
+    pub stations: usize,
+    pub removals: usize,
+    pub trials: usize,
+    pub random_index: f32,            // Mean giant-component share over the removals
+    pub targeted_index: f32,
+    pub random_half: Option<f32>,     // Removed share at which the giant component first holds under half the stations
+    pub targeted_half: Option<f32>,
+}
+
+// Robustness curves, from the intact network (removed = 0) to the last removal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobustnessReport {
+    #[serde(flatten)]
+    pub summary: RobustnessSummary,
+    pub points: Vec<RobustnessPoint>,
+}
+
+// One measurement: the station removed, the giant-component share, and the mean least delay
+type Measurement = (Option<Station>, f32, Option<f32>);
+
+// Stations in the largest component of the undirected view of the graph
+pub fn giant_component(graph: &TransitGraph) -> usize {
+    let (_, lists) = index_adjacency(&graph.undirected_adjacency());
+    connected_components(&lists).iter().map(Vec::len).max().unwrap_or(0)
+}
+
+// Removes stations randomly and by descending betweenness, measuring the network after each removal
+// Output: an invalid-parameter error for a graph without stations, a fraction outside (0, 1], no trials, or a network
+// too large for a distance matrix
+pub fn robustness(graph: &TransitGraph, opts: &RobustnessOptions) -> Result<RobustnessReport> {
+    let mut stations: Vec<Station> = graph.all_stations().into_iter().collect();
+    stations.sort();
+    let n = stations.len();
+    if n == 0 {
+        return Err(Error::InvalidParameter { name: "graph", reason: "has no stations to remove".into() });
+    }
+    if !(opts.fraction > 0.0 && opts.fraction <= 1.0) {
+        return Err(Error::InvalidParameter { name: "fraction", reason: format!("{} is not in (0, 1]", opts.fraction) });
+    }
+    if opts.trials == 0 {
+        return Err(Error::InvalidParameter { name: "trials", reason: "must be at least 1".into() });
+    }
+    let removals = ((n as f32 * opts.fraction).round() as usize).clamp(1, n);
+    let targeted = curve(graph, n, removals, |remaining| {
+        let scores = remaining.betweenness_centrality();
+        // Highest betweenness first, ties to the first station by name so reruns strike in the same order
+        scores.into_iter().max_by(|(a, x), (b, y)| x.total_cmp(y).then_with(|| b.cmp(a))).map(|(station, _)| station)
+    })?;
+    let mut rng = StdRng::seed_from_u64(opts.seed);
+    let mut random = Vec::with_capacity(opts.trials);
+    for _ in 0..opts.trials {
+        let mut order = stations.clone();
+        order.shuffle(&mut rng);
+        let mut order = order.into_iter();
+        random.push(curve(graph, n, removals, |_| order.next())?);
+    }
+    let points: Vec<RobustnessPoint> = targeted.into_iter().enumerate()
+        .map(|(step, (station, giant, delay))| {
+            let delays: Vec<f32> = random.iter().filter_map(|trial| trial[step].2).collect();
+            RobustnessPoint {
+                removed: step,
+                removed_fraction: step as f32 / n as f32,
+                random_giant: random.iter().map(|trial| trial[step].1).sum::<f32>() / random.len() as f32,
+                targeted_giant: giant,
+                random_delay: (!delays.is_empty()).then(|| delays.iter().sum::<f32>() / delays.len() as f32),
+                targeted_delay: delay,
+                targeted_station: station,
+            }
+        })
+        .collect();
+    let removed = &points[1..];
+    let index = |giant: fn(&RobustnessPoint) -> f32| removed.iter().map(giant).sum::<f32>() / removed.len() as f32;
+    let half = |giant: fn(&RobustnessPoint) -> f32| removed.iter().find(|p| giant(p) < 0.5).map(|p| p.removed_fraction);
+    Ok(RobustnessReport {
+        summary: RobustnessSummary {
+            stations: n,
+            removals,
+            trials: opts.trials,
+            random_index: index(|p| p.random_giant),
+            targeted_index: index(|p| p.targeted_giant),
+            random_half: half(|p| p.random_giant),
+            targeted_half: half(|p| p.targeted_giant),
+        },
+        points,
+    })
+}
+
+// Measures the intact network, then removes up to `removals` stations in the order `next` picks them from what is left
+fn curve(graph: &TransitGraph, n: usize, removals: usize, mut next: impl FnMut(&TransitGraph) -> Option<Station>) -> Result<Vec<Measurement>> {
+    let mut removed: HashSet<Station> = HashSet::new();
+    let mut remaining = graph.retain_segments(|_, _| true);
+    let mut points = vec![measure(&remaining, n, None)?];
+    for _ in 0..removals {
+        let Some(station) = next(&remaining) else { break };
+        removed.insert(station.clone());
+        remaining = graph.retain_segments(|from, to| !removed.contains(from) && !removed.contains(to));
+        points.push(measure(&remaining, n, Some(station))?);
+    }
+    // An attack that runs out of stations has left nothing to measure
+    while points.len() <= removals {
+        points.push((None, 0.0, None));
+    }
+    Ok(points)
+}
+
+fn measure(graph: &TransitGraph, n: usize, station: Option<Station>) -> Result<Measurement> {
+    let matrix = graph.distance_matrix()?;
+    let (mut total, mut pairs) = (0.0, 0usize);
+    for i in 0..matrix.len() {
+        for (j, &delay) in matrix.row(i as StationIndex).iter().enumerate() {
+            if j != i && !delay.is_nan() {
+                total += delay;
+                pairs += 1;
+            }
+        }
+    }
+    Ok((station, giant_component(graph) as f32 / n as f32, (pairs > 0).then(|| total / pairs as f32)))
+}
@@ -0,0 +1,141 @@
+// Per-route delay statistics for browsing every aggregated (from, to) segment
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::graph::{Graph, TransitGraph, Station, StationId};
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+use crate::stats::percentile;
+use crate::textplot::sparkline;
+
+// One row of the route list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteSummary {
+    pub from: Station,
+    pub to: Station,
+    pub trips: usize,
+    pub mean_delay: f32,   // Average delay in minutes
+    pub median_delay: f32,
+    pub p90_delay: f32,    // 90th percentile delay: how bad the worst tenth of trips get
+    pub on_time_rate: f32, // Share of trips at or below the on-time threshold
+}
+
+// Column the route list is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RouteColumn {
+    /// Origin, then destination name
+    Route,
+    /// Number of trips
+    Trips,
+    /// Average delay
+    #[default]
+    MeanDelay,
+    /// Median delay
+    MedianDelay,
+    /// 90th percentile delay
+    P90,
+    /// On-time rate
+    OnTime,
+}
+
+// Summarizes every route with at least `min_trips` trips, sorted by origin then destination
+pub fn route_summaries(graph: &TransitGraph, opts: &RankOptions) -> Vec<RouteSummary> {
+    let mut summaries: Vec<RouteSummary> = graph.stations().flat_map(|from| outbound_summaries(graph, from, opts)).collect();
+    summaries.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    summaries
+}
+
+// Summaries of the routes leaving and arriving at one station with at least `min_trips` trips, each sorted by route
+// Output: (outbound, inbound); the same rows `route_summaries` has for the station, without summarizing the rest
+pub fn station_routes(graph: &TransitGraph, station: &Station, opts: &RankOptions) -> (Vec<RouteSummary>, Vec<RouteSummary>) {
+    let outbound = outbound_summaries(graph, station, opts);
+    let mut inbound = graph.stations()
+        .filter_map(|from| {
+            let mut delays: Vec<f32> = graph.neighbors(from).filter(|(to, _)| *to == station).map(|(_, d)| d).collect();
+            delays.sort_by(f32::total_cmp);
+            summarize(from, station, &delays, opts)
+        })
+        .collect::<Vec<_>>();
+    inbound.sort_by(|a, b| a.from.cmp(&b.from));
+    (outbound, inbound)
+}
+
+// Summaries of the routes from one station, by destination
+fn outbound_summaries(graph: &TransitGraph, from: &Station, opts: &RankOptions) -> Vec<RouteSummary> {
+    let mut by_destination: Vec<(&Station, f32)> = graph.neighbors(from).collect();
+    by_destination.sort_by(|a, b| a.0.cmp(b.0).then(a.1.total_cmp(&b.1)));
+    by_destination.chunk_by(|a, b| a.0 == b.0)
+        .filter_map(|group| {
+            let delays: Vec<f32> = group.iter().map(|(_, d)| *d).collect(); // Ascending within the group
+            summarize(from, group[0].0, &delays, opts)
+        })
+        .collect()
+}
+
+// One route's summary from its delays in ascending order, or None with fewer than `min_trips`
+fn summarize(from: &Station, to: &Station, delays: &[f32], opts: &RankOptions) -> Option<RouteSummary> {
+    let trips = delays.len();
+    if trips < opts.min_trips || trips == 0 {
+        return None;
+    }
+    Some(RouteSummary {
+        from: from.clone(),
+        to: to.clone(),
+        trips,
+        mean_delay: delays.iter().sum::<f32>() / trips as f32,
+        median_delay: percentile(delays, 0.5).unwrap_or(0.0),
+        p90_delay: percentile(delays, 0.9).unwrap_or(0.0),
+        on_time_rate: delays.iter().filter(|&&d| d <= opts.on_time_threshold).count() as f32 / trips as f32,
+    })
+}
+
+// Sorts summaries by a column; ties keep route order
+pub fn sort_routes(summaries: &mut [RouteSummary], column: RouteColumn, descending: bool) {
+    summaries.sort_by(|a, b| {
+        let ordering = match column {
+            RouteColumn::Route => (&a.from, &a.to).cmp(&(&b.from, &b.to)),
+            RouteColumn::Trips => a.trips.cmp(&b.trips),
+            RouteColumn::MeanDelay => a.mean_delay.total_cmp(&b.mean_delay),
+            RouteColumn::MedianDelay => a.median_delay.total_cmp(&b.median_delay),
+            RouteColumn::P90 => a.p90_delay.total_cmp(&b.p90_delay),
+            RouteColumn::OnTime => a.on_time_rate.total_cmp(&b.on_time_rate),
+        };
+        let ordering = if descending { ordering.reverse() } else { ordering };
+        if ordering == Ordering::Equal { (&a.from, &a.to).cmp(&(&b.from, &b.to)) } else { ordering }
+    });
+}
+
+// A route row with a sparkline of its monthly average delay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteTrend {
+    #[serde(flatten)]
+    pub summary: RouteSummary,
+    pub trend: String, // One character per month in the dataset; blank where the route had no trips
+}
+
+// Attaches a monthly delay sparkline to each summary
+// Output: the rows in the same order, and the months the sparklines span
+// Logic: every sparkline covers the same months (all months in the records) so columns line up across routes
+pub fn route_trends(records: &[TrainRecord], summaries: &[RouteSummary]) -> (Vec<RouteTrend>, Vec<String>) {
+    let mut totals: HashMap<(StationId, StationId, &str), (usize, f32)> = HashMap::new(); // (from, to, month) -> (trips, total delay)
+    let mut months: BTreeSet<&str> = BTreeSet::new();
+    for r in records {
+        let Some(delay) = r.delay() else { continue };
+        let Some(month) = r.date.get(..7) else { continue };
+        months.insert(month);
+        let entry = totals.entry((r.from_station_id(), r.to_station_id(), month)).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += delay;
+    }
+    let rows = summaries.iter()
+        .map(|summary| {
+            let series: Vec<Option<f32>> = months.iter()
+                .map(|m| totals.get(&(summary.from.id.clone(), summary.to.id.clone(), *m)).map(|(trips, total)| total / *trips as f32))
+                .collect();
+            RouteTrend { summary: summary.clone(), trend: sparkline(&series) }
+        })
+        .collect();
+    (rows, months.into_iter().map(String::from).collect())
+}
@@ -0,0 +1,325 @@
+// Routing queries beyond the single-criterion shortest path: k alternatives, avoided stations, via constraints, and Pareto fronts over delay, hops, and transfers
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Reverse;
+use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
+use crate::graph::{Graph, TransitGraph, Station};
+
+// One non-dominated journey between two stations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParetoPath {
+    pub delay: f32,           // Sum of mean segment delays, in minutes
+    pub hops: usize,          // Number of segments travelled
+    pub transfers: usize,     // Number of line changes along the way
+    pub stations: Vec<Station>, // Stations from start to end
+    pub lines: Vec<String>,   // Line ridden on each segment
+}
+
+// One leg of a path with the delay it contributes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathSegment {
+    pub from: Station,
+    pub to: Station,
+    pub delay: f32,      // Least observed delay on this segment, in minutes
+    pub cumulative: f32, // Total delay from the start through this segment
+}
+
+// A station within an isochrone's delay budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reachable {
+    pub station: Station,
+    pub delay: f32,                 // Least total delay from the origin, in minutes
+    pub hops: usize,                // Segments on that least-delay path
+    pub previous: Option<Station>,  // Station before this one on that path; None for the origin
+}
+
+// Search label: a partial journey ending at `station`, reached on `line`
+struct Label {
+    station: Station,
+    line: Option<String>,
+    delay: f32,
+    hops: usize,
+    transfers: usize,
+    parent: Option<usize>, // Index of the previous label in the arena
+}
+
+impl Label {
+    // True if this label is at least as good as `other` on every criterion
+    fn dominates(&self, other: &Label) -> bool {
+        self.delay <= other.delay && self.hops <= other.hops && self.transfers <= other.transfers
+    }
+}
+
+impl TransitGraph {
+    // Mean observed delay for every (from, to) segment, as an adjacency map
+    pub fn mean_delay_adjacency(&self) -> HashMap<Station, Vec<(Station, f32)>> {
+        let mut adjacency: HashMap<Station, Vec<(Station, f32)>> = HashMap::new();
+        for ((from, to), avg, _count) in self.get_route_average_delays() {
+            adjacency.entry(from).or_default().push((to, avg));
+        }
+        adjacency
+    }
+
+    // Least observed delay on the direct segment from -> to, if one exists
+    pub fn segment_delay(&self, from: &Station, to: &Station) -> Option<f32> {
+        Graph::weight(self, from, to)
+    }
+
+    // Breaks a station sequence into segments with per-segment and running delay
+    pub fn path_segments(&self, path: &[Station]) -> Vec<PathSegment> {
+        let mut cumulative = 0.0;
+        path.windows(2)
+            .map(|w| {
+                let delay = self.segment_delay(&w[0], &w[1]).unwrap_or(f32::NAN);
+                cumulative += delay;
+                PathSegment { from: w[0].clone(), to: w[1].clone(), delay, cumulative }
+            })
+            .collect()
+    }
+
+    // Shortest path (by total delay) that never visits a station in `avoid` or uses an edge in `banned`
+    // Output: same shape as `shortest_path`; None if start/end are avoided or unreachable
+    pub fn shortest_path_avoiding(
+        &self,
+        start: &Station,
+        end: &Station,
+        avoid: &HashSet<Station>,
+        banned: &HashSet<(Station, Station)>,
+    ) -> Option<(f32, Vec<Station>)> {
+        if avoid.contains(start) || avoid.contains(end) {
+            return None;
+        }
+        let mut distances: HashMap<Station, f32> = HashMap::new();
+        let mut previous: HashMap<Station, Station> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((NotNan::<f32>::default(), start.clone())));
+        distances.insert(start.clone(), 0.0);
+        while let Some(Reverse((wrapped_dist, station))) = heap.pop() {
+            let dist = wrapped_dist.into_inner();
+            if &station == end {
+                let mut path = vec![end.clone()];
+                let mut current = end;
+                while let Some(prev) = previous.get(current) {
+                    path.push(prev.clone());
+                    current = prev;
+                }
+                path.reverse();
+                return Some((dist, path));
+            }
+            if distances.get(&station).is_some_and(|&d| dist > d) {
+                continue; // Stale heap entry
+            }
+            for (neighbor, weight) in self.neighbors(&station) {
+                if avoid.contains(neighbor) || banned.contains(&(station.clone(), neighbor.clone())) {
+                    continue;
+                }
+                let new_dist = dist + weight;
+                let Ok(wrapped) = NotNan::new(new_dist) else { continue };
+                if distances.get(neighbor).is_none_or(|&d| new_dist < d) {
+                    distances.insert(neighbor.clone(), new_dist);
+                    previous.insert(neighbor.clone(), station.clone());
+                    heap.push(Reverse((wrapped, neighbor.clone())));
+                }
+            }
+        }
+        None
+    }
+
+    // Computes up to k loopless least-delay paths from start to end, skipping stations in `avoid`
+    // Output: paths in ascending order of total delay
+    // Logic: Yen's algorithm — each new path deviates from a previous one at a spur node,
+    // with the previous paths' next edges banned and the root path's stations avoided
+    pub fn k_shortest_paths(&self, start: &Station, end: &Station, k: usize, avoid: &HashSet<Station>) -> Vec<(f32, Vec<Station>)> {
+        let mut found: Vec<(f32, Vec<Station>)> = Vec::new();
+        let Some(first) = self.shortest_path_avoiding(start, end, avoid, &HashSet::new()) else {
+            return found;
+        };
+        found.push(first);
+        let mut candidates: Vec<(f32, Vec<Station>)> = Vec::new();
+        while found.len() < k {
+            let last = found[found.len() - 1].1.clone();
+            for i in 0..last.len() - 1 {
+                let spur = &last[i];
+                let root = &last[..=i];
+                // Ban the next edge of every known path sharing this root
+                let banned: HashSet<(Station, Station)> = found.iter()
+                    .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                    .map(|(_, p)| (p[i].clone(), p[i + 1].clone()))
+                    .collect();
+                let mut blocked = avoid.clone();
+                blocked.extend(root[..i].iter().cloned());
+                if let Some((_, spur_path)) = self.shortest_path_avoiding(spur, end, &blocked, &banned) {
+                    let mut path = root[..i].to_vec();
+                    path.extend(spur_path);
+                    let delay = self.path_segments(&path).iter().map(|s| s.delay).sum();
+                    if !candidates.iter().any(|(_, p)| p == &path) && !found.iter().any(|(_, p)| p == &path) {
+                        candidates.push((delay, path));
+                    }
+                }
+            }
+            if candidates.is_empty() {
+                break;
+            }
+            // Promote the cheapest candidate (ties broken by fewer stops)
+            let best = (0..candidates.len())
+                .min_by(|&a, &b| candidates[a].0.total_cmp(&candidates[b].0).then(candidates[a].1.len().cmp(&candidates[b].1.len())))
+                .unwrap();
+            found.push(candidates.swap_remove(best));
+        }
+        found
+    }
+
+    // Computes the Pareto front of journeys from start to end over (expected delay, hops, transfers)
+    // Input: station names and the maximum number of segments a journey may use
+    // Output: non-dominated paths sorted by delay, then hops, then transfers; empty if unreachable
+    // Logic: Multi-label Dijkstra where each (station, arrival line) keeps its own set of non-dominated labels
+    pub fn pareto_paths(&self, start: &Station, end: &Station, max_hops: usize) -> Vec<ParetoPath> {
+        let adjacency = self.mean_delay_adjacency();
+        let mut arena: Vec<Label> = vec![Label { station: start.clone(), line: None, delay: 0.0, hops: 0, transfers: 0, parent: None }];
+        let mut alive: Vec<bool> = vec![true];
+        let mut bags: HashMap<(Station, Option<String>), Vec<usize>> = HashMap::new();
+        bags.insert((start.clone(), None), vec![0]);
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((NotNan::<f32>::default(), 0usize, 0usize)));
+
+        while let Some(Reverse((_, _, id))) = heap.pop() {
+            if !alive[id] || &arena[id].station == end || arena[id].hops >= max_hops {
+                continue;
+            }
+            let Some(neighbors) = adjacency.get(&arena[id].station) else { continue };
+            for (next, weight) in neighbors {
+                if next == &arena[id].station || visits(&arena, id, next) {
+                    continue; // Skip self-loops and cycles
+                }
+                let Ok(delay) = NotNan::new(arena[id].delay + weight) else { continue };
+                let lines = self.lines.get(&(arena[id].station.clone(), next.clone()));
+                for line in lines.into_iter().flatten() {
+                    let changed = arena[id].line.as_ref().is_some_and(|l| l != line);
+                    let label = Label {
+                        station: next.clone(),
+                        line: Some(line.clone()),
+                        delay: delay.into_inner(),
+                        hops: arena[id].hops + 1,
+                        transfers: arena[id].transfers + changed as usize,
+                        parent: Some(id),
+                    };
+                    let bag = bags.entry((next.clone(), label.line.clone())).or_default();
+                    if bag.iter().any(|&other| arena[other].dominates(&label)) {
+                        continue;
+                    }
+                    // Retire labels the new one dominates
+                    bag.retain(|&other| {
+                        let keep = !label.dominates(&arena[other]);
+                        if !keep {
+                            alive[other] = false;
+                        }
+                        keep
+                    });
+                    let new_id = arena.len();
+                    bag.push(new_id);
+                    heap.push(Reverse((delay, label.hops, new_id)));
+                    arena.push(label);
+                    alive.push(true);
+                }
+            }
+        }
+
+        // Merge the destination's bags across arrival lines and keep only globally non-dominated labels
+        let finals: Vec<usize> = (0..arena.len()).filter(|&i| alive[i] && &arena[i].station == end && i != 0).collect();
+        let mut front: Vec<usize> = finals.iter().copied()
+            .filter(|&i| !finals.iter().any(|&j| j != i && arena[j].dominates(&arena[i]) && !arena[i].dominates(&arena[j])))
+            .collect();
+        front.sort_by(|&a, &b| {
+            arena[a].delay.total_cmp(&arena[b].delay)
+                .then(arena[a].hops.cmp(&arena[b].hops))
+                .then(arena[a].transfers.cmp(&arena[b].transfers))
+        });
+        // Equal-cost labels reached on different lines describe the same trade-off; keep the first
+        front.dedup_by(|b, a| arena[*a].delay == arena[*b].delay && arena[*a].hops == arena[*b].hops && arena[*a].transfers == arena[*b].transfers);
+        front.into_iter().map(|id| reconstruct(&arena, id)).collect()
+    }
+
+    // Computes the shortest path (by total delay) from start to end that passes through `via`
+    // Output: same shape as `shortest_path`; None if either leg is unreachable
+    // Logic: Compose start → via and via → end searches, emitting the shared via station only once
+    // If the second leg backtracks over the first (A → X → V, then V → X → C), the walk is returned as travelled, with
+    // X twice and both X ↔ V segments in the delay: each leg is a simple path, so any loop runs through `via`, and
+    // splicing it out would drop the station the query has to pass
+    pub fn shortest_path_via(&self, start: &Station, via: &Station, end: &Station) -> Option<(f32, Vec<Station>)> {
+        if via == start || via == end {
+            return self.shortest_path(start, end);
+        }
+        let (first_delay, mut path) = self.shortest_path(start, via)?;
+        let (second_delay, second) = self.shortest_path(via, end)?;
+        path.extend(second.into_iter().skip(1)); // The second leg begins at `via`, already the last stop
+        Some((first_delay + second_delay, path))
+    }
+
+    // Finds every station whose least-delay path from `origin` accumulates at most `budget` minutes
+    // Output: the origin first, then stations by ascending delay (ties by name); the `previous` links form a shortest-path tree
+    // Logic: Dijkstra from the origin, as in `shortest_path_avoiding`, stopping once the frontier passes the budget
+    pub fn delay_isochrone(&self, origin: &Station, budget: f32) -> Vec<Reachable> {
+        let mut best: HashMap<Station, (f32, usize, Option<Station>)> = HashMap::new(); // Station -> (delay, hops, previous)
+        let mut settled: HashSet<Station> = HashSet::new();
+        let mut heap = BinaryHeap::new();
+        best.insert(origin.clone(), (0.0, 0, None));
+        heap.push(Reverse((NotNan::<f32>::default(), origin.clone())));
+        while let Some(Reverse((wrapped_dist, station))) = heap.pop() {
+            let dist = wrapped_dist.into_inner();
+            if dist > budget {
+                break;
+            }
+            if best.get(&station).is_some_and(|&(d, _, _)| dist > d) || !settled.insert(station.clone()) {
+                continue; // Stale heap entry
+            }
+            let hops = best[&station].1;
+            for (neighbor, weight) in self.neighbors(&station) {
+                let new_dist = dist + weight;
+                let Ok(wrapped) = NotNan::new(new_dist) else { continue };
+                if best.get(neighbor).is_none_or(|&(d, _, _)| new_dist < d) {
+                    best.insert(neighbor.clone(), (new_dist, hops + 1, Some(station.clone())));
+                    heap.push(Reverse((wrapped, neighbor.clone())));
+                }
+            }
+        }
+        let mut reachable: Vec<Reachable> = settled.into_iter()
+            .map(|station| {
+                let (delay, hops, previous) = best.remove(&station).unwrap_or((0.0, 0, None));
+                Reachable { station, delay, hops, previous }
+            })
+            .collect();
+        reachable.sort_by(|a, b| a.previous.is_some().cmp(&b.previous.is_some()).then(a.delay.total_cmp(&b.delay)).then(a.station.cmp(&b.station)));
+        reachable
+    }
+}
+
+// Returns true if the partial journey ending at label `id` already visited `station`
+fn visits(arena: &[Label], id: usize, station: &Station) -> bool {
+    let mut current = Some(id);
+    while let Some(i) = current {
+        if &arena[i].station == station {
+            return true;
+        }
+        current = arena[i].parent;
+    }
+    false
+}
+
+// Walks parent links back to the start to produce the journey for a label
+fn reconstruct(arena: &[Label], id: usize) -> ParetoPath {
+    let mut stations = Vec::new();
+    let mut lines = Vec::new();
+    let mut current = Some(id);
+    while let Some(i) = current {
+        stations.push(arena[i].station.clone());
+        if let Some(line) = &arena[i].line {
+            lines.push(line.clone());
+        }
+        current = arena[i].parent;
+    }
+    stations.reverse();
+    lines.reverse();
+    let label = &arena[id];
+    ParetoPath { delay: label.delay, hops: label.hops, transfers: label.transfers, stations, lines }
+}
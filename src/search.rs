@@ -0,0 +1,90 @@
+// Station name lookup: case-insensitive substring matching with an edit-distance fallback for typos
+
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::graph::{TransitGraph, Station};
+
+// Minimum edit-distance similarity (0-1) for a fuzzy match to be reported
+pub const FUZZY_THRESHOLD: f32 = 0.6;
+
+// One station matching a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationMatch {
+    pub station: Station,
+    pub kind: String, // "exact", "prefix", "substring" or "fuzzy"
+    pub score: f32,   // 1.0 for an exact match; higher is better
+}
+
+impl TransitGraph {
+    // Finds stations whose name matches the query
+    // Input: free-text query and the maximum number of matches to return
+    // Output: matches sorted by score, best first; ties broken by name
+    // Logic: exact, prefix and substring matches rank above fuzzy ones; within a kind, names closer in length to the query rank higher
+    pub fn find_stations(&self, query: &str, limit: usize) -> Vec<StationMatch> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<StationMatch> = self.all_stations().into_iter()
+            .filter_map(|station| {
+                let (kind, score) = match_score(&query, &station.name.to_lowercase())?;
+                Some(StationMatch { station, kind: kind.to_string(), score })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.station.cmp(&b.station)));
+        matches.truncate(limit);
+        matches
+    }
+
+    // Returns the station with this exact name or stop ID, otherwise an unknown-station error with the closest names as suggestions
+    pub fn resolve_station(&self, name: &str) -> Result<Station> {
+        let trimmed = name.trim();
+        if let Some(station) = self.by_id.values().filter(|s| s.name == trimmed).min().or_else(|| self.station(trimmed)) {
+            return Ok(station.clone());
+        }
+        let suggestions = self.find_stations(name, 3).into_iter().map(|m| m.station).collect();
+        Err(Error::UnknownStation { station: name.to_string(), suggestions })
+    }
+}
+
+// Scores a lowercase name against a lowercase query, or None if it does not match
+fn match_score(query: &str, name: &str) -> Option<(&'static str, f32)> {
+    let coverage = query.chars().count() as f32 / name.chars().count().max(1) as f32;
+    if name == query {
+        return Some(("exact", 1.0));
+    }
+    if name.starts_with(query) {
+        return Some(("prefix", 0.9 + 0.09 * coverage));
+    }
+    if name.contains(query) {
+        return Some(("substring", 0.8 + 0.09 * coverage));
+    }
+    // Compare against the whole name and every run of words as long as the query
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let span = query.split_whitespace().count().clamp(1, words.len().max(1));
+    let best = words.windows(span)
+        .map(|w| similarity(query, &w.join(" ")))
+        .fold(similarity(query, name), f32::max);
+    (best >= FUZZY_THRESHOLD).then_some(("fuzzy", 0.75 * best))
+}
+
+// Levenshtein similarity: 1 - distance / length of the longer string
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut diagonal = row[0]; // Distance for (i - 1, j - 1)
+        row[0] = i;
+        for j in 1..=b.len() {
+            let substitution = diagonal + (a[i - 1] != b[j - 1]) as usize;
+            diagonal = row[j];
+            row[j] = substitution.min(row[j] + 1).min(row[j - 1] + 1);
+        }
+    }
+    1.0 - row[b.len()] as f32 / longest as f32
+}
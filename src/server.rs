@@ -0,0 +1,429 @@
+// HTTP JSON API over one loaded dataset (`nj-delays serve`): the records and graph are loaded once and shared until a
+// reload swaps in a new dataset, and each request runs its computation on the blocking pool, answering with the same
+// rows `-o json` prints
+// Failures answer with the CLI's JSON error object and an HTTP status per failure class
+// With `serve --watch`, /live pushes each update of the watched directory to connected dashboards over a WebSocket
+// /metrics reports per-line delays, data freshness and request latencies for Prometheus to scrape
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use arc_swap::ArcSwap;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{MatchedPath, Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::http::HeaderMap;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+#[cfg(feature = "graphql")]
+use crate::graphql;
+use crate::cli::{ranking_section, CliError, Ranking, Section};
+use crate::graph::{Station, TransitGraph};
+use crate::export::{prometheus_label, to_prometheus, ReportMetadata};
+use crate::live::LiveUpdate;
+use crate::load::{RecordFilter, TrainRecord};
+use crate::metrics::{RankOptions, RouteStat};
+use crate::nonblocking::spawn_blocking;
+use crate::routing::PathSegment;
+use crate::stations::{station_summaries, StationSummary};
+use crate::search::StationMatch;
+
+// One loaded dataset: the filtered records, their graph, and the ranking options given on the command line
+// Immutable once built; a reload builds a new one and swaps it in
+pub struct Dataset {
+    pub records: Vec<TrainRecord>,
+    pub graph: TransitGraph,
+    pub opts: RankOptions,
+    pub generation: u64, // 0 for the dataset loaded at startup, one more per reload
+    pub loaded_at: DateTime<Local>,
+    pub newest_record: Option<DateTime<Local>>, // Latest actual time among the records, for the ingestion lag
+}
+
+impl Dataset {
+    pub fn new(records: Vec<TrainRecord>, graph: TransitGraph, opts: RankOptions, generation: u64) -> Self {
+        let newest_record = records.iter()
+            .filter_map(|r| NaiveDateTime::parse_from_str(&r.actual_time, "%Y-%m-%d %H:%M:%S").ok())
+            .max()
+            .and_then(|t| Local.from_local_datetime(&t).earliest());
+        Self { records, graph, opts, generation, loaded_at: Local::now(), newest_record }
+    }
+}
+
+// Loads a fresh dataset for a reload, e.g. re-reading --data with the command line's filter
+pub type Loader = Box<dyn Fn() -> Result<(Vec<TrainRecord>, TransitGraph), CliError> + Send + Sync>;
+
+// What every request reads
+// Logic: requests take an Arc of the current dataset when they start and keep it until they finish, so a reload
+// swaps the pointer without blocking or changing queries in flight; the old dataset is freed with its last request
+pub struct ServerState {
+    dataset: ArcSwap<Dataset>,
+    loader: Option<Loader>,
+    reloading: Mutex<()>,                             // Held while a reload loads, so reloads do not overlap
+    admin_token: Option<String>,                      // Bearer token /admin routes require, if set
+    pub live: watch::Sender<Option<Arc<LiveUpdate>>>, // Latest live update; None until one is published
+    requests: Mutex<BTreeMap<String, RouteRequests>>, // By route pattern, e.g. /rankings/{ranking}
+}
+
+// Upper bounds in seconds of the /metrics request latency buckets, below the implied +Inf
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// Answered requests of one route, for /metrics
+#[derive(Default)]
+struct RouteRequests {
+    buckets: [u64; LATENCY_BUCKETS.len()], // Requests per latency bucket, not cumulative; slower ones are only in `count`
+    seconds: f64,                           // Total latency
+    count: u64,
+    statuses: BTreeMap<u16, u64>,           // Responses by HTTP status
+}
+
+// Outcome of a reload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadReport {
+    pub generation: u64,
+    pub records: usize,
+    pub stations: usize,
+}
+
+impl ServerState {
+    pub fn new(records: Vec<TrainRecord>, graph: TransitGraph, opts: RankOptions) -> Self {
+        Self {
+            dataset: ArcSwap::from_pointee(Dataset::new(records, graph, opts, 0)),
+            loader: None,
+            reloading: Mutex::new(()),
+            admin_token: None,
+            live: watch::Sender::new(None),
+            requests: Mutex::default(),
+        }
+    }
+
+    // Enables reloading (POST /admin/reload, or SIGHUP in the serve command) with `loader`
+    pub fn with_loader(mut self, loader: Loader) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    // Requires `Authorization: Bearer <token>` on /admin routes
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    // The current dataset; holding it keeps it alive across a reload
+    pub fn dataset(&self) -> Arc<Dataset> {
+        self.dataset.load_full()
+    }
+
+    // Replaces the dataset for every request that starts after this; requests in flight finish on the old one
+    pub fn swap(&self, records: Vec<TrainRecord>, graph: TransitGraph) -> ReloadReport {
+        let current = self.dataset.load();
+        let dataset = Dataset::new(records, graph, current.opts, current.generation + 1);
+        let report = ReloadReport { generation: dataset.generation, records: dataset.records.len(), stations: dataset.graph.stations().count() };
+        self.dataset.store(Arc::new(dataset));
+        report
+    }
+
+    // Loads a fresh dataset with the loader and swaps it in; blocking, so async callers run it on the blocking pool
+    // Output: BadInput if no loader is set; the loader's error if loading fails, in which case the dataset is kept
+    pub fn reload(&self) -> Result<ReloadReport, CliError> {
+        let loader = self.loader.as_ref().ok_or_else(|| CliError::BadInput("reloading is not enabled on this server".into()))?;
+        let _reloading = self.reloading.lock().unwrap_or_else(|e| e.into_inner());
+        let (records, graph) = loader()?;
+        Ok(self.swap(records, graph))
+    }
+
+    // Sends a live update to every connected /live client
+    pub fn publish(&self, update: LiveUpdate) {
+        self.live.send_replace(Some(Arc::new(update)));
+    }
+
+    // Counts one answered request of `route` for /metrics
+    pub fn record_request(&self, route: &str, status: StatusCode, latency: Duration) {
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = requests.entry(route.to_string()).or_default();
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            entry.buckets[bucket] += 1;
+        }
+        entry.seconds += seconds;
+        entry.count += 1;
+        *entry.statuses.entry(status.as_u16()).or_default() += 1;
+    }
+
+    // Writes the /metrics page in the Prometheus text format: the `prometheus` export of the served dataset, then the
+    // server's own families (dataset generation and freshness, the latest live update's per-line delays if any, and
+    // the requests answered so far by route pattern)
+    // Logic: blocking, since the export aggregates every record; the handler runs it on the blocking pool
+    pub fn write_metrics<W: Write>(&self, data: &Dataset, mut out: W) -> io::Result<()> {
+        let metadata = ReportMetadata::new("", &RecordFilter::default(), &data.opts, &data.records);
+        to_prometheus(&data.records, &metadata, &mut out)?;
+        let seconds = |t: DateTime<Local>| t.timestamp_millis() as f64 / 1000.0;
+        let mut gauge = |name: &str, help: &str, value: f64| -> io::Result<()> {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} gauge", name)?;
+            writeln!(out, "{} {}", name, value)
+        };
+        gauge("njtransit_dataset_generation", "Reloads of the served dataset since the server started", data.generation as f64)?;
+        gauge("njtransit_dataset_loaded_timestamp_seconds", "When the served dataset was loaded", seconds(data.loaded_at))?;
+        if let Some(newest) = data.newest_record {
+            let lag = (Local::now() - newest).num_milliseconds() as f64 / 1000.0;
+            gauge("njtransit_ingestion_lag_seconds", "Seconds since the latest actual time in the served dataset", lag)?;
+        }
+        let live = self.live.borrow().clone();
+        if let Some(update) = &live {
+            gauge("njtransit_live_update_timestamp_seconds", "When the watched directory last changed", seconds(update.at))?;
+            let families = [
+                ("njtransit_live_line_average_delay_minutes", "Mean delay of trips on the line in the watched directory, in minutes"),
+                ("njtransit_live_line_on_time_ratio", "Share of trips on the line in the watched directory at or below the on-time threshold"),
+                ("njtransit_live_line_trips", "Trips on the line in the watched directory with a recorded delay"),
+            ];
+            for (i, (name, help)) in families.iter().enumerate() {
+                writeln!(out, "# HELP {} {}", name, help)?;
+                writeln!(out, "# TYPE {} gauge", name)?;
+                for line in &update.lines {
+                    let value = [line.mean_delay, line.on_time_rate, line.trips as f32][i];
+                    writeln!(out, "{}{{line=\"{}\"}} {}", name, prometheus_label(&line.line), value)?;
+                }
+            }
+        }
+        let requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(out, "# HELP njtransit_http_requests_total Requests answered, by route and HTTP status")?;
+        writeln!(out, "# TYPE njtransit_http_requests_total counter")?;
+        for (route, answered) in requests.iter() {
+            for (status, count) in &answered.statuses {
+                writeln!(out, "njtransit_http_requests_total{{route=\"{}\",status=\"{}\"}} {}", prometheus_label(route), status, count)?;
+            }
+        }
+        writeln!(out, "# HELP njtransit_http_request_duration_seconds Seconds to answer a request, by route")?;
+        writeln!(out, "# TYPE njtransit_http_request_duration_seconds histogram")?;
+        for (route, answered) in requests.iter() {
+            let name = "njtransit_http_request_duration_seconds";
+            let route = prometheus_label(route);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&answered.buckets) {
+                cumulative += count;
+                writeln!(out, "{}_bucket{{route=\"{}\",le=\"{}\"}} {}", name, route, bound, cumulative)?;
+            }
+            writeln!(out, "{}_bucket{{route=\"{}\",le=\"+Inf\"}} {}", name, route, answered.count)?;
+            writeln!(out, "{}_sum{{route=\"{}\"}} {}", name, route, answered.seconds)?;
+            writeln!(out, "{}_count{{route=\"{}\"}} {}", name, route, answered.count)?;
+        }
+        out.flush()
+    }
+}
+
+// Stations listed by /stations: every station's summary, or the matches of ?find=
+#[derive(Serialize)]
+#[serde(untagged)]
+enum StationList {
+    All(Vec<StationSummary>),
+    Matches(Vec<StationMatch>),
+}
+
+// One least-delay path of /path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathResult {
+    pub delay: f32,             // Total delay in minutes
+    pub stations: Vec<Station>, // Origin to destination
+    pub segments: Vec<PathSegment>,
+}
+
+#[derive(Deserialize)]
+struct TopQuery {
+    top: Option<usize>, // Overrides --top
+}
+
+#[derive(Deserialize)]
+struct StationsQuery {
+    find: Option<String>,
+    top: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct PathQuery {
+    from: String,
+    to: String,
+    k: Option<usize>, // Alternative loopless paths to list; 1 if omitted
+}
+
+// The routes of the API:
+// GET /stations[?find=NAME&top=N]    station summaries, or fuzzy matches for a name
+// GET /path?from=A&to=B[&k=N]        least-delay paths with per-segment delays
+// GET /rankings/{ranking}[?top=N]    any `rank` ranking (closeness, betweenness, worst-routes, ...) as {title, rows}
+// GET /routes/worst, /routes/best    routes by highest or lowest average delay
+// GET /live (WebSocket)              the latest live update on connect, then each one as it is published
+// POST /admin/reload                 reloads the dataset (see `ServerState::reload`); needs the admin token if set
+// POST /graphql, GET /graphql        with the `graphql` feature: GraphQL queries, and the schema as SDL
+// GET /metrics                       Prometheus metrics (see `ServerState::write_metrics`)
+// Every route's requests are timed and counted for /metrics
+pub fn router(state: Arc<ServerState>) -> Router {
+    let router = Router::new()
+        .route("/stations", get(stations))
+        .route("/path", get(path))
+        .route("/rankings/{ranking}", get(ranking))
+        .route("/routes/worst", get(worst_routes))
+        .route("/routes/best", get(best_routes))
+        .route("/live", get(live))
+        .route("/metrics", get(metrics))
+        .route("/admin/reload", post(reload));
+    #[cfg(feature = "graphql")]
+    let router = {
+        let schema = graphql::schema(state.clone());
+        let sdl = schema.sdl();
+        router.route(
+            "/graphql",
+            post(move |Json(request): Json<async_graphql::Request>| async move { Json(schema.execute(request).await) })
+                .get(move || async move { sdl }),
+        )
+    };
+    router.route_layer(middleware::from_fn_with_state(state.clone(), track_request)).with_state(state)
+}
+
+// Times one request and records it under its route pattern
+async fn track_request(State(state): State<Arc<ServerState>>, request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>().map_or_else(|| request.uri().path().to_string(), |p| p.as_str().to_string());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.record_request(&route, response.status(), started.elapsed());
+    response
+}
+
+// Answers requests on `listener` until the process is stopped
+pub async fn serve(listener: TcpListener, state: Arc<ServerState>) -> io::Result<()> {
+    axum::serve(listener, router(state)).await
+}
+
+impl IntoResponse for CliError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            CliError::BadInput(_) => StatusCode::BAD_REQUEST,
+            CliError::UnknownStation { .. } | CliError::NoPath { .. } => StatusCode::NOT_FOUND,
+            CliError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self.to_json())).into_response()
+    }
+}
+
+// Runs a request's computation on the blocking pool against the current dataset
+async fn compute<T, F>(state: &Arc<ServerState>, f: F) -> Result<Json<T>, CliError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Dataset) -> Result<T, CliError> + Send + 'static,
+{
+    let data = state.dataset();
+    spawn_blocking(move || f(&data)).await?.map(Json)
+}
+
+// The command-line options with ?top= applied
+fn options(data: &Dataset, top: Option<usize>) -> RankOptions {
+    RankOptions { top_n: top.unwrap_or(data.opts.top_n), ..data.opts }
+}
+
+async fn stations(State(state): State<Arc<ServerState>>, Query(query): Query<StationsQuery>) -> Result<Json<StationList>, CliError> {
+    compute(&state, move |data| {
+        Ok(match &query.find {
+            Some(name) => StationList::Matches(data.graph.find_stations(name, options(data, query.top).top_n)),
+            None => StationList::All(station_summaries(&data.records, &data.graph)),
+        })
+    })
+    .await
+}
+
+async fn path(State(state): State<Arc<ServerState>>, Query(query): Query<PathQuery>) -> Result<Json<Vec<PathResult>>, CliError> {
+    compute(&state, move |data| {
+        let graph = &data.graph;
+        let (from, to) = (graph.resolve_station(&query.from)?, graph.resolve_station(&query.to)?);
+        let paths = graph.k_shortest_paths(&from, &to, query.k.unwrap_or(1).max(1), &Default::default());
+        if paths.is_empty() {
+            return Err(CliError::NoPath { from: from.name, to: to.name });
+        }
+        Ok(paths.into_iter()
+            .map(|(delay, stations)| PathResult { delay, segments: graph.path_segments(&stations), stations })
+            .collect())
+    })
+    .await
+}
+
+async fn ranking(
+    State(state): State<Arc<ServerState>>,
+    Path(name): Path<String>,
+    Query(query): Query<TopQuery>,
+) -> Result<Json<Section>, CliError> {
+    let ranking = Ranking::from_str(&name, true).map_err(|_| {
+        let names: Vec<String> = Ranking::value_variants().iter()
+            .filter_map(|r| r.to_possible_value().map(|v| v.get_name().to_string()))
+            .collect();
+        CliError::BadInput(format!("unknown ranking {:?}; expected one of {}", name, names.join(", ")))
+    })?;
+    compute(&state, move |data| Ok(ranking_section(ranking, &data.records, &data.graph, &options(data, query.top), None))).await
+}
+
+async fn worst_routes(State(state): State<Arc<ServerState>>, Query(query): Query<TopQuery>) -> Result<Json<Vec<RouteStat>>, CliError> {
+    compute(&state, move |data| Ok(data.graph.rank_routes_by_average_delay(&options(data, query.top)))).await
+}
+
+async fn best_routes(State(state): State<Arc<ServerState>>, Query(query): Query<TopQuery>) -> Result<Json<Vec<RouteStat>>, CliError> {
+    compute(&state, move |data| Ok(data.graph.rank_routes_by_lowest_delay(&options(data, query.top)))).await
+}
+
+async fn reload(State(state): State<Arc<ServerState>>, headers: HeaderMap) -> Result<Json<ReloadReport>, Response> {
+    if let Some(token) = &state.admin_token {
+        let given = headers.get("authorization").and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer "));
+        if given != Some(token.as_str()) {
+            let error = CliError::BadInput("missing or wrong admin token".into());
+            return Err((StatusCode::UNAUTHORIZED, Json(error.to_json())).into_response());
+        }
+    }
+    let reloaded = spawn_blocking(move || state.reload()).await.map_err(CliError::from).and_then(|r| r);
+    reloaded.map(Json).map_err(IntoResponse::into_response)
+}
+
+async fn metrics(State(state): State<Arc<ServerState>>) -> Result<Response, CliError> {
+    let data = state.dataset();
+    let page = spawn_blocking(move || {
+        let mut page = Vec::new();
+        state.write_metrics(&data, &mut page).map(|()| page)
+    })
+    .await?
+    .map_err(|e| CliError::Internal(format!("cannot write metrics: {}", e)))?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")], page).into_response())
+}
+
+async fn live(State(state): State<Arc<ServerState>>, upgrade: WebSocketUpgrade) -> Response {
+    let updates = state.live.subscribe();
+    upgrade.on_upgrade(move |socket| push_updates(socket, updates))
+}
+
+// Sends each live update to one client as a JSON text message until either side closes
+// Logic: a watch channel only keeps the latest update, so a client that falls behind skips to the newest
+async fn push_updates(mut socket: WebSocket, mut updates: watch::Receiver<Option<Arc<LiveUpdate>>>) {
+    loop {
+        let update = updates.borrow_and_update().clone();
+        if let Some(update) = update {
+            let Ok(text) = serde_json::to_string(&*update) else { return };
+            if socket.send(Message::Text(text.into())).await.is_err() {
+                return;
+            }
+        }
+        // Wait for the next update, ignoring anything the client sends other than a close
+        loop {
+            tokio::select! {
+                changed = updates.changed() => match changed {
+                    Ok(()) => break,
+                    Err(_) => return, // The server is shutting down
+                },
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+}
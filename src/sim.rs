@@ -0,0 +1,139 @@
+// Monte Carlo simulation of an itinerary's total delay: each segment's observed delays are kept as a histogram, and
+// every realization draws one delay per segment from its histogram (a bin by its share of trips, then a point
+// uniformly within it) and adds them up
+// Segments are drawn independently, so delays that propagate from one segment to the next are not modelled; the
+// spread of the total is therefore a lower bound when delays cascade along a train's run
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::graph::{join_names, Station, TransitGraph};
+use crate::stats::{histogram, percentile, HistogramBin};
+
+// Realizations per simulation unless set
+pub const DEFAULT_RUNS: usize = 10_000;
+
+// Bin width in minutes of the segment histograms unless set
+pub const DEFAULT_BIN_WIDTH: f32 = 1.0;
+
+// Bin width in minutes of the reported distribution of total delay
+pub const TOTAL_BIN_WIDTH: f32 = 5.0;
+
+// How a simulation runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SimulationOptions {
+    pub runs: usize,
+    pub bin_width: f32, // Of the segment histograms
+    pub threshold: f32, // Total delay in minutes whose exceedance probability is reported
+    pub seed: u64,      // So reruns draw the same realizations
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self { runs: DEFAULT_RUNS, bin_width: DEFAULT_BIN_WIDTH, threshold: 15.0, seed: crate::metrics::DEFAULT_SEED }
+    }
+}
+
+// The delay distribution of one segment, as a histogram of its observed delays
+#[derive(Debug, Clone)]
+pub struct SegmentDistribution {
+    pub from: Station,
+    pub to: Station,
+    pub observations: usize,
+    bins: Vec<HistogramBin>,
+    cumulative: Vec<usize>, // Trips in this bin and every lower one
+}
+
+impl SegmentDistribution {
+    // The histogram of the delays observed from -> to
+    // Output: None if the segment has no observations; an invalid-parameter error for a bin width that is not positive
+    // Logic: the histogram reaches the largest observation, so the overflow bin holds only delays on its lower bound
+    pub fn from_graph(graph: &TransitGraph, from: &Station, to: &Station, bin_width: f32) -> Result<Option<Self>> {
+        let delays: Vec<f32> = graph.observations(from, to).filter(|d| d.is_finite()).collect();
+        let max = delays.iter().copied().reduce(f32::max).unwrap_or_default();
+        let bins = histogram(&delays, bin_width, max)?;
+        if delays.is_empty() {
+            return Ok(None);
+        }
+        let cumulative = bins.iter().scan(0, |total, bin| { *total += bin.count; Some(*total) }).collect();
+        Ok(Some(Self { from: from.clone(), to: to.clone(), observations: delays.len(), bins, cumulative }))
+    }
+
+    // The histogram of the segment's delays
+    pub fn bins(&self) -> &[HistogramBin] {
+        &self.bins
+    }
+
+    // Draws one delay
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        let trip = rng.random_range(0..self.observations);
+        let bin = &self.bins[self.cumulative.partition_point(|&total| total <= trip)];
+        if bin.upper.is_finite() { rng.random_range(bin.lower..bin.upper) } else { bin.lower }
+    }
+}
+
+// The simulated distribution of an itinerary's total delay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub itinerary: String,
+    pub segments: usize,
+    pub runs: usize,
+    pub mean: f32,
+    pub std_dev: f32,
+    pub p50: f32,
+    pub p90: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub min: f32,
+    pub max: f32,
+    pub threshold: f32,
+    pub exceed_probability: f32, // Share of realizations with a total delay above the threshold
+    #[serde(skip)]
+    pub distribution: Vec<HistogramBin>, // Total delay in TOTAL_BIN_WIDTH bins up to the 99th percentile
+}
+
+// Simulates the total delay of travelling the itinerary's stations in order
+// Output: an invalid-parameter error for fewer than two stations, no runs, a bad bin width or threshold, or a pair of
+// consecutive stations with no observed delays between them
+pub fn simulate(graph: &TransitGraph, itinerary: &[Station], opts: &SimulationOptions) -> Result<SimulationReport> {
+    let invalid = |reason: String| Error::InvalidParameter { name: "itinerary", reason };
+    if itinerary.len() < 2 {
+        return Err(invalid("needs at least two stations".into()));
+    }
+    if opts.runs == 0 {
+        return Err(Error::InvalidParameter { name: "runs", reason: "must be at least 1".into() });
+    }
+    if !opts.threshold.is_finite() {
+        return Err(Error::InvalidParameter { name: "threshold", reason: format!("{} is not a finite number of minutes", opts.threshold) });
+    }
+    let segments = itinerary.windows(2)
+        .map(|pair| {
+            SegmentDistribution::from_graph(graph, &pair[0], &pair[1], opts.bin_width)?
+                .ok_or_else(|| invalid(format!("no observed delays from {} to {}", pair[0].name, pair[1].name)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut rng = StdRng::seed_from_u64(opts.seed);
+    let mut totals: Vec<f32> = (0..opts.runs).map(|_| segments.iter().map(|s| s.sample(&mut rng)).sum()).collect();
+    totals.sort_by(f32::total_cmp);
+    let runs = totals.len();
+    let mean = totals.iter().sum::<f32>() / runs as f32;
+    let variance = totals.iter().map(|t| (t - mean).powi(2)).sum::<f32>() / (runs.max(2) - 1) as f32;
+    let quantile = |p: f32| percentile(&totals, p).unwrap_or_default();
+    Ok(SimulationReport {
+        itinerary: join_names(itinerary, " → "),
+        segments: segments.len(),
+        runs,
+        mean,
+        std_dev: variance.sqrt(),
+        p50: quantile(0.5),
+        p90: quantile(0.9),
+        p95: quantile(0.95),
+        p99: quantile(0.99),
+        min: totals[0],
+        max: totals[runs - 1],
+        threshold: opts.threshold,
+        exceed_probability: (runs - totals.partition_point(|&t| t <= opts.threshold)) as f32 / runs as f32,
+        distribution: histogram(&totals, TOTAL_BIN_WIDTH, quantile(0.99))?,
+    })
+}
@@ -0,0 +1,111 @@
+// PostgreSQL history of metrics (--sink postgres://...): each run adds its route and line metrics under the run's
+// timestamp, so repeated runs over new data build up a longitudinal record to query with SQL
+// Tables are created on first use; rows are upserted on (run, route) and (run, line), so writing a run again replaces it
+// Connections are unencrypted (no TLS), for a database on the same host or a trusted network
+
+use chrono::{DateTime, FixedOffset, Local};
+use postgres::{Client, NoTls};
+use serde::{Deserialize, Serialize};
+use crate::export::ReportMetadata;
+use crate::graph::TransitGraph;
+use crate::live::line_summaries;
+use crate::load::TrainRecord;
+use crate::metrics::RankOptions;
+use crate::routes::route_summaries;
+
+// Schema of the metrics history; run_at is the run's generated_at time
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS metric_runs (
+        run_at TIMESTAMPTZ PRIMARY KEY,
+        tool_version TEXT NOT NULL,
+        dataset TEXT NOT NULL,
+        filters JSONB NOT NULL,
+        parameters JSONB NOT NULL,
+        records BIGINT NOT NULL,
+        first_date TEXT,
+        last_date TEXT
+    );
+    CREATE TABLE IF NOT EXISTS route_metrics (
+        run_at TIMESTAMPTZ NOT NULL REFERENCES metric_runs (run_at) ON DELETE CASCADE,
+        from_id TEXT NOT NULL,
+        to_id TEXT NOT NULL,
+        from_station TEXT NOT NULL,
+        to_station TEXT NOT NULL,
+        trips BIGINT NOT NULL,
+        mean_delay REAL NOT NULL,
+        median_delay REAL NOT NULL,
+        p90_delay REAL NOT NULL,
+        on_time_rate REAL NOT NULL,
+        PRIMARY KEY (run_at, from_id, to_id)
+    );
+    CREATE TABLE IF NOT EXISTS line_metrics (
+        run_at TIMESTAMPTZ NOT NULL REFERENCES metric_runs (run_at) ON DELETE CASCADE,
+        line TEXT NOT NULL,
+        trips BIGINT NOT NULL,
+        mean_delay REAL NOT NULL,
+        p90_delay REAL NOT NULL,
+        on_time_rate REAL NOT NULL,
+        PRIMARY KEY (run_at, line)
+    );
+";
+
+// Rows one run wrote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkReport {
+    pub run_at: DateTime<FixedOffset>,
+    pub routes: usize,
+    pub lines: usize,
+}
+
+// Writes the run's metadata, every route with at least one trip, and every line into the database at `url`
+// Input: a postgres:// connection URL, the run's (filtered) records and graph, and its report metadata
+// Logic: one transaction, so a history query never sees a partly written run
+pub fn write_metrics(url: &str, records: &[TrainRecord], graph: &TransitGraph, metadata: &ReportMetadata) -> Result<SinkReport, postgres::Error> {
+    let run_at = DateTime::parse_from_rfc3339(&metadata.generated_at).unwrap_or_else(|_| Local::now().fixed_offset());
+    let json = |value: serde_json::Result<serde_json::Value>| value.unwrap_or_default();
+    let mut client = Client::connect(url, NoTls)?;
+    let mut tx = client.transaction()?;
+    tx.batch_execute(SCHEMA)?;
+    tx.execute(
+        "INSERT INTO metric_runs (run_at, tool_version, dataset, filters, parameters, records, first_date, last_date)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (run_at) DO UPDATE SET tool_version = EXCLUDED.tool_version, dataset = EXCLUDED.dataset,
+             filters = EXCLUDED.filters, parameters = EXCLUDED.parameters, records = EXCLUDED.records,
+             first_date = EXCLUDED.first_date, last_date = EXCLUDED.last_date",
+        &[
+            &run_at,
+            &metadata.tool_version,
+            &metadata.dataset,
+            &json(serde_json::to_value(&metadata.filters)),
+            &json(serde_json::to_value(metadata.parameters)),
+            &(metadata.records as i64),
+            &metadata.first_date,
+            &metadata.last_date,
+        ],
+    )?;
+    let routes = route_summaries(graph, &RankOptions { min_trips: 1, ..metadata.parameters });
+    let upsert_route = tx.prepare(
+        "INSERT INTO route_metrics (run_at, from_id, to_id, from_station, to_station, trips, mean_delay, median_delay, p90_delay, on_time_rate)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         ON CONFLICT (run_at, from_id, to_id) DO UPDATE SET from_station = EXCLUDED.from_station,
+             to_station = EXCLUDED.to_station, trips = EXCLUDED.trips, mean_delay = EXCLUDED.mean_delay,
+             median_delay = EXCLUDED.median_delay, p90_delay = EXCLUDED.p90_delay, on_time_rate = EXCLUDED.on_time_rate",
+    )?;
+    for r in &routes {
+        tx.execute(&upsert_route, &[
+            &run_at, &r.from.id, &r.to.id, &r.from.name, &r.to.name, &(r.trips as i64),
+            &r.mean_delay, &r.median_delay, &r.p90_delay, &r.on_time_rate,
+        ])?;
+    }
+    let lines = line_summaries(records, metadata.parameters.on_time_threshold);
+    let upsert_line = tx.prepare(
+        "INSERT INTO line_metrics (run_at, line, trips, mean_delay, p90_delay, on_time_rate) VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (run_at, line) DO UPDATE SET trips = EXCLUDED.trips, mean_delay = EXCLUDED.mean_delay,
+             p90_delay = EXCLUDED.p90_delay, on_time_rate = EXCLUDED.on_time_rate",
+    )?;
+    for l in &lines {
+        tx.execute(&upsert_line, &[&run_at, &l.line, &(l.trips as i64), &l.mean_delay, &l.p90_delay, &l.on_time_rate])?;
+    }
+    tx.commit()?;
+    Ok(SinkReport { run_at, routes: routes.len(), lines: lines.len() })
+}
@@ -0,0 +1,67 @@
+// Spectral analysis of the transit Laplacian: algebraic connectivity and Fiedler partition (requires the `linalg` feature)
+
+use std::collections::HashMap;
+use nalgebra::{DMatrix, SymmetricEigen};
+use serde::{Deserialize, Serialize};
+use crate::graph::{TransitGraph, Station};
+use crate::topology::{connected_components, index_adjacency};
+
+// Spectrum of the Laplacian of the largest connected component
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spectrum {
+    pub components: usize,           // Connected components of the whole undirected graph
+    pub component_size: usize,       // Stations in the largest component, which the spectrum describes
+    pub eigenvalues: Vec<f64>,       // Smallest Laplacian eigenvalues in ascending order
+    pub algebraic_connectivity: f64, // Second-smallest eigenvalue; larger means harder to disconnect
+    pub fiedler: Vec<(Station, f64)>, // Fiedler vector entry per station, sorted by value
+}
+
+impl Spectrum {
+    // Splits the component by the sign of the Fiedler vector (the classic spectral bisection)
+    pub fn partition(&self) -> (Vec<Station>, Vec<Station>) {
+        let (neg, pos): (Vec<_>, Vec<_>) = self.fiedler.iter().partition(|(_, v)| *v < 0.0);
+        (neg.into_iter().map(|(s, _)| s.clone()).collect(), pos.into_iter().map(|(s, _)| s.clone()).collect())
+    }
+}
+
+impl TransitGraph {
+    // Computes the `k` smallest Laplacian eigenvalues and the Fiedler vector of the largest connected component
+    // Output: None if the largest component has fewer than two stations
+    // Logic: L = D - A over the undirected simple graph, dense symmetric eigendecomposition
+    pub fn laplacian_spectrum(&self, k: usize) -> Option<Spectrum> {
+        let (names, lists) = index_adjacency(&self.undirected_adjacency());
+        let components = connected_components(&lists);
+        let count = components.len();
+        let largest = components.into_iter().max_by_key(|c| c.len())?;
+        if largest.len() < 2 {
+            return None;
+        }
+        // Reindex the largest component densely
+        let local: HashMap<usize, usize> = largest.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+        let n = largest.len();
+        let mut laplacian = DMatrix::<f64>::zeros(n, n);
+        for (i, &v) in largest.iter().enumerate() {
+            laplacian[(i, i)] = lists[v].len() as f64;
+            for w in &lists[v] {
+                laplacian[(i, local[w])] = -1.0;
+            }
+        }
+        let eigen = SymmetricEigen::new(laplacian);
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+        // Clamp tiny negative round-off on the zero eigenvalue
+        let eigenvalues: Vec<f64> = order.iter().take(k.max(2)).map(|&i| eigen.eigenvalues[i].max(0.0)).collect();
+        let fiedler_column = eigen.eigenvectors.column(order[1]);
+        let mut fiedler: Vec<(Station, f64)> = largest.iter().enumerate()
+            .map(|(i, &v)| (names[v].clone(), fiedler_column[i]))
+            .collect();
+        fiedler.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        Some(Spectrum {
+            components: count,
+            component_size: n,
+            algebraic_connectivity: eigenvalues[1],
+            eigenvalues,
+            fiedler,
+        })
+    }
+}
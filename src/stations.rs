@@ -0,0 +1,162 @@
+// Per-station summaries for browsing the network: connectivity, trip volume, inbound delay, and lines served
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use chrono::{NaiveDateTime, Timelike};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::congestion::station_throughput;
+use crate::graph::{TransitGraph, Station};
+use crate::load::TrainRecord;
+
+// One row of the station list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationSummary {
+    pub station: Station,
+    pub degree: usize,              // Distinct neighbouring stations, in either direction
+    pub trips: usize,               // Distinct trips serving the station
+    pub inbound_delay: f32,         // Average delay of arrivals at the station, in minutes
+    pub line_count: usize,          // Number of lines serving the station
+    pub lines: String,              // Line names, comma-separated and sorted
+}
+
+// Centrality and volume figures for a single station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationDetail {
+    #[serde(flatten)]
+    pub summary: StationSummary,
+    pub closeness: Option<f32>,       // None if the station reaches no other station
+    pub closeness_rank: Option<usize>, // 1 = most central
+    pub betweenness: f32,
+    pub betweenness_rank: usize,
+}
+
+// Arrivals at a station in one hour of the day, across all dates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyActivity {
+    pub hour: u32, // 0-23, from the scheduled time
+    pub arrivals: usize,
+    pub average_delay: f32,
+}
+
+// Arrivals at a station on one service date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayDelay {
+    pub date: String,
+    pub arrivals: usize,
+    pub average_delay: f32,
+    pub worst_delay: f32,
+}
+
+// Column the station list is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StationColumn {
+    /// Station name
+    #[default]
+    Station,
+    /// Number of neighbouring stations
+    Degree,
+    /// Distinct trips serving the station
+    Trips,
+    /// Average arrival delay
+    InboundDelay,
+    /// Number of lines served
+    Lines,
+}
+
+// Builds a summary for every station in the graph, sorted by name
+pub fn station_summaries(records: &[TrainRecord], graph: &TransitGraph) -> Vec<StationSummary> {
+    let adjacency = graph.undirected_adjacency();
+    let throughput: HashMap<Station, (usize, f32)> = station_throughput(records).into_iter()
+        .map(|t| (t.station, (t.trips, t.average_delay)))
+        .collect();
+    let mut lines: HashMap<&Station, BTreeSet<&str>> = HashMap::new();
+    for ((from, to), served) in &graph.lines {
+        for station in [from, to] {
+            lines.entry(station).or_default().extend(served.iter().map(String::as_str));
+        }
+    }
+    let mut summaries: Vec<StationSummary> = adjacency.iter()
+        .map(|(station, neighbors)| {
+            let (trips, inbound_delay) = throughput.get(station).copied().unwrap_or((0, 0.0));
+            let served = lines.get(station).cloned().unwrap_or_default();
+            StationSummary {
+                station: station.clone(),
+                degree: neighbors.len(),
+                trips,
+                inbound_delay,
+                line_count: served.len(),
+                lines: served.into_iter().collect::<Vec<_>>().join(", "),
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| a.station.cmp(&b.station));
+    summaries
+}
+
+// Sorts summaries by a column; ties keep name order
+pub fn sort_summaries(summaries: &mut [StationSummary], column: StationColumn, descending: bool) {
+    summaries.sort_by(|a, b| {
+        let ordering = match column {
+            StationColumn::Station => a.station.cmp(&b.station),
+            StationColumn::Degree => a.degree.cmp(&b.degree),
+            StationColumn::Trips => a.trips.cmp(&b.trips),
+            StationColumn::InboundDelay => a.inbound_delay.total_cmp(&b.inbound_delay),
+            StationColumn::Lines => a.line_count.cmp(&b.line_count),
+        };
+        let ordering = if descending { ordering.reverse() } else { ordering };
+        if ordering == Ordering::Equal { a.station.cmp(&b.station) } else { ordering }
+    });
+}
+
+// Builds the summary, centrality scores, and centrality ranks for one station; None if it is not in the graph
+pub fn station_detail(records: &[TrainRecord], graph: &TransitGraph, station: &Station) -> Option<StationDetail> {
+    let summary = station_summaries(records, graph).into_iter().find(|s| &s.station == station)?;
+    let betweenness = graph.betweenness_centrality();
+    let score = betweenness.get(station).copied().unwrap_or(0.0);
+    let betweenness_rank = 1 + betweenness.values().filter(|&&b| b > score).count();
+    let closeness = graph.closeness_centrality(station);
+    let closeness_rank = closeness.map(|c| {
+        1 + graph.all_stations().iter()
+            .filter_map(|s| graph.closeness_centrality(s))
+            .filter(|&other| other > c)
+            .count()
+    });
+    Some(StationDetail { summary, closeness, closeness_rank, betweenness: score, betweenness_rank })
+}
+
+// Groups arrivals at the station by scheduled hour, busiest first
+pub fn busiest_hours(records: &[TrainRecord], station: &Station) -> Vec<HourlyActivity> {
+    let mut hours: HashMap<u32, (usize, f32)> = HashMap::new();
+    for r in records.iter().filter(|r| r.to_station_id() == station.id) {
+        let Ok(scheduled) = NaiveDateTime::parse_from_str(&r.scheduled_time, "%Y-%m-%d %H:%M:%S") else { continue };
+        let entry = hours.entry(scheduled.hour()).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += r.delay().unwrap_or(0.0);
+    }
+    let mut activity: Vec<HourlyActivity> = hours.into_iter()
+        .map(|(hour, (arrivals, total))| HourlyActivity { hour, arrivals, average_delay: total / arrivals as f32 })
+        .collect();
+    activity.sort_by(|a, b| b.arrivals.cmp(&a.arrivals).then(a.hour.cmp(&b.hour)));
+    activity
+}
+
+// Groups arrivals at the station by date, worst average delay first
+pub fn worst_days(records: &[TrainRecord], station: &Station) -> Vec<DayDelay> {
+    let mut days: HashMap<&str, Vec<f32>> = HashMap::new();
+    for r in records.iter().filter(|r| r.to_station_id() == station.id) {
+        if let Some(delay) = r.delay() {
+            days.entry(r.date.as_str()).or_default().push(delay);
+        }
+    }
+    let mut worst: Vec<DayDelay> = days.into_iter()
+        .map(|(date, delays)| DayDelay {
+            date: date.to_string(),
+            arrivals: delays.len(),
+            average_delay: delays.iter().sum::<f32>() / delays.len() as f32,
+            worst_delay: delays.iter().copied().fold(f32::MIN, f32::max),
+        })
+        .collect();
+    worst.sort_by(|a, b| b.average_delay.total_cmp(&a.average_delay).then_with(|| a.date.cmp(&b.date)));
+    worst
+}
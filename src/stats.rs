@@ -0,0 +1,296 @@
+// Statistical comparison of delay distributions between two lines, routes, or time periods
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::load::TrainRecord;
+
+// Selects the subset of records forming one side of a comparison
+#[derive(Debug, Clone)]
+pub enum Sample {
+    Line(String),                 // All records on a line (matched case-insensitively, ignoring padding)
+    Route(String, String),        // All records on a (from, to) route
+    Period(String, String),       // All records with start <= date <= end, dates as YYYY-MM-DD
+}
+
+impl Sample {
+    // Returns true if the record belongs to this sample
+    pub fn matches(&self, r: &TrainRecord) -> bool {
+        match self {
+            Sample::Line(line) => r.line.trim().eq_ignore_ascii_case(line.trim()),
+            Sample::Route(from, to) => &r.from == from && &r.to == to,
+            Sample::Period(start, end) => r.date.as_str() >= start.as_str() && r.date.as_str() <= end.as_str(),
+        }
+    }
+
+    // Collects the delays of all matching records that have one
+    pub fn delays(&self, records: &[TrainRecord]) -> Vec<f32> {
+        records.iter()
+            .filter(|r| self.matches(r))
+            .filter_map(|r| r.delay())
+            .collect()
+    }
+}
+
+// Outcome of a single two-sample test
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TestResult {
+    pub statistic: f64,   // U for Mann-Whitney, t for Welch
+    pub p_value: f64,     // Two-sided p-value
+    pub effect_size: f64, // Rank-biserial correlation for Mann-Whitney, Cohen's d for Welch
+}
+
+// Summary of a comparison between samples A and B
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub n_a: usize,
+    pub n_b: usize,
+    pub mean_a: f64,
+    pub mean_b: f64,
+    pub median_a: f64,
+    pub median_b: f64,
+    pub mann_whitney: TestResult, // Preferred: delays are heavily right-skewed
+    pub welch: TestResult,
+}
+
+// Compares the delay distributions of two samples
+// Input: records and the two sample selectors
+// Output: None if either sample has fewer than two delays
+// Logic: Run Mann-Whitney U (normal approximation with tie correction) and Welch's t-test on the same data
+pub fn compare(records: &[TrainRecord], a: &Sample, b: &Sample) -> Option<Comparison> {
+    compare_delays(&a.delays(records), &b.delays(records))
+}
+
+// Compares two raw delay samples; see `compare`
+pub fn compare_delays(a: &[f32], b: &[f32]) -> Option<Comparison> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let a: Vec<f64> = a.iter().map(|&x| x as f64).collect();
+    let b: Vec<f64> = b.iter().map(|&x| x as f64).collect();
+    Some(Comparison {
+        n_a: a.len(),
+        n_b: b.len(),
+        mean_a: mean(&a),
+        mean_b: mean(&b),
+        median_a: median(&a),
+        median_b: median(&b),
+        mann_whitney: mann_whitney_u(&a, &b),
+        welch: welch_t(&a, &b),
+    })
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+fn variance(xs: &[f64]) -> f64 {
+    let m = mean(xs);
+    xs.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (xs.len() - 1) as f64
+}
+
+fn median(xs: &[f64]) -> f64 {
+    let mut sorted = xs.to_vec();
+    sorted.sort_by(|x, y| x.total_cmp(y));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] }
+}
+
+// Percentile of an ascending-sorted sample by linear interpolation between closest ranks; p in [0, 1]
+pub fn percentile(sorted: &[f32], p: f32) -> Option<f32> {
+    let last = sorted.len().checked_sub(1)?;
+    let rank = p.clamp(0.0, 1.0) * last as f32;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f32))
+}
+
+// One bucket of a delay histogram, covering [lower, upper)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBin {
+    pub lower: f32,
+    pub upper: f32, // Infinite for the final overflow bucket
+    pub count: usize,
+}
+
+// Counts values into fixed-width bins from the smallest value's bin up to `max`, with one overflow bin beyond it
+// Negative delays (early departures) get bins of their own below zero
+// Output: an invalid-parameter error unless `width` is positive and `max` finite
+pub fn histogram(values: &[f32], width: f32, max: f32) -> Result<Vec<HistogramBin>> {
+    let mut counter = HistogramCounter::new(width, max)?;
+    values.iter().for_each(|&v| counter.add(v));
+    Ok(counter.bins())
+}
+
+// A histogram filled one value at a time, for data that is never held in memory at once (streaming aggregation)
+// Gives the same bins as `histogram` over the same values
+#[derive(Debug, Clone)]
+pub struct HistogramCounter {
+    width: f32,
+    max: f32,
+    counts: BTreeMap<i64, usize>, // Count per bin index (value / width, floored), before clamping to the overflow bin
+}
+
+impl HistogramCounter {
+    // An empty histogram; an invalid-parameter error unless `width` is positive and `max` finite
+    pub fn new(width: f32, max: f32) -> Result<Self> {
+        if !(width > 0.0 && width.is_finite()) {
+            return Err(Error::InvalidParameter { name: "bin width", reason: format!("{} is not a positive number of minutes", width) });
+        }
+        if !max.is_finite() {
+            return Err(Error::InvalidParameter { name: "histogram maximum", reason: format!("{} is not a finite number of minutes", max) });
+        }
+        Ok(Self { width, max, counts: BTreeMap::new() })
+    }
+
+    // Counts one value; NaN and infinite values are ignored
+    pub fn add(&mut self, value: f32) {
+        if value.is_finite() {
+            *self.counts.entry((value / self.width).floor() as i64).or_default() += 1;
+        }
+    }
+
+    // Values counted so far
+    pub fn count(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    // The bins from the smallest value's bin up to the overflow bin, or none if nothing was counted
+    pub fn bins(&self) -> Vec<HistogramBin> {
+        let Some(&lowest) = self.counts.keys().next() else { return Vec::new() };
+        let first = lowest.min((self.max / self.width).floor() as i64);
+        let last = (self.max / self.width).ceil() as i64;
+        let mut bins: Vec<HistogramBin> = (first..last)
+            .map(|i| HistogramBin { lower: i as f32 * self.width, upper: (i + 1) as f32 * self.width, count: 0 })
+            .collect();
+        bins.push(HistogramBin { lower: last as f32 * self.width, upper: f32::INFINITY, count: 0 });
+        for (&i, &count) in &self.counts {
+            let i = ((i - first).max(0) as usize).min(bins.len() - 1);
+            bins[i].count += count;
+        }
+        bins
+    }
+}
+
+// Mann-Whitney U test with average ranks for ties
+fn mann_whitney_u(a: &[f64], b: &[f64]) -> TestResult {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let mut pooled: Vec<(f64, bool)> = a.iter().map(|&x| (x, true)).chain(b.iter().map(|&x| (x, false))).collect();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+    // Assign average ranks to tied groups and accumulate the tie correction term
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < pooled.len() {
+        let mut j = i;
+        while j + 1 < pooled.len() && pooled[j + 1].0 == pooled[i].0 {
+            j += 1;
+        }
+        let avg_rank = (i + j) as f64 / 2.0 + 1.0;
+        let t = (j - i + 1) as f64;
+        tie_term += t * t * t - t;
+        rank_sum_a += pooled[i..=j].iter().filter(|p| p.1).count() as f64 * avg_rank;
+        i = j + 1;
+    }
+    let u1 = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let mu = n1 * n2 / 2.0;
+    let sigma = (n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)))).sqrt();
+    let p_value = if sigma > 0.0 {
+        let z = (u1 - mu).abs() / sigma;
+        (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+    } else {
+        1.0 // All values identical
+    };
+    TestResult { statistic: u1, p_value, effect_size: 2.0 * u1 / (n1 * n2) - 1.0 }
+}
+
+// Welch's unequal-variance t-test
+fn welch_t(a: &[f64], b: &[f64]) -> TestResult {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let (v1, v2) = (variance(a), variance(b));
+    let se2 = v1 / n1 + v2 / n2;
+    let pooled_sd = (((n1 - 1.0) * v1 + (n2 - 1.0) * v2) / (n1 + n2 - 2.0)).sqrt();
+    let diff = mean(a) - mean(b);
+    let effect_size = if pooled_sd > 0.0 { diff / pooled_sd } else { 0.0 };
+    if se2 <= 0.0 {
+        return TestResult { statistic: 0.0, p_value: 1.0, effect_size };
+    }
+    let t = diff / se2.sqrt();
+    let df = se2 * se2 / ((v1 / n1).powi(2) / (n1 - 1.0) + (v2 / n2).powi(2) / (n2 - 1.0));
+    // Two-sided p-value from the Student t distribution via the regularized incomplete beta function
+    let p_value = incomplete_beta(df / 2.0, 0.5, df / (df + t * t)).clamp(0.0, 1.0);
+    TestResult { statistic: t, p_value, effect_size }
+}
+
+// Standard normal CDF using the Abramowitz-Stegun erf approximation (error < 1.5e-7)
+pub(crate) fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+// Natural log of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEF: [f64; 6] = [76.18009172947146, -86.50532032941677, 24.01409824083091,
+        -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000000000190015;
+    for (i, c) in COEF.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+// Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // Use the symmetry relation where the continued fraction converges fastest
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Lentz's method for the incomplete beta continued fraction
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-30;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY { d = TINY; }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..200 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        // Even step
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY { d = TINY; }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY { c = TINY; }
+        d = 1.0 / d;
+        h *= d * c;
+        // Odd step
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY { d = TINY; }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY { c = TINY; }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
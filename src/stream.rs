@@ -0,0 +1,90 @@
+// One-pass aggregation for datasets larger than memory: records are folded into running totals and the graph as they
+// are read, and dropped. Only the graph (one weight per delayed record) and per-route and per-line totals are kept
+// Analyses that revisit records (trip reconstruction, forecasts, heatmaps, station drill-downs) need the loaded records
+
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use crate::graph::{FastMap, GraphBuilder, Station, StationId, TransitGraph};
+use crate::load::TrainRecord;
+use crate::stats::{HistogramBin, HistogramCounter};
+use crate::table::DelayTotals;
+
+// Width and overflow bound of the streamed delay histogram unless set, as for the `histogram` command
+pub const DEFAULT_BIN_WIDTH: f32 = 5.0;
+pub const DEFAULT_HISTOGRAM_MAX: f32 = 60.0;
+
+// What a streaming pass computed, besides the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamSummary {
+    pub records: usize,                              // Records aggregated (after any filter)
+    pub delayed: usize,                              // Of those, records with a delay
+    pub routes: Vec<((Station, Station), DelayTotals)>, // Per route, ordered by (from, to)
+    pub lines: Vec<(String, DelayTotals)>,           // Per line (trimmed name), ordered by name
+    pub histogram: Vec<HistogramBin>,                // Network-wide delay distribution
+}
+
+// Running aggregates over records fed one at a time
+#[derive(Debug)]
+pub struct StreamAggregator {
+    threshold: f32, // On-time threshold in minutes
+    records: usize,
+    delayed: usize,
+    graph: GraphBuilder,
+    routes: FastMap<(StationId, StationId), DelayTotals>,
+    lines: FastMap<String, DelayTotals>,
+    histogram: HistogramCounter,
+}
+
+impl StreamAggregator {
+    // An empty aggregator; an invalid-parameter error for a histogram `stats::histogram` would reject
+    pub fn new(threshold: f32, bin_width: f32, max: f32) -> Result<Self> {
+        Ok(Self {
+            threshold,
+            records: 0,
+            delayed: 0,
+            graph: GraphBuilder::new(),
+            routes: FastMap::default(),
+            lines: FastMap::default(),
+            histogram: HistogramCounter::new(bin_width, max)?,
+        })
+    }
+
+    // Folds one record into every aggregate; the record can be dropped afterwards
+    pub fn add(&mut self, record: &TrainRecord) {
+        self.records += 1;
+        let delay = record.delay();
+        self.graph.add(record, delay);
+        let Some(delay) = delay else { return };
+        self.delayed += 1;
+        self.routes.entry((record.from_station_id(), record.to_station_id())).or_default().add(delay, self.threshold);
+        match self.lines.get_mut(record.line.trim()) {
+            Some(totals) => totals.add(delay, self.threshold),
+            None => { self.lines.entry(record.line.trim().to_string()).or_default().add(delay, self.threshold); }
+        }
+        self.histogram.add(delay);
+    }
+
+    // Records folded in so far
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    // The summary and the graph; routes are named with the graph's stations, so they match its rankings
+    pub fn finish(self) -> (StreamSummary, TransitGraph) {
+        let graph = self.graph.build();
+        let mut routes: Vec<((Station, Station), DelayTotals)> = self.routes.into_iter()
+            .filter_map(|((from, to), totals)| Some(((graph.station(&from)?.clone(), graph.station(&to)?.clone()), totals)))
+            .collect();
+        routes.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut lines: Vec<(String, DelayTotals)> = self.lines.into_iter().collect();
+        lines.sort_by(|a, b| a.0.cmp(&b.0));
+        let summary = StreamSummary {
+            records: self.records,
+            delayed: self.delayed,
+            routes,
+            lines,
+            histogram: self.histogram.bins(),
+        };
+        (summary, graph)
+    }
+}
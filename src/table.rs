@@ -0,0 +1,187 @@
+// Columnar records: the delayed records as dictionary-encoded columns, so per-route and per-line aggregation is a scan
+// over dense integer keys instead of a HashMap entry update (and a station clone) per record
+// Build the table once and aggregate it as often as needed; a single aggregation does not pay back the encoding pass
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::graph::{station_index, FastMap, Station, StationId};
+use crate::load::TrainRecord;
+use crate::report::GroupOnTime;
+
+// Rows per chunk of an aggregation pass; each chunk is aggregated on its own thread into dense per-key partials,
+// and the partials are merged in chunk order, so the sums do not depend on how many threads ran
+const CHUNK_ROWS: usize = 1 << 16;
+
+// Delay totals of one route or line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DelayTotals {
+    pub trips: usize,     // Records with a delay
+    pub total_delay: f64, // Minutes, summed in f64 so millions of rows do not lose precision
+    pub on_time: usize,   // Trips at or below the on-time threshold
+}
+
+impl DelayTotals {
+    // Counts one trip's delay
+    pub fn add(&mut self, delay: f32, threshold: f32) {
+        self.trips += 1;
+        self.total_delay += delay as f64;
+        self.on_time += (delay <= threshold) as usize;
+    }
+
+    pub fn mean_delay(&self) -> f32 {
+        (self.total_delay / self.trips as f64) as f32
+    }
+
+    pub fn on_time_rate(&self) -> f32 {
+        self.on_time as f32 / self.trips as f32
+    }
+}
+
+// Every record with a finite delay, one column per field the aggregations read
+// Stations, routes and lines are stored once in dictionaries; the row columns hold indices into them
+#[derive(Debug, Clone, Default)]
+pub struct RecordTable {
+    stations: Vec<Station>,     // Station dictionary, named by the most common spelling as in the graph
+    routes: Vec<(u32, u32)>,    // Route dictionary: (from, to) station indices, in first-seen order
+    lines: Vec<String>,         // Line dictionary: trimmed names, in first-seen order
+    route: Vec<u32>,            // Row column: route index
+    line: Vec<u32>,             // Row column: line index
+    delay: Vec<f32>,            // Row column: delay in minutes
+}
+
+impl RecordTable {
+    // Encodes the records with a finite delay (the ones the graph and the delay metrics count)
+    // Logic: one pass looking up each record's stations, route and line in hash maps of dictionary indices
+    pub fn from_records(records: &[TrainRecord]) -> Self {
+        let by_id = station_index(records);
+        let mut table = Self::default();
+        let mut station_keys: FastMap<&StationId, u32> = FastMap::default();
+        let mut route_keys: FastMap<(u32, u32), u32> = FastMap::default();
+        let mut line_keys: FastMap<&str, u32> = FastMap::default();
+        let mut station_key = |stations: &mut Vec<Station>, id: StationId| {
+            let station = &by_id[&id];
+            *station_keys.entry(&station.id).or_insert_with(|| {
+                stations.push(station.clone());
+                stations.len() as u32 - 1
+            })
+        };
+        for r in records {
+            let Some(delay) = r.delay() else { continue };
+            let from = station_key(&mut table.stations, r.from_station_id());
+            let to = station_key(&mut table.stations, r.to_station_id());
+            let route = *route_keys.entry((from, to)).or_insert_with(|| {
+                table.routes.push((from, to));
+                table.routes.len() as u32 - 1
+            });
+            let line = *line_keys.entry(r.line.trim()).or_insert_with(|| {
+                table.lines.push(r.line.trim().to_string());
+                table.lines.len() as u32 - 1
+            });
+            table.route.push(route);
+            table.line.push(line);
+            table.delay.push(delay);
+        }
+        table
+    }
+
+    // Number of rows (records with a delay)
+    pub fn len(&self) -> usize {
+        self.delay.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.delay.is_empty()
+    }
+
+    // Delay totals of every route with a delayed record, ordered by (from, to)
+    pub fn route_totals(&self, threshold: f32) -> Vec<((Station, Station), DelayTotals)> {
+        let mut routes: Vec<((Station, Station), DelayTotals)> = aggregate(&self.route, &self.delay, self.routes.len(), threshold)
+            .into_iter()
+            .zip(&self.routes)
+            .map(|(totals, &(from, to))| ((self.stations[from as usize].clone(), self.stations[to as usize].clone()), totals))
+            .collect();
+        routes.sort_by(|a, b| a.0.cmp(&b.0));
+        routes
+    }
+
+    // Delay totals of every line with a delayed record, ordered by name
+    pub fn line_totals(&self, threshold: f32) -> Vec<(String, DelayTotals)> {
+        let mut lines: Vec<(String, DelayTotals)> = aggregate(&self.line, &self.delay, self.lines.len(), threshold)
+            .into_iter()
+            .zip(&self.lines)
+            .map(|(totals, line)| (line.clone(), totals))
+            .collect();
+        lines.sort_by(|a, b| a.0.cmp(&b.0));
+        lines
+    }
+
+    // Same rows as `TransitGraph::get_route_average_delays`: ((from, to), average delay, trips), ordered by route
+    pub fn route_average_delays(&self) -> Vec<((Station, Station), f32, usize)> {
+        self.route_totals(f32::INFINITY).into_iter().map(|(route, totals)| (route, totals.mean_delay(), totals.trips)).collect()
+    }
+
+    // Same rows and order as `report::line_on_time`: on-time performance per line, worst first
+    pub fn line_on_time(&self, threshold: f32) -> Vec<GroupOnTime> {
+        let mut lines: Vec<GroupOnTime> = self.line_totals(threshold)
+            .into_iter()
+            .map(|(group, totals)| GroupOnTime {
+                group,
+                trips: totals.trips,
+                average_delay: totals.mean_delay(),
+                on_time_rate: totals.on_time_rate(),
+            })
+            .collect();
+        lines.sort_by(|a, b| a.on_time_rate.total_cmp(&b.on_time_rate).then_with(|| a.group.cmp(&b.group)));
+        lines
+    }
+}
+
+// Per-key partial totals of one chunk, as parallel arrays indexed by key
+struct Partials {
+    trips: Vec<usize>,
+    total_delay: Vec<f64>,
+    on_time: Vec<usize>,
+}
+
+impl Partials {
+    fn new(keys: usize) -> Self {
+        Self { trips: vec![0; keys], total_delay: vec![0.0; keys], on_time: vec![0; keys] }
+    }
+
+    // The inner loop of a pass: branch-free scatter-adds over a chunk of the key and delay columns
+    fn add(&mut self, keys: &[u32], delays: &[f32], threshold: f32) {
+        for (&k, &d) in keys.iter().zip(delays) {
+            let k = k as usize;
+            self.trips[k] += 1;
+            self.total_delay[k] += d as f64;
+            self.on_time[k] += (d <= threshold) as usize;
+        }
+    }
+
+    fn merge(mut self, other: &Partials) -> Self {
+        self.trips.iter_mut().zip(&other.trips).for_each(|(a, b)| *a += b);
+        self.total_delay.iter_mut().zip(&other.total_delay).for_each(|(a, b)| *a += b);
+        self.on_time.iter_mut().zip(&other.on_time).for_each(|(a, b)| *a += b);
+        self
+    }
+}
+
+// Totals per key over a key column and the delay column
+// Input: row keys in 0..groups, the delays of the same rows, the on-time threshold
+// Output: one DelayTotals per key, indexed by key
+// Logic: chunks are aggregated in parallel, then their partials are summed in chunk order (deterministic results);
+// std::simd is nightly-only, so the kernel is written branch-free for the compiler to vectorize what it can
+fn aggregate(keys: &[u32], delays: &[f32], groups: usize, threshold: f32) -> Vec<DelayTotals> {
+    let chunks: Vec<Partials> = keys.par_chunks(CHUNK_ROWS)
+        .zip(delays.par_chunks(CHUNK_ROWS))
+        .map(|(keys, delays)| {
+            let mut partials = Partials::new(groups);
+            partials.add(keys, delays, threshold);
+            partials
+        })
+        .collect();
+    let totals = chunks.iter().fold(Partials::new(groups), Partials::merge);
+    (0..groups)
+        .map(|k| DelayTotals { trips: totals.trips[k], total_delay: totals.total_delay[k], on_time: totals.on_time[k] })
+        .collect()
+}
@@ -0,0 +1,46 @@
+// Terminal-native plots: block-character histograms and sparklines, for quick checks without image files
+
+use std::io::{self, Write};
+use crate::stats::HistogramBin;
+
+// Eight bar heights, lowest first
+const SPARK: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Partial-width blocks for the fractional end of a histogram bar, in eighths
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+// Draws one character per value, scaled between the smallest and largest present value
+// Missing values (e.g. months without trips) are left as spaces so gaps stay visible; a flat series sits mid-height
+pub fn sparkline(values: &[Option<f32>]) -> String {
+    let present = values.iter().flatten().copied().filter(|v| v.is_finite());
+    let (lo, hi) = present.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    values.iter()
+        .map(|v| match v {
+            Some(v) if v.is_finite() && hi > lo => SPARK[(((v - lo) / (hi - lo)) * 7.0).round() as usize],
+            Some(v) if v.is_finite() => SPARK[3],
+            _ => ' ',
+        })
+        .collect()
+}
+
+// Writes a horizontal histogram: one row per bin with its range, a bar scaled to `width` characters, and the count
+// Input: bins from `stats::histogram`, the bar width for the fullest bin
+// Logic: bars are drawn in eighths of a character so small differences between bins still show
+pub fn write_histogram<W: Write>(out: &mut W, bins: &[HistogramBin], width: usize) -> io::Result<()> {
+    let labels: Vec<String> = bins.iter()
+        .map(|b| if b.upper.is_finite() { format!("{} to {}", b.lower, b.upper) } else { format!("{}+", b.lower) })
+        .collect();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let most = bins.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let total: usize = bins.iter().map(|b| b.count).sum();
+    for (bin, label) in bins.iter().zip(&labels) {
+        let eighths = (bin.count * width * 8).div_ceil(most);
+        let mut bar = "█".repeat(eighths / 8);
+        if eighths % 8 > 0 {
+            bar.push(EIGHTHS[eighths % 8]);
+        }
+        let share = if total > 0 { bin.count as f32 / total as f32 * 100.0 } else { 0.0 };
+        writeln!(out, "{:>lw$} │{:<bw$} {} ({:.1}%)", label, bar, bin.count, share, lw = label_width, bw = width + 1)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// Top-k centrality rankings that stop early: most readers look at the first 10–20 stations, so work that cannot change
+// them is skipped. Closeness cuts each station's search once an upper bound on its score falls below the k-th best
+// score found so far (the top k and their scores are exact); betweenness stops adding sources once bounds on the
+// remaining contributions can no longer reorder the top k (the order is exact, the scores estimated)
+
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::graph::{TransitGraph, Weight};
+use crate::metrics::{closeness_from, index_graph, RankOptions, SearchContext, StationScore};
+use crate::pipeline::Observer;
+
+// Stations scored in parallel between updates of the pruning threshold; fixed so results do not depend on the pool
+const PRUNE_BATCH: usize = 32;
+
+// Relative margin below the threshold before a search is cut: the bound is summed in f64 in settle order while scores
+// are summed in f32 in index order, so a bound may round to just under the score it bounds
+const BOUND_SLACK: f64 = 1e-4;
+
+// A top-k ranking with how much of the full computation it skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopK {
+    pub scores: Vec<StationScore>, // Best first, ties in station order, at most `top_n`
+    pub cut: usize,                // Closeness: searches stopped early; betweenness: source stations never searched
+    pub total: usize,              // Searches a full ranking runs
+}
+
+impl<W: Weight> TransitGraph<W> {
+    // The top `opts.top_n` stations by closeness, identical to `rank_stations_by_closeness` with Dijkstra
+    // Logic: stations are searched busiest first (most outgoing segments), which tend to score highest, in batches of
+    // PRUNE_BATCH run in parallel. Once k scores are known, a search stops as soon as the stations it has settled
+    // bound its score below the k-th: every unsettled target is at least as far as the last settled station, and
+    // adding targets at that distance raises reachable / total delay most when all or none are added. Negative
+    // delays void the bound, so they disable pruning
+    pub fn top_k_closeness(&self, opts: &RankOptions, observer: &dyn Observer) -> TopK {
+        let (stations, lists) = index_graph(self);
+        let n = stations.len();
+        let k = opts.top_n.min(n);
+        let targets = lists.iter().filter(|edges| !edges.is_empty()).count();
+        let prunable = lists.iter().flatten().all(|(_, weight)| weight.minutes() >= 0.0 || weight.minutes().is_nan());
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by_key(|&i| (Reverse(lists[i].len()), i));
+        let mut best: Vec<(f32, usize)> = Vec::with_capacity(k + PRUNE_BATCH);
+        let (done, cut) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        for batch in order.chunks(PRUNE_BATCH) {
+            if k == 0 {
+                break;
+            }
+            let threshold = (prunable && best.len() == k).then(|| best[k - 1].0 as f64 * (1.0 - BOUND_SLACK));
+            let scores: Vec<Option<f32>> = batch.par_iter()
+                .map_init(|| SearchContext::new(n), |search, &i| {
+                    let score = bounded_closeness(&lists, i, targets, threshold, search);
+                    if score.is_none() {
+                        cut.fetch_add(1, AtomicOrdering::Relaxed);
+                    }
+                    observer.on_metric_progress("closeness", done.fetch_add(1, AtomicOrdering::Relaxed) + 1, n);
+                    score.flatten()
+                })
+                .collect();
+            best.extend(batch.iter().zip(scores).filter_map(|(&i, score)| Some((score?, i))));
+            best.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            best.truncate(k);
+        }
+        let cut = cut.into_inner();
+        observer.on_metric_pruned("closeness", cut, n);
+        let scores = best.into_iter().map(|(score, i)| StationScore { station: stations[i].clone(), score }).collect();
+        TopK { scores, cut, total: n }
+    }
+
+    // The top `opts.top_n` stations by betweenness, in the order of `rank_stations_by_betweenness`
+    // Logic: sources run in index order as in `betweenness_centrality`, each adding to every station's running sum.
+    // A later source adds at most (stations with incoming segments − 1) to a station, and nothing to one without
+    // both incoming and outgoing segments, so each sum is bounded above by itself plus that much per remaining
+    // source. Once no station can overtake any of the first k, the rest are skipped and the sums are scaled by
+    // sources / sources searched as estimates; when every source runs, the scores are exact
+    pub fn top_k_betweenness(&self, opts: &RankOptions, observer: &dyn Observer) -> TopK {
+        let (stations, lists) = index_graph(self);
+        let n = stations.len();
+        let k = opts.top_n.min(n);
+        let mut incoming = vec![false; n];
+        for &(to, _) in lists.iter().flatten() {
+            incoming[to] = true;
+        }
+        let per_source = incoming.iter().filter(|&&i| i).count().saturating_sub(1) as f64;
+        let intermediate: Vec<bool> = (0..n).map(|i| incoming[i] && !lists[i].is_empty()).collect();
+        let sources: Vec<usize> = (0..n).filter(|&s| !lists[s].is_empty()).collect();
+        let mut centrality = vec![0.0f32; n];
+        let mut search = SearchContext::new(n);
+        let mut searched = 0;
+        for (done, &s) in sources.iter().enumerate() {
+            search.accumulate_betweenness(&lists, s, &mut centrality);
+            observer.on_metric_progress("betweenness", done + 1, sources.len());
+            searched = done + 1;
+            let remaining = (sources.len() - searched) as f64 * per_source;
+            if remaining > 0.0 && settled_top(&centrality, &intermediate, remaining, k) {
+                break;
+            }
+        }
+        let cut = sources.len() - searched;
+        observer.on_metric_pruned("betweenness", cut, sources.len());
+        let scale = if cut == 0 { 1.0 } else { sources.len() as f32 / searched as f32 };
+        let mut ranked: Vec<usize> = (0..n).filter(|&i| centrality[i].is_finite()).collect();
+        ranked.sort_by(|&a, &b| centrality[b].total_cmp(&centrality[a]).then(a.cmp(&b)));
+        let scores = ranked.into_iter().take(k)
+            .map(|i| StationScore { station: stations[i].clone(), score: centrality[i] * scale })
+            .collect();
+        TopK { scores, cut, total: sources.len() }
+    }
+}
+
+// Closeness of `node` as in `closeness_of` (None if it has no score), or None once its bound falls below `threshold`
+// Input: the number of targets in the graph (nodes with outgoing edges), and the k-th best score so far if known
+fn bounded_closeness<W: Weight>(
+    lists: &[Vec<(usize, W)>],
+    node: usize,
+    targets: usize,
+    threshold: Option<f64>,
+    search: &mut SearchContext<W>,
+) -> Option<Option<f32>> {
+    let mut unsettled = targets - usize::from(!lists[node].is_empty());
+    let (mut total, mut reachable) = (0.0f64, 0usize);
+    let finished = search.dijkstra_until(lists, node, |other, distance| {
+        let distance = distance.minutes() as f64;
+        if other != node && !lists[other].is_empty() {
+            total += distance;
+            reachable += 1;
+            unsettled -= 1;
+        }
+        let Some(threshold) = threshold else { return true };
+        closeness_bound(reachable, total, unsettled, distance) >= threshold
+    });
+    finished.then(|| closeness_from(lists, node, search.distances()))
+}
+
+// Highest closeness a source can still reach with `reachable` targets settled at `total` delay and up to `unsettled`
+// more, each at least `nearest` away
+// Logic: (r + j) / (S + j·d) is monotonic in j, so the best case adds all of the unsettled targets or none
+fn closeness_bound(reachable: usize, total: f64, unsettled: usize, nearest: f64) -> f64 {
+    if total <= 0.0 {
+        return f64::INFINITY; // No delay yet: any score is still possible
+    }
+    let (r, j) = (reachable as f64, unsettled as f64);
+    (r / total).max((r + j) / (total + j * nearest))
+}
+
+// Whether the first k stations by running sum are final in membership and order: none can be overtaken by a station
+// after it, given that each sum may still grow by `remaining` (or not at all if the station is never an intermediate)
+// Logic: stations in ranking order (sum descending, then index); a station is safe if its sum exceeds every later
+// station's bound, or every later bound is zero, in which case later stations tie it at most and sort after it
+fn settled_top(centrality: &[f32], intermediate: &[bool], remaining: f64, k: usize) -> bool {
+    let mut ranked: Vec<usize> = (0..centrality.len()).collect();
+    ranked.sort_by(|&a, &b| centrality[b].total_cmp(&centrality[a]).then(a.cmp(&b)));
+    let bound = |i: usize| centrality[i] as f64 + if intermediate[i] { remaining } else { 0.0 };
+    // Highest bound among the stations from each position on
+    let mut later = vec![0.0f64; ranked.len() + 1];
+    for pos in (0..ranked.len()).rev() {
+        later[pos] = later[pos + 1].max(bound(ranked[pos]));
+    }
+    (0..k.min(ranked.len())).all(|pos| later[pos + 1] == 0.0 || centrality[ranked[pos]] as f64 > later[pos + 1])
+}
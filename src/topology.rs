@@ -0,0 +1,331 @@
+// Network-science descriptors of the transit topology: path length, clustering, and small-world indicators
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::graph::{TransitGraph, Station};
+
+// Small-world indicators of the network against degree-preserving random baselines
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SmallWorld {
+    pub clustering: f32,         // Average local clustering coefficient C
+    pub path_length: f32,        // Mean shortest path length L in hops
+    pub random_clustering: f32,  // Mean C over the rewired baselines
+    pub random_path_length: f32, // Mean L over the rewired baselines
+    pub sigma: f32,              // (C / C_rand) / (L / L_rand); > 1 suggests a small-world network
+}
+
+// Degree assortativity of the network with its degree-mixing detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assortativity {
+    pub coefficient: f32, // Pearson correlation of degrees at either end of an edge, in [-1, 1]
+    pub mixing: Vec<DegreeMixing>, // Average neighbour degree per degree, sorted by degree
+}
+
+// Mixing detail for all stations of one degree
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DegreeMixing {
+    pub degree: usize,
+    pub stations: usize,              // Number of stations with this degree
+    pub average_neighbor_degree: f32, // Mean degree of their neighbours (k_nn)
+}
+
+// A candidate connection between two stations not currently linked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkPrediction {
+    pub a: Station,
+    pub b: Station,
+    pub common_neighbors: usize,
+    pub jaccard: f32,     // |N(a) ∩ N(b)| / |N(a) ∪ N(b)|
+    pub adamic_adar: f32, // Sum of 1 / ln(degree) over shared neighbours
+}
+
+// Undirected simple view of the graph as dense index adjacency lists
+// Output: station names in index order and, per index, its sorted distinct neighbours (no self-loops)
+pub(crate) fn index_adjacency(adj: &HashMap<Station, HashSet<Station>>) -> (Vec<Station>, Vec<Vec<usize>>) {
+    let mut names: Vec<Station> = adj.keys().cloned().collect();
+    names.sort();
+    let index: HashMap<&Station, usize> = names.iter().enumerate().map(|(i, s)| (s, i)).collect();
+    let lists = names.iter()
+        .map(|s| {
+            let mut neighbors: Vec<usize> = adj[s].iter().map(|t| index[t]).collect();
+            neighbors.sort_unstable();
+            neighbors
+        })
+        .collect();
+    (names, lists)
+}
+
+// Connected components of index adjacency lists, each as a sorted list of indices
+pub(crate) fn connected_components(lists: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut components = Vec::new();
+    for start in 0..lists.len() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut component = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(v) = queue.pop_front() {
+            for &w in &lists[v] {
+                if seen.insert(w) {
+                    component.push(w);
+                    queue.push_back(w);
+                }
+            }
+        }
+        component.sort_unstable();
+        components.push(component);
+    }
+    components
+}
+
+impl TransitGraph {
+    // Builds the undirected simple graph: every station maps to the set of stations it shares a segment with
+    // Self-loops (records where from == to) and parallel edges are dropped
+    pub fn undirected_adjacency(&self) -> HashMap<Station, HashSet<Station>> {
+        let mut adj: HashMap<Station, HashSet<Station>> = self.stations().map(|s| (s.clone(), HashSet::new())).collect();
+        for (from, to, _) in self.edges() {
+            if from != to {
+                adj.entry(from.clone()).or_default().insert(to.clone());
+                adj.entry(to.clone()).or_default().insert(from.clone());
+            }
+        }
+        adj
+    }
+
+    // Average local clustering coefficient of the undirected graph (stations with degree < 2 count as 0)
+    pub fn average_clustering(&self) -> f32 {
+        let (_, lists) = index_adjacency(&self.undirected_adjacency());
+        clustering_of(&lists)
+    }
+
+    // Mean shortest path length in hops over all connected pairs of the undirected graph
+    // Returns None if no pair of distinct stations is connected
+    pub fn average_path_length(&self) -> Option<f32> {
+        let (_, lists) = index_adjacency(&self.undirected_adjacency());
+        path_length_of(&lists)
+    }
+
+    // Computes clustering and path length against `samples` degree-preserving random rewirings
+    // Input: number of random baselines and RNG seed (for reproducible output)
+    // Output: None if the graph has no connected pairs or no edges to rewire
+    pub fn small_world(&self, samples: usize, seed: u64) -> Option<SmallWorld> {
+        let clustering = self.average_clustering();
+        let path_length = self.average_path_length()?;
+        let (_, lists) = index_adjacency(&self.undirected_adjacency());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (mut c_total, mut l_total, mut counted) = (0.0, 0.0, 0);
+        for _ in 0..samples.max(1) {
+            let random = rewire(&lists, &mut rng);
+            if let Some(l) = path_length_of(&random) {
+                c_total += clustering_of(&random);
+                l_total += l;
+                counted += 1;
+            }
+        }
+        if counted == 0 {
+            return None;
+        }
+        let random_clustering = c_total / counted as f32;
+        let random_path_length = l_total / counted as f32;
+        let sigma = if random_clustering > 0.0 && path_length > 0.0 {
+            (clustering / random_clustering) / (path_length / random_path_length)
+        } else {
+            f32::NAN // Undefined when the random baseline has no triangles
+        };
+        Some(SmallWorld { clustering, path_length, random_clustering, random_path_length, sigma })
+    }
+
+    // Degree assortativity coefficient of the undirected graph (do hubs connect to other hubs?)
+    // Output: None if the graph has no edges or every edge joins stations of identical degree
+    // Logic: Newman's r, the Pearson correlation between the degrees at both ends of each edge
+    pub fn degree_assortativity(&self) -> Option<Assortativity> {
+        let (_, lists) = index_adjacency(&self.undirected_adjacency());
+        // Each undirected edge contributes both orientations, which makes the correlation symmetric
+        let (mut sum_xy, mut sum_x, mut sum_x2, mut m) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        for (u, neighbors) in lists.iter().enumerate() {
+            for &v in neighbors {
+                let (ku, kv) = (lists[u].len() as f64, lists[v].len() as f64);
+                sum_xy += ku * kv;
+                sum_x += ku;
+                sum_x2 += ku * ku;
+                m += 1.0;
+            }
+        }
+        if m == 0.0 {
+            return None;
+        }
+        let mean = sum_x / m;
+        let variance = sum_x2 / m - mean * mean;
+        if variance <= 0.0 {
+            return None;
+        }
+        let coefficient = ((sum_xy / m - mean * mean) / variance) as f32;
+        // Average neighbour degree per degree class
+        let mut by_degree: HashMap<usize, (usize, f32)> = HashMap::new();
+        for neighbors in lists.iter().filter(|n| !n.is_empty()) {
+            let knn = neighbors.iter().map(|&v| lists[v].len() as f32).sum::<f32>() / neighbors.len() as f32;
+            let entry = by_degree.entry(neighbors.len()).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += knn;
+        }
+        let mut mixing: Vec<DegreeMixing> = by_degree.into_iter()
+            .map(|(degree, (stations, total))| DegreeMixing { degree, stations, average_neighbor_degree: total / stations as f32 })
+            .collect();
+        mixing.sort_by_key(|d| d.degree);
+        Some(Assortativity { coefficient, mixing })
+    }
+
+    // Jaccard similarity of two stations' neighbourhoods in the undirected graph
+    // Returns None if either station is not in the graph
+    pub fn jaccard_similarity(&self, a: &Station, b: &Station) -> Option<f32> {
+        let adj = self.undirected_adjacency();
+        let (na, nb) = (adj.get(a)?, adj.get(b)?);
+        let union = na.union(nb).count();
+        if union == 0 {
+            return Some(0.0);
+        }
+        Some(na.intersection(nb).count() as f32 / union as f32)
+    }
+
+    // Adamic-Adar index of two stations: shared neighbours weighted by 1 / ln(their degree)
+    // Returns None if either station is not in the graph
+    pub fn adamic_adar(&self, a: &Station, b: &Station) -> Option<f32> {
+        let adj = self.undirected_adjacency();
+        let (na, nb) = (adj.get(a)?, adj.get(b)?);
+        Some(adamic_adar_of(&adj, na, nb))
+    }
+
+    // Ranks non-adjacent station pairs sharing at least one neighbour by Adamic-Adar score
+    // Output: the top N candidates, ties broken by Jaccard then by name
+    pub fn predict_links(&self, top_n: usize) -> Vec<LinkPrediction> {
+        let adj = self.undirected_adjacency();
+        let mut names: Vec<&Station> = adj.keys().collect();
+        names.sort();
+        let mut candidates = Vec::new();
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                let (na, nb) = (&adj[*a], &adj[*b]);
+                if na.contains(*b) {
+                    continue; // Already connected
+                }
+                let common = na.intersection(nb).count();
+                if common == 0 {
+                    continue;
+                }
+                candidates.push(LinkPrediction {
+                    a: (*a).clone(),
+                    b: (*b).clone(),
+                    common_neighbors: common,
+                    jaccard: common as f32 / na.union(nb).count() as f32,
+                    adamic_adar: adamic_adar_of(&adj, na, nb),
+                });
+            }
+        }
+        candidates.sort_by(|x, y| {
+            y.adamic_adar.total_cmp(&x.adamic_adar)
+                .then(y.jaccard.total_cmp(&x.jaccard))
+                .then_with(|| (&x.a, &x.b).cmp(&(&y.a, &y.b)))
+        });
+        candidates.truncate(top_n);
+        candidates
+    }
+}
+
+// Adamic-Adar score of two neighbourhoods; degree-1 shared neighbours cannot occur, degree 2+ have ln > 0
+// Terms are summed in degree order so the float total does not depend on set iteration order
+fn adamic_adar_of(adj: &HashMap<Station, HashSet<Station>>, na: &HashSet<Station>, nb: &HashSet<Station>) -> f32 {
+    let mut degrees: Vec<usize> = na.intersection(nb).map(|z| adj[z].len()).filter(|&k| k > 1).collect();
+    degrees.sort_unstable();
+    degrees.into_iter().map(|k| 1.0 / (k as f32).ln()).sum()
+}
+
+// Average local clustering coefficient over index adjacency lists
+fn clustering_of(lists: &[Vec<usize>]) -> f32 {
+    if lists.is_empty() {
+        return 0.0;
+    }
+    let sets: Vec<HashSet<usize>> = lists.iter().map(|l| l.iter().copied().collect()).collect();
+    let total: f32 = lists.iter()
+        .map(|neighbors| {
+            let k = neighbors.len();
+            if k < 2 {
+                return 0.0;
+            }
+            let mut links = 0;
+            for (i, &u) in neighbors.iter().enumerate() {
+                for &v in &neighbors[i + 1..] {
+                    if sets[u].contains(&v) {
+                        links += 1;
+                    }
+                }
+            }
+            2.0 * links as f32 / (k * (k - 1)) as f32
+        })
+        .sum();
+    total / lists.len() as f32
+}
+
+// Mean BFS hop distance over all ordered connected pairs
+fn path_length_of(lists: &[Vec<usize>]) -> Option<f32> {
+    let mut total: u64 = 0;
+    let mut pairs: u64 = 0;
+    let mut dist = vec![usize::MAX; lists.len()];
+    let mut queue = VecDeque::new();
+    for s in 0..lists.len() {
+        dist.iter_mut().for_each(|d| *d = usize::MAX);
+        dist[s] = 0;
+        queue.push_back(s);
+        while let Some(v) = queue.pop_front() {
+            for &w in &lists[v] {
+                if dist[w] == usize::MAX {
+                    dist[w] = dist[v] + 1;
+                    total += dist[w] as u64;
+                    pairs += 1;
+                    queue.push_back(w);
+                }
+            }
+        }
+    }
+    if pairs == 0 { None } else { Some(total as f32 / pairs as f32) }
+}
+
+// Degree-preserving randomization by repeated double-edge swaps (10 attempts per edge)
+// Swaps (a, b), (c, d) -> (a, d), (c, b) unless that creates a self-loop or a parallel edge
+fn rewire(lists: &[Vec<usize>], rng: &mut StdRng) -> Vec<Vec<usize>> {
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    for (u, neighbors) in lists.iter().enumerate() {
+        edges.extend(neighbors.iter().filter(|&&v| u < v).map(|&v| (u, v)));
+    }
+    let mut present: HashSet<(usize, usize)> = edges.iter().copied().collect();
+    let ordered = |x: usize, y: usize| if x < y { (x, y) } else { (y, x) };
+    if edges.len() >= 2 {
+        for _ in 0..edges.len() * 10 {
+            let i = rng.random_range(0..edges.len());
+            let j = rng.random_range(0..edges.len());
+            if i == j {
+                continue;
+            }
+            let (a, b) = edges[i];
+            let (c, d) = if rng.random_bool(0.5) { edges[j] } else { (edges[j].1, edges[j].0) };
+            let (e1, e2) = (ordered(a, d), ordered(c, b));
+            if a == d || c == b || present.contains(&e1) || present.contains(&e2) {
+                continue;
+            }
+            present.remove(&edges[i]);
+            present.remove(&edges[j]);
+            present.insert(e1);
+            present.insert(e2);
+            edges[i] = e1;
+            edges[j] = e2;
+        }
+    }
+    let mut random = vec![Vec::new(); lists.len()];
+    for (u, v) in edges {
+        random[u].push(v);
+        random[v].push(u);
+    }
+    random
+}
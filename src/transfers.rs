@@ -0,0 +1,175 @@
+// Transfer-point recommendations: for an origin-destination pair, every interchange (a station whose segments carry at
+// least two lines) a journey could change trains at is scored on the expected delay through it and the risk of missing
+// the connection there, and the one with the lowest expected cost is recommended
+// Expected delay sums the mean observed delay of each segment on the least expected-delay legs origin → interchange
+// and interchange → destination. A record's delay is the train's lateness arriving at its `to` station, so the delays
+// observed on the last segment into the interchange are its historical arrival lateness there: the missed-connection
+// risk is the share of them above the connection slack, and the expected cost adds that risk times the wait for the
+// next train. Interchanges with too few observed arrivals to judge the risk by are left out
+
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::distances::DistanceMatrix;
+use crate::error::{Error, Result};
+use crate::flows::{od_demand, FlowOptions};
+use crate::graph::{join_names, station_id, FastMap, Station, StationId, TransitGraph};
+use crate::load::TrainRecord;
+
+// Minutes between a scheduled arrival and the connecting departure unless set
+pub const DEFAULT_SLACK: f32 = 5.0;
+
+// Minutes waited for the next train after a missed connection unless set
+pub const DEFAULT_MISS_PENALTY: f32 = 30.0;
+
+// Major origin-destination pairs evaluated unless set
+pub const DEFAULT_PAIRS: usize = 5;
+
+// How connections are judged
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TransferOptions {
+    pub slack: f32,          // An arrival later than this misses the connection
+    pub miss_penalty: f32,   // Expected extra wait after a missed connection
+    pub min_arrivals: usize, // Observed arrivals into an interchange needed to consider it
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self { slack: DEFAULT_SLACK, miss_penalty: DEFAULT_MISS_PENALTY, min_arrivals: 5 }
+    }
+}
+
+// One interchange a journey could change trains at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferOption {
+    pub transfer: Station,
+    pub expected_delay: f32,         // Sum of mean segment delays via the interchange
+    pub missed_connection_risk: f32, // Share of observed arrivals into the interchange later than the slack
+    pub expected_cost: f32,          // expected_delay + missed_connection_risk × miss penalty
+    pub arrivals: usize,             // Observed arrivals the risk is based on
+    pub route: String,
+}
+
+// The recommendation for one origin-destination pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRecommendation {
+    pub origin: Station,
+    pub destination: Station,
+    pub passengers: Option<f32>,      // Gravity-model demand, for a pair picked as a major one
+    pub direct_delay: Option<f32>,    // Expected delay of the least expected-delay path, wherever it changes trains
+    pub recommended: Option<Station>, // None if no interchange with enough arrivals lies on a journey between the two
+    pub expected_cost: Option<f32>,
+    pub missed_connection_risk: Option<f32>,
+    pub alternatives: usize,
+}
+
+// A recommendation with every option considered, cheapest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferPlan {
+    #[serde(flatten)]
+    pub recommendation: TransferRecommendation,
+    pub options: Vec<TransferOption>,
+}
+
+// Scores transfers over one dataset; the expected-delay network and its distance matrix are built once
+pub struct TransferPlanner<'g> {
+    graph: &'g TransitGraph,
+    expected: TransitGraph, // Every segment weighted by its mean observed delay
+    matrix: DistanceMatrix,
+    interchanges: Vec<Station>,
+}
+
+// Stations whose segments, in or out, carry at least two lines, in name order
+pub fn interchanges(graph: &TransitGraph) -> Vec<Station> {
+    let mut lines: FastMap<&Station, HashSet<&str>> = FastMap::default();
+    for ((from, to), served) in &graph.lines {
+        for station in [from, to] {
+            lines.entry(station).or_default().extend(served.iter().map(|l| l.trim()).filter(|l| !l.is_empty()));
+        }
+    }
+    let mut stations: Vec<Station> = lines.into_iter().filter(|(_, l)| l.len() >= 2).map(|(s, _)| s.clone()).collect();
+    stations.sort();
+    stations
+}
+
+impl<'g> TransferPlanner<'g> {
+    // Output: an invalid-parameter error if the network is too large for a distance matrix
+    pub fn new(records: &[TrainRecord], graph: &'g TransitGraph) -> Result<Self> {
+        let means: FastMap<(StationId, StationId), f32> = graph.get_route_average_delays().into_iter()
+            .map(|((from, to), delay, _)| ((from.id, to.id), delay))
+            .collect();
+        let expected = TransitGraph::from_records_with(records, |r| {
+            r.delay().and_then(|_| means.get(&(station_id(&r.from_id), station_id(&r.to_id))).copied())
+        });
+        let matrix = expected.distance_matrix()?;
+        Ok(Self { graph, expected, matrix, interchanges: interchanges(graph) })
+    }
+
+    // Scores every interchange between two stations of the graph
+    // Output: an invalid-parameter error for a negative or non-finite slack or miss penalty
+    pub fn plan(&self, origin: &Station, destination: &Station, opts: &TransferOptions) -> Result<TransferPlan> {
+        for (name, minutes) in [("slack", opts.slack), ("miss penalty", opts.miss_penalty)] {
+            if !(minutes >= 0.0 && minutes.is_finite()) {
+                return Err(Error::InvalidParameter { name, reason: format!("{} is not a non-negative number of minutes", minutes) });
+            }
+        }
+        let (o, d) = (self.matrix.index_of(origin), self.matrix.index_of(destination));
+        let mut options: Vec<TransferOption> = self.interchanges.iter()
+            .filter(|t| *t != origin && *t != destination)
+            .filter_map(|t| {
+                let (o, d, i) = (o?, d?, self.matrix.index_of(t)?);
+                let expected_delay = self.matrix.get(o, i)? + self.matrix.get(i, d)?;
+                let (_, inbound) = self.expected.shortest_path(origin, t)?;
+                let (_, outbound) = self.expected.shortest_path(t, destination)?;
+                let arrivals: Vec<f32> = self.graph.observations(&inbound[inbound.len() - 2], t).collect();
+                if arrivals.is_empty() || arrivals.len() < opts.min_arrivals {
+                    return None;
+                }
+                let risk = arrivals.iter().filter(|&&delay| delay > opts.slack).count() as f32 / arrivals.len() as f32;
+                let route = [inbound, outbound.into_iter().skip(1).collect()].concat();
+                Some(TransferOption {
+                    transfer: t.clone(),
+                    expected_delay,
+                    missed_connection_risk: risk,
+                    expected_cost: expected_delay + risk * opts.miss_penalty,
+                    arrivals: arrivals.len(),
+                    route: join_names(&route, " → "),
+                })
+            })
+            .collect();
+        options.sort_by(|a, b| {
+            a.expected_cost.total_cmp(&b.expected_cost)
+                .then(a.missed_connection_risk.total_cmp(&b.missed_connection_risk))
+                .then_with(|| a.transfer.cmp(&b.transfer))
+        });
+        let best = options.first();
+        Ok(TransferPlan {
+            recommendation: TransferRecommendation {
+                origin: origin.clone(),
+                destination: destination.clone(),
+                passengers: None,
+                direct_delay: o.zip(d).and_then(|(o, d)| self.matrix.get(o, d)),
+                recommended: best.map(|b| b.transfer.clone()),
+                expected_cost: best.map(|b| b.expected_cost),
+                missed_connection_risk: best.map(|b| b.missed_connection_risk),
+                alternatives: options.len(),
+            },
+            options,
+        })
+    }
+}
+
+// Plans the `pairs` origin-destination pairs with the most gravity-model demand whose least-delay path has an
+// intermediate station, so a change of trains along the way is possible
+// Output: the errors of `od_demand` and `TransferPlanner`
+pub fn recommend_major(records: &[TrainRecord], graph: &TransitGraph, pairs: usize, opts: &TransferOptions) -> Result<Vec<TransferPlan>> {
+    let planner = TransferPlanner::new(records, graph)?;
+    od_demand(records, graph, &FlowOptions::default())?.into_iter()
+        .filter(|pair| pair.segments >= 2)
+        .take(pairs)
+        .map(|pair| {
+            let mut plan = planner.plan(&pair.origin, &pair.destination, opts)?;
+            plan.recommendation.passengers = Some(pair.passengers);
+            Ok(plan)
+        })
+        .collect()
+}
@@ -0,0 +1,230 @@
+// Terminal dashboard over the existing metrics: delayed routes, centrality rankings, and a searchable station pane
+
+use std::collections::HashMap;
+use std::io;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use crate::graph::{TransitGraph, Station};
+use crate::metrics::{RankOptions, RouteStat, StationScore};
+
+const TAB_TITLES: [&str; 3] = ["Delayed routes", "Centrality", "Stations"];
+
+// Everything the dashboard shows, computed once up front
+pub struct App {
+    tab: usize,
+    routes: Vec<RouteStat>,        // All routes meeting the trip minimum, worst first
+    closeness: Vec<StationScore>,  // All stations, most central first
+    betweenness: Vec<StationScore>,
+    stations: Vec<Station>,        // All station names, sorted
+    outbound: HashMap<Station, Vec<RouteStat>>,
+    inbound: HashMap<Station, Vec<RouteStat>>,
+    route_state: TableState,
+    station_state: ListState,
+    query: String,                 // Station search text
+    searching: bool,               // True while typing into the search box
+    pub quit: bool,
+}
+
+impl App {
+    // Precomputes every ranking over the whole graph
+    pub fn new(graph: &TransitGraph, opts: &RankOptions) -> Self {
+        let all = RankOptions { top_n: usize::MAX, ..*opts };
+        let every_route = RankOptions { min_trips: 1, ..all };
+        let mut outbound: HashMap<Station, Vec<RouteStat>> = HashMap::new();
+        let mut inbound: HashMap<Station, Vec<RouteStat>> = HashMap::new();
+        for route in graph.rank_routes_by_average_delay(&every_route) {
+            outbound.entry(route.from.clone()).or_default().push(route.clone());
+            inbound.entry(route.to.clone()).or_default().push(route);
+        }
+        let mut stations: Vec<Station> = graph.all_stations().into_iter().collect();
+        stations.sort();
+        let mut app = Self {
+            tab: 0,
+            routes: graph.rank_routes_by_average_delay(&all),
+            closeness: graph.rank_stations_by_closeness(&all),
+            betweenness: graph.rank_stations_by_betweenness(&all),
+            stations,
+            outbound,
+            inbound,
+            route_state: TableState::default(),
+            station_state: ListState::default(),
+            query: String::new(),
+            searching: false,
+            quit: false,
+        };
+        app.route_state.select(Some(0));
+        app.station_state.select(Some(0));
+        app
+    }
+
+    // Stations whose name contains the search text, case-insensitively
+    pub fn filtered_stations(&self) -> Vec<&Station> {
+        let query = self.query.to_lowercase();
+        self.stations.iter().filter(|s| s.name.to_lowercase().contains(&query)).collect()
+    }
+
+    // The station highlighted in the station list, if any
+    pub fn selected_station(&self) -> Option<&Station> {
+        self.filtered_stations().get(self.station_state.selected()?).copied()
+    }
+
+    // Updates state for one key press
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.searching {
+            match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.searching = false,
+                KeyCode::Backspace => { self.query.pop(); }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => {}
+            }
+            self.station_state.select(Some(0));
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab | KeyCode::Right => self.tab = (self.tab + 1) % TAB_TITLES.len(),
+            KeyCode::BackTab | KeyCode::Left => self.tab = (self.tab + TAB_TITLES.len() - 1) % TAB_TITLES.len(),
+            KeyCode::Char('/') => {
+                self.tab = 2;
+                self.searching = true;
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        let (len, state) = match self.tab {
+            0 => (self.routes.len(), self.route_state.selected_mut()),
+            2 => (self.filtered_stations().len(), self.station_state.selected_mut()),
+            _ => return,
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.unwrap_or(0) as isize;
+        *state = Some((current + step).clamp(0, len as isize - 1) as usize);
+    }
+
+    // Draws the whole dashboard
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.area());
+        let tabs = Tabs::new(TAB_TITLES.to_vec())
+            .block(Block::default().borders(Borders::ALL).title("nj-delays"))
+            .select(self.tab)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+        frame.render_widget(tabs, chunks[0]);
+        match self.tab {
+            0 => self.draw_routes(frame, chunks[1]),
+            1 => self.draw_centrality(frame, chunks[1]),
+            _ => self.draw_stations(frame, chunks[1]),
+        }
+        let help = if self.searching {
+            format!("Search: {}_  (Enter/Esc to finish)", self.query)
+        } else {
+            "Tab/←→ switch tabs · ↑↓ move · / search stations · q quit".to_string()
+        };
+        frame.render_widget(Paragraph::new(help), chunks[2]);
+    }
+
+    fn draw_routes(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.routes.iter().enumerate().map(|(i, r)| {
+            Row::new(vec![
+                format!("{}", i + 1),
+                r.from.name.clone(),
+                r.to.name.clone(),
+                format!("{:.2}", r.average_delay),
+                r.trips.to_string(),
+                format!("{:.0}%", r.on_time_rate * 100.0),
+            ])
+        });
+        let table = Table::new(rows, [
+            Constraint::Length(4), Constraint::Percentage(35), Constraint::Percentage(35),
+            Constraint::Length(9), Constraint::Length(6), Constraint::Length(8),
+        ])
+            .header(Row::new(vec!["#", "From", "To", "Avg min", "Trips", "On time"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title("Routes by average delay"))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.route_state);
+    }
+
+    fn draw_centrality(&self, frame: &mut Frame, area: Rect) {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        for (scores, title, half) in [(&self.closeness, "Closeness", halves[0]), (&self.betweenness, "Betweenness", halves[1])] {
+            let rows = scores.iter().enumerate()
+                .map(|(i, s)| Row::new(vec![format!("{}", i + 1), s.station.name.clone(), format!("{:.4}", s.score)]));
+            let table = Table::new(rows, [Constraint::Length(4), Constraint::Min(10), Constraint::Length(12)])
+                .header(Row::new(vec!["#", "Station", "Score"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                .block(Block::default().borders(Borders::ALL).title(title));
+            frame.render_widget(table, half);
+        }
+    }
+
+    fn draw_stations(&mut self, frame: &mut Frame, area: Rect) {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(area);
+        let items: Vec<ListItem> = self.filtered_stations().into_iter().map(|s| ListItem::new(s.name.clone())).collect();
+        let title = if self.query.is_empty() { "Stations".to_string() } else { format!("Stations matching \"{}\"", self.query) };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, halves[0], &mut self.station_state);
+
+        let mut lines: Vec<Line> = Vec::new();
+        if let Some(station) = self.selected_station() {
+            let rank_of = |scores: &[StationScore]| scores.iter().position(|s| &s.station == station);
+            lines.push(Line::from(station.name.clone()).style(Style::default().add_modifier(Modifier::BOLD)));
+            match rank_of(&self.closeness) {
+                Some(i) => lines.push(Line::from(format!("Closeness:   {:.4} (#{})", self.closeness[i].score, i + 1))),
+                None => lines.push(Line::from("Closeness:   n/a")),
+            }
+            if let Some(i) = rank_of(&self.betweenness) {
+                lines.push(Line::from(format!("Betweenness: {:.4} (#{})", self.betweenness[i].score, i + 1)));
+            }
+            for (label, routes, outgoing) in [("Outbound", self.outbound.get(station), true), ("Inbound", self.inbound.get(station), false)] {
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("{} routes:", label)).style(Style::default().add_modifier(Modifier::BOLD)));
+                for r in routes.into_iter().flatten() {
+                    let other = if outgoing { &r.to } else { &r.from };
+                    lines.push(Line::from(format!("  {:<28} {:>6.2} min  {:>3} trips", other, r.average_delay, r.trips)));
+                }
+            }
+        }
+        let detail = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Detail"));
+        frame.render_widget(detail, halves[1]);
+    }
+}
+
+// Runs the dashboard until the user quits, restoring the terminal afterwards
+pub fn run(graph: &TransitGraph, opts: &RankOptions) -> io::Result<()> {
+    let mut app = App::new(graph, opts);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+// Data-quality checks over the raw CSV, run row by row without building the graph
+
+use chrono::{Datelike, NaiveDate};
+use csv::ReaderBuilder;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::load::TrainRecord;
+
+// Delays above this many minutes are flagged as likely data errors
+pub const OUTLIER_DELAY_MINUTES: f32 = 180.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,   // The row cannot be used
+    Warning, // The row loads but looks suspicious
+}
+
+// One problem found on one row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub row: u64,            // 1-based line number in the file, counting the header
+    pub severity: Severity,
+    pub field: String,       // Column the issue concerns, or "record" for parse failures
+    pub message: String,
+}
+
+// Pass/fail summary for a whole file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationSummary {
+    pub file: String,
+    pub rows: usize,       // Data rows read, excluding the header
+    pub valid_rows: usize, // Rows without any error-level issue
+    pub errors: usize,
+    pub warnings: usize,
+    pub result: String,    // "PASS" if there are no errors, otherwise "FAIL"
+}
+
+// Reads every row of the file and checks it
+// Input: path to CSV file as &str
+// Output: summary and row-level issues; Err only if the file or its header cannot be read
+// Logic: Deserialize rows one at a time so a bad row is reported instead of aborting the load
+pub fn validate_file(path: &str) -> Result<(ValidationSummary, Vec<Issue>)> {
+    let failed = |source| Error::Load { path: path.to_string(), source };
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_path(path).map_err(failed)?;
+    let headers = rdr.headers().map_err(failed)?.clone();
+    let mut issues = Vec::new();
+    let mut rows = 0;
+    let mut valid_rows = 0;
+    for (i, result) in rdr.records().enumerate() {
+        rows += 1;
+        let row = result.as_ref().ok().and_then(|r| r.position()).map_or(i as u64 + 2, |p| p.line());
+        let parsed = result.map_err(|e| e.to_string())
+            .and_then(|r| r.deserialize::<TrainRecord>(Some(&headers)).map_err(|e| e.to_string()));
+        let found = match parsed {
+            Ok(record) => check_record(&record),
+            Err(message) => vec![(Severity::Error, "record".to_string(), message)],
+        };
+        if found.iter().all(|(severity, _, _)| *severity != Severity::Error) {
+            valid_rows += 1;
+        }
+        issues.extend(found.into_iter().map(|(severity, field, message)| Issue { row, severity, field, message }));
+    }
+    let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+    let summary = ValidationSummary {
+        file: path.to_string(),
+        rows,
+        valid_rows,
+        errors,
+        warnings: issues.len() - errors,
+        result: if errors == 0 { "PASS" } else { "FAIL" }.to_string(),
+    };
+    Ok((summary, issues))
+}
+
+// Runs the per-record quality checks
+// Output: (severity, field, message) for each problem found
+pub fn check_record(r: &TrainRecord) -> Vec<(Severity, String, String)> {
+    let mut found = Vec::new();
+    let mut issue = |severity, field: &str, message: String| found.push((severity, field.to_string(), message));
+    for (field, name) in [("from", &r.from), ("to", &r.to)] {
+        if name.trim().is_empty() {
+            issue(Severity::Error, field, "empty station name".into());
+        }
+    }
+    if !r.from.trim().is_empty() && r.from == r.to {
+        issue(Severity::Warning, "to", format!("segment starts and ends at {}", r.to));
+    }
+    match NaiveDate::parse_from_str(&r.date, "%Y-%m-%d") {
+        Ok(date) => {
+            if r.year.trim().parse::<i32>().is_ok_and(|y| y != date.year()) {
+                issue(Severity::Warning, "year", format!("year {} disagrees with date {}", r.year, r.date));
+            }
+            if r.month.trim().parse::<u32>().is_ok_and(|m| m != date.month()) {
+                issue(Severity::Warning, "month", format!("month {} disagrees with date {}", r.month, r.date));
+            }
+        }
+        Err(_) => issue(Severity::Error, "date", format!("unparsable date {:?}", r.date)),
+    }
+    match r.delay_minutes {
+        None => issue(Severity::Warning, "delay_minutes", "missing delay".into()),
+        Some(d) if !d.is_finite() => issue(Severity::Error, "delay_minutes", format!("non-finite delay {}", d)),
+        Some(d) if d < 0.0 => issue(Severity::Warning, "delay_minutes", format!("negative delay {}", d)),
+        Some(d) if d > OUTLIER_DELAY_MINUTES => issue(Severity::Warning, "delay_minutes", format!("delay of {} minutes exceeds {}", d, OUTLIER_DELAY_MINUTES)),
+        Some(_) => {}
+    }
+    if r.stop_sequence.trim().parse::<f32>().is_err() {
+        issue(Severity::Warning, "stop_sequence", format!("unparsable stop sequence {:?}", r.stop_sequence));
+    }
+    found
+}
@@ -0,0 +1,138 @@
+// Polls a directory of CSV files, re-parsing only files that are new or changed since the last scan
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::graph::TransitGraph;
+use crate::load::{load_data, TrainRecord};
+
+// What changed in the directory during one scan
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub loaded: Vec<PathBuf>,                // New or modified files parsed successfully
+    pub removed: Vec<PathBuf>,               // Files that disappeared since the last scan
+    pub failed: Vec<(PathBuf, String)>,      // Files that could not be parsed (retried next scan)
+}
+
+impl ScanResult {
+    // True if the combined dataset changed
+    pub fn changed(&self) -> bool {
+        !self.loaded.is_empty() || !self.removed.is_empty()
+    }
+}
+
+// Headline numbers over everything ingested so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub files: usize,
+    pub records: usize,
+    pub stations: usize,
+    pub routes: usize,      // Distinct (from, to) segments with delay data
+    pub average_delay: f32, // Mean delay over all records with one, in minutes
+    pub on_time_rate: f32,  // Share of those records at or below the on-time threshold
+}
+
+// Incrementally ingested contents of a watched directory
+pub struct DirectoryWatcher {
+    dir: PathBuf,
+    files: HashMap<PathBuf, (SystemTime, Vec<TrainRecord>)>, // Last seen modification time and parsed records per file
+}
+
+impl DirectoryWatcher {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), files: HashMap::new() }
+    }
+
+    // Checks the directory once
+    // Output: files loaded, removed, or failed in this scan; Err only if the directory cannot be listed
+    // Logic: a file is re-parsed when its modification time differs from the last successful load;
+    // a file that fails to parse keeps its previous records (if any) and is retried on the next scan, as does one
+    // whose metadata cannot be read; a file deleted between listing and reading its metadata counts as removed
+    pub fn scan(&mut self) -> io::Result<ScanResult> {
+        let mut result = ScanResult::default();
+        let mut present: Vec<(PathBuf, SystemTime)> = Vec::new();
+        let mut unreadable: Vec<PathBuf> = Vec::new();
+        let mut complete = true; // Whether every entry of the listing could be read
+        for entry in fs::read_dir(&self.dir)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    result.failed.push((self.dir.clone(), e.to_string()));
+                    complete = false;
+                    continue;
+                }
+            };
+            if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+                match fs::metadata(&path).and_then(|m| m.modified()) {
+                    Ok(modified) => present.push((path, modified)),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        result.failed.push((path.clone(), e.to_string()));
+                        unreadable.push(path);
+                    }
+                }
+            }
+        }
+        present.sort();
+        // A listing with an unreadable entry may have missed files that are still there, so nothing is removed after one
+        let removed: Vec<PathBuf> = self.files.keys()
+            .filter(|p| complete && !present.iter().any(|(q, _)| q == *p) && !unreadable.contains(p))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.files.remove(&path);
+            result.removed.push(path);
+        }
+        for (path, modified) in present {
+            if self.files.get(&path).is_some_and(|(seen, _)| *seen == modified) {
+                continue;
+            }
+            match load_data(&path.to_string_lossy()) {
+                Ok(records) => {
+                    self.files.insert(path.clone(), (modified, records));
+                    result.loaded.push(path);
+                }
+                Err(e) => result.failed.push((path, e.to_string())),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Files ingested so far, in name order
+    pub fn paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = self.files.keys().map(PathBuf::as_path).collect();
+        paths.sort();
+        paths
+    }
+
+    // All records ingested so far, in file-name order
+    pub fn records(&self) -> Vec<TrainRecord> {
+        self.paths().into_iter().flat_map(|p| self.file_records(p).iter().cloned()).collect()
+    }
+
+    // Records parsed from one file, empty if it is not (or no longer) ingested
+    pub fn file_records(&self, path: &Path) -> &[TrainRecord] {
+        self.files.get(path).map_or(&[], |(_, records)| records)
+    }
+
+    // Headline numbers over the given (possibly filtered) records and their graph
+    pub fn snapshot(&self, records: &[TrainRecord], graph: &TransitGraph, on_time_threshold: f32) -> Snapshot {
+        let delays: Vec<f32> = records.iter().filter_map(|r| r.delay()).collect();
+        let count = delays.len().max(1) as f32;
+        Snapshot {
+            files: self.files.len(),
+            records: records.len(),
+            stations: graph.all_stations().len(),
+            routes: graph.lines.len(),
+            average_delay: delays.iter().sum::<f32>() / count,
+            on_time_rate: delays.iter().filter(|&&d| d <= on_time_threshold).count() as f32 / count,
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// What-if analysis: the network with stations or routes taken out of service, or hypothetical connections added (a new
+// transfer, a restored line), set against the baseline on reachability, least delays between the stations in both,
+// the worst least-delay journey, and centrality
+// Removing a station removes every segment into and out of it; removing a route removes its directed (from, to)
+// segment. An added connection is one directed segment weighted with an assumed number of minutes, in the graph's own
+// weight (delay, so the delay expected on the new connection)
+
+use serde::{Deserialize, Serialize};
+use crate::comparison::{centrality_shifts, CentralityShift};
+use crate::distances::{DistanceMatrix, StationIndex};
+use crate::error::{Error, Result};
+use crate::graph::{Station, TransitGraph};
+use crate::metrics::RankOptions;
+
+// Line name of the segments a scenario adds
+pub const HYPOTHETICAL_LINE: &str = "Hypothetical";
+
+// A part of the network to take out, by station name or stop ID as `resolve_station` accepts them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element<'a> {
+    Station(&'a str),
+    Route(&'a str, &'a str),
+}
+
+// How one station's journeys change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationImpact {
+    pub station: Station,
+    pub lost_destinations: usize,          // Stations other than removed ones it reached before but no longer does
+    pub gained_destinations: usize,        // Stations of the baseline it reaches only in the scenario
+    pub average_delay_before: Option<f32>, // Mean least delay to the destinations it reaches in both networks
+    pub average_delay_after: Option<f32>,
+    pub delay_change: Option<f32>,         // After − before; positive means longer delays
+}
+
+// The network-wide impact of a scenario against the baseline network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactSummary {
+    pub scenario: String,
+    pub stations_before: usize,
+    pub stations_after: usize,
+    pub reachable_pairs_before: usize,     // Ordered pairs of distinct stations joined by a path
+    pub reachable_pairs_after: usize,
+    pub lost_pairs: usize,                 // Pairs reachable before but not after, except those of removed stations
+    pub gained_pairs: usize,               // Pairs of baseline stations reachable only in the scenario
+    pub average_delay_before: Option<f32>, // Mean least delay over the pairs reachable in both networks
+    pub average_delay_after: Option<f32>,
+    pub diameter_before: Option<f32>,      // Largest least delay of any reachable pair
+    pub diameter_after: Option<f32>,
+}
+
+// The impact of a scenario: network-wide, per station, and on centrality
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactReport {
+    #[serde(flatten)]
+    pub summary: ImpactSummary,
+    pub affected: Vec<StationImpact>, // Stations whose destinations or delays changed: most lost, then most gained first
+    pub centrality_shifts: Vec<CentralityShift>, // Stations in both networks by closeness change (A = baseline, B = scenario)
+}
+
+// Changes to a baseline network, applied in `network` and measured in `evaluate`
+#[derive(Debug, Clone)]
+pub struct Scenario<'g> {
+    graph: &'g TransitGraph,
+    removed_stations: Vec<Station>,
+    removed_routes: Vec<(Station, Station)>,
+    added: Vec<(Station, Station, f32)>,
+    steps: Vec<String>, // Description of each change, in order
+}
+
+impl<'g> Scenario<'g> {
+    // A scenario that changes nothing yet
+    pub fn new(graph: &'g TransitGraph) -> Self {
+        Self { graph, removed_stations: Vec::new(), removed_routes: Vec::new(), added: Vec::new(), steps: Vec::new() }
+    }
+
+    // Takes a station or route out of service
+    // Output: an unknown-station error for a name the graph does not know, or an invalid-parameter error for a route
+    // the graph has no segment for
+    pub fn remove(mut self, element: Element) -> Result<Self> {
+        match element {
+            Element::Station(name) => {
+                let station = self.graph.resolve_station(name)?;
+                self.steps.push(format!("remove station {}", station.name));
+                self.removed_stations.push(station);
+            }
+            Element::Route(from, to) => {
+                let (from, to) = (self.graph.resolve_station(from)?, self.graph.resolve_station(to)?);
+                if self.graph.observations(&from, &to).next().is_none() {
+                    return Err(Error::InvalidParameter { name: "route", reason: format!("no segment from {} to {}", from.name, to.name) });
+                }
+                self.steps.push(format!("remove route {} → {}", from.name, to.name));
+                self.removed_routes.push((from, to));
+            }
+        }
+        Ok(self)
+    }
+
+    // Adds a hypothetical direct connection between two stations of the graph, weighted `minutes`
+    // Output: an unknown-station error for a name the graph does not know, or an invalid-parameter error for a
+    // negative or non-finite weight or a connection from a station to itself
+    pub fn connect(mut self, from: &str, to: &str, minutes: f32) -> Result<Self> {
+        let (from, to) = (self.graph.resolve_station(from)?, self.graph.resolve_station(to)?);
+        if !(minutes >= 0.0 && minutes.is_finite()) {
+            return Err(Error::InvalidParameter { name: "connection", reason: format!("{} is not a non-negative number of minutes", minutes) });
+        }
+        if from == to {
+            return Err(Error::InvalidParameter { name: "connection", reason: format!("{} cannot connect to itself", from.name) });
+        }
+        self.steps.push(format!("connect {} → {} at {} min", from.name, to.name, minutes));
+        self.added.push((from, to, minutes));
+        Ok(self)
+    }
+
+    // What the scenario does, e.g. "remove station Secaucus; connect Hoboken → Newark Penn at 4 min"
+    pub fn describe(&self) -> String {
+        if self.steps.is_empty() { "no changes".to_string() } else { self.steps.join("; ") }
+    }
+
+    // The changed network: removals first, then the added connections (which may serve removed stations again)
+    pub fn network(&self) -> TransitGraph {
+        let kept = self.graph.retain_segments(|from, to| {
+            !self.removed_stations.iter().any(|s| s == from || s == to) && !self.removed_routes.iter().any(|(a, b)| a == from && b == to)
+        });
+        if self.added.is_empty() { kept } else { kept.with_segments(&self.added, HYPOTHETICAL_LINE) }
+    }
+
+    // Measures the changed network against the baseline
+    // Output: an invalid-parameter error if either network is too large for a distance matrix
+    pub fn evaluate(&self) -> Result<ImpactReport> {
+        let served = |s: &Station| self.added.iter().any(|(a, b, _)| a == s || b == s);
+        let removed: Vec<Station> = self.removed_stations.iter().filter(|s| !served(s)).cloned().collect();
+        impact(self.describe(), self.graph, &self.network(), &removed)
+    }
+}
+
+// Takes one station or route out of the graph and reports the impact; see `Scenario::remove`
+pub fn remove(graph: &TransitGraph, element: Element) -> Result<ImpactReport> {
+    Scenario::new(graph).remove(element)?.evaluate()
+}
+
+// Adds one hypothetical connection to the graph and reports the impact; see `Scenario::connect`
+pub fn connect(graph: &TransitGraph, from: &str, to: &str, minutes: f32) -> Result<ImpactReport> {
+    Scenario::new(graph).connect(from, to, minutes)?.evaluate()
+}
+
+// Compares a scenario network with the baseline; journeys from and to `removed` stations are not counted as lost
+// Logic: a baseline station with no segment left in the scenario reaches nothing and is reached by nothing
+// Output: an invalid-parameter error if either network is too large for a distance matrix
+pub fn impact(scenario: String, baseline: &TransitGraph, modified: &TransitGraph, removed: &[Station]) -> Result<ImpactReport> {
+    let (before, after) = (baseline.distance_matrix()?, modified.distance_matrix()?);
+    // Baseline stations that were not removed, with their index in each matrix
+    let common: Vec<(usize, Option<StationIndex>)> = (0..before.len())
+        .filter(|&i| !removed.contains(before.station(i as StationIndex)))
+        .map(|i| (i, after.index_of(before.station(i as StationIndex))))
+        .collect();
+    let (mut lost_pairs, mut gained_pairs, mut total_before, mut total_after, mut pairs) = (0, 0, 0.0, 0.0, 0usize);
+    let mut affected = Vec::new();
+    for &(i, i2) in &common {
+        let row_before = before.row(i as StationIndex);
+        let row_after = i2.map(|i2| after.row(i2));
+        let (mut lost, mut gained, mut sum_before, mut sum_after, mut reached) = (0, 0, 0.0, 0.0, 0usize);
+        for &(j, j2) in common.iter().filter(|&&(j, _)| j != i) {
+            let b = row_before[j];
+            let a = row_after.zip(j2).map_or(f32::NAN, |(row, j2)| row[j2 as usize]);
+            match (b.is_nan(), a.is_nan()) {
+                (false, true) => lost += 1,
+                (true, false) => gained += 1,
+                (false, false) => {
+                    sum_before += b;
+                    sum_after += a;
+                    reached += 1;
+                }
+                (true, true) => {}
+            }
+        }
+        lost_pairs += lost;
+        gained_pairs += gained;
+        total_before += sum_before;
+        total_after += sum_after;
+        pairs += reached;
+        let mean = |sum: f32| (reached > 0).then(|| sum / reached as f32);
+        let (average_delay_before, average_delay_after) = (mean(sum_before), mean(sum_after));
+        affected.push(StationImpact {
+            station: before.station(i as StationIndex).clone(),
+            lost_destinations: lost,
+            gained_destinations: gained,
+            delay_change: average_delay_before.zip(average_delay_after).map(|(b, a)| a - b),
+            average_delay_before,
+            average_delay_after,
+        });
+    }
+    affected.retain(|s| s.lost_destinations > 0 || s.gained_destinations > 0 || s.delay_change.is_some_and(|c| c.abs() > f32::EPSILON));
+    affected.sort_by(|x, y| {
+        y.lost_destinations.cmp(&x.lost_destinations)
+            .then(y.gained_destinations.cmp(&x.gained_destinations))
+            .then(y.delay_change.unwrap_or(0.0).abs().total_cmp(&x.delay_change.unwrap_or(0.0).abs()))
+            .then_with(|| x.station.cmp(&y.station))
+    });
+    let mean = |sum: f32| (pairs > 0).then(|| sum / pairs as f32);
+    let summary = ImpactSummary {
+        scenario,
+        stations_before: before.len(),
+        stations_after: after.len(),
+        reachable_pairs_before: reachable_pairs(&before),
+        reachable_pairs_after: reachable_pairs(&after),
+        lost_pairs,
+        gained_pairs,
+        average_delay_before: mean(total_before),
+        average_delay_after: mean(total_after),
+        diameter_before: before.diameter().map(|d| d.delay),
+        diameter_after: after.diameter().map(|d| d.delay),
+    };
+    Ok(ImpactReport {
+        summary,
+        affected,
+        centrality_shifts: centrality_shifts(baseline, modified, &RankOptions { top_n: usize::MAX, ..Default::default() }),
+    })
+}
+
+fn reachable_pairs(matrix: &DistanceMatrix) -> usize {
+    (0..matrix.len()).map(|i| matrix.row(i as StationIndex).iter().enumerate().filter(|&(j, d)| j != i && !d.is_nan()).count()).sum()
+}